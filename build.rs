@@ -21,6 +21,7 @@ fn main() {
     println!("cargo:rustc-link-lib=dylib=nppicc"); // NPP Image Color Conversion library
     println!("cargo:rustc-link-lib=dylib=nppial"); // NPP Image Arithmetic and Logical Operations
     println!("cargo:rustc-link-lib=dylib=nppidei"); // NPP Image Data Exchange and Initialization
+    println!("cargo:rustc-link-lib=dylib=nppitc"); // NPP Image Threshold and Compare Operations
 
     //
     // ---- Generate NPP bindings ----
@@ -30,6 +31,7 @@ fn main() {
             #include <nppi_color_conversion.h>
             #include <nppi_data_exchange_and_initialization.h>
             #include <nppi_arithmetic_and_logical_operations.h>
+            #include <nppi_threshold_and_compare_operations.h>
             #include <nppdefs.h>
         "#)
         .clang_arg("-I/usr/local/cuda/include")
@@ -46,10 +48,16 @@ fn main() {
         .allowlist_function("nppiAddC_32f_C3IR")
         .allowlist_function("nppiDivC_32f_C3R")
         .allowlist_function("nppiDivC_32f_C3IR")
+        // Clamping below a threshold
+        .allowlist_function("nppiThreshold_LTVal_32f_C3IR")
+        // Half-precision arithmetic for WorkingPrecision::F16
+        .allowlist_function("nppiSubC_16f_C3IR")
+        .allowlist_function("nppiMulC_16f_C3IR")
         // Color matrix transformation
         .allowlist_function("nppiColorTwist_32f_C3R")
         .allowlist_function("nppiColorTwist32f_32f_C3R")
         .allowlist_function("nppiColorTwist32f_32f_C3IR")
+        .allowlist_function("nppiColorTwist32f_16f_C3R")
         // Types
         .allowlist_type("NppStatus")
         .allowlist_type("NppiSize")
@@ -84,6 +92,8 @@ fn main() {
     let kernels = [
         "src/cuda/kernels/debayer_rggb_bilinear.cu",
         "src/cuda/kernels/color_pipeline.cu",
+        "src/cuda/kernels/srgb_gamma.cu",
+        "src/cuda/kernels/half_precision.cu",
     ];
 
     for kernel in kernels {
@@ -0,0 +1,134 @@
+//! Checks the histogram fallback for raws that decode without usable levels.
+//!
+//! A mock reader returns a 12-bit frame with a 256 pedestal and highlights up to 3900,
+//! once with all-zero levels and once with the white level below the black level. Both
+//! conversions must report estimated levels close to the true pedestal and peak and
+//! produce nearly the same image as a conversion with the true levels, and valid
+//! metadata must be left untouched. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example auto_levels`.
+
+use tiff::decoder::{Decoder, DecodingResult};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionReport, DebayerQuality, OutputMode,
+    RawImageData, RawImageReader, RawToTiffPipeline, Result, StandardTiffWriter,
+    raw::corrections::estimate_missing_levels,
+};
+
+const PEDESTAL: u16 = 256;
+const PEAK: u16 = 3900;
+
+/// Ignores the input bytes and returns a 12-bit RGGB ramp with the given levels
+struct LevelsReader {
+    blacklevels: [u16; 4],
+    whitelevels: [u16; 4],
+}
+
+fn frame(blacklevels: [u16; 4], whitelevels: [u16; 4]) -> RawImageData {
+    let (width, height) = (128, 64);
+    let span = (width * height - 1) as f32;
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height)
+            .map(|i| PEDESTAL + ((PEAK - PEDESTAL) as f32 * i as f32 / span).round() as u16)
+            .collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels,
+        whitelevels,
+        ..Default::default()
+    }
+}
+
+impl RawImageReader for LevelsReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(frame(self.blacklevels, self.whitelevels))
+    }
+}
+
+/// Converts with the given levels, returning the report and the decoded 16-bit RGB samples
+fn convert(
+    blacklevels: [u16; 4],
+    whitelevels: [u16; 4],
+) -> anyhow::Result<(ConversionReport, Vec<u16>)> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .build();
+    let pipeline = RawToTiffPipeline::with_custom(
+        LevelsReader {
+            blacklevels,
+            whitelevels,
+        },
+        StandardTiffWriter,
+        config.clone(),
+    )?;
+
+    let mut tiff = Vec::new();
+    let report = pipeline.convert_with_report(&[], &mut tiff, &config)?;
+    match Decoder::new(std::io::Cursor::new(tiff))?.read_image()? {
+        DecodingResult::U16(samples) => Ok((report, samples)),
+        _ => anyhow::bail!("Expected 16-bit output"),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let cases = [
+        ("all-zero levels", [0; 4], [0; 4]),
+        ("white below black", [512; 4], [128; 4]),
+    ];
+
+    let (_, reference) = convert([PEDESTAL; 4], [PEAK; 4])?;
+
+    for (name, blacklevels, whitelevels) in cases {
+        let (report, samples) = convert(blacklevels, whitelevels)?;
+        let (black, white) = (report.black_levels[0], report.white_levels[0]);
+        println!("{}: estimated black {}, white {}", name, black, white);
+
+        if report.black_levels != [black; 4] || report.white_levels != [white; 4] {
+            anyhow::bail!(
+                "{}: levels differ per channel: {:?} / {:?}",
+                name,
+                report.black_levels,
+                report.white_levels
+            );
+        }
+        if black.abs_diff(PEDESTAL) > 16 || white.abs_diff(PEAK) > 16 {
+            anyhow::bail!(
+                "{}: levels {}..{} are far from {}..{}",
+                name,
+                black,
+                white,
+                PEDESTAL,
+                PEAK
+            );
+        }
+
+        let error = samples
+            .iter()
+            .zip(&reference)
+            .map(|(&a, &b)| a.abs_diff(b) as f64)
+            .sum::<f64>()
+            / samples.len() as f64;
+        println!("{}: mean difference from the reference {:.1}", name, error);
+        if error > 512.0 {
+            anyhow::bail!(
+                "{}: output differs from the correctly leveled conversion",
+                name
+            );
+        }
+    }
+
+    let mut valid = frame([200; 4], [4000; 4]);
+    if estimate_missing_levels(&mut valid)
+        || valid.blacklevels != [200; 4]
+        || valid.whitelevels != [4000; 4]
+    {
+        anyhow::bail!("Valid levels were replaced");
+    }
+
+    println!("Levels estimated from the histogram when the metadata is invalid");
+    Ok(())
+}
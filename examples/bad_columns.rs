@@ -0,0 +1,122 @@
+//! Checks that `bad_columns` interpolates defective sensor columns in the raw domain.
+//!
+//! A mock reader returns a 12-bit RGGB mosaic whose colors ramp linearly across the
+//! frame, with column 21 dead (all zero). Converted to `BayerGray` with that column
+//! flagged, the column must be rebuilt from its same-color neighbours to within one code
+//! value while every other column, including the adjacent 20 and 22, stays untouched.
+//! Flagged neighbours and edge columns are checked on the mosaic directly. Exits non-zero
+//! otherwise.
+//!
+//! Run with `cargo run --example bad_columns`.
+
+use std::io::Cursor;
+
+use tiff::decoder::{Decoder, DecodingResult};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, StandardTiffWriter, raw::corrections::correct_bad_columns,
+};
+
+const WIDTH: usize = 48;
+const HEIGHT: usize = 16;
+const DEAD: usize = 21;
+
+/// Undamaged mosaic: every color site ramps linearly with x, at its own offset
+fn clean() -> RawImageData {
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let (x, y) = (i % WIDTH, i / WIDTH);
+                let site = (y % 2 * 2 + x % 2) as u16;
+                400 + site * 300 + x as u16 * 40
+            })
+            .collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    }
+}
+
+fn with_dead_columns(columns: &[usize]) -> RawImageData {
+    let mut image = clean();
+    for row in image.data.chunks_exact_mut(WIDTH) {
+        for &x in columns {
+            row[x] = 0;
+        }
+    }
+    image
+}
+
+struct DeadColumnReader;
+
+impl RawImageReader for DeadColumnReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(with_dead_columns(&[DEAD]))
+    }
+}
+
+fn column(data: &[u16], x: usize) -> Vec<u16> {
+    data.chunks_exact(WIDTH).map(|row| row[x]).collect()
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::BayerGray)
+        .bad_columns(vec![DEAD])
+        .build();
+    let tiff = RawToTiffPipeline::with_custom(DeadColumnReader, StandardTiffWriter, config)?
+        .convert_to_vec(&[])?;
+    let DecodingResult::U16(corrected) = Decoder::new(Cursor::new(tiff))?.read_image()? else {
+        anyhow::bail!("Expected 16-bit output");
+    };
+
+    let reference = clean().data;
+    println!(
+        "Column {}: {:?}, expected {:?}",
+        DEAD,
+        &column(&corrected, DEAD)[..2],
+        &column(&reference, DEAD)[..2]
+    );
+    for x in 0..WIDTH {
+        let (got, expected) = (column(&corrected, x), column(&reference, x));
+        let worst = got.iter().zip(&expected).map(|(a, b)| a.abs_diff(*b)).max();
+        let tolerance = if x == DEAD { 1 } else { 0 };
+        if worst > Some(tolerance) {
+            anyhow::bail!("Column {} is off by {:?}", x, worst);
+        }
+    }
+
+    // With 23 dead too, 21 and 23 both skip to the good columns 19 and 25; column 0 has
+    // same-color neighbours on the right only and copies column 2
+    let mut image = with_dead_columns(&[0, DEAD, DEAD + 2]);
+    correct_bad_columns(&mut image, &[0, DEAD, DEAD + 2]);
+    let mean = |a: &[u16], b: &[u16]| -> Vec<u16> {
+        a.iter()
+            .zip(b)
+            .map(|(&a, &b)| (a + b).div_ceil(2))
+            .collect()
+    };
+    let spanned = mean(&column(&reference, DEAD - 2), &column(&reference, DEAD + 4));
+    let expected = [
+        (DEAD, spanned.clone()),
+        (DEAD + 2, spanned),
+        (0, column(&reference, 2)),
+    ];
+    for (x, expected) in expected {
+        if column(&image.data, x) != expected {
+            anyhow::bail!(
+                "Column {} was not rebuilt from its nearest good neighbours",
+                x
+            );
+        }
+    }
+
+    println!("Dead column interpolated, neighbours untouched");
+    Ok(())
+}
@@ -0,0 +1,84 @@
+//! Checks the `baseline_exposure` and `black_clip` develop controls of the CPU debayer.
+//!
+//! A synthetic 12-bit RGGB gradient is debayered to linear float RGB. Raising
+//! `baseline_exposure` by +1 EV must double every linear value, and a positive
+//! `black_clip` must turn more pixels fully black than the unclipped conversion.
+//! Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example baseline_exposure`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, CpuDebayer, DebayerQuality, RawImageData,
+};
+
+fn gradient() -> RawImageData {
+    let (width, height) = (64, 48);
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height)
+            .map(|i| (256 + i * 3839 / (width * height - 1)) as u16)
+            .collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+fn debayer(baseline_exposure: f32, black_clip: f32) -> anyhow::Result<CpuDebayer> {
+    let config = ConversionConfig::builder()
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .exposure(1.0)
+        .baseline_exposure(baseline_exposure)
+        .black_clip(black_clip)
+        .build();
+    CpuDebayer::with_config(&config)
+}
+
+/// Pixels whose three quantized samples are all zero
+fn black_pixels(data: &[u16]) -> usize {
+    data.chunks_exact(3)
+        .filter(|px| px.iter().all(|&v| v == 0))
+        .count()
+}
+
+fn main() -> anyhow::Result<()> {
+    let raw = gradient();
+
+    let base = debayer(0.0, 0.0)?.process_f32(&raw)?.data;
+    let brighter = debayer(1.0, 0.0)?.process_f32(&raw)?.data;
+    if let Some((i, (b, x))) = base
+        .iter()
+        .zip(&brighter)
+        .enumerate()
+        .find(|(_, (b, x))| (*x - 2.0 * *b).abs() > 1e-4 * b.abs().max(1e-3))
+    {
+        anyhow::bail!(
+            "+1 EV turned sample {} from {} into {}, expected {}",
+            i,
+            b,
+            x,
+            2.0 * b
+        );
+    }
+    println!("+1 EV doubled all {} linear samples", base.len());
+
+    let unclipped = black_pixels(&debayer(0.0, 0.0)?.process(&raw)?.data);
+    let clipped = black_pixels(&debayer(0.0, 0.2)?.process(&raw)?.data);
+    if clipped <= unclipped {
+        anyhow::bail!(
+            "black_clip 0.2 left {} black pixels, unclipped had {}",
+            clipped,
+            unclipped
+        );
+    }
+    println!(
+        "Black pixels: {} unclipped, {} with black_clip 0.2",
+        unclipped, clipped
+    );
+
+    println!("Baseline exposure and black clip behave as configured");
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Checks that `convert_batch` scales with worker threads and produces the same output
+//! regardless of thread count.
+//!
+//! A synthetic reader stands in for rawloader so no sample files are needed; each job
+//! decodes a gradient frame, debayers it with the pipeline's shared backend and writes RGB.
+//! The batch runs once on a single worker and once on every core, then the outputs are
+//! compared byte for byte. Exits non-zero if they differ or the parallel run is not faster.
+//!
+//! Run with `cargo run --release --example batch_scaling`.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, StandardTiffWriter,
+};
+
+const JOBS: usize = 16;
+const WIDTH: usize = 1024;
+const HEIGHT: usize = 768;
+
+/// Minimum speedup of the parallel run over a single worker when 2+ cores are available
+const MIN_SPEEDUP: f64 = 1.3;
+
+/// Ignores the input bytes and returns a 12-bit RGGB gradient seeded by the first byte
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, data: &[u8]) -> Result<RawImageData> {
+        let seed = data.first().copied().unwrap_or(0) as usize;
+        let pixels = (0..HEIGHT)
+            .flat_map(|y| (0..WIDTH).map(move |x| ((x * 3 + y * 2 + seed * 97) % 3840 + 256) as u16))
+            .collect();
+
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: pixels,
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+fn run(jobs: &[(PathBuf, PathBuf)], threads: usize) -> anyhow::Result<(Duration, Vec<Vec<u8>>)> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .batch_threads(Some(threads))
+        .build();
+    let pipeline = RawToTiffPipeline::with_custom(SyntheticReader, StandardTiffWriter, config)?;
+
+    let start = Instant::now();
+    for result in pipeline.convert_batch(jobs) {
+        result?;
+    }
+    let elapsed = start.elapsed();
+
+    let outputs = jobs
+        .iter()
+        .map(|(_, output)| std::fs::read(output))
+        .collect::<std::io::Result<_>>()?;
+    Ok((elapsed, outputs))
+}
+
+fn main() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let jobs: Vec<(PathBuf, PathBuf)> = (0..JOBS)
+        .map(|i| {
+            let input = dir.path().join(format!("frame_{i}.raw"));
+            let output = dir.path().join(format!("frame_{i}.tiff"));
+            std::fs::write(&input, [i as u8])?;
+            Ok((input, output))
+        })
+        .collect::<std::io::Result<_>>()?;
+
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    let (serial_time, serial) = run(&jobs, 1)?;
+    let (parallel_time, parallel) = run(&jobs, cores)?;
+
+    println!("1 thread:    {:?}", serial_time);
+    println!("{} threads: {:?}", cores, parallel_time);
+
+    if serial != parallel {
+        anyhow::bail!("Parallel batch output differs from the single-threaded run");
+    }
+
+    let speedup = serial_time.as_secs_f64() / parallel_time.as_secs_f64();
+    println!("Speedup: {:.2}x", speedup);
+    if cores >= 2 && speedup < MIN_SPEEDUP {
+        anyhow::bail!("Batch conversion did not scale ({:.2}x < {:.2}x)", speedup, MIN_SPEEDUP);
+    }
+
+    println!("Batch conversion output matches and scales with threads");
+    Ok(())
+}
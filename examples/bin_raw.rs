@@ -0,0 +1,158 @@
+//! Checks `RawImageData::bin` on mosaics with known values.
+//!
+//! Every CFA site of a 12-bit RGGB mosaic holds a base level for its color plus a value
+//! that depends on its position. Binning by 2 must halve both dimensions and give each
+//! output site the rounded mean of the four same-color input sites of its 4x4 square, so
+//! the CFA phase is unchanged; binning by 4 must quarter them. A flat mosaic must stay
+//! flat, an RGB raw must average per channel, and odd or zero factors must be rejected
+//! for a mosaic. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example bin_raw`.
+
+use ffed_protosat_rs::image_pipeline::{Orientation, RawImageData};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+/// Base level of the R, G and B sites
+const BASE: [u16; 3] = [1000, 2000, 3000];
+
+fn color(x: usize, y: usize) -> usize {
+    match (y % 2, x % 2) {
+        (0, 0) => 0,
+        (1, 1) => 2,
+        _ => 1,
+    }
+}
+
+fn mosaic(width: usize, height: usize, value: impl Fn(usize, usize) -> u16) -> RawImageData {
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height)
+            .map(|i| value(i % width, i / width))
+            .collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        orientation: Orientation::Rotate180,
+        ..Default::default()
+    }
+}
+
+/// Expected value of output site (x, y) after binning `raw` by `factor`
+fn expected_bin(raw: &RawImageData, factor: usize, x: usize, y: usize) -> u16 {
+    let origin = |o: usize| o / 2 * 2 * factor + o % 2;
+    let (x0, y0) = (origin(x), origin(y));
+    let mut sum = 0u64;
+    for j in 0..factor {
+        for i in 0..factor {
+            sum += u64::from(raw.data[(y0 + 2 * j) * raw.width + x0 + 2 * i]);
+        }
+    }
+    let count = (factor * factor) as u64;
+    ((sum + count / 2) / count) as u16
+}
+
+fn main() -> anyhow::Result<()> {
+    let raw = mosaic(WIDTH, HEIGHT, |x, y| {
+        BASE[color(x, y)] + (x * 3 + y * 7) as u16
+    });
+
+    for factor in [2, 4] {
+        let binned = raw.bin(factor)?;
+        if (binned.width, binned.height) != (WIDTH / factor, HEIGHT / factor) {
+            anyhow::bail!(
+                "Bin by {}: {}x{}, expected {}x{}",
+                factor,
+                binned.width,
+                binned.height,
+                WIDTH / factor,
+                HEIGHT / factor
+            );
+        }
+        if !binned.is_bayer
+            || binned.orientation != Orientation::Rotate180
+            || binned.whitelevels != raw.whitelevels
+        {
+            anyhow::bail!("Bin by {} did not keep the mosaic metadata", factor);
+        }
+        for y in 0..binned.height {
+            for x in 0..binned.width {
+                let (value, expected) = (
+                    binned.data[y * binned.width + x],
+                    expected_bin(&raw, factor, x, y),
+                );
+                // Each site's offset stays below the gap between the color bases
+                let base = BASE[color(x, y)];
+                if value != expected || !(base..base + 1000).contains(&value) {
+                    anyhow::bail!(
+                        "Bin by {}: site ({}, {}) is {}, expected {} in the {} color",
+                        factor,
+                        x,
+                        y,
+                        value,
+                        expected,
+                        color(x, y)
+                    );
+                }
+            }
+        }
+        println!(
+            "Bin by {}: {}x{}, every site averages its color",
+            factor, binned.width, binned.height
+        );
+    }
+
+    // 2x2 binning of a 4x4 mosaic by hand: R sites 100, 110, 120, 130 average to 115
+    let small = mosaic(4, 4, |x, y| match color(x, y) {
+        0 => 100 + (x as u16 / 2) * 10 + (y as u16 / 2) * 20,
+        1 => 500,
+        _ => 900 + (x + y) as u16,
+    });
+    let binned = small.bin(2)?;
+    if binned.data != [115, 500, 500, 904] {
+        anyhow::bail!(
+            "4x4 mosaic binned to {:?}, expected [115, 500, 500, 904]",
+            binned.data
+        );
+    }
+    println!("4x4 mosaic bins to {:?}", binned.data);
+
+    let flat = mosaic(WIDTH + 3, HEIGHT + 1, |x, y| BASE[color(x, y)]);
+    let binned = flat.bin(2)?;
+    if (binned.width, binned.height) != (WIDTH / 2, HEIGHT / 2)
+        || (0..binned.data.len())
+            .any(|i| binned.data[i] != BASE[color(i % binned.width, i / binned.width)])
+    {
+        anyhow::bail!("Flat mosaic with trailing rows did not bin to the same levels");
+    }
+
+    let rgb = RawImageData {
+        data: (0..WIDTH * HEIGHT)
+            .flat_map(|i| BASE.map(|base| base + ((i / WIDTH) % 3) as u16))
+            .collect(),
+        is_bayer: false,
+        ..raw.clone()
+    };
+    let binned = rgb.bin(3)?;
+    if (binned.width, binned.height) != (WIDTH / 3, HEIGHT / 3)
+        || binned
+            .data
+            .chunks_exact(3)
+            .any(|px| px != [1001, 2001, 3001])
+    {
+        anyhow::bail!("RGB raw did not average per channel");
+    }
+    println!("RGB raw bins by 3 per channel");
+
+    for factor in [0, 3] {
+        if raw.bin(factor).is_ok() {
+            anyhow::bail!("Binning a mosaic by {} was accepted", factor);
+        }
+    }
+
+    println!("OK");
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Checks that the pipeline rejects bit depths the u16 pipeline can't represent.
+//!
+//! A mock reader reports a frame at a given `bits_per_sample`. At 0 and 24 bits the
+//! conversion must fail validation with `UnsupportedFormat`; at 8, 12 and 16 bits, the
+//! ends and middle of `SUPPORTED_BITS_PER_SAMPLE`, it must convert. Exits non-zero
+//! otherwise.
+//!
+//! Run with `cargo run --example bit_depth_range`.
+
+use ffed_protosat_rs::image_pipeline::raw::types::SUPPORTED_BITS_PER_SAMPLE;
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, OutputMode, PipelineStage, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, StandardTiffWriter,
+};
+
+/// Returns a small mid-gray frame claiming `bits` bits per sample
+struct DepthReader {
+    bits: u32,
+}
+
+impl RawImageReader for DepthReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: 8,
+            height: 8,
+            data: vec![100; 64],
+            bits_per_sample: self.bits,
+            ..Default::default()
+        })
+    }
+}
+
+fn convert(bits: u32) -> Result<Vec<u8>> {
+    let config = ConversionConfig::builder().output(OutputMode::BayerGray).build();
+    RawToTiffPipeline::with_custom(DepthReader { bits }, StandardTiffWriter, config)?.convert_to_vec(&[])
+}
+
+fn main() -> anyhow::Result<()> {
+    for bits in [0, 24] {
+        match convert(bits) {
+            Err(e)
+                if e.stage() == Some(PipelineStage::Validate)
+                    && matches!(e.without_stage(), ConversionError::UnsupportedFormat(_)) =>
+            {
+                println!("{} bits rejected: {}", bits, e)
+            }
+            Err(e) => anyhow::bail!("{} bits: expected an UnsupportedFormat validation error, got {}", bits, e),
+            Ok(_) => anyhow::bail!("{} bits converted", bits),
+        }
+    }
+
+    for bits in [*SUPPORTED_BITS_PER_SAMPLE.start(), 12, *SUPPORTED_BITS_PER_SAMPLE.end()] {
+        let tiff = convert(bits).map_err(|e| anyhow::anyhow!("{} bits failed: {}", bits, e))?;
+        println!("{} bits converted to {} bytes", bits, tiff.len());
+    }
+
+    println!("Bit depths outside {:?} are rejected", SUPPORTED_BITS_PER_SAMPLE);
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! Checks that black-level subtraction in the grayscale path clamps instead of wrapping.
+//!
+//! A 12-bit Bayer frame with a 512 black level, where a third of the samples sit below
+//! the black level, is converted to `BayerGray` with `normalize_bayer`. Samples at or
+//! below their black level must read back as 0, never as values wrapped up near 65535,
+//! and the white level must still map to 65535. `corrections::subtract_black` is checked
+//! directly as well. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example black_level_clamp`.
+
+use std::io::Cursor;
+
+use ffed_protosat_rs::image_pipeline::raw::corrections::subtract_black;
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawToTiffPipeline,
+};
+use tiff::decoder::{Decoder, DecodingResult};
+
+const WIDTH: usize = 48;
+const HEIGHT: usize = 32;
+const BLACK: u16 = 512;
+const WHITE: u16 = 4095;
+
+/// Cycles through values below, at and above the black level up to the white level
+fn sample(i: usize) -> u16 {
+    match i % 3 {
+        0 => (i % BLACK as usize) as u16,
+        1 => BLACK,
+        _ => BLACK + (i % (WHITE - BLACK + 1) as usize) as u16,
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let cases = [(0, 0), (BLACK - 1, 0), (BLACK, 0), (BLACK + 7, 7), (WHITE, WHITE - BLACK)];
+    for (value, expected) in cases {
+        let actual = subtract_black(value, BLACK);
+        if actual != expected {
+            anyhow::bail!("subtract_black({}, {}) is {}, expected {}", value, BLACK, actual, expected);
+        }
+    }
+
+    let mut data: Vec<u16> = (0..WIDTH * HEIGHT).map(sample).collect();
+    data[WIDTH * HEIGHT - 1] = WHITE;
+    let raw = RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data,
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [BLACK; 4],
+        whitelevels: [WHITE; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    };
+    let input = raw.data.clone();
+
+    let config = ConversionConfig::builder()
+        .output(OutputMode::BayerGray)
+        .normalize_bayer(true)
+        .build();
+    let mut tiff = Vec::new();
+    RawToTiffPipeline::new(config)?.convert_raw_image(raw, &mut tiff)?;
+    let DecodingResult::U16(samples) = Decoder::new(Cursor::new(&tiff))?.read_image()? else {
+        anyhow::bail!("Expected 16-bit samples");
+    };
+
+    let range = (WHITE - BLACK) as f32;
+    for (i, (&raw, &out)) in input.iter().zip(&samples).enumerate() {
+        let expected = (subtract_black(raw, BLACK) as f32 * u16::MAX as f32 / range).round() as u16;
+        if out != expected {
+            anyhow::bail!(
+                "Sample {} ({} raw) normalized to {}, expected {}",
+                i,
+                raw,
+                out,
+                expected
+            );
+        }
+    }
+    let below = input.iter().filter(|&&v| v < BLACK).count();
+    let peak = samples.iter().copied().max().unwrap_or(0);
+    if peak != u16::MAX {
+        anyhow::bail!("White level normalized to {}, expected 65535", peak);
+    }
+
+    println!(
+        "{} samples below the black level clamp to 0, white level maps to {}",
+        below, peak
+    );
+    Ok(())
+}
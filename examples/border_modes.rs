@@ -0,0 +1,107 @@
+//! Checks `BorderMode::Crop` against the padded border modes on a horizontal ramp.
+//!
+//! A grey 12-bit scene rising linearly from left to right is mosaiced and debayered by
+//! `CpuDebayer` with the Malvar-He-Cutler demosaic, with white balance and color matrix
+//! set up to pass camera values through. Both filters reproduce a linear ramp exactly
+//! wherever their window fits, so only edge pixels can be off. `Crop` output must be
+//! `BORDER` pixels smaller on every side and match the scene everywhere, edges included,
+//! while `Replicate` and `Mirror` keep the input dimensions. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example border_modes`.
+
+use ffed_protosat_rs::image_pipeline::{
+    BorderMode, ConversionConfig, CpuDebayer, DebayerQuality,
+    RawImageData,
+};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 16;
+/// Matches `debayer::border::BORDER`
+const BORDER: usize = 2;
+const DARK: f32 = 0.1;
+const BRIGHT: f32 = 0.8;
+
+fn scene(x: usize) -> f32 {
+    DARK + (BRIGHT - DARK) * x as f32 / (WIDTH - 1) as f32
+}
+
+fn ramp_mosaic() -> RawImageData {
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT)
+            .map(|i| (scene(i % WIDTH) * 4095.0).round() as u16)
+            .collect(),
+        bits_per_sample: 12,
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+/// Output dimensions and the largest absolute error of any channel against the scene,
+/// with `offset` the number of input columns trimmed from the left of the output
+fn max_error(
+    mode: BorderMode,
+    raw: &RawImageData,
+    offset: usize,
+) -> anyhow::Result<((usize, usize), f32)> {
+    let config = ConversionConfig::builder()
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .border_mode(mode)
+        .exposure(1.0)
+        .build();
+    let rgb = CpuDebayer::with_config(&config)?.process_f32(raw)?;
+    let error = rgb
+        .data
+        .chunks_exact(3)
+        .enumerate()
+        .flat_map(|(i, px)| {
+            let expected = scene(i % rgb.width + offset);
+            px.iter().map(move |v| (v - expected).abs())
+        })
+        .fold(0.0f32, f32::max);
+    Ok(((rgb.width, rgb.height), error))
+}
+
+fn main() -> anyhow::Result<()> {
+    let raw = ramp_mosaic();
+
+    let (cropped, crop_error) = max_error(BorderMode::Crop, &raw, BORDER)?;
+    println!("Crop: {}x{}, max error {:.5}", cropped.0, cropped.1, crop_error);
+    if cropped != (WIDTH - 2 * BORDER, HEIGHT - 2 * BORDER) {
+        anyhow::bail!(
+            "Crop output is {}x{}, expected {}x{}",
+            cropped.0,
+            cropped.1,
+            WIDTH - 2 * BORDER,
+            HEIGHT - 2 * BORDER
+        );
+    }
+    if crop_error > 1e-3 {
+        anyhow::bail!("Crop output is off by up to {} at its edges", crop_error);
+    }
+
+    for mode in [BorderMode::Replicate, BorderMode::Mirror] {
+        let (dimensions, error) = max_error(mode, &raw, 0)?;
+        println!(
+            "{:?}: {}x{}, max error {:.5}",
+            mode, dimensions.0, dimensions.1, error
+        );
+        if dimensions != (WIDTH, HEIGHT) {
+            anyhow::bail!(
+                "{:?} output is {}x{}, expected the input's {}x{}",
+                mode,
+                dimensions.0,
+                dimensions.1,
+                WIDTH,
+                HEIGHT
+            );
+        }
+        if error <= crop_error {
+            anyhow::bail!("{:?} edges are no worse than Crop's, the ramp does not reach them", mode);
+        }
+    }
+
+    println!("Crop trims the border and leaves only full-window pixels");
+    Ok(())
+}
@@ -0,0 +1,95 @@
+//! Checks the `byte_order` option of TIFF output.
+//!
+//! Output with the default and with `ByteOrder::NATIVE` must start with the host's magic
+//! bytes (`II*\0` little-endian, `MM\0*` big-endian). The tiff encoder can't write the
+//! other order, so requesting it must fail at the encode stage with `UnsupportedFormat`
+//! and leave the output empty. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example byte_order`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ByteOrder, ConversionConfig, ConversionError, PipelineStage,
+    RawImageData, RawToTiffPipeline, Result,
+};
+
+fn mosaic() -> RawImageData {
+    let (width, height) = (32, 24);
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height).map(|i| (i * 5 + 256) as u16).collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    }
+}
+
+fn magic(order: ByteOrder) -> &'static [u8; 4] {
+    match order {
+        ByteOrder::LittleEndian => b"II*\0",
+        ByteOrder::BigEndian => b"MM\0*",
+    }
+}
+
+fn convert(config: ConversionConfig) -> (Result<()>, Vec<u8>) {
+    let mut tiff = Vec::new();
+    let result = RawToTiffPipeline::new(config)
+        .and_then(|pipeline| pipeline.convert_raw_image(mosaic(), &mut tiff));
+    (result, tiff)
+}
+
+fn main() -> anyhow::Result<()> {
+    for (name, config) in [
+        ("default", ConversionConfig::default()),
+        (
+            "native",
+            ConversionConfig::builder()
+                .byte_order(ByteOrder::NATIVE)
+                .build(),
+        ),
+    ] {
+        let (result, tiff) = convert(config);
+        result?;
+        if tiff.get(..4) != Some(magic(ByteOrder::NATIVE).as_slice()) {
+            anyhow::bail!(
+                "{} byte order: output starts with {:?}, expected {:?}",
+                name,
+                tiff.get(..4),
+                magic(ByteOrder::NATIVE)
+            );
+        }
+        println!(
+            "{} byte order: {:?}",
+            name,
+            String::from_utf8_lossy(&tiff[..2])
+        );
+    }
+
+    let foreign = match ByteOrder::NATIVE {
+        ByteOrder::LittleEndian => ByteOrder::BigEndian,
+        ByteOrder::BigEndian => ByteOrder::LittleEndian,
+    };
+    let (result, tiff) = convert(ConversionConfig::builder().byte_order(foreign).build());
+    match result {
+        Err(e)
+            if e.stage() == Some(PipelineStage::Encode)
+                && matches!(e.without_stage(), ConversionError::UnsupportedFormat(_))
+                && tiff.is_empty() =>
+        {
+            println!("{:?} rejected: {}", foreign, e)
+        }
+        Err(e) => anyhow::bail!(
+            "{:?}: unexpected failure {} ({} bytes written)",
+            foreign,
+            e,
+            tiff.len()
+        ),
+        Ok(()) => anyhow::bail!("{:?} output was written as {:?}", foreign, tiff.get(..4)),
+    }
+
+    println!("OK");
+    Ok(())
+}
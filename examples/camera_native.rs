@@ -0,0 +1,107 @@
+//! Checks `ColorTransform::CameraNative` output of the CPU debayer.
+//!
+//! A 12-bit mosaic whose R, G and B sites each hold one level is debayered with the
+//! default `CameraToSrgb` and with `CameraNative`. The camera-native pixels must equal
+//! the black-subtracted, normalized and white-balanced site levels, i.e. the values the
+//! color matrix would have been applied to, while the sRGB output must differ from them.
+//! The float output is compared, before quantization clamps anything. Exits non-zero
+//! otherwise.
+//!
+//! Run with `cargo run --example camera_native`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ColorTransform, ConversionConfig, CpuDebayer, DebayerQuality,
+    OutputMode, RawImageData, WhiteBalance,
+};
+
+const WIDTH: usize = 48;
+const HEIGHT: usize = 32;
+const LEVELS: [u16; 3] = [1256, 2256, 856];
+const BLACK: u16 = 256;
+const WHITE: u16 = 4095;
+
+/// RGGB mosaic with every site of a color at its `LEVELS` entry
+fn flat_mosaic() -> RawImageData {
+    let data = (0..WIDTH * HEIGHT)
+        .map(|i| match ((i / WIDTH) % 2, (i % WIDTH) % 2) {
+            (0, 0) => LEVELS[0],
+            (1, 1) => LEVELS[2],
+            _ => LEVELS[1],
+        })
+        .collect();
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data,
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [BLACK; 4],
+        whitelevels: [WHITE; 4],
+        cam_to_xyz: [
+            [0.6, 0.3, 0.1, 0.0],
+            [0.3, 0.6, 0.1, 0.0],
+            [0.1, 0.2, 0.7, 0.0],
+        ],
+        ..Default::default()
+    }
+}
+
+fn debayer(raw: &RawImageData, transform: ColorTransform) -> anyhow::Result<Vec<f32>> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .exposure(1.0)
+        .color_transform(transform)
+        .build();
+    Ok(CpuDebayer::with_config(&config)?.process_f32(raw)?.data)
+}
+
+fn main() -> anyhow::Result<()> {
+    let raw = flat_mosaic();
+    let multipliers = WhiteBalance::AsShot.multipliers(&raw);
+    let range = (WHITE - BLACK) as f32;
+    let expected: [f32; 3] =
+        std::array::from_fn(|c| (LEVELS[c] - BLACK) as f32 / range * multipliers[c]);
+
+    let native = debayer(&raw, ColorTransform::CameraNative)?;
+    let tolerance = 1e-5;
+    for (i, px) in native.chunks_exact(3).enumerate() {
+        if px
+            .iter()
+            .zip(&expected)
+            .any(|(v, e)| (v - e).abs() > tolerance)
+        {
+            anyhow::bail!(
+                "Camera-native pixel {} is {:?}, expected {:?}",
+                i,
+                px,
+                expected
+            );
+        }
+    }
+    println!(
+        "Camera-native output matches the pre-matrix values {:?}",
+        expected
+    );
+
+    let srgb = debayer(&raw, ColorTransform::CameraToSrgb)?;
+    let difference = srgb[..3]
+        .iter()
+        .zip(&expected)
+        .map(|(v, e)| (v - e).abs())
+        .fold(0.0f32, f32::max);
+    if difference < 0.01 {
+        anyhow::bail!(
+            "sRGB output {:?} is indistinguishable from camera-native",
+            &srgb[..3]
+        );
+    }
+    println!(
+        "sRGB output {:?} differs by up to {:.4}",
+        &srgb[..3],
+        difference
+    );
+
+    println!("OK");
+    Ok(())
+}
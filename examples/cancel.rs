@@ -0,0 +1,156 @@
+//! Checks that `convert_cancellable` stops at the next stage once its flag is set.
+//!
+//! A debayer wrapping `CpuDebayer` sets the cancel flag as it finishes, standing in for
+//! a client that disconnects mid-conversion. The conversion must then fail with
+//! `Cancelled` tagged at the encode stage without the writer running. A flag set up front
+//! must stop the conversion before decoding, and an unset flag must give the same bytes
+//! as `convert`. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example cancel`.
+
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, CpuDebayer, Debayer, DebayerQuality, GrayImageData, OutputMode, PipelineStage, RawImageData, RawImageReader, RawToTiffPipeline,
+    Result, RgbImageData, RgbImageDataF32, StandardTiffWriter, TiffWriter,
+};
+
+/// Ignores the input bytes and returns a small 12-bit RGGB gradient, counting calls
+struct SyntheticReader(Arc<AtomicUsize>);
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        let (width, height) = (64, 48);
+        Ok(RawImageData {
+            width,
+            height,
+            data: (0..width * height)
+                .map(|i| (i % 3840 + 256) as u16)
+                .collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+/// Debayers with `CpuDebayer`, then raises the cancel flag when `armed`
+struct CancellingDebayer {
+    inner: CpuDebayer,
+    flag: Arc<AtomicBool>,
+    armed: Arc<AtomicBool>,
+}
+
+impl Debayer for CancellingDebayer {
+    fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        let image = self.inner.process(raw_image)?;
+        if self.armed.load(Ordering::SeqCst) {
+            self.flag.store(true, Ordering::SeqCst);
+        }
+        Ok(image)
+    }
+}
+
+/// `StandardTiffWriter` that counts RGB writes
+struct CountingWriter(Arc<AtomicUsize>);
+
+impl TiffWriter for CountingWriter {
+    fn write_tiff(
+        &self,
+        image: &RawImageData,
+        output: &mut dyn Write,
+        config: &ConversionConfig,
+    ) -> Result<()> {
+        StandardTiffWriter.write_tiff(image, output, config)
+    }
+
+    fn write_gray_tiff(
+        &self,
+        image: &GrayImageData,
+        output: &mut dyn Write,
+        config: &ConversionConfig,
+    ) -> Result<()> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        StandardTiffWriter.write_gray_tiff(image, output, config)
+    }
+
+    fn write_rgb_tiff(
+        &self,
+        image: &RgbImageData,
+        output: &mut dyn Write,
+        config: &ConversionConfig,
+    ) -> Result<()> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        StandardTiffWriter.write_rgb_tiff(image, output, config)
+    }
+
+    fn write_rgb_tiff_f32(
+        &self,
+        image: &RgbImageDataF32,
+        output: &mut dyn Write,
+        config: &ConversionConfig,
+    ) -> Result<()> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        StandardTiffWriter.write_rgb_tiff_f32(image, output, config)
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .build();
+    let flag = Arc::new(AtomicBool::new(false));
+    let armed = Arc::new(AtomicBool::new(true));
+    let (reads, writes) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+    let debayer = CancellingDebayer {
+        inner: CpuDebayer::with_config(&config)?,
+        flag: flag.clone(),
+        armed: armed.clone(),
+    };
+    let pipeline = RawToTiffPipeline::with_debayer(
+        SyntheticReader(reads.clone()),
+        CountingWriter(writes.clone()),
+        Box::new(debayer),
+        config,
+    )?;
+
+    let mut output = Vec::new();
+    match pipeline.convert_cancellable(&[], &mut output, &flag) {
+        Err(e) if matches!(e.without_stage(), ConversionError::Cancelled) => {
+            println!("Cancelled after debayer: {}", e);
+            if e.stage() != Some(PipelineStage::Encode) {
+                anyhow::bail!("Cancelled at {:?}, expected the encode stage", e.stage());
+            }
+        }
+        Err(e) => anyhow::bail!("Expected Cancelled, got {}", e),
+        Ok(()) => anyhow::bail!("Conversion finished despite the cancel flag"),
+    }
+    if writes.load(Ordering::SeqCst) != 0 || !output.is_empty() {
+        anyhow::bail!("The writer ran after cancellation");
+    }
+
+    let reads_before = reads.load(Ordering::SeqCst);
+    match pipeline.convert_cancellable(&[], &mut output, &flag) {
+        Err(e) if matches!(e.without_stage(), ConversionError::Cancelled) => {}
+        other => anyhow::bail!("Expected an early Cancelled, got {:?}", other.err()),
+    }
+    if reads.load(Ordering::SeqCst) != reads_before {
+        anyhow::bail!("A pre-set flag did not stop the conversion before decoding");
+    }
+
+    armed.store(false, Ordering::SeqCst);
+    flag.store(false, Ordering::SeqCst);
+    pipeline.convert_cancellable(&[], &mut output, &flag)?;
+    if output != pipeline.convert_to_vec(&[])? {
+        anyhow::bail!("An uncancelled conversion differs from convert");
+    }
+
+    println!("Cancellation stops the conversion between stages");
+    Ok(())
+}
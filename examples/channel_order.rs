@@ -0,0 +1,88 @@
+//! Checks that `channel_order` permutes the samples of RGB output.
+//!
+//! The same synthetic frame is converted with every `ChannelOrder`. Decoded, each file
+//! must hold the `Rgb` file's samples permuted as the order prescribes; in particular
+//! `Bgr` must swap R and B and keep G on a known pixel. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example channel_order`.
+
+use std::io::Cursor;
+
+use tiff::decoder::{Decoder, DecodingResult};
+
+use ffed_protosat_rs::image_pipeline::{
+    ChannelOrder, ConversionConfig, DebayerQuality, OutputMode,
+    RawImageData, RawImageReader, RawToTiffPipeline, Result, StandardTiffWriter,
+};
+
+/// Ignores the input bytes and returns a 12-bit RGGB frame with distinct R, G and B levels
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        let (width, height) = (32, 24);
+        Ok(RawImageData {
+            width,
+            height,
+            data: (0..width * height)
+                .map(|i| match ((i / width) % 2, i % 2) {
+                    (0, 0) => 1400,
+                    (1, 1) => 600,
+                    _ => 900,
+                })
+                .collect(),
+            bits_per_sample: 12,
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+fn convert(order: ChannelOrder) -> anyhow::Result<Vec<u16>> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .exposure(1.0)
+        .channel_order(order)
+        .build();
+    let tiff = RawToTiffPipeline::with_custom(SyntheticReader, StandardTiffWriter, config)?
+        .convert_to_vec(&[])?;
+    match Decoder::new(Cursor::new(tiff))?.read_image()? {
+        DecodingResult::U16(samples) => Ok(samples),
+        _ => anyhow::bail!("Expected 16-bit output"),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let rgb = convert(ChannelOrder::Rgb)?;
+
+    // A pixel away from the border, where the demosaic sees the full pattern
+    let pixel = (10 * 32 + 10) * 3;
+    let [r, g, b] = [rgb[pixel], rgb[pixel + 1], rgb[pixel + 2]];
+    let bgr = convert(ChannelOrder::Bgr)?;
+    println!("Rgb {:?}, Bgr {:?}", [r, g, b], &bgr[pixel..pixel + 3]);
+    if r == b || bgr[pixel..pixel + 3] != [b, g, r] {
+        anyhow::bail!("Bgr did not swap red and blue");
+    }
+
+    for order in [
+        ChannelOrder::Rbg,
+        ChannelOrder::Grb,
+        ChannelOrder::Gbr,
+        ChannelOrder::Brg,
+        ChannelOrder::Bgr,
+    ] {
+        let reordered = convert(order)?;
+        let expected: Vec<u16> = rgb
+            .chunks_exact(3)
+            .flat_map(|px| order.source_indices().map(|i| px[i]))
+            .collect();
+        if reordered != expected {
+            anyhow::bail!("{:?} output is not the permuted RGB output", order);
+        }
+    }
+
+    println!("Channel order applied to RGB output");
+    Ok(())
+}
@@ -0,0 +1,139 @@
+//! Checks `compute_color_matrix` against hand-computed matrices and the CPU debayer.
+//!
+//! With an identity camera to XYZ matrix the result must be the XYZ to sRGB matrix
+//! scaled by the exposure; a `color_matrix` override must replace the raw's matrix;
+//! `CameraNative` must give the exposure on the diagonal. Debayering a mosaic of flat
+//! color sites must yield exactly that matrix applied to the white-balanced site levels.
+//! Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example color_matrix_inspect`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ColorTransform, ConversionConfig, CpuDebayer, DebayerQuality,
+    OutputMode, RawImageData, WhiteBalance, compute_color_matrix,
+};
+
+const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.969266, 1.8760108, 0.041556],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+const IDENTITY: [[f32; 4]; 3] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+];
+const LEVELS: [u16; 3] = [1256, 2256, 856];
+
+/// 48x32 RGGB mosaic with every site of a color at its `LEVELS` entry
+fn flat_mosaic(cam_to_xyz: [[f32; 4]; 3]) -> RawImageData {
+    let (width, height) = (48, 32);
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height)
+            .map(|i| match ((i / width) % 2, (i % width) % 2) {
+                (0, 0) => LEVELS[0],
+                (1, 1) => LEVELS[2],
+                _ => LEVELS[1],
+            })
+            .collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        cam_to_xyz,
+        ..Default::default()
+    }
+}
+
+fn expect_matrix(name: &str, actual: [[f32; 4]; 3], expected: [[f32; 4]; 3]) -> anyhow::Result<()> {
+    let error = actual
+        .iter()
+        .flatten()
+        .zip(expected.iter().flatten())
+        .map(|(a, e)| (a - e).abs())
+        .fold(0.0f32, f32::max);
+    if error > 1e-6 {
+        anyhow::bail!("{}: got {:?}, expected {:?}", name, actual, expected);
+    }
+    println!("{}: {:?}", name, actual);
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let exposure = 2.0;
+    let srgb_scaled: [[f32; 4]; 3] = std::array::from_fn(|r| {
+        std::array::from_fn(|c| {
+            if c < 3 {
+                XYZ_TO_SRGB[r][c] * exposure
+            } else {
+                0.0
+            }
+        })
+    });
+
+    let identity_raw = flat_mosaic(IDENTITY);
+    let config = ConversionConfig::builder().exposure(exposure).build();
+    expect_matrix(
+        "identity camera",
+        compute_color_matrix(&config, &identity_raw),
+        srgb_scaled,
+    )?;
+
+    // The override wins over the raw's own matrix
+    let skewed_raw = flat_mosaic([
+        [0.6, 0.3, 0.1, 0.0],
+        [0.3, 0.6, 0.1, 0.0],
+        [0.1, 0.2, 0.7, 0.0],
+    ]);
+    let overridden = ConversionConfig::builder()
+        .exposure(exposure)
+        .color_matrix(Some(IDENTITY))
+        .build();
+    expect_matrix(
+        "override",
+        compute_color_matrix(&overridden, &skewed_raw),
+        srgb_scaled,
+    )?;
+
+    let native = ConversionConfig::builder()
+        .exposure(exposure)
+        .color_transform(ColorTransform::CameraNative)
+        .build();
+    let diagonal: [[f32; 4]; 3] =
+        std::array::from_fn(|r| std::array::from_fn(|c| if r == c { exposure } else { 0.0 }));
+    expect_matrix(
+        "camera native",
+        compute_color_matrix(&native, &skewed_raw),
+        diagonal,
+    )?;
+
+    // The debayer must apply exactly the reported matrix
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .exposure(0.5)
+        .build();
+    let matrix = compute_color_matrix(&config, &skewed_raw);
+    let multipliers = WhiteBalance::AsShot.multipliers(&skewed_raw);
+    let balanced: [f32; 3] =
+        std::array::from_fn(|c| (LEVELS[c] - 256) as f32 / (4095 - 256) as f32 * multipliers[c]);
+    let expected: [f32; 3] = std::array::from_fn(|r| {
+        (0..3).map(|c| matrix[r][c] * balanced[c]).sum::<f32>() + matrix[r][3]
+    });
+    let image = CpuDebayer::with_config(&config)?.process_f32(&skewed_raw)?;
+    for px in image.data.chunks_exact(3) {
+        if px.iter().zip(&expected).any(|(v, e)| (v - e).abs() > 1e-5) {
+            anyhow::bail!(
+                "Debayered pixel {:?} does not match the reported matrix ({:?})",
+                px,
+                expected
+            );
+        }
+    }
+    println!("Debayer applies the reported matrix: {:?}", expected);
+
+    println!("OK");
+    Ok(())
+}
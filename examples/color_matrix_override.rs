@@ -0,0 +1,96 @@
+//! Checks that `color_matrix` replaces the decoded camera matrix before debayering.
+//!
+//! A synthetic reader reports an sRGB-like matrix. Debayering with an override that
+//! swaps the red and blue rows must change the output colors and match a reader that
+//! decodes the override matrix itself. The same matrix loaded from a JSON profile by
+//! camera model must equal the override, and unknown models must find none. Exits
+//! non-zero otherwise.
+//!
+//! Run with `cargo run --example color_matrix_override`.
+
+use ffed_protosat_rs::image_pipeline::{
+    CameraProfiles, ConversionConfig, DebayerQuality, OutputMode,
+    RawImageData, RawImageReader, RawToTiffPipeline, Result, StandardTiffWriter,
+};
+
+const DECODED: [[f32; 4]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375, 0.0],
+    [0.2126729, 0.7151522, 0.0721750, 0.0],
+    [0.0193339, 0.119192, 0.9503041, 0.0],
+];
+
+/// `DECODED` with the X and Z rows swapped
+const OVERRIDE: [[f32; 4]; 3] = [
+    [0.0193339, 0.119192, 0.9503041, 0.0],
+    [0.2126729, 0.7151522, 0.0721750, 0.0],
+    [0.4124564, 0.3575761, 0.1804375, 0.0],
+];
+
+const PROFILE: &str = r#"{
+    "Synthetic Cam": [
+        [0.0193339, 0.119192, 0.9503041, 0.0],
+        [0.2126729, 0.7151522, 0.0721750, 0.0],
+        [0.4124564, 0.3575761, 0.1804375, 0.0]
+    ]
+}"#;
+
+/// Ignores the input bytes and returns a 12-bit RGGB gradient with `cam_to_xyz`
+struct SyntheticReader {
+    cam_to_xyz: [[f32; 4]; 3],
+}
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        let (width, height) = (64, 48);
+        Ok(RawImageData {
+            width,
+            height,
+            data: (0..width * height).map(|i| (i % 3840 + 256) as u16).collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            cam_to_xyz: self.cam_to_xyz,
+            ..Default::default()
+        })
+    }
+}
+
+fn debayer(cam_to_xyz: [[f32; 4]; 3], color_matrix: Option<[[f32; 4]; 3]>) -> anyhow::Result<Vec<u16>> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .color_matrix(color_matrix)
+        .build();
+    let pipeline = RawToTiffPipeline::with_custom(SyntheticReader { cam_to_xyz }, StandardTiffWriter, config)?;
+    Ok(pipeline.debayer_only(&[])?.data)
+}
+
+fn main() -> anyhow::Result<()> {
+    let decoded = debayer(DECODED, None)?;
+    let overridden = debayer(DECODED, Some(OVERRIDE))?;
+    let expected = debayer(OVERRIDE, None)?;
+
+    if overridden == decoded {
+        anyhow::bail!("Override matrix left the output colors unchanged");
+    }
+    if overridden != expected {
+        anyhow::bail!("Output with the override differs from decoding the override matrix");
+    }
+    println!(
+        "First pixel: decoded matrix {:?}, override {:?}",
+        &decoded[..3],
+        &overridden[..3]
+    );
+
+    let profiles = CameraProfiles::from_json(PROFILE)?;
+    if profiles.color_matrix("Synthetic Cam") != Some(OVERRIDE) {
+        anyhow::bail!("Profile matrix for \"Synthetic Cam\" differs from the override");
+    }
+    if profiles.color_matrix("Other Cam").is_some() {
+        anyhow::bail!("Profile returned a matrix for an unknown model");
+    }
+
+    println!("Color matrix override replaced the decoded matrix");
+    Ok(())
+}
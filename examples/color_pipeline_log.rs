@@ -0,0 +1,110 @@
+//! Checks the `log_color_pipeline` diagnostics of debayered conversions.
+//!
+//! A recording subscriber layer captures every event while a synthetic 12-bit mosaic is
+//! converted to RGB with `log_color_pipeline` on and off. With it on, exactly one
+//! `info` event must carry the white balance multipliers, black and white levels and
+//! combined color matrix the debayer applies; with it off, none may. Exits non-zero
+//! otherwise.
+//!
+//! Run with `cargo run --example color_pipeline_log`.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawToTiffPipeline,
+    compute_color_matrix,
+};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+/// Recorded fields of each `Color pipeline` event, including its `level`
+type Events = Arc<Mutex<Vec<BTreeMap<String, String>>>>;
+
+struct Recorder(Events);
+
+struct FieldVisitor<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Recorder {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = BTreeMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        if fields.get("message").map(String::as_str) == Some("Color pipeline") {
+            fields.insert("level".to_string(), event.metadata().level().to_string());
+            self.0.lock().unwrap().push(fields);
+        }
+    }
+}
+
+fn gradient_raw() -> RawImageData {
+    let (width, height) = (64, 48);
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height)
+            .map(|i| ((i % width) * 50 + (i / width) * 20 + 256) as u16)
+            .collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+/// `Color pipeline` events logged while converting `gradient_raw` with `config`
+fn logged(config: ConversionConfig) -> anyhow::Result<Vec<BTreeMap<String, String>>> {
+    let pipeline = RawToTiffPipeline::new(config)?;
+    let events = Events::default();
+    let subscriber = tracing_subscriber::registry().with(Recorder(events.clone()));
+    tracing::subscriber::with_default(subscriber, || {
+        pipeline.convert_raw_image(gradient_raw(), &mut Vec::<u8>::new())
+    })?;
+    let events = events.lock().unwrap().clone();
+    Ok(events)
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .log_color_pipeline(true)
+        .build();
+    let raw = gradient_raw();
+    let expected = BTreeMap::from([
+        ("black_level".to_string(), format!("{:?}", 256.0f64)),
+        ("level".to_string(), Level::INFO.to_string()),
+        (
+            "matrix".to_string(),
+            format!("{:?}", compute_color_matrix(&config, &raw)),
+        ),
+        ("message".to_string(), "Color pipeline".to_string()),
+        (
+            "white_balance".to_string(),
+            format!("{:?}", config.white_balance.multipliers(&raw)),
+        ),
+        ("white_level".to_string(), format!("{:?}", 4095.0f64)),
+    ]);
+
+    let events = logged(config)?;
+    match events.as_slice() {
+        [fields] if *fields == expected => println!("Logged: {:?}", fields),
+        [fields] => anyhow::bail!("Color pipeline logged {:?}, expected {:?}", fields, expected),
+        _ => anyhow::bail!("Color pipeline logged {} times, expected once", events.len()),
+    }
+
+    let quiet = logged(ConversionConfig::builder().output(OutputMode::Rgb).build())?;
+    if !quiet.is_empty() {
+        anyhow::bail!("Color pipeline logged {} times with log_color_pipeline off", quiet.len());
+    }
+
+    println!("log_color_pipeline reports the debayer's white balance, levels and matrix");
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! Checks that `ConversionConfig::validate` rejects impossible settings up front.
+//!
+//! Each invalid config must fail `validate` with `InvalidConfig`, and so must building a
+//! pipeline with it, before any image is read. A config combining many valid non-default
+//! settings must pass both. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example config_validate`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, OutputMode, RawToTiffPipeline, TiffCompression,
+};
+
+fn main() -> anyhow::Result<()> {
+    let invalid = [
+        ("predictor 4", ConversionConfig::builder().predictor(Some(4)).build()),
+        ("zero GPU concurrency", ConversionConfig::builder().max_gpu_concurrency(0).build()),
+        ("zero max_dimension", ConversionConfig::builder().max_dimension(Some(0)).build()),
+        ("zero-row bands", ConversionConfig::builder().cpu_band_height(Some(0)).build()),
+        ("NaN exposure", ConversionConfig::builder().exposure(f32::NAN).build()),
+        ("negative saturation", ConversionConfig::builder().saturation(-0.5).build()),
+        ("black_clip of 1.0", ConversionConfig::builder().black_clip(1.0).build()),
+        ("zero chroma sigma", ConversionConfig::builder().chroma_denoise(Some(0.0)).build()),
+    ];
+    for (name, config) in invalid {
+        match config.validate() {
+            Err(ConversionError::InvalidConfig(message)) => println!("{}: {}", name, message),
+            Err(e) => anyhow::bail!("{}: failed with {}, expected InvalidConfig", name, e),
+            Ok(()) => anyhow::bail!("{}: validate accepted it", name),
+        }
+        match RawToTiffPipeline::new(config) {
+            Err(ConversionError::InvalidConfig(_)) => {}
+            Err(e) => anyhow::bail!("{}: pipeline failed with {}, expected InvalidConfig", name, e),
+            Ok(_) => anyhow::bail!("{}: pipeline was built", name),
+        }
+    }
+
+    let valid = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .output_float(true)
+        .compression(TiffCompression::Lzw)
+        .predictor(Some(2))
+        .max_gpu_concurrency(2)
+        .cpu_band_height(Some(64))
+        .black_clip(0.05)
+        .saturation(0.0)
+        .chroma_denoise(Some(1.5))
+        .build();
+    valid.validate()?;
+    RawToTiffPipeline::new(valid)?;
+
+    println!("Invalid configs are rejected at construction, valid ones pass");
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! Checks `contact_sheet` tile placement and downsizing.
+//!
+//! Four flat-colored 2x2 tiles on a 2-column sheet must land in reading order in a 4x4
+//! image. A fifth tile must start a third row whose empty second cell stays black, and a
+//! 512x256 frame must be box-filtered to a 256x128 tile. Frames with a zero width or
+//! height must leave their cells empty. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example contact_sheet`.
+
+use ffed_protosat_rs::image_pipeline::{CONTACT_SHEET_TILE_EDGE, ExifMetadata, RgbImageData, contact_sheet};
+
+/// `width`x`height` image filled with `rgb`
+fn flat(width: usize, height: usize, rgb: [u16; 3]) -> RgbImageData {
+    RgbImageData {
+        width,
+        height,
+        data: rgb.repeat(width * height),
+        bits_per_sample: 16,
+        exif: ExifMetadata::default(),
+    }
+}
+
+fn pixel(image: &RgbImageData, x: usize, y: usize) -> [u16; 3] {
+    let at = (y * image.width + x) * 3;
+    [image.data[at], image.data[at + 1], image.data[at + 2]]
+}
+
+fn main() -> anyhow::Result<()> {
+    let colors = [[1000, 0, 0], [0, 2000, 0], [0, 0, 3000], [4000, 4000, 4000], [5000, 6000, 7000]];
+    let tiles: Vec<RgbImageData> = colors.iter().map(|&rgb| flat(2, 2, rgb)).collect();
+
+    let sheet = contact_sheet(&tiles[..4], 2);
+    if (sheet.width, sheet.height) != (4, 4) || sheet.data.len() != 4 * 4 * 3 {
+        anyhow::bail!("Sheet of four 2x2 tiles is {}x{}, expected 4x4", sheet.width, sheet.height);
+    }
+    for y in 0..4 {
+        for x in 0..4 {
+            let expected = colors[y / 2 * 2 + x / 2];
+            if pixel(&sheet, x, y) != expected {
+                anyhow::bail!("Pixel ({}, {}) is {:?}, expected {:?}", x, y, pixel(&sheet, x, y), expected);
+            }
+        }
+    }
+    println!("Four tiles placed in a 2x2 grid");
+
+    let sheet = contact_sheet(&tiles, 2);
+    if (sheet.width, sheet.height) != (4, 6) {
+        anyhow::bail!("Sheet of five tiles is {}x{}, expected 4x6", sheet.width, sheet.height);
+    }
+    for (x, y, expected) in [(0, 4, colors[4]), (1, 5, colors[4]), (2, 4, [0; 3]), (3, 5, [0; 3])] {
+        if pixel(&sheet, x, y) != expected {
+            anyhow::bail!("Last row pixel ({}, {}) is {:?}, expected {:?}", x, y, pixel(&sheet, x, y), expected);
+        }
+    }
+    println!("Partial last row padded with black");
+
+    let sheet = contact_sheet(&[flat(512, 256, [100, 200, 300])], 3);
+    if (sheet.width, sheet.height) != (CONTACT_SHEET_TILE_EDGE, CONTACT_SHEET_TILE_EDGE / 2) {
+        anyhow::bail!("512x256 frame became a {}x{} sheet, expected 256x128", sheet.width, sheet.height);
+    }
+    if sheet.data.chunks_exact(3).any(|px| px != [100, 200, 300]) {
+        anyhow::bail!("Downsized flat frame is not flat");
+    }
+    println!("Large frame downsized to {}x{}", sheet.width, sheet.height);
+
+    let sheet = contact_sheet(&[flat(0, 8, [0; 3]), flat(2, 2, colors[0]), flat(8, 0, [0; 3])], 3);
+    if (sheet.width, sheet.height) != (6, 2) || pixel(&sheet, 2, 1) != colors[0] {
+        anyhow::bail!("Sheet with zero-sized frames is {}x{}, expected 6x2 with the 2x2 tile in the middle", sheet.width, sheet.height);
+    }
+    println!("Zero-sized frames left as empty cells");
+
+    println!("Contact sheet tiles placed correctly");
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! Checks that `convert_both` writes a valid Bayer TIFF and a valid RGB TIFF from one decode.
+//!
+//! A synthetic reader stands in for rawloader and counts how often it is called. Both
+//! outputs must decode as TIFFs of the frame's dimensions, grayscale and RGB respectively,
+//! and the input must have been decoded exactly once. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example convert_both`.
+
+use std::io::Cursor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, StandardTiffWriter,
+};
+use tiff::ColorType;
+use tiff::decoder::Decoder;
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+
+/// Calls to `SyntheticReader::read_raw`
+static READS: AtomicUsize = AtomicUsize::new(0);
+
+/// Ignores the input bytes and returns a small 12-bit RGGB gradient
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        READS.fetch_add(1, Ordering::Relaxed);
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: (0..WIDTH * HEIGHT).map(|i| (i % 3840 + 256) as u16).collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+fn check(name: &str, encoded: Vec<u8>, expected: ColorType) -> anyhow::Result<()> {
+    let mut decoder = Decoder::new(Cursor::new(encoded))?;
+    let dimensions = decoder.dimensions()?;
+    let color_type = decoder.colortype()?;
+    decoder.read_image()?;
+
+    if dimensions != (WIDTH as u32, HEIGHT as u32) || color_type != expected {
+        anyhow::bail!(
+            "{} output is {}x{} {:?}, expected {}x{} {:?}",
+            name, dimensions.0, dimensions.1, color_type, WIDTH, HEIGHT, expected
+        );
+    }
+    println!("{}: {}x{} {:?}", name, dimensions.0, dimensions.1, color_type);
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::builder().output(OutputMode::Rgb).build();
+    let pipeline = RawToTiffPipeline::with_custom(SyntheticReader, StandardTiffWriter, config)?;
+
+    let mut bayer = Vec::new();
+    let mut rgb = Vec::new();
+    pipeline.convert_both(&[], &mut bayer, &mut rgb)?;
+
+    check("Bayer", bayer, ColorType::Gray(16))?;
+    check("RGB", rgb, ColorType::RGB(16))?;
+
+    let reads = READS.load(Ordering::Relaxed);
+    if reads != 1 {
+        anyhow::bail!("Input was decoded {} times, expected once", reads);
+    }
+
+    println!("Both outputs written from a single decode");
+    Ok(())
+}
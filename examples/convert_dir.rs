@@ -0,0 +1,124 @@
+//! Checks that `convert_dir` converts matching files and skips the ones that fail.
+//!
+//! A temp dir holds mock raws (`a.arw`, `B.NEF`, the dotted `c.v1.arw` and `c.v2.arw`,
+//! and `e.arw` and `e.nef` sharing a stem), a truncated `bad.arw`, an unrelated
+//! `notes.txt` and a subdirectory. Converting with `*.arw` and `*.nef` must write one TIFF
+//! per stem with the mock dimensions, keeping the dotted stems whole, report `bad.arw` and
+//! the second `e` as failed without aborting, ignore everything else, and reject an
+//! invalid pattern. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example convert_dir`.
+
+use std::path::PathBuf;
+
+use tiff::decoder::Decoder;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, OutputMode, RawImageData,
+    RawImageReader, RawToTiffPipeline, Result, StandardTiffWriter,
+};
+
+/// Decodes `[width: u16, height: u16, samples: u16...]`, all little-endian
+struct HeaderReader;
+
+impl RawImageReader for HeaderReader {
+    fn read_raw(&self, data: &[u8]) -> Result<RawImageData> {
+        let mut words = data
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]));
+        let (Some(width), Some(height)) = (words.next(), words.next()) else {
+            return Err(ConversionError::DecodeError("Missing header".to_string()));
+        };
+        Ok(RawImageData {
+            width: width as usize,
+            height: height as usize,
+            data: words.collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            cam_to_xyz: [[0.0; 4]; 3],
+            ..Default::default()
+        })
+    }
+}
+
+/// Mock raw of the given size in `HeaderReader`'s layout
+fn mock_raw(width: u16, height: u16) -> Vec<u8> {
+    [width, height]
+        .into_iter()
+        .chain((0..width as usize * height as usize).map(|i| (256 + (i * 7) % 3840) as u16))
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+fn main() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let input_dir = dir.path().join("raw");
+    let output_dir = dir.path().join("tiff");
+    std::fs::create_dir_all(input_dir.join("nested.arw"))?;
+    std::fs::write(input_dir.join("a.arw"), mock_raw(32, 24))?;
+    std::fs::write(input_dir.join("B.NEF"), mock_raw(16, 8))?;
+    std::fs::write(input_dir.join("c.v1.arw"), mock_raw(8, 4))?;
+    std::fs::write(input_dir.join("c.v2.arw"), mock_raw(4, 8))?;
+    std::fs::write(input_dir.join("e.arw"), mock_raw(6, 6))?;
+    std::fs::write(input_dir.join("e.nef"), mock_raw(10, 10))?;
+    std::fs::write(input_dir.join("bad.arw"), [0x42; 3])?;
+    std::fs::write(input_dir.join("notes.txt"), mock_raw(4, 4))?;
+
+    let config = ConversionConfig::builder()
+        .output(OutputMode::BayerGray)
+        .build();
+    let pipeline = RawToTiffPipeline::with_custom(HeaderReader, StandardTiffWriter, config)?;
+    let summary = pipeline.convert_dir(&input_dir, &output_dir, &["*.arw", "*.nef"])?;
+
+    println!("Converted: {:?}", summary.converted);
+    for (input, e) in &summary.failed {
+        println!("Failed: {} ({})", input.display(), e);
+    }
+
+    let expected: Vec<PathBuf> = ["B.tiff", "a.tiff", "c.v1.tiff", "c.v2.tiff", "e.tiff"]
+        .iter()
+        .map(|name| output_dir.join(name))
+        .collect();
+    if summary.converted != expected {
+        anyhow::bail!("Expected {:?} to convert", expected);
+    }
+    let mut failed: Vec<_> = summary
+        .failed
+        .iter()
+        .map(|(input, _)| input.clone())
+        .collect();
+    failed.sort();
+    if failed != [input_dir.join("bad.arw"), input_dir.join("e.nef")] || summary.all_succeeded() {
+        anyhow::bail!("Expected only bad.arw and e.nef to fail");
+    }
+
+    for (path, dimensions) in expected.iter().zip([(16, 8), (32, 24), (8, 4), (4, 8), (6, 6)]) {
+        let mut decoder = Decoder::new(std::fs::File::open(path)?)?;
+        if decoder.dimensions()? != dimensions {
+            anyhow::bail!(
+                "{} is {:?}, expected {:?}",
+                path.display(),
+                decoder.dimensions()?,
+                dimensions
+            );
+        }
+    }
+    if ["notes.tiff", "nested.tiff"]
+        .iter()
+        .any(|name| output_dir.join(name).exists())
+    {
+        anyhow::bail!("A file outside the filter was converted");
+    }
+
+    if pipeline
+        .convert_dir(&input_dir, &output_dir, &["[arw"])
+        .is_ok()
+    {
+        anyhow::bail!("An invalid pattern was accepted");
+    }
+
+    println!("Matching files converted, bad input skipped");
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Checks `RawToTiffPipeline::convert_to_vec`.
+//!
+//! A synthetic frame is converted in memory. The buffer must start with the little-endian
+//! TIFF header `II*\0`, match what `convert` writes for the same input, and decode to
+//! the frame's dimensions. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example convert_to_vec`.
+
+use std::io::Cursor;
+
+use tiff::decoder::Decoder;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader, RawToTiffPipeline, Result,
+    StandardTiffWriter,
+};
+
+const WIDTH: usize = 24;
+const HEIGHT: usize = 16;
+
+/// Returns a 12-bit gradient mosaic
+struct GradientReader;
+
+impl RawImageReader for GradientReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: (0..WIDTH * HEIGHT).map(|i| 256 + (i * 11 % 3800) as u16).collect(),
+            bits_per_sample: 12,
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    for output in [OutputMode::BayerGray, OutputMode::Rgb] {
+        let config = ConversionConfig::builder().output(output).build();
+        let pipeline = RawToTiffPipeline::with_custom(GradientReader, StandardTiffWriter, config)?;
+
+        let tiff = pipeline.convert_to_vec(&[])?;
+        if !tiff.starts_with(b"II*\0") {
+            anyhow::bail!("{:?} output starts with {:?}, expected II*\\0", output, &tiff[..tiff.len().min(4)]);
+        }
+
+        let mut written = Vec::new();
+        pipeline.convert(&[], &mut written)?;
+        if written != tiff {
+            anyhow::bail!("{:?}: convert_to_vec differs from convert", output);
+        }
+
+        let (width, height) = Decoder::new(Cursor::new(&tiff))?.dimensions()?;
+        if (width as usize, height as usize) != (WIDTH, HEIGHT) {
+            anyhow::bail!("{:?} output is {}x{}, expected {}x{}", output, width, height, WIDTH, HEIGHT);
+        }
+        println!("{:?}: {} bytes, {}x{}", output, tiff.len(), width, height);
+    }
+
+    println!("convert_to_vec returns the TIFF convert writes");
+    Ok(())
+}
@@ -0,0 +1,84 @@
+//! Checks that the CPU debayer opens tracing spans for its demosaic and color phases.
+//!
+//! A recording subscriber layer captures every new span while a synthetic frame is
+//! debayered. `cpu_demosaic` and `cpu_color_correction` must each fire once with the
+//! frame's `width` and `height` and the configured `algorithm`. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example cpu_debayer_spans`.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use ffed_protosat_rs::image_pipeline::{
+    BorderMode, ConversionConfig, CpuDebayer, DebayerQuality, RawImageData,
+};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+/// Name and recorded fields of each span opened
+type Spans = Arc<Mutex<Vec<(String, BTreeMap<String, String>)>>>;
+
+struct Recorder(Spans);
+
+struct FieldVisitor<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Recorder {
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        let mut fields = BTreeMap::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        self.0.lock().unwrap().push((attrs.metadata().name().to_string(), fields));
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let (width, height) = (64, 48);
+    let raw = RawImageData {
+        width,
+        height,
+        data: (0..width * height).map(|i| (i % 3840 + 256) as u16).collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    };
+    let quality = DebayerQuality::MalvarHeCutler;
+    // Replicate and Mirror pad the mosaic before demosaicing, so only with Crop do the
+    // spans see the frame's own dimensions
+    let config = ConversionConfig::builder()
+        .debayer_quality(quality)
+        .border_mode(BorderMode::Crop)
+        .build();
+    let debayer = CpuDebayer::with_config(&config)?;
+
+    let spans = Spans::default();
+    let subscriber = tracing_subscriber::registry().with(Recorder(spans.clone()));
+    tracing::subscriber::with_default(subscriber, || debayer.process(&raw))?;
+
+    let spans = spans.lock().unwrap();
+    let expected = BTreeMap::from([
+        ("algorithm".to_string(), format!("{:?}", quality)),
+        ("height".to_string(), height.to_string()),
+        ("width".to_string(), width.to_string()),
+    ]);
+    for name in ["cpu_demosaic", "cpu_color_correction"] {
+        let fired: Vec<_> = spans.iter().filter(|(span, _)| span == name).collect();
+        match fired.as_slice() {
+            [(_, fields)] if *fields == expected => println!("{}: {:?}", name, fields),
+            [(_, fields)] => anyhow::bail!("{} recorded {:?}, expected {:?}", name, fields, expected),
+            _ => anyhow::bail!("{} fired {} times, expected once", name, fired.len()),
+        }
+    }
+
+    println!("CPU debayer spans fire with frame dimensions and algorithm");
+    Ok(())
+}
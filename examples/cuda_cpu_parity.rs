@@ -0,0 +1,98 @@
+//! Cross-checks the bilinear CUDA kernel (`debayer16_to_xyz`) against `CpuDebayer`.
+//!
+//! Both paths run the same synthetic RGGB frame and the per-channel mean absolute
+//! difference is compared against `TOLERANCE`. Exits non-zero when the two paths diverge.
+//!
+//! Expected tolerance: both sides are bilinear, but the kernel normalizes each sample
+//! before interpolating while the CPU path interpolates raw values and normalizes after,
+//! so clipping at the black level and f32 rounding differ slightly. On smooth content
+//! this stays well under 1% of full scale. The kernel skips a 1-pixel border, so only
+//! the interior is compared.
+//!
+//! Run on a Jetson with `cargo run --release --example cuda_cpu_parity`.
+
+#[cfg(jetson_cuda)]
+use ffed_protosat_rs::image_pipeline::{
+    CpuDebayer, CudaDebayer, ExifMetadata, Orientation, RawImageData,
+};
+
+/// Maximum allowed per-channel mean absolute difference, in 16-bit output units (~1%)
+#[cfg(jetson_cuda)]
+const TOLERANCE: f64 = 655.0;
+
+#[cfg(jetson_cuda)]
+fn synthetic_raw(width: usize, height: usize) -> RawImageData {
+    const BLACK: u16 = 256;
+    const WHITE: u16 = 4095;
+
+    // Smooth per-channel gradients so interpolation differences stay small
+    let data = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let fx = x as f32 / width as f32;
+            let fy = y as f32 / height as f32;
+            let level = match (y % 2, x % 2) {
+                (0, 0) => 0.2 + 0.5 * fx,
+                (1, 1) => 0.2 + 0.5 * fy,
+                _ => 0.3 + 0.3 * (fx + fy) / 2.0,
+            };
+            BLACK + (level * (WHITE - BLACK) as f32) as u16
+        })
+        .collect();
+
+    RawImageData {
+        width,
+        height,
+        data,
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [BLACK; 4],
+        whitelevels: [WHITE; 4],
+        // Linear sRGB to XYZ (D65), so the camera space is sRGB itself
+        cam_to_xyz: [
+            [0.4124564, 0.3575761, 0.1804375, 0.0],
+            [0.2126729, 0.7151522, 0.0721750, 0.0],
+            [0.0193339, 0.1191920, 0.9503041, 0.0],
+        ],
+        ..Default::default()
+    }
+}
+
+#[cfg(jetson_cuda)]
+fn main() -> anyhow::Result<()> {
+    let raw = synthetic_raw(256, 192);
+
+    let cpu = CpuDebayer::new()?.process(&raw)?;
+    let gpu = CudaDebayer::new()?.process(&raw)?;
+
+    let mut sums = [0.0f64; 3];
+    let mut count = 0usize;
+    for y in 1..raw.height - 1 {
+        for x in 1..raw.width - 1 {
+            let idx = (y * raw.width + x) * 3;
+            for (c, sum) in sums.iter_mut().enumerate() {
+                *sum += (cpu.data[idx + c] as f64 - gpu.data[idx + c] as f64).abs();
+            }
+            count += 1;
+        }
+    }
+
+    let mut diverged = false;
+    for (name, sum) in ["R", "G", "B"].iter().zip(sums) {
+        let mad = sum / count as f64;
+        println!("{}: mean abs diff {:.2} (tolerance {:.2})", name, mad, TOLERANCE);
+        diverged |= mad > TOLERANCE;
+    }
+
+    if diverged {
+        anyhow::bail!("CUDA and CPU debayer outputs diverge beyond tolerance");
+    }
+
+    println!("CUDA and CPU debayer agree within tolerance");
+    Ok(())
+}
+
+#[cfg(not(jetson_cuda))]
+fn main() {
+    println!("CUDA parity check requires a Jetson build (jetson_cuda), skipping.");
+}
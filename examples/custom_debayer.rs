@@ -0,0 +1,86 @@
+//! Checks that a custom `Debayer` plugged into the pipeline replaces the built-in one.
+//!
+//! A fake debayer fills every pixel with one fixed color. Converting a synthetic frame
+//! to RGB through `RawToTiffPipeline::with_debayer` must yield a TIFF holding only that
+//! color, and `debayer_only` must return it unchanged. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example custom_debayer`.
+
+use std::io::Cursor;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, Debayer, OutputMode, RawImageData,
+    RawImageReader, RawToTiffPipeline, Result, RgbImageData, StandardTiffWriter,
+};
+use tiff::decoder::{Decoder, DecodingResult};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 24;
+const COLOR: [u16; 3] = [60000, 1234, 32768];
+
+/// Ignores the input bytes and returns a small 12-bit RGGB gradient
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: (0..WIDTH * HEIGHT).map(|i| (i % 3840 + 256) as u16).collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            cam_to_xyz: [[0.0; 4]; 3],
+            ..Default::default()
+        })
+    }
+}
+
+/// Returns a frame of `COLOR` with the input's dimensions
+struct FixedColorDebayer;
+
+impl Debayer for FixedColorDebayer {
+    fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        Ok(RgbImageData {
+            width: raw_image.width,
+            height: raw_image.height,
+            data: COLOR.repeat(raw_image.width * raw_image.height),
+            bits_per_sample: 16,
+            exif: raw_image.exif,
+        })
+    }
+}
+
+fn check_pixels(name: &str, data: &[u16]) -> anyhow::Result<()> {
+    if data.len() != WIDTH * HEIGHT * 3 {
+        anyhow::bail!("{} holds {} samples, expected {}", name, data.len(), WIDTH * HEIGHT * 3);
+    }
+    if let Some(pixel) = data.chunks_exact(3).find(|pixel| *pixel != COLOR) {
+        anyhow::bail!("{} has pixel {:?}, expected {:?}", name, pixel, COLOR);
+    }
+    println!("{}: every pixel is {:?}", name, COLOR);
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::builder().output(OutputMode::Rgb).build();
+    let pipeline = RawToTiffPipeline::with_debayer(
+        SyntheticReader,
+        StandardTiffWriter,
+        Box::new(FixedColorDebayer),
+        config,
+    )?;
+
+    let encoded = pipeline.convert_to_vec(&[])?;
+    let mut decoder = Decoder::new(Cursor::new(encoded))?;
+    match decoder.read_image()? {
+        DecodingResult::U16(data) => check_pixels("TIFF output", &data)?,
+        _ => anyhow::bail!("TIFF output is not 16-bit"),
+    }
+
+    check_pixels("debayer_only", &pipeline.debayer_only(&[])?.data)?;
+
+    println!("Custom debayer replaced the built-in one");
+    Ok(())
+}
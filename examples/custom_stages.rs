@@ -0,0 +1,161 @@
+//! Checks that custom stages added through `PipelineBuilder` run in the order they were added.
+//!
+//! Two stages each log their name into a shared journal and change the image in a way
+//! that does not commute: one adds an offset, the other doubles every sample. Running
+//! `debayer_only` on a synthetic frame must log both raw hooks and then both RGB hooks
+//! in order, and the pixels must match offset-then-double. A failing stage must
+//! surface as a `custom stage` error, and stages combined with `output_float` RGB output
+//! must fail with `InvalidConfig`, both at build time and for a per-call config. Exits
+//! non-zero otherwise.
+//!
+//! Run with `cargo run --example custom_stages`.
+
+use std::sync::{Arc, Mutex};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, Debayer, OutputMode,
+    PipelineBuilder, PipelineStage, RawImageData, RawImageReader, Result, RgbImageData, Stage,
+};
+
+const WIDTH: usize = 16;
+const HEIGHT: usize = 12;
+const COLOR: [u16; 3] = [1000, 2000, 3000];
+const OFFSET: u16 = 100;
+
+type Journal = Arc<Mutex<Vec<String>>>;
+
+/// Ignores the input bytes and returns a flat 12-bit RGGB frame
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: vec![1024; WIDTH * HEIGHT],
+            bits_per_sample: 12,
+            whitelevels: [4095; 4],
+            cam_to_xyz: [[0.0; 4]; 3],
+            ..Default::default()
+        })
+    }
+}
+
+/// Returns a frame of `COLOR`, so the stages are the only thing changing the pixels
+struct FixedColorDebayer;
+
+impl Debayer for FixedColorDebayer {
+    fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        Ok(RgbImageData {
+            width: raw_image.width,
+            height: raw_image.height,
+            data: COLOR.repeat(raw_image.width * raw_image.height),
+            bits_per_sample: 16,
+            exif: raw_image.exif,
+        })
+    }
+}
+
+struct AddOffset(Journal);
+
+impl Stage for AddOffset {
+    fn apply_raw(&self, _image: &mut RawImageData) -> Result<()> {
+        self.0.lock().unwrap().push("offset raw".to_string());
+        Ok(())
+    }
+
+    fn apply(&self, image: &mut RgbImageData) -> Result<()> {
+        self.0.lock().unwrap().push("offset rgb".to_string());
+        image.data.iter_mut().for_each(|v| *v += OFFSET);
+        Ok(())
+    }
+}
+
+struct Double(Journal);
+
+impl Stage for Double {
+    fn apply_raw(&self, _image: &mut RawImageData) -> Result<()> {
+        self.0.lock().unwrap().push("double raw".to_string());
+        Ok(())
+    }
+
+    fn apply(&self, image: &mut RgbImageData) -> Result<()> {
+        self.0.lock().unwrap().push("double rgb".to_string());
+        image.data.iter_mut().for_each(|v| *v *= 2);
+        Ok(())
+    }
+}
+
+struct Failing;
+
+impl Stage for Failing {
+    fn apply_raw(&self, _image: &mut RawImageData) -> Result<()> {
+        Err(ConversionError::DecodeError(
+            "rejected by stage".to_string(),
+        ))
+    }
+}
+
+fn config() -> ConversionConfig {
+    ConversionConfig::builder().output(OutputMode::Rgb).build()
+}
+
+fn main() -> anyhow::Result<()> {
+    let journal = Journal::default();
+    let pipeline = PipelineBuilder::new(config())
+        .reader(SyntheticReader)
+        .debayer(Box::new(FixedColorDebayer))
+        .stage(AddOffset(journal.clone()))
+        .stage(Double(journal.clone()))
+        .build()?;
+
+    let rgb = pipeline.debayer_only(&[])?;
+
+    let order = journal.lock().unwrap().clone();
+    let expected_order = ["offset raw", "double raw", "offset rgb", "double rgb"];
+    if order != expected_order {
+        anyhow::bail!("stages ran as {:?}, expected {:?}", order, expected_order);
+    }
+
+    let expected: Vec<u16> = COLOR.map(|v| (v + OFFSET) * 2).repeat(WIDTH * HEIGHT);
+    if rgb.data != expected {
+        anyhow::bail!(
+            "first pixel is {:?}, expected {:?} from offset then double",
+            &rgb.data[..3],
+            &expected[..3]
+        );
+    }
+    println!("stages ran in order: {}", order.join(", "));
+
+    let failing = PipelineBuilder::new(config())
+        .reader(SyntheticReader)
+        .debayer(Box::new(FixedColorDebayer))
+        .stage(Failing)
+        .build()?;
+    match failing.debayer_only(&[]) {
+        Err(e) if e.stage() == Some(PipelineStage::Custom) => println!("failing stage: {}", e),
+        Err(e) => anyhow::bail!("failing stage reported at the wrong stage: {}", e),
+        Ok(_) => anyhow::bail!("failing stage did not abort the conversion"),
+    }
+
+    let float_config = ConversionConfig::builder().output(OutputMode::Rgb).output_float(true).build();
+    let built = PipelineBuilder::new(float_config.clone())
+        .reader(SyntheticReader)
+        .debayer(Box::new(FixedColorDebayer))
+        .stage(Double(Journal::default()))
+        .build();
+    match built {
+        Err(ConversionError::InvalidConfig(msg)) => println!("float output with stages: {}", msg),
+        Err(e) => anyhow::bail!("float output with stages failed with the wrong error: {}", e),
+        Ok(_) => anyhow::bail!("float output with stages was accepted at build time"),
+    }
+    let mut out = Vec::new();
+    match pipeline.convert_with_config(&[], &mut out, &float_config) {
+        Err(ConversionError::InvalidConfig(msg)) => println!("per-call float output with stages: {}", msg),
+        Err(e) => anyhow::bail!("per-call float output with stages failed with the wrong error: {}", e),
+        Ok(()) => anyhow::bail!("per-call float output with stages was converted without the stages"),
+    }
+
+    println!("OK");
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! Checks that the pipeline converts buffers that fit their dimensions and rejects the rest.
+//!
+//! A Bayer mosaic holds one sample per pixel and an already-RGB raw three. Each must
+//! convert when its buffer holds exactly that many samples, the mosaic to grayscale and
+//! RGB output and the RGB raw to RGB output, and fail with `DataLengthMismatch` when it
+//! is one sample short, one sample long, or sized for the other layout. Exits non-zero
+//! otherwise.
+//!
+//! Run with `cargo run --example data_length`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, OutputMode, RawImageData, RawToTiffPipeline, Result,
+};
+
+const WIDTH: usize = 16;
+const HEIGHT: usize = 12;
+
+fn frame(is_bayer: bool, samples: usize) -> RawImageData {
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..samples).map(|i| (i % 3840 + 256) as u16).collect(),
+        is_bayer,
+        bits_per_sample: 12,
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+fn convert(raw: RawImageData, output: OutputMode) -> Result<Vec<u8>> {
+    let config = ConversionConfig::builder().output(output).build();
+    let mut tiff = Vec::new();
+    RawToTiffPipeline::new(config)?.convert_raw_image(raw, &mut tiff)?;
+    Ok(tiff)
+}
+
+fn main() -> anyhow::Result<()> {
+    let pixels = WIDTH * HEIGHT;
+
+    for (layout, is_bayer, samples_per_pixel, outputs) in [
+        ("Bayer", true, 1, &[OutputMode::BayerGray, OutputMode::Rgb][..]),
+        ("RGB", false, 3, &[OutputMode::Rgb][..]),
+    ] {
+        let expected = pixels * samples_per_pixel;
+        let other_layout = pixels * (4 - samples_per_pixel);
+
+        for &output in outputs {
+            let tiff = convert(frame(is_bayer, expected), output)?;
+            if tiff.is_empty() {
+                anyhow::bail!("{} raw of {} samples wrote no {:?} output", layout, expected, output);
+            }
+            println!("{} raw of {} samples: {:?} output of {} bytes", layout, expected, output, tiff.len());
+
+            for actual in [expected - 1, expected + 1, other_layout] {
+                match convert(frame(is_bayer, actual), output) {
+                    Err(e) => match e.without_stage() {
+                        &ConversionError::DataLengthMismatch {
+                            expected: reported_expected,
+                            actual: reported_actual,
+                        } if (reported_expected, reported_actual) == (expected, actual) => {}
+                        other => anyhow::bail!(
+                            "{} raw of {} samples: expected DataLengthMismatch, got {}",
+                            layout,
+                            actual,
+                            other
+                        ),
+                    },
+                    Ok(_) => anyhow::bail!(
+                        "{} raw of {} samples converted to {:?}, expected {}",
+                        layout,
+                        actual,
+                        output,
+                        expected
+                    ),
+                }
+            }
+        }
+    }
+
+    println!("Buffers are accepted exactly when they fit their dimensions");
+    Ok(())
+}
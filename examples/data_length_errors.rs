@@ -0,0 +1,88 @@
+//! Checks that dimension and buffer-length failures are reported as different errors.
+//!
+//! Converting a raw with a zero width or one above `max_dimension` must fail validation
+//! with `InvalidDimensions`, while a raw whose buffer is shorter or longer than its
+//! dimensions must fail with `DataLengthMismatch` carrying the expected and actual
+//! sample counts. `stack_average` must make the same distinction. Exits non-zero
+//! otherwise.
+//!
+//! Run with `cargo run --example data_length_errors`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, PipelineStage, RawImageData,
+    RawToTiffPipeline, Result, stack_average,
+};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 24;
+
+fn mosaic(width: usize, height: usize, samples: usize) -> RawImageData {
+    RawImageData {
+        width,
+        height,
+        data: (0..samples).map(|i| (i % 3840 + 256) as u16).collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    }
+}
+
+fn convert(raw: RawImageData) -> Result<()> {
+    let config = ConversionConfig::builder().max_dimension(Some(64)).build();
+    RawToTiffPipeline::new(config)?.convert_raw_image(raw, &mut Vec::new())
+}
+
+fn main() -> anyhow::Result<()> {
+    for (name, raw) in [
+        ("zero width", mosaic(0, HEIGHT, 0)),
+        ("above max_dimension", mosaic(128, HEIGHT, 128 * HEIGHT)),
+    ] {
+        match convert(raw) {
+            Err(e)
+                if e.stage() == Some(PipelineStage::Validate)
+                    && matches!(e.without_stage(), ConversionError::InvalidDimensions(..)) =>
+            {
+                println!("{}: {}", name, e)
+            }
+            Err(e) => anyhow::bail!("{}: expected InvalidDimensions, got {}", name, e),
+            Ok(()) => anyhow::bail!("{}: converted", name),
+        }
+    }
+
+    let expected = WIDTH * HEIGHT;
+    for (name, actual) in [
+        ("short buffer", expected - 7),
+        ("long buffer", expected + 32),
+    ] {
+        match convert(mosaic(WIDTH, HEIGHT, actual)) {
+            Err(e) if e.stage() == Some(PipelineStage::Validate) => match e.without_stage() {
+                &ConversionError::DataLengthMismatch {
+                    expected: reported_expected,
+                    actual: reported_actual,
+                } if (reported_expected, reported_actual) == (expected, actual) => {
+                    println!("{}: {}", name, e)
+                }
+                other => anyhow::bail!("{}: expected DataLengthMismatch, got {}", name, other),
+            },
+            Err(e) => anyhow::bail!("{}: failed outside validation: {}", name, e),
+            Ok(()) => anyhow::bail!("{}: converted", name),
+        }
+    }
+
+    let frame = mosaic(WIDTH, HEIGHT, expected);
+    match stack_average(&[frame.clone(), mosaic(WIDTH, HEIGHT, expected - 1)]) {
+        Err(ConversionError::DataLengthMismatch { .. }) => {}
+        other => anyhow::bail!("Stacking a short frame gave {:?}", other.err()),
+    }
+    match stack_average(&[frame.clone(), mosaic(WIDTH, HEIGHT + 2, expected)]) {
+        Err(ConversionError::InvalidDimensions(..)) => {}
+        other => anyhow::bail!("Stacking a taller frame gave {:?}", other.err()),
+    }
+    println!("stack_average distinguishes length from dimension mismatches");
+
+    println!("OK");
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! Checks `RawToTiffPipeline::debayer_only`.
+//!
+//! Synthetic frames, one with odd dimensions, are debayered without writing. The RGB
+//! image must keep the frame's width and height, hold `width * height * 3` samples, and
+//! equal the pixels `convert` writes to a 16-bit RGB TIFF for the same input. Exits
+//! non-zero otherwise.
+//!
+//! Run with `cargo run --example debayer_only`.
+
+use std::io::Cursor;
+
+use tiff::decoder::{Decoder, DecodingResult};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader, RawToTiffPipeline, Result,
+    StandardTiffWriter,
+};
+
+/// Returns a 12-bit gradient mosaic of its dimensions
+struct GradientReader {
+    width: usize,
+    height: usize,
+}
+
+impl RawImageReader for GradientReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: self.width,
+            height: self.height,
+            data: (0..self.width * self.height).map(|i| 256 + (i * 7 % 3800) as u16).collect(),
+            bits_per_sample: 12,
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    for (width, height) in [(32, 24), (25, 17)] {
+        let config = ConversionConfig::builder().output(OutputMode::Rgb).build();
+        let pipeline = RawToTiffPipeline::with_custom(GradientReader { width, height }, StandardTiffWriter, config)?;
+
+        let rgb = pipeline.debayer_only(&[])?;
+        if (rgb.width, rgb.height) != (width, height) {
+            anyhow::bail!("{}x{} frame debayered to {}x{}", width, height, rgb.width, rgb.height);
+        }
+        if rgb.data.len() != width * height * 3 {
+            anyhow::bail!(
+                "{}x{} frame debayered to {} samples, expected {}",
+                width,
+                height,
+                rgb.data.len(),
+                width * height * 3
+            );
+        }
+
+        let mut decoder = Decoder::new(Cursor::new(pipeline.convert_to_vec(&[])?))?;
+        match decoder.read_image()? {
+            DecodingResult::U16(samples) if samples == rgb.data => {}
+            DecodingResult::U16(_) => anyhow::bail!("{}x{}: debayer_only differs from the converted TIFF", width, height),
+            _ => anyhow::bail!("{}x{}: converted TIFF is not 16-bit", width, height),
+        }
+        println!("{}x{}: {} samples, matching convert", width, height, rgb.data.len());
+    }
+
+    println!("debayer_only returns the image convert writes");
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! Checks that every `DebayerQuality` algorithm of `CpuDebayer` yields a full RGB frame.
+//!
+//! A 12-bit gradient mosaic, once with even and once with odd dimensions, is debayered
+//! with each algorithm. The 16-bit and float outputs must both keep the mosaic's width and
+//! height and hold `width * height * 3` samples. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example debayer_quality`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, CpuDebayer, DebayerQuality, RawImageData,
+};
+
+const QUALITIES: [DebayerQuality; 5] = [
+    DebayerQuality::NearestNeighbour,
+    DebayerQuality::Linear,
+    DebayerQuality::Cubic,
+    DebayerQuality::MalvarHeCutler,
+    DebayerQuality::EdgeDirected,
+];
+
+fn gradient(width: usize, height: usize) -> RawImageData {
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height).map(|i| 256 + (i * 13 % 3800) as u16).collect(),
+        bits_per_sample: 12,
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    for (width, height) in [(64, 48), (33, 21)] {
+        let raw = gradient(width, height);
+        let expected = width * height * 3;
+
+        for quality in QUALITIES {
+            let config = ConversionConfig::builder().debayer_quality(quality).build();
+            let debayer = CpuDebayer::with_config(&config)?;
+
+            let rgb = debayer.process(&raw)?;
+            if (rgb.width, rgb.height, rgb.data.len()) != (width, height, expected) {
+                anyhow::bail!(
+                    "{:?} on {}x{} gave a {}x{} image of {} samples, expected {}",
+                    quality,
+                    width,
+                    height,
+                    rgb.width,
+                    rgb.height,
+                    rgb.data.len(),
+                    expected
+                );
+            }
+            let rgb_f32 = debayer.process_f32(&raw)?;
+            if (rgb_f32.width, rgb_f32.height, rgb_f32.data.len()) != (width, height, expected) {
+                anyhow::bail!(
+                    "{:?} on {}x{} gave a {}x{} float image of {} samples, expected {}",
+                    quality,
+                    width,
+                    height,
+                    rgb_f32.width,
+                    rgb_f32.height,
+                    rgb_f32.data.len(),
+                    expected
+                );
+            }
+            println!("{:?} on {}x{}: {} samples", quality, width, height, rgb.data.len());
+        }
+    }
+
+    println!("Every demosaic quality returns width * height * 3 samples");
+    Ok(())
+}
@@ -0,0 +1,101 @@
+//! Benchmarks the debayer-enabled pipeline on the CPU backend across frame sizes.
+//!
+//! Synthetic frames go straight into `RawToTiffPipeline::convert_raw_image`, so the
+//! timings cover validation, debayer, the color pipeline and TIFF encoding without any
+//! RAW decoding. Each size and demosaic quality is converted `RUNS` times after a warm-up
+//! run, and the best time is reported as megapixels per second alongside the gray
+//! (non-debayer) path for reference. Exits non-zero if a conversion fails or writes
+//! nothing.
+//!
+//! Run with `cargo run --release --example debayer_throughput`.
+
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, DebayerBackend, DebayerQuality, OutputMode,
+    RawImageData, RawLoaderReader, RawToTiffPipeline, StandardTiffWriter,
+};
+
+const SIZES: [(usize, usize); 3] = [(640, 480), (1920, 1080), (4096, 3072)];
+const RUNS: usize = 3;
+
+/// 12-bit RGGB gradient with an sRGB-like camera matrix
+fn frame(width: usize, height: usize) -> RawImageData {
+    RawImageData {
+        width,
+        height,
+        data: (0..height)
+            .flat_map(|y| (0..width).map(move |x| ((x * 3 + y * 2) % 3840 + 256) as u16))
+            .collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+/// Fastest of `RUNS` conversions of `raw` after one warm-up run
+fn best_time(
+    pipeline: &RawToTiffPipeline<RawLoaderReader, StandardTiffWriter>,
+    raw: &RawImageData,
+) -> anyhow::Result<Duration> {
+    let mut best = Duration::MAX;
+    for run in 0..=RUNS {
+        let mut output = Cursor::new(Vec::new());
+        let start = Instant::now();
+        pipeline.convert_raw_image(raw.clone(), &mut output)?;
+        let elapsed = start.elapsed();
+
+        if output.get_ref().is_empty() {
+            anyhow::bail!("{}x{} conversion wrote nothing", raw.width, raw.height);
+        }
+        if run > 0 {
+            best = best.min(elapsed);
+        }
+    }
+    Ok(best)
+}
+
+fn main() -> anyhow::Result<()> {
+    let paths: [(&str, OutputMode, DebayerQuality); 4] = [
+        ("gray", OutputMode::BayerGray, DebayerQuality::default()),
+        ("rgb linear", OutputMode::Rgb, DebayerQuality::Linear),
+        ("rgb cubic", OutputMode::Rgb, DebayerQuality::Cubic),
+        (
+            "rgb malvar",
+            OutputMode::Rgb,
+            DebayerQuality::MalvarHeCutler,
+        ),
+    ];
+
+    println!(
+        "{:>11} {:>12} {:>10} {:>10}",
+        "size", "path", "best", "MP/s"
+    );
+    for (width, height) in SIZES {
+        let raw = frame(width, height);
+        let megapixels = (width * height) as f64 / 1e6;
+
+        for (name, output, quality) in paths {
+            let config = ConversionConfig::builder()
+                .output(output)
+                .backend(DebayerBackend::Cpu)
+                .debayer_quality(quality)
+                .build();
+            let pipeline = RawToTiffPipeline::new(config)?;
+
+            let best = best_time(&pipeline, &raw)?;
+            println!(
+                "{:>11} {:>12} {:>10.1?} {:>10.1}",
+                format!("{}x{}", width, height),
+                name,
+                best,
+                megapixels / best.as_secs_f64()
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Checks that the median denoise removes salt-and-pepper noise from a flat image.
+//!
+//! A flat mid-gray RGB frame gets 5% of its samples forced to black or full scale.
+//! After `DenoiseStrength::Light` the per-sample variance must drop below `MAX_RATIO`
+//! of the noisy variance and the mean must stay at the original level. Exits non-zero
+//! otherwise.
+//!
+//! Run with `cargo run --example denoise`.
+
+use ffed_protosat_rs::image_pipeline::{DenoiseStrength, ExifMetadata, RgbImageData};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+const LEVEL: u16 = 30000;
+/// Fraction of the noisy variance allowed to remain after filtering
+const MAX_RATIO: f64 = 0.05;
+
+/// Mean and variance of all samples
+fn stats(data: &[u16]) -> (f64, f64) {
+    let n = data.len() as f64;
+    let mean = data.iter().map(|&v| v as f64).sum::<f64>() / n;
+    let variance = data.iter().map(|&v| (v as f64 - mean).powi(2)).sum::<f64>() / n;
+    (mean, variance)
+}
+
+fn main() -> anyhow::Result<()> {
+    // Deterministic LCG so the noise pattern is the same on every run
+    let mut state = 0x2545_f491_u32;
+    let mut next = move || {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        state >> 8
+    };
+
+    let data = (0..WIDTH * HEIGHT * 3)
+        .map(|_| match next() % 40 {
+            0 => 0,
+            1 => u16::MAX,
+            _ => LEVEL,
+        })
+        .collect();
+    let noisy = RgbImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data,
+        bits_per_sample: 16,
+        exif: ExifMetadata::default(),
+    };
+
+    let (_, noisy_variance) = stats(&noisy.data);
+    let denoised = noisy.denoised(Some(DenoiseStrength::Light));
+    let (mean, variance) = stats(&denoised.data);
+    println!("variance {:.0} -> {:.0}, mean {:.1}", noisy_variance, variance, mean);
+
+    if variance > noisy_variance * MAX_RATIO {
+        anyhow::bail!("Median filter left {:.1}% of the noise variance", 100.0 * variance / noisy_variance);
+    }
+    if (mean - LEVEL as f64).abs() > 1.0 {
+        anyhow::bail!("Median filter shifted the mean level to {:.1}", mean);
+    }
+
+    println!("Salt-and-pepper noise removed");
+    Ok(())
+}
@@ -0,0 +1,117 @@
+//! Checks that a DNG's `AsShotNeutral` drives the as-shot white balance.
+//!
+//! A minimal little-endian TIFF with `DNGVersion` and `AsShotNeutral` in IFD0 stands in
+//! for a DNG header: `read_as_shot_neutral` must return its neutral, and nothing for the
+//! same file without `DNGVersion`. A mock DNG-origin frame whose `wb_coeffs` disagree
+//! with the neutral then takes the neutral through `apply_as_shot_neutral`; the as-shot
+//! multipliers must be its reciprocal, and a flat patch of the neutral color must come
+//! out of `CpuDebayer` gray. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example dng_as_shot_neutral`.
+
+use ffed_protosat_rs::image_pipeline::raw::exif::read_as_shot_neutral;
+use ffed_protosat_rs::image_pipeline::{
+    ColorTransform, ConversionConfig, CpuDebayer, RawImageData,
+    WhiteBalance,
+};
+
+const DNG_VERSION: u16 = 0xC612;
+const AS_SHOT_NEUTRAL: u16 = 0xC628;
+/// [R, G, B] neutral as rationals over 1000
+const NEUTRAL: [u32; 3] = [500, 1000, 800];
+const WIDTH: usize = 16;
+const HEIGHT: usize = 12;
+/// Green response of the flat patch
+const LEVEL: f32 = 2000.0;
+
+/// IFD0 holding `AsShotNeutral`, plus `DNGVersion` when `dng` is set
+fn dng_header(dng: bool) -> Vec<u8> {
+    let mut entries: Vec<(u16, u16, u32, [u8; 4])> = Vec::new();
+    if dng {
+        entries.push((DNG_VERSION, 1, 4, [1, 4, 0, 0]));
+    }
+    let ifd_len = 2 + 12 * (entries.len() + 1) + 4;
+    let rationals_at = (8 + ifd_len) as u32;
+    entries.push((AS_SHOT_NEUTRAL, 5, 3, rationals_at.to_le_bytes()));
+
+    let mut tiff = b"II*\0".to_vec();
+    tiff.extend(8u32.to_le_bytes());
+    tiff.extend((entries.len() as u16).to_le_bytes());
+    for (tag, field_type, count, value) in entries {
+        tiff.extend(tag.to_le_bytes());
+        tiff.extend(field_type.to_le_bytes());
+        tiff.extend(count.to_le_bytes());
+        tiff.extend(value);
+    }
+    tiff.extend(0u32.to_le_bytes());
+    for numerator in NEUTRAL {
+        tiff.extend(numerator.to_le_bytes());
+        tiff.extend(1000u32.to_le_bytes());
+    }
+    tiff
+}
+
+/// RGGB mosaic of a patch whose camera response is `neutral` scaled to `LEVEL`
+fn neutral_patch(neutral: [f32; 3]) -> RawImageData {
+    let site = |x: usize, y: usize| match (y % 2, x % 2) {
+        (0, 0) => neutral[0],
+        (1, 1) => neutral[2],
+        _ => neutral[1],
+    };
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT)
+            .map(|i| (site(i % WIDTH, i / WIDTH) * LEVEL).round() as u16)
+            .collect(),
+        bits_per_sample: 12,
+        // What a reader without AsShotNeutral support would have taken
+        wb_coeffs: [2.4, 1.0, 1.3, 1.0],
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let expected = NEUTRAL.map(|v| v as f32 / 1000.0);
+    let Some(neutral) = read_as_shot_neutral(&dng_header(true)) else {
+        anyhow::bail!("No AsShotNeutral read from the DNG header");
+    };
+    if neutral != expected {
+        anyhow::bail!("AsShotNeutral read as {:?}, expected {:?}", neutral, expected);
+    }
+    if let Some(neutral) = read_as_shot_neutral(&dng_header(false)) {
+        anyhow::bail!("Read AsShotNeutral {:?} from a file without DNGVersion", neutral);
+    }
+
+    let mut raw = neutral_patch(neutral);
+    raw.apply_as_shot_neutral(neutral);
+    let multipliers = WhiteBalance::AsShot.multipliers(&raw);
+    let reciprocal = [neutral[1] / neutral[0], 1.0, neutral[1] / neutral[2]];
+    println!("AsShotNeutral {:?} gives multipliers {:?}", neutral, multipliers);
+    if multipliers
+        .iter()
+        .zip(reciprocal)
+        .any(|(m, r)| (m - r).abs() > 1e-6)
+    {
+        anyhow::bail!("Multipliers {:?}, expected {:?}", multipliers, reciprocal);
+    }
+
+    let config = ConversionConfig::builder()
+        .color_transform(ColorTransform::CameraNative)
+        .exposure(1.0)
+        .build();
+    let rgb = CpuDebayer::with_config(&config)?.process_f32(&raw)?;
+    let cast = rgb
+        .data
+        .chunks_exact(3)
+        .map(|px| (px[0] - px[1]).abs().max((px[2] - px[1]).abs()))
+        .fold(0.0f32, f32::max);
+    if cast > 1e-3 {
+        anyhow::bail!("Neutral patch debayers with a color cast of up to {}", cast);
+    }
+
+    println!("The DNG neutral is used for white balance and renders gray");
+    Ok(())
+}
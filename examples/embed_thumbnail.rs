@@ -0,0 +1,92 @@
+//! Checks that `embed_thumbnail` writes a reduced-resolution preview as a sub-IFD.
+//!
+//! A 640x480 RGB frame, red on the left half and blue on the right, is written with a
+//! 160 pixel thumbnail. The main image must decode unchanged and link a `SubIfd` whose
+//! directory is marked reduced-resolution (`NewSubfileType` 1), measures 160x120 and
+//! holds the same two colors at 8 bits. A frame that already fits must get no thumbnail.
+//! Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example embed_thumbnail`.
+
+use std::io::{Cursor, Read};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, RgbImageData, StandardTiffWriter, TiffWriter,
+};
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+
+const WIDTH: usize = 640;
+const HEIGHT: usize = 480;
+const MAX_EDGE: u32 = 160;
+
+fn split_frame(width: usize, height: usize) -> RgbImageData {
+    let data = (0..width * height)
+        .flat_map(|i| if i % width < width / 2 { [65535, 0, 0] } else { [0, 0, 65535] })
+        .collect();
+    RgbImageData {
+        width,
+        height,
+        data,
+        bits_per_sample: 16,
+        exif: ExifMetadata::default(),
+    }
+}
+
+fn encode(image: &RgbImageData) -> anyhow::Result<Vec<u8>> {
+    let config = ConversionConfig::builder().embed_thumbnail(Some(MAX_EDGE)).build();
+    let mut output = Vec::new();
+    StandardTiffWriter.write_rgb_tiff(image, &mut output, &config)?;
+    Ok(output)
+}
+
+fn main() -> anyhow::Result<()> {
+    let image = split_frame(WIDTH, HEIGHT);
+    let mut decoder = Decoder::new(Cursor::new(encode(&image)?))?;
+
+    if decoder.dimensions()? != (WIDTH as u32, HEIGHT as u32) {
+        anyhow::bail!("Main image is {:?}, expected {}x{}", decoder.dimensions()?, WIDTH, HEIGHT);
+    }
+    match decoder.read_image()? {
+        DecodingResult::U16(data) if data == image.data => {}
+        _ => anyhow::bail!("Main image does not decode to the source samples"),
+    }
+
+    let pointer = decoder.get_tag(Tag::SubIfd)?.into_ifd_pointer()?;
+    let directory = decoder.read_directory(pointer)?;
+    let mut tags = decoder.read_directory_tags(&directory);
+    let tag = |tags: &mut tiff::decoder::IfdDecoder<'_>, tag| -> anyhow::Result<u32> {
+        tags.find_tag_unsigned::<u32>(tag)?
+            .ok_or_else(|| anyhow::anyhow!("Thumbnail IFD has no {:?} tag", tag))
+    };
+    let subfile_type = tag(&mut tags, Tag::NewSubfileType)?;
+    let (thumb_width, thumb_height) = (tag(&mut tags, Tag::ImageWidth)?, tag(&mut tags, Tag::ImageLength)?);
+    let (offset, byte_count) = (tag(&mut tags, Tag::StripOffsets)?, tag(&mut tags, Tag::StripByteCounts)?);
+
+    println!("Thumbnail IFD: {}x{}, NewSubfileType {}", thumb_width, thumb_height, subfile_type);
+    if subfile_type != 1 {
+        anyhow::bail!("Thumbnail is not marked reduced-resolution");
+    }
+    if (thumb_width, thumb_height) != (MAX_EDGE, MAX_EDGE * 3 / 4) {
+        anyhow::bail!("Thumbnail should be {}x{}", MAX_EDGE, MAX_EDGE * 3 / 4);
+    }
+
+    let mut pixels = vec![0u8; byte_count as usize];
+    decoder.goto_offset(offset)?;
+    decoder.inner().read_exact(&mut pixels)?;
+    let thumb_width = thumb_width as usize;
+    for (i, pixel) in pixels.chunks_exact(3).enumerate() {
+        let expected = if i % thumb_width < thumb_width / 2 { [255, 0, 0] } else { [0, 0, 255] };
+        if pixel != expected {
+            anyhow::bail!("Thumbnail pixel {} is {:?}, expected {:?}", i, pixel, expected);
+        }
+    }
+
+    let small = split_frame(MAX_EDGE as usize, 90);
+    if Decoder::new(Cursor::new(encode(&small)?))?.find_tag(Tag::SubIfd)?.is_some() {
+        anyhow::bail!("Image within the thumbnail size still got a thumbnail");
+    }
+
+    println!("Thumbnail embedded as a reduced-resolution sub-IFD");
+    Ok(())
+}
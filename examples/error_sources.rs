@@ -0,0 +1,107 @@
+//! Checks that decode and debayer failures keep the underlying error as their source.
+//!
+//! A truncated TIFF-based RAW must fail in rawloader with a `RawDecode` error whose
+//! `source()` chain, through the pipeline's stage tag, reaches rawloader's own error. A
+//! debayer failing with a two-level `anyhow` error must surface as a `Debayer` error whose
+//! chain still holds both levels. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example error_sources`.
+
+use std::error::Error;
+
+use anyhow::Context;
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, Debayer, OutputMode,
+    RawImageData, RawImageReader, RawLoaderReader, RawToTiffPipeline, Result, RgbImageData,
+    StandardTiffWriter,
+};
+
+/// Little-endian TIFF header followed by an empty first IFD
+const TRUNCATED_TIFF: &[u8] =
+    b"II*\x00\x08\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+
+/// Returns a small mid-gray mosaic
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: 16,
+            height: 16,
+            data: vec![2048; 256],
+            bits_per_sample: 12,
+            whitelevels: [4095; 4],
+            cam_to_xyz: [[0.0; 4]; 3],
+            ..Default::default()
+        })
+    }
+}
+
+/// Fails every frame with a device error wrapped in context
+struct FailingDebayer;
+
+impl Debayer for FailingDebayer {
+    fn process(&self, _raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        Err(std::io::Error::other("device lost")).context("kernel launch failed")
+    }
+}
+
+/// Messages of `error` and every source below it
+fn chain(error: &ConversionError) -> Vec<String> {
+    let mut messages = vec![error.to_string()];
+    let mut source = error.source();
+    while let Some(inner) = source {
+        messages.push(inner.to_string());
+        source = inner.source();
+    }
+    messages
+}
+
+fn main() -> anyhow::Result<()> {
+    let decode = match RawLoaderReader.read_raw(TRUNCATED_TIFF) {
+        Err(e @ ConversionError::RawDecode { .. }) => e,
+        Err(e) => anyhow::bail!("Truncated TIFF failed with {:?}, expected RawDecode", e),
+        Ok(_) => anyhow::bail!("Truncated TIFF decoded"),
+    };
+    if decode.source().is_none() {
+        anyhow::bail!("RawDecode error has no source: {}", decode);
+    }
+    println!("read_raw: {:?}", chain(&decode));
+
+    let pipeline = RawToTiffPipeline::with_custom(
+        RawLoaderReader,
+        StandardTiffWriter,
+        ConversionConfig::builder().output(OutputMode::BayerGray).build(),
+    )?;
+    let Err(tagged) = pipeline.convert_to_vec(TRUNCATED_TIFF) else {
+        anyhow::bail!("Pipeline converted a truncated TIFF");
+    };
+    let messages = chain(&tagged);
+    println!("convert: {:?}", messages);
+    if messages.len() < 3 {
+        anyhow::bail!("Expected stage, RawDecode and rawloader errors in the chain, got {:?}", messages);
+    }
+
+    let pipeline = RawToTiffPipeline::with_debayer(
+        SyntheticReader,
+        StandardTiffWriter,
+        Box::new(FailingDebayer),
+        ConversionConfig::builder().output(OutputMode::Rgb).build(),
+    )?;
+    let Err(failed) = pipeline.convert_to_vec(&[]) else {
+        anyhow::bail!("Conversion with a failing debayer succeeded");
+    };
+    if !matches!(failed.without_stage(), ConversionError::Debayer { .. }) {
+        anyhow::bail!("Failing debayer surfaced as {:?}, expected a Debayer error", failed);
+    }
+    let messages = chain(&failed);
+    println!("debayer: {:?}", messages);
+    for expected in ["kernel launch failed", "device lost"] {
+        if !messages.iter().any(|m| m == expected) {
+            anyhow::bail!("{:?} missing from the error chain {:?}", expected, messages);
+        }
+    }
+
+    println!("Decode and debayer errors keep their source chain");
+    Ok(())
+}
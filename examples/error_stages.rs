@@ -0,0 +1,87 @@
+//! Checks that pipeline errors are tagged with the stage that failed.
+//!
+//! A writer that always fails must surface as `PipelineStage::Encode`, and a reader
+//! returning a zero-sized frame as `PipelineStage::Validate`. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example error_stages`.
+
+use std::io::Write;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, GrayImageData, PipelineStage, RawImageData,
+    RawImageReader, RawToTiffPipeline, Result, RgbImageData, RgbImageDataF32, StandardTiffWriter,
+    TiffWriter,
+};
+
+/// Returns a `width`x`height` frame of mid-gray samples
+struct SyntheticReader {
+    width: usize,
+    height: usize,
+}
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: self.width,
+            height: self.height,
+            data: vec![2048; self.width * self.height],
+            bits_per_sample: 12,
+            whitelevels: [4095; 4],
+            cam_to_xyz: [[0.0; 4]; 3],
+            ..Default::default()
+        })
+    }
+}
+
+/// Fails every write
+struct FailingWriter;
+
+impl TiffWriter for FailingWriter {
+    fn write_tiff(&self, _: &RawImageData, _: &mut dyn Write, _: &ConversionConfig) -> Result<()> {
+        Err(ConversionError::EncodeError("forced failure".to_string()))
+    }
+
+    fn write_gray_tiff(&self, _: &GrayImageData, _: &mut dyn Write, _: &ConversionConfig) -> Result<()> {
+        Err(ConversionError::EncodeError("forced failure".to_string()))
+    }
+
+    fn write_rgb_tiff(&self, _: &RgbImageData, _: &mut dyn Write, _: &ConversionConfig) -> Result<()> {
+        Err(ConversionError::EncodeError("forced failure".to_string()))
+    }
+
+    fn write_rgb_tiff_f32(&self, _: &RgbImageDataF32, _: &mut dyn Write, _: &ConversionConfig) -> Result<()> {
+        Err(ConversionError::EncodeError("forced failure".to_string()))
+    }
+}
+
+fn expect_stage(result: Result<Vec<u8>>, expected: PipelineStage) -> anyhow::Result<()> {
+    match result {
+        Err(e) if e.stage() == Some(expected) => {
+            println!("{}", e);
+            Ok(())
+        }
+        Err(e) => anyhow::bail!("expected a {} error, got {:?}", expected, e),
+        Ok(_) => anyhow::bail!("expected a {} error, conversion succeeded", expected),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::default();
+
+    let encode = RawToTiffPipeline::with_custom(
+        SyntheticReader { width: 16, height: 16 },
+        FailingWriter,
+        config.clone(),
+    )?;
+    expect_stage(encode.convert_to_vec(&[]), PipelineStage::Encode)?;
+
+    let validate = RawToTiffPipeline::with_custom(
+        SyntheticReader { width: 0, height: 16 },
+        StandardTiffWriter,
+        config,
+    )?;
+    expect_stage(validate.convert_to_vec(&[]), PipelineStage::Validate)?;
+
+    println!("Errors carry the failing stage");
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Checks that the uncompressed size estimate matches what the pipeline actually writes.
+//!
+//! A synthetic reader stands in for rawloader so no sample files are needed. Every output
+//! mode is estimated and converted, both at a single-strip and a multi-strip frame size,
+//! with and without an EXIF IFD and with and without a thumbnail. Exits non-zero on the
+//! first mismatch.
+//!
+//! Run with `cargo run --example estimate_size`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, OutputMode, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, StandardTiffWriter,
+};
+
+/// Ignores the input bytes and returns a 12-bit RGGB gradient of the given size
+struct SyntheticReader {
+    width: usize,
+    height: usize,
+    exif: ExifMetadata,
+}
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        let (width, height) = (self.width, self.height);
+        Ok(RawImageData {
+            width,
+            height,
+            data: (0..width * height).map(|i| (i % 3840 + 256) as u16).collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            exif: self.exif,
+            ..Default::default()
+        })
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let exif = ExifMetadata {
+        exposure_time: Some((1, 250)),
+        f_number: Some((28, 10)),
+        iso: Some(400),
+        focal_length: None,
+    };
+    let modes = [
+        (OutputMode::BayerGray, false),
+        (OutputMode::Rgb, false),
+        (OutputMode::Rgb, true),
+        (OutputMode::Luminance, false),
+    ];
+
+    for (width, height) in [(64, 48), (700, 500)] {
+        for (preserve_exif, thumbnail) in [(false, None), (true, None), (true, Some(32))] {
+            for (output, output_float) in modes {
+                let config = ConversionConfig::builder()
+                    .output(output)
+                    .output_float(output_float)
+                    .preserve_exif(preserve_exif)
+                    .embed_thumbnail(thumbnail)
+                    .build();
+                let reader = SyntheticReader { width, height, exif };
+                let pipeline = RawToTiffPipeline::with_custom(reader, StandardTiffWriter, config)?;
+
+                let estimate = pipeline.estimate(&[])?;
+                let written = pipeline.convert_to_vec(&[])?.len();
+                println!(
+                    "{}x{} {:?} (float: {}, exif: {}, thumbnail: {:?}): estimated {}, wrote {}",
+                    width, height, output, output_float, preserve_exif, thumbnail, estimate, written
+                );
+                if estimate != written {
+                    anyhow::bail!("Uncompressed estimate is off by {} bytes", estimate as i64 - written as i64);
+                }
+            }
+        }
+    }
+
+    println!("Uncompressed estimates match the written size");
+    Ok(())
+}
@@ -0,0 +1,58 @@
+//! Checks that `preserve_exif` carries exposure time, ISO, focal length and aperture
+//! into the output TIFF.
+//!
+//! Writes a small grayscale frame with known EXIF metadata through `StandardTiffWriter`,
+//! then reads the output back with the same EXIF parser the RAW reader uses (the output
+//! is itself TIFF-structured). Exits non-zero if any tag is missing or differs, or if
+//! EXIF is written while `preserve_exif` is off.
+//!
+//! Run with `cargo run --example exif_roundtrip`.
+
+use ffed_protosat_rs::image_pipeline::raw::exif::read_exif;
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, RawImageData, StandardTiffWriter, TiffWriter,
+};
+
+fn write(image: &RawImageData, preserve_exif: bool) -> anyhow::Result<Vec<u8>> {
+    let config = ConversionConfig::builder()
+        .preserve_exif(preserve_exif)
+        .build();
+    let mut output = Vec::new();
+    StandardTiffWriter.write_tiff(image, &mut output, &config)?;
+    Ok(output)
+}
+
+fn main() -> anyhow::Result<()> {
+    let exif = ExifMetadata {
+        exposure_time: Some((1, 250)),
+        f_number: Some((56, 10)),
+        iso: Some(400),
+        focal_length: Some((350, 10)),
+    };
+
+    let image = RawImageData {
+        width: 8,
+        height: 4,
+        data: (0..32).map(|v| v * 100).collect(),
+        bits_per_sample: 12,
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        exif,
+        ..Default::default()
+    };
+
+    let preserved = read_exif(&write(&image, true)?);
+    println!("With preserve_exif:    {:?}", preserved);
+    if preserved != exif {
+        anyhow::bail!("EXIF tags were not preserved: expected {:?}", exif);
+    }
+
+    let dropped = read_exif(&write(&image, false)?);
+    println!("Without preserve_exif: {:?}", dropped);
+    if !dropped.is_empty() {
+        anyhow::bail!("EXIF tags written although preserve_exif is off");
+    }
+
+    println!("EXIF passthrough OK");
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! Checks `extract_preview` on a synthetic TIFF-based RAW.
+//!
+//! The fixture is built in memory like a NEF: IFD0 holds a 160x120 JPEG preview as its
+//! only strip, a SubIFD holds lossless JPEG raw data larger than the preview, and IFD1
+//! holds a 32x24 thumbnail located by `JPEGInterchangeFormat`. The extracted bytes must
+//! be exactly the preview and decode as a 160x120 JPEG; the raw data and the thumbnail
+//! must be passed over. A PNG and a TIFF without any JPEG must be rejected. Exits
+//! non-zero otherwise.
+//!
+//! Run with `cargo run --example extract_preview`.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{ExtendedColorType, ImageFormat};
+use ffed_protosat_rs::image_pipeline::extract_preview;
+
+const LONG: u16 = 4;
+const SHORT: u16 = 3;
+
+/// Baseline JPEG of a `width`x`height` gradient
+fn jpeg(width: u32, height: u32) -> anyhow::Result<Vec<u8>> {
+    let rgb: Vec<u8> = (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            [(x * 255 / width) as u8, (y * 255 / height) as u8, 128]
+        })
+        .collect();
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, 90).encode(&rgb, width, height, ExtendedColorType::Rgb8)?;
+    Ok(out)
+}
+
+/// Little-endian IFD with `entries` of `(tag, type, value)`, each a count of 1
+fn ifd(entries: &[(u16, u16, u32)], next: u32) -> Vec<u8> {
+    let mut out = (entries.len() as u16).to_le_bytes().to_vec();
+    for &(tag, field_type, value) in entries {
+        out.extend(tag.to_le_bytes());
+        out.extend(field_type.to_le_bytes());
+        out.extend(1u32.to_le_bytes());
+        out.extend(value.to_le_bytes());
+    }
+    out.extend(next.to_le_bytes());
+    out
+}
+
+/// TIFF-based RAW with a preview strip, a lossless raw SubIFD and a thumbnail IFD1
+fn fixture(preview: &[u8], raw: &[u8], thumbnail: &[u8]) -> Vec<u8> {
+    let ifd_len = |entries: usize| (2 + 12 * entries + 4) as u32;
+    let ifd0 = 8;
+    let sub_ifd = ifd0 + ifd_len(5);
+    let ifd1 = sub_ifd + ifd_len(3);
+    let preview_at = ifd1 + ifd_len(2);
+    let raw_at = preview_at + preview.len() as u32;
+    let thumbnail_at = raw_at + raw.len() as u32;
+
+    let mut out = b"II*\0".to_vec();
+    out.extend(ifd0.to_le_bytes());
+    out.extend(ifd(
+        &[
+            (0x00FE, LONG, 1),
+            (0x0103, SHORT, 6),
+            (0x0111, LONG, preview_at),
+            (0x0117, LONG, preview.len() as u32),
+            (0x014A, LONG, sub_ifd),
+        ],
+        ifd1,
+    ));
+    out.extend(ifd(
+        &[(0x0103, SHORT, 7), (0x0111, LONG, raw_at), (0x0117, LONG, raw.len() as u32)],
+        0,
+    ));
+    out.extend(ifd(&[(0x0201, LONG, thumbnail_at), (0x0202, LONG, thumbnail.len() as u32)], 0));
+    out.extend(preview);
+    out.extend(raw);
+    out.extend(thumbnail);
+    out
+}
+
+fn main() -> anyhow::Result<()> {
+    let preview = jpeg(160, 120)?;
+    let thumbnail = jpeg(32, 24)?;
+    // SOI, a lossless SOF3 header for a 16-bit 2-component frame, then filler data
+    let mut raw = vec![0xFF, 0xD8, 0xFF, 0xC3, 0x00, 0x0E, 16, 0x0F, 0xA0, 0x17, 0x70, 2, 1, 0x11, 0, 2, 0x11, 0];
+    raw.resize(preview.len() * 4, 0x5A);
+    raw.extend([0xFF, 0xD9]);
+
+    let extracted = extract_preview(&fixture(&preview, &raw, &thumbnail))?;
+    println!("Extracted {} bytes ({} byte thumbnail, {} byte raw data)", extracted.len(), thumbnail.len(), raw.len());
+    if extracted != preview {
+        anyhow::bail!("Extracted {} bytes, expected the {} byte preview", extracted.len(), preview.len());
+    }
+    if !extracted.starts_with(&[0xFF, 0xD8]) || !extracted.ends_with(&[0xFF, 0xD9]) {
+        anyhow::bail!("Extracted bytes are not delimited by JPEG SOI and EOI markers");
+    }
+    let decoded = image::load_from_memory_with_format(&extracted, ImageFormat::Jpeg)?;
+    if (decoded.width(), decoded.height()) != (160, 120) {
+        anyhow::bail!("Preview decodes as {}x{}, expected 160x120", decoded.width(), decoded.height());
+    }
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.resize(64, 0);
+    let no_preview = fixture(&[], &[], &[]);
+    for (name, input) in [("PNG", png), ("TIFF without a JPEG", no_preview)] {
+        match extract_preview(&input) {
+            Ok(bytes) => anyhow::bail!("{} yielded a {} byte preview", name, bytes.len()),
+            Err(e) => println!("{}: {}", name, e),
+        }
+    }
+
+    println!("The embedded preview is extracted as a valid JPEG");
+    Ok(())
+}
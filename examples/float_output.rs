@@ -0,0 +1,85 @@
+//! Checks that `output_float` writes the debayer's linear float RGB.
+//!
+//! A synthetic frame is converted with `output_float` set. The TIFF must decode as
+//! 32-bit float RGB of the frame's dimensions, and every sample must be within `EPSILON`
+//! of `CpuDebayer::process_f32` on the same frame, including values above 1.0 that the
+//! 16-bit output would clip. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example float_output`.
+
+use std::io::Cursor;
+
+use tiff::ColorType;
+use tiff::decoder::{Decoder, DecodingResult};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, CpuDebayer, OutputMode, RawImageData, RawImageReader, RawToTiffPipeline,
+    Result, StandardTiffWriter,
+};
+
+const WIDTH: usize = 24;
+const HEIGHT: usize = 16;
+const EPSILON: f32 = 1e-6;
+
+fn gradient() -> RawImageData {
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT).map(|i| 256 + (i * 11 % 3800) as u16).collect(),
+        bits_per_sample: 12,
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+/// Ignores the input bytes and returns `gradient`
+struct GradientReader;
+
+impl RawImageReader for GradientReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(gradient())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .output_float(true)
+        .build();
+    let expected = CpuDebayer::with_config(&config)?.process_f32(&gradient())?.data;
+    let pipeline = RawToTiffPipeline::with_custom(GradientReader, StandardTiffWriter, config)?;
+
+    let mut decoder = Decoder::new(Cursor::new(pipeline.convert_to_vec(&[])?))?;
+    let color_type = decoder.colortype()?;
+    if color_type != ColorType::RGB(32) {
+        anyhow::bail!("Float output is {:?}, expected RGB(32)", color_type);
+    }
+    let (width, height) = decoder.dimensions()?;
+    if (width as usize, height as usize) != (WIDTH, HEIGHT) {
+        anyhow::bail!("Float output is {}x{}, expected {}x{}", width, height, WIDTH, HEIGHT);
+    }
+    let DecodingResult::F32(samples) = decoder.read_image()? else {
+        anyhow::bail!("Float output did not decode as f32 samples");
+    };
+    if samples.len() != expected.len() {
+        anyhow::bail!("Read back {} samples, expected {}", samples.len(), expected.len());
+    }
+    if let Some((i, (read, want))) = samples
+        .iter()
+        .zip(&expected)
+        .enumerate()
+        .find(|(_, (read, want))| (*read - *want).abs() > EPSILON)
+    {
+        anyhow::bail!("Sample {} read back as {}, expected {}", i, read, want);
+    }
+
+    let peak = samples.iter().copied().fold(f32::MIN, f32::max);
+    if peak <= 1.0 {
+        anyhow::bail!("Peak sample is {}, expected the default exposure to push some above 1.0", peak);
+    }
+    println!("{} samples read back within {} (peak {:.3})", samples.len(), EPSILON, peak);
+
+    println!("Float RGB output round-trips the debayer's values");
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Checks the floating-point predictor for compressed float TIFF output.
+//!
+//! A smooth float RGB image with values outside 0.0..=1.0 is written with LZW and Deflate,
+//! with `predictor` 3 and 2. Each file must be tagged with predictor 3, decode to exactly
+//! the input values and come out smaller than without a predictor, and `parallel_strips`
+//! must not change the bytes. Uncompressed output must ignore the predictor. Exits
+//! non-zero otherwise.
+//!
+//! Run with `cargo run --example float_predictor`.
+
+use std::io::Cursor;
+
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, RgbImageDataF32, StandardTiffWriter, TiffCompression,
+    TiffWriter,
+};
+
+fn encode(image: &RgbImageDataF32, config: &ConversionConfig) -> anyhow::Result<Vec<u8>> {
+    let mut tiff = Vec::new();
+    StandardTiffWriter.write_rgb_tiff_f32(image, &mut tiff, config)?;
+    Ok(tiff)
+}
+
+/// Predictor tag (1 when absent) and the decoded samples
+fn decode(tiff: &[u8]) -> anyhow::Result<(u16, Vec<f32>)> {
+    let mut decoder = Decoder::new(Cursor::new(tiff))?;
+    let predictor = match decoder.find_tag(Tag::Predictor)? {
+        Some(value) => value.into_u16()?,
+        None => 1,
+    };
+    match decoder.read_image()? {
+        DecodingResult::F32(samples) => Ok((predictor, samples)),
+        _ => anyhow::bail!("Expected float output"),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let (width, height) = (320, 240);
+    let image = RgbImageDataF32 {
+        width,
+        height,
+        data: (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                [x / 200.0, y / 180.0 - 0.1, (x + y) / 400.0]
+            })
+            .collect(),
+        exif: ExifMetadata::default(),
+    };
+
+    for compression in [TiffCompression::Lzw, TiffCompression::DeflateBalanced] {
+        let plain = encode(
+            &image,
+            &ConversionConfig::builder().compression(compression).build(),
+        )?;
+        for predictor in [3, 2] {
+            let config = |parallel| {
+                ConversionConfig::builder()
+                    .compression(compression)
+                    .predictor(Some(predictor))
+                    .parallel_strips(parallel)
+                    .build()
+            };
+            let tiff = encode(&image, &config(false))?;
+            let (tag, samples) = decode(&tiff)?;
+            println!(
+                "{:?}, predictor {}: {} bytes, {} without predictor",
+                compression,
+                predictor,
+                tiff.len(),
+                plain.len()
+            );
+
+            if tag != 3 {
+                anyhow::bail!("Tagged with predictor {}, expected 3", tag);
+            }
+            if samples
+                .iter()
+                .zip(&image.data)
+                .any(|(a, b)| a.to_bits() != b.to_bits())
+            {
+                anyhow::bail!("Decoded samples differ from the input");
+            }
+            if tiff.len() >= plain.len() {
+                anyhow::bail!("The predictor did not improve compression");
+            }
+            if encode(&image, &config(true))? != tiff {
+                anyhow::bail!("parallel_strips changed the file");
+            }
+        }
+    }
+
+    let uncompressed = ConversionConfig::builder().predictor(Some(3)).build();
+    let (tag, samples) = decode(&encode(&image, &uncompressed)?)?;
+    if tag != 1 || samples != image.data {
+        anyhow::bail!("Uncompressed output applied predictor {}", tag);
+    }
+
+    println!("Floating-point predictor round-trips compressed float output");
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! Checks the folded-stack export of `PipelineTimings`.
+//!
+//! Hand-made timings must give one `convert;<stage> <microseconds>` line per stage that
+//! ran, in pipeline order, and no line for stages left at zero. The timings of a real
+//! conversion must parse the same way and add up to its total. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example folded_stacks`.
+
+use std::time::Duration;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, PipelineTimings, RawImageData,
+    RawImageReader, RawToTiffPipeline, Result, StandardTiffWriter,
+};
+
+/// Ignores the input bytes and returns a small 12-bit mosaic
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        let (width, height) = (64, 48);
+        Ok(RawImageData {
+            width,
+            height,
+            data: (0..width * height).map(|i| (i % 3840 + 256) as u16).collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            cam_to_xyz: [[0.0; 4]; 3],
+            ..Default::default()
+        })
+    }
+}
+
+/// `(stage, microseconds)` of each line, failing on anything not in folded-stack form
+fn parse(folded: &str) -> anyhow::Result<Vec<(String, u128)>> {
+    folded
+        .lines()
+        .map(|line| {
+            let (stack, weight) = line
+                .rsplit_once(' ')
+                .ok_or_else(|| anyhow::anyhow!("No weight on line {:?}", line))?;
+            let stage = stack
+                .strip_prefix("convert;")
+                .filter(|stage| !stage.is_empty() && !stage.contains([';', ' ']))
+                .ok_or_else(|| anyhow::anyhow!("Line {:?} is not a convert;<stage> stack", line))?;
+            Ok((stage.to_string(), weight.parse()?))
+        })
+        .collect()
+}
+
+fn main() -> anyhow::Result<()> {
+    let timings = PipelineTimings {
+        read: Duration::from_micros(1500),
+        decode: Duration::from_millis(42),
+        debayer: Duration::from_micros(123_456),
+        encode: Duration::from_nanos(7_900),
+        ..PipelineTimings::default()
+    };
+    let folded = timings.to_folded_stacks();
+    print!("{}", folded);
+    let expected = [("read", 1500), ("decode", 42_000), ("debayer", 123_456), ("encode", 7)];
+    let lines = parse(&folded)?;
+    if lines.len() != expected.len()
+        || lines.iter().zip(expected).any(|((stage, us), (e_stage, e_us))| stage != e_stage || *us != e_us)
+    {
+        anyhow::bail!("Folded stacks {:?}, expected {:?}", lines, expected);
+    }
+
+    let config = ConversionConfig::builder().output(OutputMode::Rgb).build();
+    let pipeline = RawToTiffPipeline::with_custom(SyntheticReader, StandardTiffWriter, config)?;
+    let report = pipeline.convert_with_report(&[], &mut Vec::<u8>::new(), pipeline.config())?;
+    let lines = parse(&report.timings.to_folded_stacks())?;
+    let stages: Vec<&str> = lines.iter().map(|(stage, _)| stage.as_str()).collect();
+    for stage in ["decode", "debayer", "encode"] {
+        if !stages.contains(&stage) {
+            anyhow::bail!("Conversion profile {:?} has no {} frame", stages, stage);
+        }
+    }
+    let sum: u128 = lines.iter().map(|(_, us)| us).sum();
+    let total = report.timings.total().as_micros();
+    // Each line truncates to whole microseconds
+    if sum > total || total - sum > lines.len() as u128 {
+        anyhow::bail!("Stage weights add up to {} us, total is {} us", sum, total);
+    }
+    println!("Conversion profile: {:?}, {} us", stages, sum);
+
+    println!("Timings export as folded stacks");
+    Ok(())
+}
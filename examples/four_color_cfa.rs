@@ -0,0 +1,37 @@
+//! Checks that four-color sensors are rejected instead of debayered as RGGB.
+//!
+//! `RawLoaderReader::check_layout` is given the layouts rawloader can report. An RGGB
+//! mosaic must be accepted as Bayer and three components per pixel as RGB, while an RGBE
+//! pattern, whose fourth color the debayer has no channel for, and four components per
+//! pixel must fail with `UnsupportedFormat`. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example four_color_cfa`.
+
+use rawloader::CFA;
+
+use ffed_protosat_rs::image_pipeline::{ConversionError, RawLoaderReader};
+
+fn main() -> anyhow::Result<()> {
+    let rggb = CFA::new("RGGB");
+    let rgbe = CFA::new("RGEB");
+
+    if !RawLoaderReader::check_layout(1, &rggb)? {
+        anyhow::bail!("RGGB mosaic was not taken as Bayer");
+    }
+    if RawLoaderReader::check_layout(3, &rggb)? {
+        anyhow::bail!("Three components per pixel were taken as Bayer");
+    }
+
+    for (cpp, cfa) in [(1, &rgbe), (4, &rggb)] {
+        match RawLoaderReader::check_layout(cpp, cfa) {
+            Err(ConversionError::UnsupportedFormat(message)) => {
+                println!("{} with {} component(s) rejected: {}", cfa.name, cpp, message)
+            }
+            Err(e) => anyhow::bail!("{} with {} component(s): expected UnsupportedFormat, got {}", cfa.name, cpp, e),
+            Ok(is_bayer) => anyhow::bail!("{} with {} component(s) accepted (is_bayer {})", cfa.name, cpp, is_bayer),
+        }
+    }
+
+    println!("Four-color layouts fail loudly");
+    Ok(())
+}
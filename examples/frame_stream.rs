@@ -0,0 +1,111 @@
+//! Checks frame-by-frame conversion through `FrameProcessor` and `convert_all`.
+//!
+//! A reader that decodes its input bytes (a width/height header followed by 12-bit
+//! little-endian samples) stands in for a capture source. Several distinct frames, one of
+//! them a different size, are pushed through a single `FrameProcessor` to RGB; each output
+//! must be byte-identical to `convert_to_vec` of the same frame and decode to that frame's
+//! dimensions. `convert_all` must yield the same outputs in order, and a truncated frame
+//! must fail without stopping the frames after it. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example frame_stream`.
+
+use std::io::Cursor;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, OutputMode, RawImageData,
+    RawImageReader, RawToTiffPipeline, Result, StandardTiffWriter,
+};
+use tiff::decoder::Decoder;
+
+/// Decodes `[width: u16, height: u16, samples: u16...]`, all little-endian
+struct HeaderReader;
+
+impl RawImageReader for HeaderReader {
+    fn read_raw(&self, data: &[u8]) -> Result<RawImageData> {
+        let mut words = data
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]));
+        let (Some(width), Some(height)) = (words.next(), words.next()) else {
+            return Err(ConversionError::DecodeError("Missing header".to_string()));
+        };
+        Ok(RawImageData {
+            width: width as usize,
+            height: height as usize,
+            data: words.collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+/// Frame `index` of a stream, its gradient shifted per frame so every frame differs
+fn frame(index: usize, width: usize, height: usize) -> Vec<u8> {
+    [width as u16, height as u16]
+        .into_iter()
+        .chain((0..width * height).map(|i| (256 + (i * 7 + index * 131) % 3840) as u16))
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+fn dimensions(tiff: &[u8]) -> anyhow::Result<(u32, u32)> {
+    Ok(Decoder::new(Cursor::new(tiff))?.dimensions()?)
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::builder().output(OutputMode::Rgb).build();
+    let pipeline = RawToTiffPipeline::with_custom(HeaderReader, StandardTiffWriter, config)?;
+
+    let sizes = [(64, 48), (64, 48), (96, 32), (64, 48)];
+    let frames: Vec<Vec<u8>> = sizes
+        .iter()
+        .enumerate()
+        .map(|(i, &(width, height))| frame(i, width, height))
+        .collect();
+
+    let processor = pipeline.frame_processor();
+    let mut outputs = Vec::new();
+    for (i, (input, &(width, height))) in frames.iter().zip(&sizes).enumerate() {
+        let output = processor.process_frame(input)?;
+        if output != pipeline.convert_to_vec(input)? {
+            anyhow::bail!("Frame {} differs from a one-off conversion", i);
+        }
+        let decoded = dimensions(&output)?;
+        if decoded != (width as u32, height as u32) {
+            anyhow::bail!(
+                "Frame {} decodes as {}x{}, expected {}x{}",
+                i,
+                decoded.0,
+                decoded.1,
+                width,
+                height
+            );
+        }
+        println!("Frame {}: {}x{}, {} bytes", i, width, height, output.len());
+        outputs.push(output);
+    }
+    if outputs[0] == outputs[1] {
+        anyhow::bail!("Distinct frames encoded identically");
+    }
+
+    let streamed = pipeline.convert_all(&frames).collect::<Result<Vec<_>>>()?;
+    if streamed != outputs {
+        anyhow::bail!("convert_all output differs from process_frame");
+    }
+
+    let mut truncated = frames[0].clone();
+    truncated.truncate(frames[0].len() / 2);
+    let results: Vec<_> = pipeline
+        .convert_all([truncated, frames[1].clone()])
+        .collect();
+    match results.as_slice() {
+        [Err(_), Ok(output)] if *output == outputs[1] => {}
+        [Ok(_), _] => anyhow::bail!("Truncated frame converted"),
+        _ => anyhow::bail!("Frame after a failed one did not convert"),
+    }
+
+    println!("{} frames streamed through one pipeline", frames.len());
+    Ok(())
+}
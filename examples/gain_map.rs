@@ -0,0 +1,154 @@
+//! Checks reading a DNG `GainMap` opcode and flattening a radial falloff with it.
+//!
+//! A minimal little-endian DNG header with an `OpcodeList2` holding one whole-image
+//! `GainMap` must read back through `read_gain_map`. A flat 12-bit field darkened by a
+//! radial falloff then carries a 17x17 map of the inverse falloff; converted to
+//! `BayerGray`, every sample must come out within 1% of the flat level, while with
+//! `apply_gain_map` off the corners must keep their falloff. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example gain_map`.
+
+use std::io::Cursor;
+
+use ffed_protosat_rs::image_pipeline::raw::exif::read_gain_map;
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, GainMap, OutputMode, RawImageData,
+    RawToTiffPipeline,
+};
+use tiff::decoder::{Decoder, DecodingResult};
+
+const WIDTH: usize = 96;
+const HEIGHT: usize = 64;
+const BLACK: u16 = 256;
+/// Signal above black of the flat field before falloff
+const LEVEL: f32 = 3000.0;
+const POINTS: usize = 17;
+
+const DNG_VERSION: u16 = 0xC612;
+const OPCODE_LIST_2: u16 = 0xC741;
+
+/// Relative brightness at (`x`, `y`), dropping to 60% in the corners
+fn falloff(x: f64, y: f64) -> f64 {
+    let (dx, dy) = (2.0 * x - 1.0, 2.0 * y - 1.0);
+    1.0 - 0.4 * (dx * dx + dy * dy) / 2.0
+}
+
+fn inverse_falloff_map() -> GainMap {
+    let step = 1.0 / (POINTS - 1) as f64;
+    GainMap {
+        points: (POINTS, POINTS),
+        origin: (0.0, 0.0),
+        spacing: (step, step),
+        gains: (0..POINTS * POINTS)
+            .map(|i| (1.0 / falloff((i % POINTS) as f64 * step, (i / POINTS) as f64 * step)) as f32)
+            .collect(),
+    }
+}
+
+/// Big-endian `OpcodeList2` payload holding `map` as its only opcode
+fn opcode_list(map: &GainMap) -> Vec<u8> {
+    let mut params = Vec::new();
+    let (height, width) = (HEIGHT as u32, WIDTH as u32);
+    for value in [0, 0, height, width, 0, 1, 1, 1, map.points.0 as u32, map.points.1 as u32] {
+        params.extend(value.to_be_bytes());
+    }
+    for value in [map.spacing.0, map.spacing.1, map.origin.0, map.origin.1] {
+        params.extend(value.to_be_bytes());
+    }
+    params.extend(1u32.to_be_bytes());
+    for gain in &map.gains {
+        params.extend(gain.to_be_bytes());
+    }
+
+    let mut list = Vec::new();
+    list.extend(1u32.to_be_bytes());
+    // GainMap, version 1.3.0.0, flags, parameter length
+    for value in [9, 0x0103_0000, 0, params.len() as u32] {
+        list.extend(value.to_be_bytes());
+    }
+    list.extend(params);
+    list
+}
+
+/// IFD0 with `DNGVersion` and `OpcodeList2`, the list stored after the IFD
+fn dng_header(list: &[u8]) -> Vec<u8> {
+    let list_at = 8 + 2 + 12 * 2 + 4;
+    let mut tiff = b"II*\0".to_vec();
+    tiff.extend(8u32.to_le_bytes());
+    tiff.extend(2u16.to_le_bytes());
+    for (tag, field_type, count, value) in [
+        (DNG_VERSION, 1u16, 4u32, [1, 4, 0, 0]),
+        (OPCODE_LIST_2, 7, list.len() as u32, (list_at as u32).to_le_bytes()),
+    ] {
+        tiff.extend(tag.to_le_bytes());
+        tiff.extend(field_type.to_le_bytes());
+        tiff.extend(count.to_le_bytes());
+        tiff.extend(value);
+    }
+    tiff.extend(0u32.to_le_bytes());
+    tiff.extend(list);
+    tiff
+}
+
+fn shaded_field(gain_map: GainMap) -> RawImageData {
+    let relative = |i: usize, len: usize| i as f64 / (len - 1) as f64;
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let shade = falloff(relative(i % WIDTH, WIDTH), relative(i / WIDTH, HEIGHT));
+                BLACK + (LEVEL as f64 * shade).round() as u16
+            })
+            .collect(),
+        bits_per_sample: 12,
+        blacklevels: [BLACK; 4],
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        gain_map: Some(gain_map),
+        ..Default::default()
+    }
+}
+
+/// Largest relative deviation from the flat level after converting with `apply`
+fn max_deviation(gain_map: GainMap, apply: bool) -> anyhow::Result<f32> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::BayerGray)
+        .apply_gain_map(apply)
+        .build();
+    let mut tiff = Vec::new();
+    RawToTiffPipeline::new(config)?.convert_raw_image(shaded_field(gain_map), &mut tiff)?;
+    let DecodingResult::U16(samples) = Decoder::new(Cursor::new(&tiff))?.read_image()? else {
+        anyhow::bail!("Expected 16-bit samples");
+    };
+    Ok(samples
+        .iter()
+        .map(|&v| ((v - BLACK) as f32 - LEVEL).abs() / LEVEL)
+        .fold(0.0, f32::max))
+}
+
+fn main() -> anyhow::Result<()> {
+    let map = inverse_falloff_map();
+    let Some(read) = read_gain_map(&dng_header(&opcode_list(&map))) else {
+        anyhow::bail!("No gain map read from the DNG header");
+    };
+    if read != map {
+        anyhow::bail!("Gain map read back differs from the one written");
+    }
+    println!("Read a {}x{} gain map from OpcodeList2", read.points.0, read.points.1);
+
+    let corrected = max_deviation(read.clone(), true)?;
+    println!("With the gain map: max deviation {:.4}", corrected);
+    if corrected > 0.01 {
+        anyhow::bail!("Gain map left the field off flat by up to {:.4}", corrected);
+    }
+
+    let shaded = max_deviation(read, false)?;
+    println!("Without the gain map: max deviation {:.4}", shaded);
+    if shaded < 0.35 {
+        anyhow::bail!("apply_gain_map(false) still corrected the falloff");
+    }
+
+    println!("The gain map flattens the radial falloff");
+    Ok(())
+}
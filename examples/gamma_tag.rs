@@ -0,0 +1,126 @@
+//! Checks the EXIF `Gamma` tag and sRGB transfer curve of TIFF output.
+//!
+//! A synthetic 12-bit mosaic is converted with `apply_srgb_gamma` off and on. Linear RGB
+//! output must be tagged `Gamma` 1.0 and embed the linear ICC profile; gamma-encoded RGB
+//! and luminance output must be tagged 2.2 and embed the sRGB profile, while the Bayer
+//! mosaic stays tagged 1.0. The encoded float samples must equal the sRGB transfer
+//! function applied to the linear ones. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example gamma_tag`.
+
+use std::io::Cursor;
+
+use tiff::decoder::Decoder;
+use tiff::decoder::ifd::Value;
+use tiff::tags::Tag;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, CpuDebayer, DebayerQuality, OutputMode,
+    RawImageData, RawToTiffPipeline,
+};
+
+const GAMMA_TAG: u16 = 0xA500;
+
+fn gradient_raw() -> RawImageData {
+    let (width, height) = (64, 48);
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height)
+            .map(|i| ((i % width) * 50 + (i / width) * 20 + 256) as u16)
+            .collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+fn config(output: OutputMode, gamma: bool) -> ConversionConfig {
+    ConversionConfig::builder()
+        .output(output)
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .exposure(1.0)
+        .embed_icc(true)
+        .apply_srgb_gamma(gamma)
+        .build()
+}
+
+/// `Gamma` tag value and whether the embedded ICC profile is the linear one
+fn read_tags(tiff: &[u8]) -> anyhow::Result<(f64, Option<bool>)> {
+    let mut decoder = Decoder::new(Cursor::new(tiff))?;
+    let gamma = match decoder.find_tag(Tag::Unknown(GAMMA_TAG))? {
+        Some(Value::Rational(n, d)) => n as f64 / d as f64,
+        other => anyhow::bail!("Gamma tag is {:?}, expected a rational", other),
+    };
+    let icc = decoder
+        .find_tag(Tag::IccProfile)?
+        .map(|value| value.into_u8_vec())
+        .transpose()?
+        .map(|profile| profile.windows(11).any(|w| w == b"Linear sRGB"));
+    Ok((gamma, icc))
+}
+
+fn expect_tags(
+    output: OutputMode,
+    gamma: bool,
+    expected: f64,
+    linear_icc: Option<bool>,
+) -> anyhow::Result<()> {
+    let mut tiff = Vec::new();
+    RawToTiffPipeline::new(config(output, gamma))?.convert_raw_image(gradient_raw(), &mut tiff)?;
+    let (tagged, icc) = read_tags(&tiff)?;
+    if (tagged - expected).abs() > 1e-9 || icc != linear_icc {
+        anyhow::bail!(
+            "{:?} with apply_srgb_gamma={}: Gamma {}, linear ICC {:?}; expected {}, {:?}",
+            output,
+            gamma,
+            tagged,
+            icc,
+            expected,
+            linear_icc
+        );
+    }
+    println!(
+        "{:?}, apply_srgb_gamma={}: Gamma {}, linear ICC {:?}",
+        output, gamma, tagged, icc
+    );
+    Ok(())
+}
+
+fn srgb_encode(x: f32) -> f32 {
+    if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    expect_tags(OutputMode::Rgb, false, 1.0, Some(true))?;
+    expect_tags(OutputMode::Rgb, true, 2.2, Some(false))?;
+    expect_tags(OutputMode::Luminance, false, 1.0, None)?;
+    expect_tags(OutputMode::Luminance, true, 2.2, None)?;
+    expect_tags(OutputMode::BayerGray, true, 1.0, None)?;
+
+    let raw = gradient_raw();
+    let linear = CpuDebayer::with_config(&config(OutputMode::Rgb, false))?.process_f32(&raw)?;
+    let encoded = CpuDebayer::with_config(&config(OutputMode::Rgb, true))?.process_f32(&raw)?;
+    let error = linear
+        .data
+        .iter()
+        .zip(&encoded.data)
+        .map(|(&l, &e)| (srgb_encode(l.max(0.0)) - e.max(0.0)).abs())
+        .fold(0.0f32, f32::max);
+    if error > 1e-5 {
+        anyhow::bail!("Encoded samples deviate from the sRGB curve by {}", error);
+    }
+    println!(
+        "Encoded samples follow the sRGB curve (max error {:.2e})",
+        error
+    );
+
+    println!("OK");
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! Checks that building many GPU debayers reuses the shared CUDA context.
+//!
+//! `ITERATIONS` NPP and CUDA debayers are constructed, used on a small frame and dropped.
+//! Free device memory afterwards must be within `SLACK_BYTES` of the level after the
+//! first pair, and every debayer must have attached to the same context. The shared
+//! context is released at the end. Exits non-zero otherwise.
+//!
+//! Run on a Jetson with `cargo run --release --example gpu_context_reuse`.
+
+#[cfg(jetson_cuda)]
+use std::sync::Arc;
+
+#[cfg(jetson_cuda)]
+use ffed_protosat_rs::image_pipeline::debayer::cuda_context::{release_shared_context, shared_context};
+#[cfg(jetson_cuda)]
+use ffed_protosat_rs::image_pipeline::{
+    CudaDebayer, ExifMetadata, NppDebayer, Orientation, RawImageData,
+};
+
+#[cfg(jetson_cuda)]
+const ITERATIONS: usize = 200;
+
+/// Allowed drift in free device memory, covering allocator caching
+#[cfg(jetson_cuda)]
+const SLACK_BYTES: usize = 64 << 20;
+
+#[cfg(jetson_cuda)]
+fn synthetic_raw() -> RawImageData {
+    let (width, height) = (256, 192);
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height).map(|i| (i % 3840 + 256) as u16).collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+#[cfg(jetson_cuda)]
+fn free_memory() -> anyhow::Result<usize> {
+    shared_context()?.bind_to_thread()?;
+    Ok(cudarc::driver::result::mem_get_info()?.0)
+}
+
+#[cfg(jetson_cuda)]
+fn main() -> anyhow::Result<()> {
+    let raw = synthetic_raw();
+    let ctx = shared_context()?;
+
+    let mut baseline = None;
+    for i in 0..ITERATIONS {
+        let npp = NppDebayer::new()?;
+        let cuda = CudaDebayer::new()?;
+        npp.process(&raw)?;
+        cuda.process(&raw)?;
+        drop((npp, cuda));
+
+        if !Arc::ptr_eq(&ctx, &shared_context()?) {
+            anyhow::bail!("Iteration {} attached to a new CUDA context", i);
+        }
+
+        let free = free_memory()?;
+        let baseline = *baseline.get_or_insert(free);
+        if free + SLACK_BYTES < baseline {
+            anyhow::bail!(
+                "Free device memory fell from {} to {} bytes after {} debayers",
+                baseline, free, i + 1
+            );
+        }
+    }
+
+    drop(ctx);
+    release_shared_context();
+
+    println!("{} NPP and CUDA debayers shared one context", ITERATIONS);
+    Ok(())
+}
+
+#[cfg(not(jetson_cuda))]
+fn main() {
+    println!("GPU context reuse check requires a Jetson build (jetson_cuda), skipping.");
+}
@@ -0,0 +1,148 @@
+//! Checks the retry and CPU fallback of a failing GPU debayer.
+//!
+//! A mock GPU debayer returns one fixed color but fails a configurable number of times
+//! first. Failing once, the pipeline must retry and return the mock's output. Failing
+//! every time, it must give up on the GPU after the retry and return exactly what
+//! `CpuDebayer` produces. With `gpu_fallback(false)` a single failure must fail the
+//! conversion at the debayer stage without a retry. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example gpu_fallback`.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, CpuDebayer, Debayer, DebayerQuality, OutputMode,
+    PipelineStage, RawImageData, RawImageReader, RawToTiffPipeline, Result, RgbImageData,
+    StandardTiffWriter,
+};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 24;
+const COLOR: [u16; 3] = [60000, 1234, 32768];
+
+/// Ignores the input bytes and returns a small 12-bit RGGB gradient
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: (0..WIDTH * HEIGHT)
+                .map(|i| (i * 5 % 3840 + 256) as u16)
+                .collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+/// Claims to run on the GPU and fails its first `failures` calls
+struct FlakyGpuDebayer {
+    failures: usize,
+    calls: AtomicUsize,
+}
+
+impl FlakyGpuDebayer {
+    fn new(failures: usize) -> Self {
+        Self {
+            failures,
+            calls: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Debayer for FlakyGpuDebayer {
+    fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        let call = self.calls.fetch_add(1, Ordering::Relaxed);
+        if call < self.failures {
+            anyhow::bail!("nppiColorTwist_32f_C3R returned NPP_CUDA_KERNEL_EXECUTION_ERROR");
+        }
+        Ok(RgbImageData {
+            width: raw_image.width,
+            height: raw_image.height,
+            data: COLOR.repeat(raw_image.width * raw_image.height),
+            bits_per_sample: 16,
+            exif: raw_image.exif,
+        })
+    }
+
+    fn uses_gpu(&self) -> bool {
+        true
+    }
+}
+
+/// Lets the example keep a handle on the mock's call counter
+struct Shared(Arc<FlakyGpuDebayer>);
+
+impl Debayer for Shared {
+    fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        self.0.process(raw_image)
+    }
+
+    fn uses_gpu(&self) -> bool {
+        self.0.uses_gpu()
+    }
+}
+
+fn config(gpu_fallback: bool) -> ConversionConfig {
+    ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .gpu_fallback(gpu_fallback)
+        .build()
+}
+
+/// Runs `debayer_only` through a mock failing `failures` times, returning the result
+/// and how often the mock was called
+fn run(failures: usize, gpu_fallback: bool) -> anyhow::Result<(Result<RgbImageData>, usize)> {
+    let flaky = Arc::new(FlakyGpuDebayer::new(failures));
+    let pipeline = RawToTiffPipeline::with_debayer(
+        SyntheticReader,
+        StandardTiffWriter,
+        Box::new(Shared(flaky.clone())),
+        config(gpu_fallback),
+    )?;
+    let result = pipeline.debayer_only(&[]);
+    Ok((result, flaky.calls.load(Ordering::Relaxed)))
+}
+
+fn main() -> anyhow::Result<()> {
+    let (result, calls) = run(1, true)?;
+    let rgb = result?;
+    if calls != 2 || rgb.data[..3] != COLOR {
+        anyhow::bail!(
+            "One failure: {} calls, first pixel {:?}",
+            calls,
+            &rgb.data[..3]
+        );
+    }
+    println!("One failure: retried and kept the GPU result");
+
+    let (result, calls) = run(usize::MAX, true)?;
+    let rgb = result?;
+    let cpu = CpuDebayer::with_config(&config(true))?.process(&SyntheticReader.read_raw(&[])?)?;
+    if calls != 2 || rgb.data != cpu.data {
+        anyhow::bail!(
+            "Persistent failure: {} GPU calls, output differs from the CPU debayer",
+            calls
+        );
+    }
+    println!("Persistent failure: fell back to the CPU after one retry");
+
+    let (result, calls) = run(1, false)?;
+    match result {
+        Err(e) if e.stage() == Some(PipelineStage::Debayer) && calls == 1 => {
+            println!("Fallback disabled: {}", e)
+        }
+        Err(e) => anyhow::bail!("Fallback disabled: {} after {} calls", e, calls),
+        Ok(_) => anyhow::bail!("Fallback disabled, but the failure was hidden"),
+    }
+
+    println!("OK");
+    Ok(())
+}
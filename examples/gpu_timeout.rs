@@ -0,0 +1,93 @@
+//! Checks that a GPU wait past `gpu_timeout` fails instead of hanging.
+//!
+//! A mock GPU debayer waits through `wait_with_timeout` on work that never completes,
+//! standing in for a hung device. The conversion must fail with a `CudaError` timeout
+//! well within a second, and a wait on work that completes must succeed. A zero timeout
+//! must fail validation. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example gpu_timeout`.
+
+use std::time::{Duration, Instant};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, Debayer, OutputMode,
+    RawImageData, RawImageReader, RawToTiffPipeline, Result, RgbImageData, StandardTiffWriter,
+    wait_with_timeout,
+};
+
+const TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Returns a small mid-gray mosaic
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: 16,
+            height: 16,
+            data: vec![2048; 256],
+            bits_per_sample: 12,
+            whitelevels: [4095; 4],
+            cam_to_xyz: [[0.0; 4]; 3],
+            ..Default::default()
+        })
+    }
+}
+
+/// GPU debayer whose device work never completes
+struct HungDebayer {
+    timeout: Duration,
+}
+
+impl Debayer for HungDebayer {
+    fn process(&self, _raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        wait_with_timeout(self.timeout, || Ok(false))?;
+        anyhow::bail!("hung device reported completion")
+    }
+
+    fn uses_gpu(&self) -> bool {
+        true
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .gpu_fallback(false)
+        .gpu_timeout(Some(TIMEOUT))
+        .build();
+    let pipeline = RawToTiffPipeline::with_debayer(
+        SyntheticReader,
+        StandardTiffWriter,
+        Box::new(HungDebayer { timeout: TIMEOUT }),
+        config,
+    )?;
+
+    let start = Instant::now();
+    let result = pipeline.convert_to_vec(&[]);
+    let elapsed = start.elapsed();
+    println!("Hung GPU: {:?} after {:?}", result.as_ref().err(), elapsed);
+    match result {
+        Err(e) if matches!(e.without_stage(), ConversionError::CudaError(m) if m.starts_with("timeout")) => {}
+        Err(e) => anyhow::bail!("Hung GPU failed with {:?}, expected a CudaError timeout", e),
+        Ok(_) => anyhow::bail!("Conversion on a hung GPU succeeded"),
+    }
+    if elapsed < TIMEOUT || elapsed > Duration::from_secs(1) {
+        anyhow::bail!("Timed out after {:?}, expected just over {:?}", elapsed, TIMEOUT);
+    }
+
+    let mut polls = 0;
+    wait_with_timeout(TIMEOUT, || {
+        polls += 1;
+        Ok(polls == 3)
+    })?;
+    println!("Completing work finished after {} polls", polls);
+
+    let invalid = ConversionConfig::builder().gpu_timeout(Some(Duration::ZERO)).build();
+    if invalid.validate().is_ok() {
+        anyhow::bail!("validate accepted a zero gpu_timeout");
+    }
+
+    println!("Hung GPU work times out with a CudaError");
+    Ok(())
+}
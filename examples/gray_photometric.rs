@@ -0,0 +1,127 @@
+//! Checks the `PhotometricInterpretation` tag written for grayscale output.
+//!
+//! A 12-bit frame is written as Gray16 by `StandardTiffWriter`. By default the tag must
+//! read back as BlackIsZero; with `GrayPhotometric::WhiteIsZero` it must read back as
+//! WhiteIsZero on both the serial and the parallel-strip path while the stored samples
+//! stay unchanged (the decoder inverts them on read). RGB output must keep its RGB tag.
+//! Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example gray_photometric`.
+
+use std::io::Cursor;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, GrayPhotometric, RawImageData, RgbImageData,
+    StandardTiffWriter, TiffCompression, TiffWriter,
+};
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::{PhotometricInterpretation, Tag};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+
+fn frame() -> RawImageData {
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT)
+            .map(|i| (256 + i % 3840) as u16)
+            .collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    }
+}
+
+fn config(photometric: GrayPhotometric, parallel_strips: bool) -> ConversionConfig {
+    ConversionConfig::builder()
+        .photometric(photometric)
+        .compression(TiffCompression::DeflateFast)
+        .parallel_strips(parallel_strips)
+        .build()
+}
+
+/// Photometric tag and decoded samples of an encoded single-image TIFF
+fn read_back(encoded: Vec<u8>) -> anyhow::Result<(PhotometricInterpretation, Vec<u16>)> {
+    let mut decoder = Decoder::new(Cursor::new(encoded))?;
+    let tag = decoder.get_tag_unsigned::<u16>(Tag::PhotometricInterpretation)?;
+    let photometric = PhotometricInterpretation::from_u16(tag)
+        .ok_or_else(|| anyhow::anyhow!("Unknown photometric interpretation {}", tag))?;
+    let DecodingResult::U16(samples) = decoder.read_image()? else {
+        anyhow::bail!("Expected 16-bit samples");
+    };
+    Ok((photometric, samples))
+}
+
+fn main() -> anyhow::Result<()> {
+    let raw = frame();
+
+    for parallel_strips in [false, true] {
+        let path = if parallel_strips {
+            "parallel"
+        } else {
+            "serial"
+        };
+
+        let mut encoded = Vec::new();
+        StandardTiffWriter.write_tiff(
+            &raw,
+            &mut encoded,
+            &config(GrayPhotometric::default(), parallel_strips),
+        )?;
+        let (photometric, samples) = read_back(encoded)?;
+        if photometric != PhotometricInterpretation::BlackIsZero || samples != raw.data {
+            anyhow::bail!(
+                "Default {} output was tagged {:?} or changed its samples",
+                path,
+                photometric
+            );
+        }
+
+        let mut encoded = Vec::new();
+        StandardTiffWriter.write_tiff(
+            &raw,
+            &mut encoded,
+            &config(GrayPhotometric::WhiteIsZero, parallel_strips),
+        )?;
+        let (photometric, samples) = read_back(encoded)?;
+        if photometric != PhotometricInterpretation::WhiteIsZero {
+            anyhow::bail!("WhiteIsZero {} output was tagged {:?}", path, photometric);
+        }
+        if samples
+            .iter()
+            .zip(&raw.data)
+            .any(|(&read, &stored)| read != u16::MAX - stored)
+        {
+            anyhow::bail!(
+                "WhiteIsZero {} output did not store the samples unchanged",
+                path
+            );
+        }
+        println!("{} path: default BlackIsZero, override WhiteIsZero", path);
+    }
+
+    let rgb = RgbImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: vec![1000; WIDTH * HEIGHT * 3],
+        bits_per_sample: 16,
+        exif: ExifMetadata::default(),
+    };
+    let mut encoded = Vec::new();
+    StandardTiffWriter.write_rgb_tiff(
+        &rgb,
+        &mut encoded,
+        &config(GrayPhotometric::WhiteIsZero, false),
+    )?;
+    let (photometric, _) = read_back(encoded)?;
+    if photometric != PhotometricInterpretation::RGB {
+        anyhow::bail!("RGB output was tagged {:?}", photometric);
+    }
+
+    println!("Grayscale photometric interpretation follows the config");
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Checks that the two green CFA sites are balanced with their own coefficients.
+//!
+//! A flat 12-bit RGGB frame is balanced with distinct G1/G2 coefficients. Red, blue and
+//! the first green must be untouched and every second-green sample scaled by G2/G1 above
+//! the black level. A camera without a second green coefficient (`NaN`) must leave the
+//! frame unchanged. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example green_balance`.
+
+use ffed_protosat_rs::image_pipeline::raw::corrections::balance_green_sites;
+use ffed_protosat_rs::image_pipeline::RawImageData;
+
+const BLACK: u16 = 256;
+const FLAT: u16 = 1256;
+
+fn flat_frame(wb_coeffs: [f32; 4]) -> RawImageData {
+    let (width, height) = (8, 6);
+    RawImageData {
+        width,
+        height,
+        data: vec![FLAT; width * height],
+        bits_per_sample: 12,
+        wb_coeffs,
+        blacklevels: [BLACK; 4],
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let (g1, g2) = (1.0, 1.1);
+    let mut image = flat_frame([2.0, g1, 1.5, g2]);
+    balance_green_sites(&mut image);
+
+    let expected_g2 = (BLACK as f32 + (FLAT - BLACK) as f32 * g2 / g1).round() as u16;
+    for (i, &value) in image.data.iter().enumerate() {
+        let (x, y) = (i % image.width, i / image.width);
+        let (site, expected) = match (y % 2, x % 2) {
+            (0, 0) => ("R", FLAT),
+            (0, _) => ("G1", FLAT),
+            (_, 0) => ("G2", expected_g2),
+            _ => ("B", FLAT),
+        };
+        if value != expected {
+            anyhow::bail!("{} site at ({}, {}) is {}, expected {}", site, x, y, value, expected);
+        }
+    }
+    println!("G1 kept at {}, G2 scaled to {}", FLAT, expected_g2);
+
+    let mut image = flat_frame([2.0, 1.0, 1.5, f32::NAN]);
+    balance_green_sites(&mut image);
+    if image.data.iter().any(|&value| value != FLAT) {
+        anyhow::bail!("Frame without a second green coefficient was modified");
+    }
+
+    println!("Green sites balanced independently");
+    Ok(())
+}
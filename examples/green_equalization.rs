@@ -0,0 +1,105 @@
+//! Checks that `green_equalization` removes a G1/G2 split before debayering.
+//!
+//! A flat 12-bit RGGB frame gets a +60 offset on every second-green site, which the
+//! debayer turns into a checkerboard in the green channel. `equalize_green_sites` must
+//! measure and undo the offset exactly while leaving clipped samples at white, and with
+//! `green_equalization` enabled the checkerboard in `debayer_only` output must drop to
+//! under a tenth of what it is with the option off. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example green_equalization`.
+
+use ffed_protosat_rs::image_pipeline::raw::corrections::equalize_green_sites;
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, RgbImageData, StandardTiffWriter,
+};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 24;
+const FLAT: u16 = 1500;
+const G2_OFFSET: u16 = 60;
+
+/// Flat frame whose second-green sites read `G2_OFFSET` high
+fn split_frame() -> RawImageData {
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT)
+            .map(|i| match (i / WIDTH % 2, i % WIDTH % 2) {
+                (1, 0) => FLAT + G2_OFFSET,
+                _ => FLAT,
+            })
+            .collect(),
+        bits_per_sample: 12,
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+/// Ignores the input bytes and returns `split_frame`
+struct SplitReader;
+
+impl RawImageReader for SplitReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(split_frame())
+    }
+}
+
+/// Mean absolute difference between horizontally adjacent green samples, away from the
+/// border the debayer handles differently
+fn checkerboard(image: &RgbImageData) -> f64 {
+    let green = |x: usize, y: usize| f64::from(image.data[(y * image.width + x) * 3 + 1]);
+    let pairs: Vec<f64> = (2..image.height - 2)
+        .flat_map(|y| (2..image.width - 3).map(move |x| (x, y)))
+        .map(|(x, y)| (green(x, y) - green(x + 1, y)).abs())
+        .collect();
+    pairs.iter().sum::<f64>() / pairs.len() as f64
+}
+
+fn debayered(equalize: bool) -> anyhow::Result<RgbImageData> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .exposure(1.0)
+        .green_equalization(equalize)
+        .build();
+    let pipeline = RawToTiffPipeline::with_custom(SplitReader, StandardTiffWriter, config)?;
+    Ok(pipeline.debayer_only(&[])?)
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut image = split_frame();
+    let offset = equalize_green_sites(&mut image);
+    println!("Measured offset: {:?}", offset);
+    if offset != Some(-f32::from(G2_OFFSET)) {
+        anyhow::bail!("Measured a G2 offset of {:?}, expected -{}", offset, G2_OFFSET);
+    }
+    if let Some(i) = image.data.iter().position(|&value| value != FLAT) {
+        anyhow::bail!("Sample at ({}, {}) is {} after equalization, expected {}", i % WIDTH, i / WIDTH, image.data[i], FLAT);
+    }
+
+    // A clipped second green carries no measurement, so it must stay at white
+    let mut clipped = split_frame();
+    let white = clipped.whitelevels[1];
+    clipped.data[WIDTH] = white;
+    equalize_green_sites(&mut clipped);
+    if clipped.data[WIDTH] != white {
+        anyhow::bail!("Clipped G2 sample moved from {} to {}", white, clipped.data[WIDTH]);
+    }
+
+    if ConversionConfig::default().green_equalization {
+        anyhow::bail!("green_equalization is on by default");
+    }
+    let before = checkerboard(&debayered(false)?);
+    let after = checkerboard(&debayered(true)?);
+    println!("Green checkerboard: {:.2} without equalization, {:.2} with", before, after);
+    if before < 1.0 {
+        anyhow::bail!("The injected offset left no checkerboard to correct ({:.2})", before);
+    }
+    if after > before / 10.0 {
+        anyhow::bail!("Equalization only reduced the checkerboard from {:.2} to {:.2}", before, after);
+    }
+
+    println!("G1/G2 split equalized before debayering");
+    Ok(())
+}
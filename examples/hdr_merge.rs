@@ -0,0 +1,99 @@
+//! Checks `hdr_merge` on a synthetic 2-stop bracket of a gradient.
+//!
+//! The scene is a gray ramp from 0 to 4x the sensor's white level. The 0 EV frame clips
+//! above 1.0 and the -2 EV frame holds the whole ramp, so the merge must follow the ramp
+//! past 1.0 while the 0 EV frame alone tops out at 1.0. A mismatched EV list or an empty
+//! bracket must be rejected. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example hdr_merge`.
+
+use ffed_protosat_rs::image_pipeline::{
+    RawImageData, RgbImageDataF32, hdr_merge,
+};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 8;
+const BLACK: u16 = 256;
+const WHITE: u16 = 4095;
+/// Brightest point of the ramp, in multiples of the 0 EV frame's white
+const PEAK: f32 = 4.0;
+
+/// Scene radiance at column `x`
+fn radiance(x: usize) -> f32 {
+    PEAK * x as f32 / (WIDTH - 1) as f32
+}
+
+/// The ramp captured at `ev` stops relative to the reference, clipping at white
+fn exposure(ev: f32) -> RawImageData {
+    let range = f32::from(WHITE - BLACK);
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let level = (radiance(i % WIDTH) * ev.exp2()).min(1.0);
+                BLACK + (level * range).round() as u16
+            })
+            .collect(),
+        bits_per_sample: 12,
+        blacklevels: [BLACK; 4],
+        whitelevels: [WHITE; 4],
+        // sRGB to XYZ, so the color matrix comes out as the identity
+        cam_to_xyz: [
+            [0.4124564, 0.3575761, 0.1804375, 0.0],
+            [0.2126729, 0.7151522, 0.0721750, 0.0],
+            [0.0193339, 0.119192, 0.9503041, 0.0],
+        ],
+        ..Default::default()
+    }
+}
+
+fn max_value(image: &RgbImageDataF32) -> f32 {
+    image.data.iter().copied().fold(f32::MIN, f32::max)
+}
+
+fn main() -> anyhow::Result<()> {
+    let bright = exposure(0.0);
+    let dark = exposure(-2.0);
+
+    let single = hdr_merge(std::slice::from_ref(&bright), &[0.0])?;
+    println!("0 EV alone peaks at {:.3}", max_value(&single));
+    if max_value(&single) > 1.05 {
+        anyhow::bail!("0 EV frame alone peaks at {}, expected it to clip at 1.0", max_value(&single));
+    }
+
+    let merged = hdr_merge(&[dark.clone(), bright.clone()], &[-2.0, 0.0])?;
+    if (merged.width, merged.height) != (WIDTH, HEIGHT) {
+        anyhow::bail!("Merged image is {}x{}, expected {}x{}", merged.width, merged.height, WIDTH, HEIGHT);
+    }
+    println!("Merge peaks at {:.3}", max_value(&merged));
+    if max_value(&merged) < PEAK * 0.9 {
+        anyhow::bail!("Merge peaks at {}, expected extended range up to {}", max_value(&merged), PEAK);
+    }
+
+    // Columns at the edges see mirrored neighbours and are skipped
+    for y in 0..HEIGHT {
+        for x in 2..WIDTH - 2 {
+            let expected = radiance(x);
+            let i = (y * WIDTH + x) * 3;
+            for (c, &value) in merged.data[i..i + 3].iter().enumerate() {
+                if (value - expected).abs() > expected * 0.02 + 0.005 {
+                    anyhow::bail!(
+                        "Pixel ({}, {}) channel {} is {}, expected {} on the ramp",
+                        x, y, c, value, expected
+                    );
+                }
+            }
+        }
+    }
+
+    if hdr_merge(&[dark.clone(), bright], &[-2.0]).is_ok() {
+        anyhow::bail!("Merge accepted one EV step for two frames");
+    }
+    if hdr_merge(&[], &[]).is_ok() {
+        anyhow::bail!("Merge accepted an empty bracket");
+    }
+
+    println!("HDR merge follows the ramp beyond the 0 EV frame's white");
+    Ok(())
+}
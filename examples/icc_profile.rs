@@ -0,0 +1,131 @@
+//! Checks that `embed_icc` tags RGB output with a well-formed linear sRGB ICC profile.
+//!
+//! A synthetic RGB image is written through the buffered, parallel-strip and streaming
+//! paths of `StandardTiffWriter` with `embed_icc` set. Each file must carry a non-empty
+//! `ICCProfile` whose header is consistent (size, `acsp` signature, RGB display class)
+//! and whose tone curves are the identity. Grayscale output and RGB output without
+//! `embed_icc` must carry no profile. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example icc_profile`.
+
+use std::io::Cursor;
+
+use tiff::decoder::Decoder;
+use tiff::tags::Tag;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, RgbImageData, StandardTiffWriter,
+    TiffCompression, TiffWriter,
+};
+
+/// `ICCProfile` of the first IFD, `None` if absent
+fn icc_profile(tiff: Vec<u8>) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut decoder = Decoder::new(Cursor::new(tiff))?;
+    match decoder.find_tag(Tag::IccProfile)? {
+        Some(_) => Ok(Some(decoder.get_tag_u8_vec(Tag::IccProfile)?)),
+        None => Ok(None),
+    }
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Checks the header and that the red, green and blue TRC tags are empty `curv` elements
+fn check_profile(profile: &[u8]) -> anyhow::Result<()> {
+    if profile.len() < 132 || u32_at(profile, 0) as usize != profile.len() {
+        anyhow::bail!(
+            "Profile size field does not match its {} bytes",
+            profile.len()
+        );
+    }
+    if &profile[36..40] != b"acsp" || &profile[12..16] != b"mntr" || &profile[16..20] != b"RGB " {
+        anyhow::bail!("Profile header is not an RGB display profile");
+    }
+
+    let mut curves = 0;
+    for entry in 0..u32_at(profile, 128) as usize {
+        let base = 132 + entry * 12;
+        let (offset, size) = (
+            u32_at(profile, base + 4) as usize,
+            u32_at(profile, base + 8) as usize,
+        );
+        if offset + size > profile.len() {
+            anyhow::bail!("Tag {} points outside the profile", entry);
+        }
+        if profile[base..base + 4].ends_with(b"TRC") {
+            let element = &profile[offset..offset + size];
+            if &element[..4] != b"curv" || u32_at(element, 8) != 0 {
+                anyhow::bail!("Tone curve is not the identity");
+            }
+            curves += 1;
+        }
+    }
+    if curves != 3 {
+        anyhow::bail!("Expected 3 tone curves, found {}", curves);
+    }
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let (width, height) = (48, 32);
+    let rgb = RgbImageData {
+        width,
+        height,
+        data: (0..width * height * 3)
+            .map(|i| (i * 97 % 65536) as u16)
+            .collect(),
+        bits_per_sample: 16,
+        exif: ExifMetadata::default(),
+    };
+    let gray = rgb.luminance_image();
+
+    let embed = ConversionConfig::builder().embed_icc(true).build();
+    let parallel = ConversionConfig::builder()
+        .embed_icc(true)
+        .compression(TiffCompression::Lzw)
+        .parallel_strips(true)
+        .build();
+
+    let mut buffered = Vec::new();
+    StandardTiffWriter.write_rgb_tiff(&rgb, &mut buffered, &embed)?;
+    let mut strips = Vec::new();
+    StandardTiffWriter.write_rgb_tiff(&rgb, &mut strips, &parallel)?;
+    let mut streamed = Cursor::new(Vec::new());
+    StandardTiffWriter.write_rgb_tiff_streaming(
+        width,
+        height,
+        rgb.data.chunks_exact(width * 3),
+        &rgb.exif,
+        &mut streamed,
+        &embed,
+    )?;
+
+    let mut embedded = Vec::new();
+    for (name, tiff) in [
+        ("buffered", buffered),
+        ("parallel strips", strips),
+        ("streaming", streamed.into_inner()),
+    ] {
+        let Some(profile) = icc_profile(tiff)? else {
+            anyhow::bail!("{} output has no ICCProfile", name);
+        };
+        check_profile(&profile)?;
+        println!("{}: {}-byte profile", name, profile.len());
+        embedded.push(profile);
+    }
+    if embedded.windows(2).any(|pair| pair[0] != pair[1]) {
+        anyhow::bail!("Writer paths embedded different profiles");
+    }
+
+    let mut plain = Vec::new();
+    StandardTiffWriter.write_rgb_tiff(&rgb, &mut plain, &ConversionConfig::default())?;
+    let mut gray_tiff = Vec::new();
+    StandardTiffWriter.write_gray_tiff(&gray, &mut gray_tiff, &embed)?;
+    if icc_profile(plain)?.is_some() || icc_profile(gray_tiff)?.is_some() {
+        anyhow::bail!("Profile written without embed_icc or on grayscale output");
+    }
+
+    println!("ICC profile embedded only where requested");
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! Round-trips `RgbImageData` through the `image` crate's `ImageBuffer`.
+//!
+//! A synthetic 16-bit RGB frame with a distinct value in every sample is converted with
+//! `to_image_buffer`. The buffer must keep the frame's dimensions and every pixel, and an
+//! `image` crate transform (a horizontal flip) must move pixels where expected. Exits
+//! non-zero otherwise.
+//!
+//! Run with `cargo run --example image_interop --features image-interop`.
+
+use ffed_protosat_rs::image_pipeline::{ExifMetadata, RgbImageData};
+use image::imageops;
+
+const WIDTH: usize = 24;
+const HEIGHT: usize = 10;
+
+fn pixel(x: usize, y: usize) -> [u16; 3] {
+    let base = ((y * WIDTH + x) * 3) as u16;
+    [base * 11, base * 11 + 1, 65535 - base * 11]
+}
+
+fn main() -> anyhow::Result<()> {
+    let rgb = RgbImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT)
+            .flat_map(|i| pixel(i % WIDTH, i / WIDTH))
+            .collect(),
+        bits_per_sample: 16,
+        exif: ExifMetadata::default(),
+    };
+
+    let buffer = rgb.to_image_buffer();
+    println!("ImageBuffer is {}x{}", buffer.width(), buffer.height());
+    if buffer.dimensions() != (WIDTH as u32, HEIGHT as u32) {
+        anyhow::bail!(
+            "ImageBuffer is {}x{}, expected {}x{}",
+            buffer.width(),
+            buffer.height(),
+            WIDTH,
+            HEIGHT
+        );
+    }
+    for (x, y) in [(0, 0), (WIDTH - 1, 0), (5, 3), (0, HEIGHT - 1), (WIDTH - 1, HEIGHT - 1)] {
+        let actual = buffer.get_pixel(x as u32, y as u32).0;
+        if actual != pixel(x, y) {
+            anyhow::bail!("Pixel ({}, {}) is {:?}, expected {:?}", x, y, actual, pixel(x, y));
+        }
+    }
+    if buffer.as_raw() != &rgb.data {
+        anyhow::bail!("ImageBuffer samples differ from the RgbImageData");
+    }
+
+    let flipped = imageops::flip_horizontal(&buffer);
+    let actual = flipped.get_pixel(0, 3).0;
+    if actual != pixel(WIDTH - 1, 3) {
+        anyhow::bail!(
+            "Flipped pixel (0, 3) is {:?}, expected {:?}",
+            actual,
+            pixel(WIDTH - 1, 3)
+        );
+    }
+
+    println!("RgbImageData round-trips through ImageBuffer");
+    Ok(())
+}
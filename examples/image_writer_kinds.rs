@@ -0,0 +1,130 @@
+//! Checks writing every `ImageKind` through a boxed `ImageWriter`.
+//!
+//! A `StandardTiffWriter` and an 8-bit PNM writer, both held as `Box<dyn ImageWriter>`,
+//! get a 16-bit grayscale, a 16-bit RGB and a float RGB image. The TIFFs must decode to
+//! the matching color type, dimensions and samples; the PNM writer must emit a PGM and a
+//! PPM and reject the float image as unsupported. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example image_writer_kinds`.
+
+use std::io::{Cursor, Write};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, ExifMetadata, ImageKind, ImageWriter,
+    RawImageData, Result, RgbImageData, RgbImageDataF32, StandardTiffWriter,
+};
+use tiff::ColorType;
+use tiff::decoder::{Decoder, DecodingResult};
+
+const WIDTH: usize = 20;
+const HEIGHT: usize = 12;
+
+/// Binary 8-bit PGM for grayscale and PPM for 16-bit RGB, keeping the high byte
+struct PnmWriter;
+
+impl ImageWriter for PnmWriter {
+    fn write(
+        &self,
+        image: ImageKind<'_>,
+        output: &mut dyn Write,
+        _config: &ConversionConfig,
+    ) -> Result<()> {
+        let (magic, data) = match image {
+            ImageKind::Gray16(image) => ("P5", &image.data),
+            ImageKind::Rgb16(image) => ("P6", &image.data),
+            ImageKind::RgbF32(_) => {
+                return Err(ConversionError::UnsupportedFormat(
+                    "PNM holds integer samples only".to_string(),
+                ));
+            }
+        };
+        let (width, height) = image.dimensions();
+        write!(output, "{}\n{} {}\n255\n", magic, width, height)?;
+        let bytes: Vec<u8> = data.iter().map(|&v| (v >> 8) as u8).collect();
+        output.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+fn gray() -> RawImageData {
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT).map(|i| (i * 257) as u16).collect(),
+        is_bayer: false,
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    }
+}
+
+fn rgb_f32() -> RgbImageDataF32 {
+    RgbImageDataF32 {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT * 3)
+            .map(|i| i as f32 / (WIDTH * HEIGHT * 3) as f32)
+            .collect(),
+        exif: ExifMetadata::default(),
+    }
+}
+
+/// Decodes `tiff`, checking its dimensions and color type, and returns the samples
+fn decode(tiff: Vec<u8>, color_type: ColorType) -> anyhow::Result<DecodingResult> {
+    let mut decoder = Decoder::new(Cursor::new(tiff))?;
+    if decoder.dimensions()? != (WIDTH as u32, HEIGHT as u32) {
+        anyhow::bail!("TIFF is {:?}, expected {}x{}", decoder.dimensions()?, WIDTH, HEIGHT);
+    }
+    if decoder.colortype()? != color_type {
+        anyhow::bail!("TIFF is {:?}, expected {:?}", decoder.colortype()?, color_type);
+    }
+    Ok(decoder.read_image()?)
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::default();
+    let gray = gray();
+    let rgb_f32 = rgb_f32();
+    let rgb: RgbImageData = rgb_f32.to_u16();
+
+    let tiff: Box<dyn ImageWriter> = Box::new(StandardTiffWriter);
+    let mut out = Vec::new();
+    tiff.write(ImageKind::Gray16(&gray), &mut out, &config)?;
+    match decode(out, ColorType::Gray(16))? {
+        DecodingResult::U16(samples) if samples == gray.data => println!("Gray16 TIFF round-trips"),
+        _ => anyhow::bail!("Gray16 TIFF samples differ"),
+    }
+    let mut out = Vec::new();
+    tiff.write(ImageKind::Rgb16(&rgb), &mut out, &config)?;
+    match decode(out, ColorType::RGB(16))? {
+        DecodingResult::U16(samples) if samples == rgb.data => println!("Rgb16 TIFF round-trips"),
+        _ => anyhow::bail!("Rgb16 TIFF samples differ"),
+    }
+    let mut out = Vec::new();
+    tiff.write(ImageKind::RgbF32(&rgb_f32), &mut out, &config)?;
+    match decode(out, ColorType::RGB(32))? {
+        DecodingResult::F32(samples) if samples == rgb_f32.data => println!("RgbF32 TIFF round-trips"),
+        _ => anyhow::bail!("RgbF32 TIFF samples differ"),
+    }
+
+    let pnm: Box<dyn ImageWriter> = Box::new(PnmWriter);
+    for (kind, magic, samples) in [
+        (ImageKind::Gray16(&gray), "P5", WIDTH * HEIGHT),
+        (ImageKind::Rgb16(&rgb), "P6", WIDTH * HEIGHT * 3),
+    ] {
+        let mut out = Vec::new();
+        pnm.write(kind, &mut out, &config)?;
+        let header = format!("{}\n{} {}\n255\n", magic, WIDTH, HEIGHT);
+        if !out.starts_with(header.as_bytes()) || out.len() != header.len() + samples {
+            anyhow::bail!("{} output has an unexpected header or length", magic);
+        }
+        println!("{}: {} bytes", magic, out.len());
+    }
+    match pnm.write(ImageKind::RgbF32(&rgb_f32), &mut Vec::new(), &config) {
+        Err(ConversionError::UnsupportedFormat(reason)) => println!("RgbF32 rejected: {}", reason),
+        Err(e) => anyhow::bail!("RgbF32 failed with {}, expected UnsupportedFormat", e),
+        Ok(()) => anyhow::bail!("PNM writer accepted float samples"),
+    }
+
+    println!("Every image kind reaches its writer through dyn ImageWriter");
+    Ok(())
+}
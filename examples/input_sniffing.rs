@@ -0,0 +1,96 @@
+//! Checks that `RawLoaderReader` rejects inputs that are obviously not RAW files.
+//!
+//! A JPEG header, a PNG header and a zero-length buffer must fail with an
+//! `UnsupportedFormat` error naming what was detected, from `read_raw`, `probe` and a
+//! full pipeline conversion (tagged as the decode stage). A truncated TIFF-based RAW
+//! must still reach rawloader and fail with a `RawDecode` error naming the container.
+//! Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example input_sniffing`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, OutputMode, PipelineStage, RawImageReader, RawLoaderReader,
+    RawToTiffPipeline,
+};
+
+const JPEG: &[u8] = b"\xFF\xD8\xFF\xE0\x00\x10JFIF\x00\x01\x01\x00\x00\x01\x00\x01\x00\x00";
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00\x00\x01";
+/// Little-endian TIFF header followed by an empty first IFD, as left by a file cut off
+/// before any image data
+const TRUNCATED_TIFF: &[u8] =
+    b"II*\x00\x08\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+
+fn expect_unsupported(
+    name: &str,
+    result: Result<impl std::fmt::Debug, ConversionError>,
+    detail: &str,
+) -> anyhow::Result<()> {
+    match result {
+        Err(e) => match e.without_stage() {
+            ConversionError::UnsupportedFormat(message) if message.contains(detail) => {
+                println!("{}: {}", name, e);
+                Ok(())
+            }
+            other => anyhow::bail!(
+                "{}: expected UnsupportedFormat with {:?}, got {}",
+                name,
+                detail,
+                other
+            ),
+        },
+        Ok(value) => anyhow::bail!("{}: accepted as {:?}", name, value),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let cases: [(&str, &[u8], &str); 3] = [
+        ("JPEG", JPEG, "not a RAW file: detected JPEG"),
+        ("PNG", PNG, "not a RAW file: detected PNG"),
+        ("empty", &[], "not a RAW file: input is empty"),
+    ];
+
+    let pipeline = RawToTiffPipeline::new(
+        ConversionConfig::builder()
+            .output(OutputMode::BayerGray)
+            .build(),
+    )?;
+
+    for (name, data, detail) in cases {
+        expect_unsupported(
+            &format!("{} read_raw", name),
+            RawLoaderReader.read_raw(data).map(|_| ()),
+            detail,
+        )?;
+        expect_unsupported(
+            &format!("{} probe", name),
+            RawLoaderReader.probe(data),
+            detail,
+        )?;
+
+        let result = pipeline.convert_to_vec(data);
+        if let Err(e) = &result
+            && e.stage() != Some(PipelineStage::Decode)
+        {
+            anyhow::bail!("{} conversion failed outside the decode stage: {}", name, e);
+        }
+        expect_unsupported(
+            &format!("{} convert", name),
+            result.map(|tiff| tiff.len()),
+            detail,
+        )?;
+    }
+
+    match RawLoaderReader.read_raw(TRUNCATED_TIFF) {
+        Err(e @ ConversionError::RawDecode { .. }) if e.to_string().contains("TIFF-based RAW") => {
+            println!("truncated TIFF: {}", e)
+        }
+        Err(e) => anyhow::bail!(
+            "truncated TIFF: expected a RawDecode error naming the container, got {}",
+            e
+        ),
+        Ok(_) => anyhow::bail!("truncated TIFF decoded"),
+    }
+
+    println!("OK");
+    Ok(())
+}
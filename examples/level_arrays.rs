@@ -0,0 +1,71 @@
+//! Checks how `expand_levels` maps black and white level lists of any length.
+//!
+//! One value must apply to every channel, three values must give the second green the
+//! green level, four must pass through, and other counts must average. A mosaic whose
+//! black levels come from three values is then normalized with `normalized_to_16_bit`.
+//! Every CFA site must lose its own channel's black level, including the blue-row green
+//! site that had no value of its own. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example level_arrays`.
+
+use ffed_protosat_rs::image_pipeline::raw::types::expand_levels;
+use ffed_protosat_rs::image_pipeline::RawImageData;
+
+const WIDTH: usize = 8;
+const HEIGHT: usize = 6;
+const SAMPLE: u16 = 1000;
+
+fn main() -> anyhow::Result<()> {
+    let cases: [(&[u16], [u16; 4]); 6] = [
+        (&[512], [512; 4]),
+        (&[100, 200, 300], [100, 200, 300, 200]),
+        (&[100, 200, 300, 210], [100, 200, 300, 210]),
+        (&[100, 201], [151; 4]),
+        (&[4000, 4010, 4020, 4030, 4040], [4020; 4]),
+        (&[], [0; 4]),
+    ];
+    for (values, expected) in cases {
+        let actual = expand_levels(values);
+        if actual != expected {
+            anyhow::bail!("{:?} expanded to {:?}, expected {:?}", values, actual, expected);
+        }
+        println!("{:?} -> {:?}", values, actual);
+    }
+
+    let blacklevels = expand_levels(&[100, 200, 300]);
+    let raw = RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: vec![SAMPLE; WIDTH * HEIGHT],
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels,
+        whitelevels: expand_levels(&[4095]),
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    };
+    let normalized = raw.normalized_to_16_bit();
+    for (i, &value) in normalized.iter().enumerate() {
+        let (x, y) = (i % WIDTH, i / WIDTH);
+        let black = match (y % 2, x % 2) {
+            (0, 0) => 100,
+            (1, 1) => 300,
+            _ => 200,
+        };
+        let range = (4095 - black) as f32;
+        let expected = ((SAMPLE - black) as f32 * u16::MAX as f32 / range).round() as u16;
+        if value != expected {
+            anyhow::bail!(
+                "Site ({}, {}) normalized to {}, expected {} for black level {}",
+                x,
+                y,
+                value,
+                expected,
+                black
+            );
+        }
+    }
+
+    println!("Expanded levels reach every CFA site");
+    Ok(())
+}
@@ -0,0 +1,84 @@
+//! Checks `OutputMode::Luminance`.
+//!
+//! Full-scale primaries must collapse to their Rec.709 weights: pure green to 0.7152,
+//! red to 0.2126 and blue to 0.0722 of 65535, within one code. A synthetic frame
+//! converted with `OutputMode::Luminance` must then decode as a single-channel 16-bit
+//! TIFF of the frame's dimensions holding the luminance of the `debayer_only` output.
+//! Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example luminance_output`.
+
+use std::io::Cursor;
+
+use tiff::ColorType;
+use tiff::decoder::{Decoder, DecodingResult};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, OutputMode, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, RgbImageData, StandardTiffWriter,
+};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 24;
+
+/// Returns a 12-bit gradient mosaic
+struct GradientReader;
+
+impl RawImageReader for GradientReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: (0..WIDTH * HEIGHT).map(|i| 256 + (i * 5 % 3800) as u16).collect(),
+            bits_per_sample: 12,
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    for (name, pixel, weight) in [
+        ("green", [0, u16::MAX, 0], 0.7152),
+        ("red", [u16::MAX, 0, 0], 0.2126),
+        ("blue", [0, 0, u16::MAX], 0.0722),
+    ] {
+        let image = RgbImageData {
+            width: 1,
+            height: 1,
+            data: pixel.to_vec(),
+            bits_per_sample: 16,
+            exif: ExifMetadata::default(),
+        };
+        let luminance = image.luminance()[0];
+        let expected = weight * f64::from(u16::MAX);
+        println!("Pure {}: luminance {} ({:.4} of full scale)", name, luminance, f64::from(luminance) / 65535.0);
+        if (f64::from(luminance) - expected).abs() > 1.0 {
+            anyhow::bail!("Pure {} gave luminance {}, expected {:.1}", name, luminance, expected);
+        }
+    }
+
+    let config = ConversionConfig::builder().output(OutputMode::Luminance).build();
+    let pipeline = RawToTiffPipeline::with_custom(GradientReader, StandardTiffWriter, config)?;
+    let tiff = pipeline.convert_to_vec(&[])?;
+    let expected = pipeline.debayer_only(&[])?.luminance();
+
+    let mut decoder = Decoder::new(Cursor::new(tiff))?;
+    let color_type = decoder.colortype()?;
+    if color_type != ColorType::Gray(16) {
+        anyhow::bail!("Luminance output is {:?}, expected Gray(16)", color_type);
+    }
+    let (width, height) = decoder.dimensions()?;
+    if (width as usize, height as usize) != (WIDTH, HEIGHT) {
+        anyhow::bail!("Luminance output is {}x{}, expected {}x{}", width, height, WIDTH, HEIGHT);
+    }
+    match decoder.read_image()? {
+        DecodingResult::U16(samples) if samples == expected => {}
+        DecodingResult::U16(_) => anyhow::bail!("Luminance samples differ from the debayered image's luminance"),
+        _ => anyhow::bail!("Luminance output is not 16-bit"),
+    }
+
+    println!("Luminance output is the Rec.709 luminance of the debayered image");
+    Ok(())
+}
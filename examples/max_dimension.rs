@@ -0,0 +1,67 @@
+//! Checks that `max_dimension` bounds the frames the pipeline accepts.
+//!
+//! A mock reader reports frames of a given size to a pipeline limited to 64 pixels a
+//! side. Frames at and under the limit must convert; a frame 65 pixels wide or 65 tall
+//! must fail validation with `InvalidDimensions` carrying its size. With
+//! `validate_dimensions` off the oversized frame must convert. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example max_dimension`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, OutputMode, PipelineStage, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, StandardTiffWriter,
+};
+
+const MAX: usize = 64;
+
+/// Returns a flat frame of its dimensions
+struct SizedReader {
+    width: usize,
+    height: usize,
+}
+
+impl RawImageReader for SizedReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: self.width,
+            height: self.height,
+            data: vec![1000; self.width * self.height],
+            ..Default::default()
+        })
+    }
+}
+
+fn convert(width: usize, height: usize, validate: bool) -> Result<Vec<u8>> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::BayerGray)
+        .max_dimension(Some(MAX))
+        .validate_dimensions(validate)
+        .build();
+    RawToTiffPipeline::with_custom(SizedReader { width, height }, StandardTiffWriter, config)?.convert_to_vec(&[])
+}
+
+fn main() -> anyhow::Result<()> {
+    for (width, height) in [(16, 16), (MAX, 8), (8, MAX), (MAX, MAX)] {
+        convert(width, height, true).map_err(|e| anyhow::anyhow!("{}x{} within the limit failed: {}", width, height, e))?;
+        println!("{}x{} converted", width, height);
+    }
+
+    for (width, height) in [(MAX + 1, 8), (8, MAX + 1), (MAX + 1, MAX + 1)] {
+        match convert(width, height, true) {
+            Err(e) if e.stage() == Some(PipelineStage::Validate) => match e.without_stage() {
+                ConversionError::InvalidDimensions(w, h) if (*w, *h) == (width, height) => {
+                    println!("{}x{} rejected: {}", width, height, e)
+                }
+                other => anyhow::bail!("{}x{}: expected InvalidDimensions, got {}", width, height, other),
+            },
+            Err(e) => anyhow::bail!("{}x{}: expected a validation error, got {}", width, height, e),
+            Ok(_) => anyhow::bail!("{}x{} over the limit of {} converted", width, height, MAX),
+        }
+    }
+
+    convert(MAX + 1, MAX + 1, false)
+        .map_err(|e| anyhow::anyhow!("Oversized frame failed without validate_dimensions: {}", e))?;
+
+    println!("max_dimension accepts frames up to {} pixels a side", MAX);
+    Ok(())
+}
@@ -0,0 +1,128 @@
+//! Checks that `max_open_files` caps the output files a batch holds open at once,
+//! independently of `batch_threads`.
+//!
+//! A writer counts how many jobs are between their first write, which opens the output
+//! file, and returning, sleeping in between so the jobs overlap. The batch runs on 8
+//! workers once unbounded and once with `max_open_files(Some(2))`. Exits non-zero if the
+//! limited run ever has more than 2 files open, or the unbounded run never exceeds 2
+//! (which would mean the check proves nothing).
+//!
+//! Run with `cargo run --example max_open_files`.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, GrayImageData, OutputMode, RawImageData,
+    RawImageReader, RawToTiffPipeline, Result, RgbImageData, RgbImageDataF32, TiffWriter,
+};
+
+const JOBS: usize = 24;
+const THREADS: usize = 8;
+const LIMIT: usize = 2;
+
+/// Returns an 8x8 frame of mid-gray samples
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: 8,
+            height: 8,
+            data: vec![2048; 64],
+            bits_per_sample: 12,
+            whitelevels: [4095; 4],
+            cam_to_xyz: [[0.0; 4]; 3],
+            ..Default::default()
+        })
+    }
+}
+
+/// Tracks how many writes hold their output open, and the most seen at once
+#[derive(Default)]
+struct OpenCounter {
+    open: AtomicUsize,
+    max: AtomicUsize,
+}
+
+struct CountingWriter<'a> {
+    counter: &'a OpenCounter,
+}
+
+impl TiffWriter for CountingWriter<'_> {
+    fn write_tiff(&self, image: &RawImageData, output: &mut dyn Write, _: &ConversionConfig) -> Result<()> {
+        // The first write opens the output file; it stays open until the job returns
+        output.write_all(b"II")?;
+        let open = self.counter.open.fetch_add(1, Ordering::SeqCst) + 1;
+        self.counter.max.fetch_max(open, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(20));
+        self.counter.open.fetch_sub(1, Ordering::SeqCst);
+
+        let bytes: Vec<u8> = image.data.iter().flat_map(|v| v.to_le_bytes()).collect();
+        output.write_all(&bytes)?;
+        Ok(())
+    }
+
+    fn write_gray_tiff(&self, _: &GrayImageData, _: &mut dyn Write, _: &ConversionConfig) -> Result<()> {
+        Err(ConversionError::EncodeError("Gray output not expected".to_string()))
+    }
+
+    fn write_rgb_tiff(&self, _: &RgbImageData, _: &mut dyn Write, _: &ConversionConfig) -> Result<()> {
+        Err(ConversionError::EncodeError("RGB output not expected".to_string()))
+    }
+
+    fn write_rgb_tiff_f32(&self, _: &RgbImageDataF32, _: &mut dyn Write, _: &ConversionConfig) -> Result<()> {
+        Err(ConversionError::EncodeError("RGB output not expected".to_string()))
+    }
+}
+
+/// Runs the batch and returns the most outputs seen open at once
+fn run(jobs: &[(PathBuf, PathBuf)], max_open_files: Option<usize>) -> anyhow::Result<usize> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::BayerGray)
+        .batch_threads(Some(THREADS))
+        .max_open_files(max_open_files)
+        .build();
+    let counter = OpenCounter::default();
+    let pipeline = RawToTiffPipeline::with_custom(SyntheticReader, CountingWriter { counter: &counter }, config)?;
+
+    for result in pipeline.convert_batch(jobs) {
+        result?;
+    }
+    for (_, output) in jobs {
+        let len = std::fs::metadata(output)?.len();
+        if len != 2 + 64 * 2 {
+            anyhow::bail!("{} is {} bytes, expected {}", output.display(), len, 2 + 64 * 2);
+        }
+    }
+    Ok(counter.max.load(Ordering::SeqCst))
+}
+
+fn main() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let jobs: Vec<(PathBuf, PathBuf)> = (0..JOBS)
+        .map(|i| {
+            let input = dir.path().join(format!("frame_{i}.raw"));
+            let output = dir.path().join(format!("frame_{i}.tiff"));
+            std::fs::write(&input, [i as u8])?;
+            Ok((input, output))
+        })
+        .collect::<std::io::Result<_>>()?;
+
+    let unbounded = run(&jobs, None)?;
+    println!("Unbounded: at most {} outputs open", unbounded);
+    if unbounded <= LIMIT {
+        anyhow::bail!("Unbounded batch never had more than {} outputs open, check is meaningless", LIMIT);
+    }
+
+    let limited = run(&jobs, Some(LIMIT))?;
+    println!("max_open_files({}): at most {} outputs open", LIMIT, limited);
+    if limited > LIMIT {
+        anyhow::bail!("{} outputs open at once, limit was {}", limited, LIMIT);
+    }
+
+    println!("max_open_files bounds concurrently open outputs");
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Checks that memory-mapped input converts exactly like input read onto the heap.
+//!
+//! A reader that decodes its input bytes (a width/height header followed by 12-bit
+//! little-endian samples) stands in for rawloader. A large frame is written to disk and
+//! converted with `convert_file`, which maps it with the `mmap` feature; the result must
+//! be byte-identical to converting `std::fs::read` of the same file. `read_input` must map
+//! the frame and fall back to reading for an empty file, which cannot be mapped. Exits
+//! non-zero otherwise.
+//!
+//! Run with `cargo run --example mmap_input --features mmap`.
+
+use ffed_protosat_rs::image_pipeline::common::{InputData, read_input};
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, OutputMode, RawImageData,
+    RawImageReader, RawToTiffPipeline, Result, StandardTiffWriter, TiffCompression,
+};
+
+const WIDTH: usize = 2048;
+const HEIGHT: usize = 1536;
+
+/// Decodes `[width: u16, height: u16, samples: u16...]`, all little-endian
+struct HeaderReader;
+
+impl RawImageReader for HeaderReader {
+    fn read_raw(&self, data: &[u8]) -> Result<RawImageData> {
+        let mut words = data
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]));
+        let (Some(width), Some(height)) = (words.next(), words.next()) else {
+            return Err(ConversionError::DecodeError("Missing header".to_string()));
+        };
+        Ok(RawImageData {
+            width: width as usize,
+            height: height as usize,
+            data: words.collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            cam_to_xyz: [[0.0; 4]; 3],
+            ..Default::default()
+        })
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let input = dir.path().join("frame.raw");
+    let output = dir.path().join("frame.tiff");
+
+    let bytes: Vec<u8> = [WIDTH as u16, HEIGHT as u16]
+        .into_iter()
+        .chain((0..WIDTH * HEIGHT).map(|i| (256 + (i * 7) % 3840) as u16))
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    std::fs::write(&input, &bytes)?;
+
+    match read_input(&input)? {
+        InputData::Mapped(map) if map[..] == bytes[..] => {
+            println!("Mapped {} bytes", map.len())
+        }
+        InputData::Mapped(_) => anyhow::bail!("Mapped input differs from the file contents"),
+        InputData::Owned(_) => anyhow::bail!("Input was read instead of mapped"),
+    }
+
+    let empty = dir.path().join("empty.raw");
+    std::fs::write(&empty, [])?;
+    match read_input(&empty)? {
+        InputData::Owned(data) if data.is_empty() => println!("Empty file fell back to reading"),
+        _ => anyhow::bail!("Empty file should fall back to an empty read"),
+    }
+
+    let config = ConversionConfig::builder()
+        .output(OutputMode::BayerGray)
+        .compression(TiffCompression::Lzw)
+        .build();
+    let pipeline = RawToTiffPipeline::with_custom(HeaderReader, StandardTiffWriter, config)?;
+
+    pipeline.convert_file(&input, &output)?;
+    let mapped = std::fs::read(&output)?;
+    let read = pipeline.convert_to_vec(&std::fs::read(&input)?)?;
+
+    if mapped != read {
+        anyhow::bail!(
+            "Memory-mapped conversion wrote {} bytes, read-based conversion {} bytes, and they differ",
+            mapped.len(),
+            read.len()
+        );
+    }
+    println!(
+        "{}x{} frame converted identically ({} bytes)",
+        WIDTH,
+        HEIGHT,
+        mapped.len()
+    );
+
+    println!("Memory-mapped input matches read-based conversion");
+    Ok(())
+}
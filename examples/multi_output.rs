@@ -0,0 +1,125 @@
+//! Checks that `convert_multi` hands one debayered image to every registered writer.
+//!
+//! The pipeline gets a `StandardTiffWriter` and an 8-bit PPM preview writer that also
+//! records the image it was given. The TIFF must decode to exactly the recorded image,
+//! the PPM must hold the same pixels scaled to 8 bits, and a call with the wrong number
+//! of outputs must fail. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example multi_output`.
+
+use std::io::{Cursor, Write};
+use std::sync::{Arc, Mutex};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ConversionError, DebayerQuality, ImageKind, ImageWriter, OutputMode, RawImageData, RawImageReader, RawToTiffPipeline, Result,
+    RgbImageData, StandardTiffWriter,
+};
+use tiff::decoder::{Decoder, DecodingResult};
+
+/// Ignores the input bytes and returns a small 12-bit RGGB gradient
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        let (width, height) = (64, 48);
+        Ok(RawImageData {
+            width,
+            height,
+            data: (0..width * height)
+                .map(|i| (i % 3840 + 256) as u16)
+                .collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+/// Writes a binary 8-bit PPM and keeps a copy of the image it received
+struct PpmPreviewWriter {
+    received: Arc<Mutex<Option<RgbImageData>>>,
+}
+
+impl ImageWriter for PpmPreviewWriter {
+    fn write(
+        &self,
+        image: ImageKind<'_>,
+        output: &mut dyn Write,
+        _config: &ConversionConfig,
+    ) -> Result<()> {
+        let ImageKind::Rgb16(image) = image else {
+            return Err(ConversionError::UnsupportedFormat(
+                "PPM previews are 16-bit RGB only".to_string(),
+            ));
+        };
+        write!(output, "P6\n{} {}\n255\n", image.width, image.height)?;
+        let shift = image.bits_per_sample.saturating_sub(8);
+        let bytes: Vec<u8> = image.data.iter().map(|&v| (v >> shift) as u8).collect();
+        output.write_all(&bytes)?;
+        *self.received.lock().unwrap() = Some(image.clone());
+        Ok(())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .build();
+    let mut pipeline = RawToTiffPipeline::with_custom(SyntheticReader, StandardTiffWriter, config)?;
+
+    let received = Arc::new(Mutex::new(None));
+    pipeline.add_writer(Box::new(StandardTiffWriter));
+    pipeline.add_writer(Box::new(PpmPreviewWriter {
+        received: received.clone(),
+    }));
+
+    let (mut tiff_out, mut ppm_out) = (Vec::new(), Vec::new());
+    pipeline.convert_multi(&[], &mut [&mut tiff_out, &mut ppm_out])?;
+
+    let image = received
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("Preview writer was not called"))?;
+
+    let mut decoder = Decoder::new(Cursor::new(tiff_out))?;
+    let DecodingResult::U16(tiff_samples) = decoder.read_image()? else {
+        anyhow::bail!("TIFF output is not 16-bit");
+    };
+    if decoder.dimensions()? != (image.width as u32, image.height as u32)
+        || tiff_samples != image.data
+    {
+        anyhow::bail!("TIFF output differs from the image the preview writer received");
+    }
+    println!(
+        "TIFF: {}x{}, {} samples",
+        image.width,
+        image.height,
+        tiff_samples.len()
+    );
+
+    let header = format!("P6\n{} {}\n255\n", image.width, image.height);
+    let pixels = ppm_out
+        .strip_prefix(header.as_bytes())
+        .ok_or_else(|| anyhow::anyhow!("PPM output has an unexpected header"))?;
+    if pixels.len() != image.data.len()
+        || pixels
+            .iter()
+            .zip(&image.data)
+            .any(|(&p, &v)| p != (v >> 8) as u8)
+    {
+        anyhow::bail!("PPM pixels do not match the debayered image");
+    }
+    println!("PPM preview: {} bytes", ppm_out.len());
+
+    match pipeline.convert_multi(&[], &mut [&mut Vec::new()]) {
+        Ok(()) => anyhow::bail!("convert_multi accepted one output for two writers"),
+        Err(e) => println!("Mismatched outputs rejected: {}", e),
+    }
+
+    println!("All writers received the same debayered image");
+    Ok(())
+}
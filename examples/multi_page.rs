@@ -0,0 +1,76 @@
+//! Checks `MultiPageTiffWriter`.
+//!
+//! Three RGB frames, the last with other dimensions, are written as one burst.
+//! `page_count` must track the pages added and `finish` must report 3. Decoding the file
+//! must walk exactly three pages, each with its frame's dimensions and pixels. Finishing
+//! a writer without pages must fail. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example multi_page`.
+
+use std::io::Cursor;
+
+use tiff::decoder::{Decoder, DecodingResult};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, MultiPageTiffWriter, RgbImageData,
+};
+
+fn frame(width: usize, height: usize, seed: usize) -> RgbImageData {
+    RgbImageData {
+        width,
+        height,
+        data: (0..width * height * 3).map(|i| ((i * 131 + seed * 4099) % 65536) as u16).collect(),
+        bits_per_sample: 16,
+        exif: ExifMetadata::default(),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let frames = [frame(16, 12, 0), frame(16, 12, 1), frame(9, 20, 2)];
+    let config = ConversionConfig::default();
+
+    let mut file = Cursor::new(Vec::new());
+    let mut writer = MultiPageTiffWriter::begin(&mut file, &config)?;
+    for (i, image) in frames.iter().enumerate() {
+        writer.add_page(image)?;
+        if writer.page_count() != i + 1 {
+            anyhow::bail!("page_count is {} after {} pages", writer.page_count(), i + 1);
+        }
+    }
+    let pages = writer.finish()?;
+    if pages != 3 {
+        anyhow::bail!("finish reported {} pages, expected 3", pages);
+    }
+
+    let mut decoder = Decoder::new(Cursor::new(file.into_inner()))?;
+    let mut decoded = 0;
+    loop {
+        let Some(image) = frames.get(decoded) else {
+            anyhow::bail!("File holds more than {} pages", frames.len());
+        };
+        let (width, height) = decoder.dimensions()?;
+        if (width as usize, height as usize) != (image.width, image.height) {
+            anyhow::bail!("Page {} is {}x{}, expected {}x{}", decoded, width, height, image.width, image.height);
+        }
+        match decoder.read_image()? {
+            DecodingResult::U16(samples) if samples == image.data => {}
+            _ => anyhow::bail!("Page {} pixels differ from its frame", decoded),
+        }
+        println!("Page {}: {}x{}", decoded, width, height);
+        decoded += 1;
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image()?;
+    }
+    if decoded != 3 {
+        anyhow::bail!("Decoded {} pages, expected 3", decoded);
+    }
+
+    if MultiPageTiffWriter::begin(Cursor::new(Vec::new()), &config)?.finish().is_ok() {
+        anyhow::bail!("Finishing without pages succeeded");
+    }
+
+    println!("Three frames written as a three-page TIFF");
+    Ok(())
+}
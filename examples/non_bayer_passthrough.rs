@@ -0,0 +1,158 @@
+//! Checks that raws which are already RGB skip the demosaic but keep the color stages.
+//!
+//! A synthetic reader returns interleaved 12-bit RGB with `is_bayer: false`: the left half
+//! of the frame red, the right half blue. Debayering must keep the edge sharp (a demosaic
+//! would blend the columns around it), a flat frame must match the same color delivered as
+//! an RGGB mosaic, the preview must still bin 2x2 blocks, and `BayerGray` output must be
+//! refused. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example non_bayer_passthrough`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, DebayerQuality, OutputMode, RawImageData,
+    RawImageReader, RawToTiffPipeline, Result, StandardTiffWriter,
+};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 16;
+const RED: [u16; 3] = [3000, 600, 400];
+const BLUE: [u16; 3] = [400, 700, 2500];
+
+/// Ignores the input bytes and returns `data`, either an RGGB mosaic or interleaved RGB
+struct SyntheticReader {
+    data: Vec<u16>,
+    is_bayer: bool,
+}
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: self.data.clone(),
+            is_bayer: self.is_bayer,
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+/// Interleaved RGB with `color(x)` in every row
+fn rgb(color: impl Fn(usize) -> [u16; 3]) -> Vec<u16> {
+    (0..WIDTH * HEIGHT).flat_map(|i| color(i % WIDTH)).collect()
+}
+
+/// RGGB mosaic sampling a flat `color`
+fn mosaic(color: [u16; 3]) -> Vec<u16> {
+    (0..WIDTH * HEIGHT)
+        .map(|i| match ((i / WIDTH) % 2, (i % WIDTH) % 2) {
+            (0, 0) => color[0],
+            (1, 1) => color[2],
+            _ => color[1],
+        })
+        .collect()
+}
+
+fn pipeline(
+    reader: SyntheticReader,
+    output: OutputMode,
+) -> anyhow::Result<RawToTiffPipeline<SyntheticReader, StandardTiffWriter>> {
+    let config = ConversionConfig::builder()
+        .output(output)
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .exposure(1.0)
+        .build();
+    Ok(RawToTiffPipeline::with_custom(
+        reader,
+        StandardTiffWriter,
+        config,
+    )?)
+}
+
+fn debayer(data: Vec<u16>, is_bayer: bool) -> anyhow::Result<Vec<u16>> {
+    Ok(
+        pipeline(SyntheticReader { data, is_bayer }, OutputMode::Rgb)?
+            .debayer_only(&[])?
+            .data,
+    )
+}
+
+fn main() -> anyhow::Result<()> {
+    let split = rgb(|x| if x < WIDTH / 2 { RED } else { BLUE });
+    let out = debayer(split.clone(), false)?;
+    if out.len() != WIDTH * HEIGHT * 3 {
+        anyhow::bail!(
+            "Output holds {} samples, expected {}",
+            out.len(),
+            WIDTH * HEIGHT * 3
+        );
+    }
+    let left = &out[..3];
+    let right = &out[(WIDTH - 1) * 3..WIDTH * 3];
+    for (i, px) in out.chunks_exact(3).enumerate() {
+        let expected = if i % WIDTH < WIDTH / 2 { left } else { right };
+        if px != expected {
+            anyhow::bail!(
+                "Pixel {} is {:?}, expected {:?}: the passthrough mixed neighbours",
+                i,
+                px,
+                expected
+            );
+        }
+    }
+    if !(left[0] > left[2] && right[2] > right[0]) {
+        anyhow::bail!(
+            "Left half {:?} should be red and right half {:?} blue",
+            left,
+            right
+        );
+    }
+    println!("Sharp edge kept: left {:?}, right {:?}", left, right);
+
+    let flat_rgb = debayer(rgb(|_| RED), false)?;
+    let flat_mosaic = debayer(mosaic(RED), true)?;
+    if flat_rgb != flat_mosaic {
+        anyhow::bail!(
+            "Flat RGB gave {:?}, the same color as a mosaic gave {:?}",
+            &flat_rgb[..3],
+            &flat_mosaic[..3]
+        );
+    }
+    println!(
+        "Flat RGB input matches the equivalent mosaic: {:?}",
+        &flat_rgb[..3]
+    );
+
+    let raw = SyntheticReader {
+        data: split.clone(),
+        is_bayer: false,
+    }
+    .read_raw(&[])?;
+    let preview = raw.to_rgb_preview();
+    if preview.data.len() * 4 != WIDTH * HEIGHT * 3 || preview.data[0] <= preview.data[2] {
+        anyhow::bail!(
+            "Preview of {} samples starts with {:?}",
+            preview.data.len(),
+            &preview.data[..3]
+        );
+    }
+    println!("Preview first pixel: {:?}", &preview.data[..3]);
+
+    let gray = pipeline(
+        SyntheticReader {
+            data: split,
+            is_bayer: false,
+        },
+        OutputMode::BayerGray,
+    )?;
+    match gray.convert_to_vec(&[]) {
+        Ok(_) => anyhow::bail!("BayerGray output accepted an RGB raw"),
+        Err(e) => println!("BayerGray refused: {}", e),
+    }
+
+    println!("Non-Bayer raws skip the demosaic");
+    Ok(())
+}
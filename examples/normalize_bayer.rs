@@ -0,0 +1,89 @@
+//! Checks that `normalize_bayer` stretches a 12-bit mosaic to the full 16-bit range.
+//!
+//! A 12-bit mosaic holding the maximum code, zero and a mid value is written as a Bayer
+//! TIFF. With `normalize_bayer` the maximum code must become 65535, zero stay 0 and the
+//! mid value scale by `65535 / 4095`; with a black level, black must become 0 and the
+//! maximum code still 65535. Without the option the samples must be written unchanged.
+//! Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example normalize_bayer`.
+
+use std::io::Cursor;
+
+use tiff::decoder::{Decoder, DecodingResult};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader, RawToTiffPipeline, Result,
+    StandardTiffWriter,
+};
+
+const WIDTH: usize = 4;
+const HEIGHT: usize = 2;
+const MAX_12_BIT: u16 = 4095;
+
+/// Returns a 12-bit mosaic of `samples` with `black` at every site
+struct TwelveBitReader {
+    samples: Vec<u16>,
+    black: u16,
+}
+
+impl RawImageReader for TwelveBitReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: self.samples.clone(),
+            bits_per_sample: 12,
+            blacklevels: [self.black; 4],
+            whitelevels: [MAX_12_BIT; 4],
+            ..Default::default()
+        })
+    }
+}
+
+fn written(samples: &[u16], black: u16, normalize: bool) -> anyhow::Result<Vec<u16>> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::BayerGray)
+        .normalize_bayer(normalize)
+        .build();
+    let reader = TwelveBitReader { samples: samples.to_vec(), black };
+    let tiff = RawToTiffPipeline::with_custom(reader, StandardTiffWriter, config)?.convert_to_vec(&[])?;
+    match Decoder::new(Cursor::new(tiff))?.read_image()? {
+        DecodingResult::U16(samples) => Ok(samples),
+        _ => anyhow::bail!("Output is not 16-bit"),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let samples = [MAX_12_BIT, 0, 2048, 1, MAX_12_BIT, 4094, 0, 1024];
+    let scale = 65535.0 / MAX_12_BIT as f32;
+
+    let normalized = written(&samples, 0, true)?;
+    println!("Normalized: {:?}", normalized);
+    for (&input, &output) in samples.iter().zip(&normalized) {
+        let expected = (input as f32 * scale).round() as u16;
+        if output != expected {
+            anyhow::bail!("12-bit {} normalized to {}, expected {}", input, output, expected);
+        }
+    }
+    if normalized[0] != u16::MAX {
+        anyhow::bail!("12-bit max normalized to {}, expected {}", normalized[0], u16::MAX);
+    }
+
+    let with_black = written(&[MAX_12_BIT, 256, 100, MAX_12_BIT, 256, 0, 4000, 256], 256, true)?;
+    println!("Normalized above black 256: {:?}", with_black);
+    if with_black[0] != u16::MAX || with_black[3] != u16::MAX {
+        anyhow::bail!("12-bit max above black normalized to {}, expected {}", with_black[0], u16::MAX);
+    }
+    if [1, 2, 4, 5, 7].iter().any(|&i| with_black[i] != 0) {
+        anyhow::bail!("Samples at or below black did not normalize to 0: {:?}", with_black);
+    }
+
+    let unchanged = written(&samples, 0, false)?;
+    if unchanged != samples {
+        anyhow::bail!("Without normalize_bayer the samples became {:?}", unchanged);
+    }
+
+    println!("normalize_bayer maps the 12-bit maximum to 65535");
+    Ok(())
+}
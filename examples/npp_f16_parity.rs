@@ -0,0 +1,87 @@
+//! Compares `NppDebayer` output in `WorkingPrecision::F16` with `F32`.
+//!
+//! The same synthetic RGGB frame is debayered by NPP in both working precisions, linear
+//! and with `apply_srgb_gamma`. Every half-precision sample must be within `TOLERANCE`
+//! of its single-precision counterpart. Exits non-zero when they diverge.
+//!
+//! Expected tolerance: half floats round each of the black level, white balance and
+//! color matrix stages to about 5e-4, and the sRGB matrix amplifies its input error, so
+//! linear samples stay within about 4e-3 of full scale. The sRGB curve is steeper than
+//! linear below about 0.2, so encoded samples get twice the budget.
+//!
+//! Run on a Jetson with `cargo run --release --example npp_f16_parity`.
+
+#[cfg(jetson_cuda)]
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, NppDebayer, Orientation, OutputMode, RawImageData,
+    WorkingPrecision,
+};
+
+/// Maximum allowed absolute difference of linear float samples
+#[cfg(jetson_cuda)]
+const TOLERANCE: f32 = 4e-3;
+
+#[cfg(jetson_cuda)]
+fn synthetic_raw(width: usize, height: usize) -> RawImageData {
+    const BLACK: u16 = 256;
+    const WHITE: u16 = 4095;
+
+    // Levels from just below black to white, so negative samples are compared too
+    let data = (0..width * height)
+        .map(|i| BLACK - 64 + ((i * 7) % (WHITE - BLACK + 64) as usize) as u16)
+        .collect();
+
+    RawImageData {
+        width,
+        height,
+        data,
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [BLACK; 4],
+        whitelevels: [WHITE; 4],
+        ..Default::default()
+    }
+}
+
+#[cfg(jetson_cuda)]
+fn main() -> anyhow::Result<()> {
+    let raw = synthetic_raw(512, 384);
+    let config = |precision, gamma| {
+        ConversionConfig::builder()
+            .output(OutputMode::Rgb)
+            .exposure(1.0)
+            .working_precision(precision)
+            .apply_srgb_gamma(gamma)
+            .build()
+    };
+
+    for (gamma, tolerance) in [(false, TOLERANCE), (true, 2.0 * TOLERANCE)] {
+        let single = NppDebayer::with_config(&config(WorkingPrecision::F32, gamma))?.process_f32(&raw)?;
+        let half = NppDebayer::with_config(&config(WorkingPrecision::F16, gamma))?.process_f32(&raw)?;
+
+        let max_diff = single
+            .data
+            .iter()
+            .zip(&half.data)
+            .map(|(&s, &h)| (s - h).abs())
+            .fold(0.0f32, f32::max);
+        println!(
+            "{}: max abs diff between F16 and F32 {:.3e} (tolerance {:.0e})",
+            if gamma { "sRGB" } else { "Linear" },
+            max_diff,
+            tolerance
+        );
+
+        if max_diff > tolerance {
+            anyhow::bail!("F16 working precision diverges from F32");
+        }
+    }
+
+    println!("F16 and F32 NPP output agree within tolerance");
+    Ok(())
+}
+
+#[cfg(not(jetson_cuda))]
+fn main() {
+    println!("NPP f16 parity check requires a Jetson build (jetson_cuda), skipping.");
+}
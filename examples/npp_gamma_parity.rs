@@ -0,0 +1,92 @@
+//! Cross-checks the on-device sRGB gamma of `NppDebayer` against the CPU transfer curve.
+//!
+//! The same synthetic RGGB frame is debayered by NPP with and without
+//! `apply_srgb_gamma`. Applying the sRGB transfer function on the CPU to the linear NPP
+//! output must reproduce the gamma-encoded NPP output within `TOLERANCE`, so only the
+//! kernel is compared, not the demosaic. Exits non-zero when they diverge.
+//!
+//! Expected tolerance: the kernel's `powf` may differ from Rust's by a few ulps, which
+//! stays far below one 16-bit output step.
+//!
+//! Run on a Jetson with `cargo run --release --example npp_gamma_parity`.
+
+#[cfg(jetson_cuda)]
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, NppDebayer, Orientation, OutputMode, RawImageData,
+};
+
+/// Maximum allowed absolute difference of encoded float samples
+#[cfg(jetson_cuda)]
+const TOLERANCE: f32 = 1e-5;
+
+#[cfg(jetson_cuda)]
+fn synthetic_raw(width: usize, height: usize) -> RawImageData {
+    const BLACK: u16 = 256;
+    const WHITE: u16 = 4095;
+
+    // Levels from black to white so both branches of the curve are exercised
+    let data = (0..width * height)
+        .map(|i| BLACK + ((i * 7) % (WHITE - BLACK) as usize) as u16)
+        .collect();
+
+    RawImageData {
+        width,
+        height,
+        data,
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [BLACK; 4],
+        whitelevels: [WHITE; 4],
+        ..Default::default()
+    }
+}
+
+/// sRGB transfer function, mirrored for negative values like the pipeline's
+#[cfg(jetson_cuda)]
+fn srgb_encode(x: f32) -> f32 {
+    let magnitude = x.abs();
+    let encoded = if magnitude <= 0.0031308 {
+        magnitude * 12.92
+    } else {
+        1.055 * magnitude.powf(1.0 / 2.4) - 0.055
+    };
+    encoded.copysign(x)
+}
+
+#[cfg(jetson_cuda)]
+fn main() -> anyhow::Result<()> {
+    let raw = synthetic_raw(512, 384);
+    let config = |gamma| {
+        ConversionConfig::builder()
+            .output(OutputMode::Rgb)
+            .exposure(1.0)
+            .apply_srgb_gamma(gamma)
+            .build()
+    };
+
+    let linear = NppDebayer::with_config(&config(false))?.process_f32(&raw)?;
+    let encoded = NppDebayer::with_config(&config(true))?.process_f32(&raw)?;
+
+    let max_diff = linear
+        .data
+        .iter()
+        .zip(&encoded.data)
+        .map(|(&l, &e)| (srgb_encode(l) - e).abs())
+        .fold(0.0f32, f32::max);
+    println!(
+        "Max abs diff between GPU and CPU gamma: {:.3e} (tolerance {:.0e})",
+        max_diff, TOLERANCE
+    );
+
+    if max_diff > TOLERANCE {
+        anyhow::bail!("On-device sRGB gamma diverges from the CPU transfer curve");
+    }
+
+    println!("GPU and CPU sRGB gamma agree within tolerance");
+    Ok(())
+}
+
+#[cfg(not(jetson_cuda))]
+fn main() {
+    println!("NPP gamma parity check requires a Jetson build (jetson_cuda), skipping.");
+}
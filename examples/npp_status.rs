@@ -0,0 +1,38 @@
+//! Checks the symbolic names reported for NPP status codes.
+//!
+//! A handful of codes from `nppdefs.h` must map to their names, and unknown codes must
+//! be reported as such. Runs on any platform. Exits non-zero on the first mismatch.
+//!
+//! Run with `cargo run --example npp_status`.
+
+use ffed_protosat_rs::image_pipeline::debayer::npp_status::{describe_npp_status, npp_status_name};
+
+const KNOWN: [(i32, &str); 7] = [
+    (0, "NPP_NO_ERROR"),
+    (-4, "NPP_NO_MEMORY_ERROR"),
+    (-6, "NPP_SIZE_ERROR"),
+    (-8, "NPP_NULL_POINTER_ERROR"),
+    (-14, "NPP_STEP_ERROR"),
+    (-22, "NPP_INTERPOLATION_ERROR"),
+    (-1000, "NPP_CUDA_KERNEL_EXECUTION_ERROR"),
+];
+
+fn main() -> anyhow::Result<()> {
+    for (status, expected) in KNOWN {
+        let name = npp_status_name(status);
+        if name != Some(expected) {
+            anyhow::bail!("status {} maps to {:?}, expected {}", status, name, expected);
+        }
+        println!("{}", describe_npp_status(status));
+    }
+
+    if npp_status_name(-12345).is_some() {
+        anyhow::bail!("unknown status -12345 was given a name");
+    }
+    if describe_npp_status(-12345) != "unknown status -12345" {
+        anyhow::bail!("unknown status described as {:?}", describe_npp_status(-12345));
+    }
+
+    println!("NPP status codes map to their nppdefs.h names");
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Checks that `CpuDebayer` handles mosaics with an odd width, height or both.
+//!
+//! A flat patch of one camera color is mosaiced at odd sizes and debayered with each CPU
+//! demosaic, with white balance and color matrix set up to pass camera values through.
+//! Output must keep the input dimensions, and every pixel, including the last column
+//! and row, must come out as the patch color; a misaligned CFA phase would swap red and
+//! blue. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example odd_dimensions`.
+
+use ffed_protosat_rs::image_pipeline::{
+    BorderMode, ColorTransform, ConversionConfig, CpuDebayer, DebayerQuality, RawImageData,
+};
+
+/// Camera response of the patch
+const COLOR: [f32; 3] = [0.7, 0.45, 0.2];
+
+fn patch(width: usize, height: usize) -> RawImageData {
+    let site = |x: usize, y: usize| match (y % 2, x % 2) {
+        (0, 0) => COLOR[0],
+        (1, 1) => COLOR[2],
+        _ => COLOR[1],
+    };
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height)
+            .map(|i| (site(i % width, i / width) * 4095.0).round() as u16)
+            .collect(),
+        bits_per_sample: 12,
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let qualities = [
+        DebayerQuality::Linear,
+        DebayerQuality::MalvarHeCutler,
+        DebayerQuality::EdgeDirected,
+    ];
+    for (width, height) in [(33, 24), (32, 25), (33, 25), (7, 5)] {
+        let raw = patch(width, height);
+        for quality in qualities {
+            for mode in [BorderMode::Mirror, BorderMode::Replicate] {
+                let config = ConversionConfig::builder()
+                    .debayer_quality(quality)
+                    .border_mode(mode)
+                    .color_transform(ColorTransform::CameraNative)
+                    .exposure(1.0)
+                    .build();
+                let rgb = CpuDebayer::with_config(&config)?.process_f32(&raw)?;
+                if (rgb.width, rgb.height) != (width, height) {
+                    anyhow::bail!(
+                        "{}x{} with {:?}/{:?} debayered to {}x{}",
+                        width,
+                        height,
+                        quality,
+                        mode,
+                        rgb.width,
+                        rgb.height
+                    );
+                }
+                for (i, px) in rgb.data.chunks_exact(3).enumerate() {
+                    if px.iter().zip(COLOR).any(|(v, c)| (v - c).abs() > 1e-3) {
+                        anyhow::bail!(
+                            "{}x{} with {:?}/{:?}: pixel ({}, {}) is {:?}, expected {:?}",
+                            width,
+                            height,
+                            quality,
+                            mode,
+                            i % width,
+                            i / width,
+                            px,
+                            COLOR
+                        );
+                    }
+                }
+            }
+        }
+        println!("{}x{}: dimensions and colors preserved", width, height);
+    }
+
+    println!("Odd-dimension mosaics debayer without dropped rows or color shifts");
+    Ok(())
+}
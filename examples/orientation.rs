@@ -0,0 +1,82 @@
+//! Checks that `apply_orientation` turns debayered output upright.
+//!
+//! A synthetic reader returns a non-square frame tagged as rotated 90°. With
+//! `apply_orientation` on, the RGB TIFF must come out with width and height swapped;
+//! with it off, as stored. The pixel mapping of each orientation is checked on a tiny
+//! labelled image as well. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example orientation`.
+
+use std::io::Cursor;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, Orientation, OutputMode, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, StandardTiffWriter,
+};
+use tiff::decoder::Decoder;
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+
+/// Ignores the input bytes and returns a 12-bit RGGB gradient shot in portrait
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: (0..WIDTH * HEIGHT).map(|i| (i % 3840 + 256) as u16).collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            orientation: Orientation::Rotate90,
+            ..Default::default()
+        })
+    }
+}
+
+fn output_dimensions(apply_orientation: bool) -> anyhow::Result<(u32, u32)> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .apply_orientation(apply_orientation)
+        .build();
+    let pipeline = RawToTiffPipeline::with_custom(SyntheticReader, StandardTiffWriter, config)?;
+    let encoded = pipeline.convert_to_vec(&[])?;
+    Ok(Decoder::new(Cursor::new(encoded))?.dimensions()?)
+}
+
+fn main() -> anyhow::Result<()> {
+    let rotated = output_dimensions(true)?;
+    if rotated != (HEIGHT as u32, WIDTH as u32) {
+        anyhow::bail!("Rotated output is {}x{}, expected {}x{}", rotated.0, rotated.1, HEIGHT, WIDTH);
+    }
+    let stored = output_dimensions(false)?;
+    if stored != (WIDTH as u32, HEIGHT as u32) {
+        anyhow::bail!("Unrotated output is {}x{}, expected {}x{}", stored.0, stored.1, WIDTH, HEIGHT);
+    }
+    println!("Rotate90: {}x{} stored, {}x{} upright", stored.0, stored.1, rotated.0, rotated.1);
+
+    // 3x2 image labelled row by row:  1 2 3
+    //                                  4 5 6
+    let image = [1, 2, 3, 4, 5, 6];
+    let cases: [(Orientation, [u8; 6]); 7] = [
+        (Orientation::FlipHorizontal, [3, 2, 1, 6, 5, 4]),
+        (Orientation::Rotate180, [6, 5, 4, 3, 2, 1]),
+        (Orientation::FlipVertical, [4, 5, 6, 1, 2, 3]),
+        (Orientation::Transpose, [1, 4, 2, 5, 3, 6]),
+        (Orientation::Rotate90, [4, 1, 5, 2, 6, 3]),
+        (Orientation::Transverse, [6, 3, 5, 2, 4, 1]),
+        (Orientation::Rotate270, [3, 6, 2, 5, 1, 4]),
+    ];
+    for (orientation, expected) in cases {
+        let oriented = orientation.apply(&image, 3, 2, 1);
+        if oriented != expected {
+            anyhow::bail!("{:?} gave {:?}, expected {:?}", orientation, oriented, expected);
+        }
+    }
+
+    println!("Orientation is applied to debayered output");
+    Ok(())
+}
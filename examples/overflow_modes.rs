@@ -0,0 +1,68 @@
+//! Checks each `OverflowMode` of the final 16-bit quantization and its clip statistics.
+//!
+//! A four-pixel float image holds one highlight at twice diffuse white and one pixel with
+//! a negative sample, so half its pixels are out of range. `Clamp` must match `to_u16`,
+//! `ScaleToFit` must divide by the peak, and `Error` must pass at a 50% limit and fail
+//! below it. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example overflow_modes`.
+
+use ffed_protosat_rs::image_pipeline::{ExifMetadata, OverflowMode, RgbImageDataF32};
+
+fn image() -> RgbImageDataF32 {
+    RgbImageDataF32 {
+        width: 2,
+        height: 2,
+        data: vec![
+            0.5, 0.5, 0.5,
+            2.0, 0.5, 0.25,
+            -0.1, 0.2, 0.3,
+            1.0, 1.0, 1.0,
+        ],
+        exif: ExifMetadata::default(),
+    }
+}
+
+fn expect(mode: &str, actual: &[u16], expected: &[u16]) -> anyhow::Result<()> {
+    if actual != expected {
+        anyhow::bail!("{} produced {:?}, expected {:?}", mode, actual, expected);
+    }
+    println!("{}: {:?}", mode, actual);
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let image = image();
+
+    let (clamped, stats) = image.quantize(OverflowMode::Clamp)?;
+    expect("Clamp", &clamped.data, &image.to_u16().data)?;
+    expect("Clamp", &clamped.data[3..9], &[65535, 32767, 16383, 0, 13107, 19660])?;
+    if stats.clipped_pixels != 2 || stats.total_pixels != 4 || stats.clipped_percent() != 50.0 {
+        anyhow::bail!("Expected 2 of 4 pixels clipped, got {:?}", stats);
+    }
+    if stats.peak != 2.0 || stats.scale != 1.0 {
+        anyhow::bail!("Expected peak 2.0 and no scaling, got {:?}", stats);
+    }
+    println!("Clipped {}% of pixels, peak {}", stats.clipped_percent(), stats.peak);
+
+    let (scaled, stats) = image.quantize(OverflowMode::ScaleToFit)?;
+    expect(
+        "ScaleToFit",
+        &scaled.data,
+        &[16383, 16383, 16383, 65535, 16383, 8191, 0, 6553, 9830, 32767, 32767, 32767],
+    )?;
+    if stats.scale != 2.0 || stats.clipped_pixels != 2 {
+        anyhow::bail!("Expected a scale of 2.0 over 2 clipped pixels, got {:?}", stats);
+    }
+
+    let (within, _) = image.quantize(OverflowMode::Error { max_clipped_percent: 50.0 })?;
+    expect("Error (50% limit)", &within.data, &clamped.data)?;
+
+    match image.quantize(OverflowMode::Error { max_clipped_percent: 25.0 }) {
+        Ok(_) => anyhow::bail!("Error mode accepted 50% clipped pixels with a 25% limit"),
+        Err(e) => println!("Error (25% limit): {}", e),
+    }
+
+    println!("All overflow modes behave as configured");
+    Ok(())
+}
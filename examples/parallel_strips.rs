@@ -0,0 +1,153 @@
+//! Benchmarks strip-parallel TIFF compression against serial encoding and checks that
+//! both produce the same file.
+//!
+//! A synthetic 16-bit RGB frame large enough for many strips is written with every
+//! compression method, with and without the horizontal predictor, once serially and once
+//! with `parallel_strips`. The two files must be byte-identical and decode to the source
+//! samples; a float frame and a grayscale frame are checked the same way. Prints the
+//! speedup per setting. Exits non-zero on the first mismatch.
+//!
+//! Run with `cargo run --release --example parallel_strips`.
+
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, RawImageData, RgbImageData, RgbImageDataF32,
+    StandardTiffWriter, TiffCompression, TiffWriter,
+};
+use tiff::decoder::{Decoder, DecodingResult};
+
+const WIDTH: usize = 2048;
+const HEIGHT: usize = 1536;
+const RUNS: usize = 3;
+
+/// Smooth gradient with a little pseudo-random noise, so compressors have work to do
+fn samples(count: usize) -> Vec<u16> {
+    let mut state = 0x2545_f491u32;
+    (0..count)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            ((i / 3 % WIDTH) * 24 + (state % 64) as usize) as u16
+        })
+        .collect()
+}
+
+/// Writes with `write` `RUNS` times and returns the last output and the fastest time
+fn timed(write: impl Fn(&mut Vec<u8>) -> anyhow::Result<()>) -> anyhow::Result<(Vec<u8>, Duration)> {
+    let mut best = Duration::MAX;
+    let mut output = Vec::new();
+    for _ in 0..RUNS {
+        output.clear();
+        let start = Instant::now();
+        write(&mut output)?;
+        best = best.min(start.elapsed());
+    }
+    Ok((output, best))
+}
+
+fn same_samples(a: &DecodingResult, b: &DecodingResult) -> bool {
+    match (a, b) {
+        (DecodingResult::U16(a), DecodingResult::U16(b)) => a == b,
+        (DecodingResult::F32(a), DecodingResult::F32(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn compare(
+    name: &str,
+    config: ConversionConfig,
+    expected: &DecodingResult,
+    write: impl Fn(&mut Vec<u8>, &ConversionConfig) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let parallel_config = ConversionConfig { parallel_strips: true, ..config.clone() };
+    let (serial, serial_time) = timed(|output| write(output, &config))?;
+    let (parallel, parallel_time) = timed(|output| write(output, &parallel_config))?;
+
+    println!(
+        "{} {:?} (predictor: {:?}): serial {:.1} ms, parallel {:.1} ms ({:.2}x), {} bytes",
+        name,
+        config.compression,
+        config.predictor,
+        serial_time.as_secs_f64() * 1000.0,
+        parallel_time.as_secs_f64() * 1000.0,
+        serial_time.as_secs_f64() / parallel_time.as_secs_f64(),
+        parallel.len()
+    );
+    if parallel != serial {
+        anyhow::bail!("{}: parallel output differs from serial output", name);
+    }
+    let decoded = Decoder::new(Cursor::new(parallel))?.read_image()?;
+    if !same_samples(&decoded, expected) {
+        anyhow::bail!("{}: parallel output does not decode to the source samples", name);
+    }
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let exif = ExifMetadata {
+        exposure_time: Some((1, 500)),
+        f_number: Some((4, 1)),
+        iso: Some(200),
+        focal_length: Some((35, 1)),
+    };
+    let rgb = RgbImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: samples(WIDTH * HEIGHT * 3),
+        bits_per_sample: 16,
+        exif,
+    };
+    let rgb_expected = DecodingResult::U16(rgb.data.clone());
+
+    let compressions = [
+        TiffCompression::Lzw,
+        TiffCompression::DeflateFast,
+        TiffCompression::DeflateBalanced,
+        TiffCompression::DeflateBest,
+    ];
+    for compression in compressions {
+        for predictor in [None, Some(2)] {
+            let config = ConversionConfig::builder()
+                .compression(compression)
+                .predictor(predictor)
+                .preserve_exif(true)
+                .build();
+            compare("RGB16", config, &rgb_expected, |output, config| {
+                Ok(StandardTiffWriter.write_rgb_tiff(&rgb, output, config)?)
+            })?;
+        }
+    }
+
+    let float = RgbImageDataF32 {
+        width: WIDTH,
+        height: HEIGHT / 4,
+        data: rgb.data[..WIDTH * HEIGHT / 4 * 3].iter().map(|&v| v as f32 / 65535.0).collect(),
+        exif,
+    };
+    let config = ConversionConfig::builder().compression(TiffCompression::DeflateFast).build();
+    compare("RGB32F", config, &DecodingResult::F32(float.data.clone()), |output, config| {
+        Ok(StandardTiffWriter.write_rgb_tiff_f32(&float, output, config)?)
+    })?;
+
+    let gray = RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: samples(WIDTH * HEIGHT),
+        cam_to_xyz: [[0.0; 4]; 3],
+        exif,
+        ..Default::default()
+    };
+    let config = ConversionConfig::builder()
+        .compression(TiffCompression::Lzw)
+        .predictor(Some(2))
+        .build();
+    compare("Gray16", config, &DecodingResult::U16(gray.data.clone()), |output, config| {
+        Ok(StandardTiffWriter.write_tiff(&gray, output, config)?)
+    })?;
+
+    println!("Parallel strip compression matches serial encoding");
+    Ok(())
+}
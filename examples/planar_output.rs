@@ -0,0 +1,158 @@
+//! Checks planar (`PlanarConfiguration=2`) RGB TIFF output.
+//!
+//! A 16-bit RGB image large enough to need several strips per plane is written with
+//! `planar(true)`, uncompressed and with LZW and Deflate plus the horizontal predictor,
+//! and a float image with LZW and the floating-point predictor. Each file must be tagged
+//! planar with three samples per pixel, and reading its strips plane by plane must give
+//! back exactly the input's red, green and blue channels. A pipeline conversion with
+//! `verify_output` must pass and match the interleaved conversion. Exits non-zero
+//! otherwise.
+//!
+//! Run with `cargo run --example planar_output`.
+
+use std::io::Cursor;
+
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, DebayerQuality, ExifMetadata, OutputMode, RawImageData,
+    RawToTiffPipeline, RgbImageData, RgbImageDataF32, StandardTiffWriter, TiffCompression,
+    TiffWriter,
+};
+
+const WIDTH: usize = 1000;
+const HEIGHT: usize = 700;
+
+/// Reads every strip of a planar TIFF, returning its samples plane after plane
+fn decode_planar(tiff: &[u8]) -> anyhow::Result<(Vec<u16>, Vec<f32>)> {
+    let mut decoder = Decoder::new(Cursor::new(tiff))?;
+    let planar = decoder.get_tag_u32(Tag::PlanarConfiguration)?;
+    let samples_per_pixel = decoder.get_tag_u32(Tag::SamplesPerPixel)?;
+    if planar != 2 || samples_per_pixel != 3 {
+        anyhow::bail!(
+            "PlanarConfiguration {}, SamplesPerPixel {}, expected 2 and 3",
+            planar,
+            samples_per_pixel
+        );
+    }
+    let (mut ints, mut floats) = (Vec::new(), Vec::new());
+    for strip in 0..decoder.strip_count()? {
+        match decoder.read_chunk(strip)? {
+            DecodingResult::U16(data) => ints.extend(data),
+            DecodingResult::F32(data) => floats.extend(data),
+            _ => anyhow::bail!("Unexpected sample format"),
+        }
+    }
+    Ok((ints, floats))
+}
+
+/// Interleaved samples split into consecutive R, G and B planes
+fn deinterleave<T: Copy>(data: &[T]) -> Vec<T> {
+    (0..3)
+        .flat_map(|channel| data.iter().skip(channel).step_by(3).copied())
+        .collect()
+}
+
+fn gradient_raw() -> RawImageData {
+    let (width, height) = (64, 48);
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height)
+            .map(|i| ((i % width) * 50 + (i / width) * 20 + 256) as u16)
+            .collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let image = RgbImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT)
+            .flat_map(|i| {
+                let (x, y) = (i % WIDTH, i / WIDTH);
+                [(x * 60) as u16, (y * 90) as u16, ((x * y) % 65536) as u16]
+            })
+            .collect(),
+        bits_per_sample: 16,
+        exif: ExifMetadata::default(),
+    };
+    let expected = deinterleave(&image.data);
+
+    for (compression, predictor) in [
+        (TiffCompression::None, None),
+        (TiffCompression::Lzw, Some(2)),
+        (TiffCompression::DeflateBalanced, Some(2)),
+    ] {
+        let config = ConversionConfig::builder()
+            .compression(compression)
+            .predictor(predictor)
+            .planar(true)
+            .build();
+        let mut tiff = Vec::new();
+        StandardTiffWriter.write_rgb_tiff(&image, &mut tiff, &config)?;
+        let (planes, _) = decode_planar(&tiff)?;
+        if planes != expected {
+            anyhow::bail!("{:?}: planes do not match the input channels", compression);
+        }
+        println!(
+            "{:?}: {} bytes, channels reconstructed",
+            compression,
+            tiff.len()
+        );
+    }
+
+    let float_image = RgbImageDataF32 {
+        width: WIDTH,
+        height: HEIGHT,
+        data: image
+            .data
+            .iter()
+            .map(|&v| v as f32 / 40000.0 - 0.1)
+            .collect(),
+        exif: ExifMetadata::default(),
+    };
+    let config = ConversionConfig::builder()
+        .compression(TiffCompression::Lzw)
+        .predictor(Some(3))
+        .planar(true)
+        .build();
+    let mut tiff = Vec::new();
+    StandardTiffWriter.write_rgb_tiff_f32(&float_image, &mut tiff, &config)?;
+    let (_, planes) = decode_planar(&tiff)?;
+    if planes != deinterleave(&float_image.data) {
+        anyhow::bail!("Float planes do not match the input channels");
+    }
+    println!("Float: {} bytes, channels reconstructed", tiff.len());
+
+    let convert = |planar| -> anyhow::Result<Vec<u8>> {
+        let config = ConversionConfig::builder()
+            .output(OutputMode::Rgb)
+            .debayer_quality(DebayerQuality::MalvarHeCutler)
+            .exposure(1.0)
+            .verify_output(true)
+            .planar(planar)
+            .build();
+        let mut tiff = Vec::new();
+        RawToTiffPipeline::new(config)?.convert_raw_image(gradient_raw(), &mut tiff)?;
+        Ok(tiff)
+    };
+    let interleaved = match Decoder::new(Cursor::new(convert(false)?))?.read_image()? {
+        DecodingResult::U16(data) => data,
+        _ => anyhow::bail!("Expected 16-bit output"),
+    };
+    let (planes, _) = decode_planar(&convert(true)?)?;
+    if planes != deinterleave(&interleaved) {
+        anyhow::bail!("Planar pipeline output differs from the interleaved output");
+    }
+    println!("Pipeline: verified planar output matches interleaved output");
+
+    println!("OK");
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! Checks `config_for_model` for a known and an unknown camera.
+//!
+//! The Sony a7 III must get its tuned exposure whatever the case and surrounding
+//! whitespace of the model name, and still pass validation; an unknown model must get
+//! `ConversionConfig::default()` unchanged. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example presets`.
+
+use ffed_protosat_rs::image_pipeline::{ConversionConfig, config_for_model};
+
+fn main() -> anyhow::Result<()> {
+    let default = ConversionConfig::default();
+
+    for model in ["ILCE-7M3", " ilce-7m3 "] {
+        let tuned = config_for_model(model);
+        println!("{:?}: exposure {}, baseline {} EV", model, tuned.exposure, tuned.baseline_exposure);
+        if tuned.exposure != 3.0 {
+            anyhow::bail!("{:?} got exposure {}, expected the a7 III's 3.0", model, tuned.exposure);
+        }
+        if tuned.exposure == default.exposure {
+            anyhow::bail!("{:?} kept the generic exposure", model);
+        }
+        tuned.validate()?;
+    }
+
+    let generic = config_for_model("Unknown Cam 9000");
+    if format!("{:?}", generic) != format!("{:?}", default) {
+        anyhow::bail!("Unknown model did not get the default config: {:?}", generic);
+    }
+    println!("Unknown model: default config");
+
+    println!("Known models get tuned presets, others the defaults");
+    Ok(())
+}
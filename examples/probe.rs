@@ -0,0 +1,103 @@
+//! Checks `RawImageReader::probe` on a mock reader and on rawloader.
+//!
+//! A mock reader relying on the default `probe` must report the dimensions, bit depth and
+//! orientation `read_raw` decodes, with no camera. A mock that overrides `probe` to parse
+//! only a header must never have its `read_raw` called. `RawLoaderReader` must reject
+//! input that is not a RAW file. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example probe`.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ffed_protosat_rs::image_pipeline::{
+    Orientation, RawImageData, RawImageInfo, RawImageReader, RawLoaderReader, Result,
+};
+
+/// Decodes a 64x48 12-bit frame, counting how often it was asked to
+struct MockReader {
+    decodes: AtomicUsize,
+}
+
+impl RawImageReader for MockReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        self.decodes.fetch_add(1, Ordering::Relaxed);
+        let (width, height) = (64, 48);
+        Ok(RawImageData {
+            width,
+            height,
+            data: vec![256; width * height],
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            cam_to_xyz: [[0.0; 4]; 3],
+            orientation: Orientation::Rotate90,
+            ..Default::default()
+        })
+    }
+}
+
+/// Like `MockReader`, but answers `probe` from a fixed header
+struct HeaderMockReader(MockReader);
+
+impl RawImageReader for HeaderMockReader {
+    fn read_raw(&self, data: &[u8]) -> Result<RawImageData> {
+        self.0.read_raw(data)
+    }
+
+    fn probe(&self, _data: &[u8]) -> Result<RawImageInfo> {
+        Ok(RawImageInfo {
+            width: 64,
+            height: 48,
+            bits_per_sample: 12,
+            make: Some("Synthetic".to_string()),
+            model: Some("Cam".to_string()),
+            orientation: Orientation::Rotate90,
+        })
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let mock = MockReader {
+        decodes: AtomicUsize::new(0),
+    };
+    let info = mock.probe(&[])?;
+    let expected = RawImageInfo {
+        width: 64,
+        height: 48,
+        bits_per_sample: 12,
+        make: None,
+        model: None,
+        orientation: Orientation::Rotate90,
+    };
+    if info != expected {
+        anyhow::bail!("Default probe returned {:?}, expected {:?}", info, expected);
+    }
+    println!(
+        "Default probe: {}x{}, {} bits",
+        info.width, info.height, info.bits_per_sample
+    );
+
+    let header = HeaderMockReader(MockReader {
+        decodes: AtomicUsize::new(0),
+    });
+    let info = header.probe(&[])?;
+    if (info.width, info.height) != (64, 48) || info.model.as_deref() != Some("Cam") {
+        anyhow::bail!("Header probe returned {:?}", info);
+    }
+    if header.0.decodes.load(Ordering::Relaxed) != 0 {
+        anyhow::bail!("Header probe decoded the pixel data");
+    }
+    println!(
+        "Header probe: {}x{} {:?} {:?}, no pixel decode",
+        info.width, info.height, info.make, info.model
+    );
+
+    match RawLoaderReader.probe(b"not a raw file") {
+        Ok(info) => anyhow::bail!("rawloader probed garbage as {:?}", info),
+        Err(e) => println!("rawloader rejected garbage: {}", e),
+    }
+
+    println!("Probe reports the header without decoding pixels");
+    Ok(())
+}
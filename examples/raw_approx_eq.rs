@@ -0,0 +1,66 @@
+//! Checks `RawImageData::approx_eq`.
+//!
+//! A frame must equal its clone at tolerance 0, including its `NaN` second-green
+//! coefficient. A copy with one sample off by 3 must match at tolerance 3 but not 2. A
+//! frame with other dimensions but the same samples, and one with another black level,
+//! must never match. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example raw_approx_eq`.
+
+use ffed_protosat_rs::image_pipeline::RawImageData;
+
+fn frame(width: usize, height: usize) -> RawImageData {
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height).map(|i| 256 + (i * 37 % 3000) as u16).collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, f32::NAN],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.5; 4]; 3],
+        xyz_to_cam: [[0.25; 3]; 4],
+        ..Default::default()
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let original = frame(8, 6);
+
+    if !original.approx_eq(&original.clone(), 0) {
+        anyhow::bail!("Frame does not equal its clone");
+    }
+
+    let mut noisy = original.clone();
+    noisy.data[13] += 3;
+    if !original.approx_eq(&noisy, 3) {
+        anyhow::bail!("Sample off by 3 rejected at tolerance 3");
+    }
+    if original.approx_eq(&noisy, 2) {
+        anyhow::bail!("Sample off by 3 accepted at tolerance 2");
+    }
+    if noisy.approx_eq(&original, 2) {
+        anyhow::bail!("approx_eq is not symmetric");
+    }
+
+    let reshaped = RawImageData {
+        width: 6,
+        height: 8,
+        ..original.clone()
+    };
+    if original.approx_eq(&reshaped, u16::MAX) {
+        anyhow::bail!("6x8 frame matched an 8x6 one with the same samples");
+    }
+    if original.approx_eq(&frame(8, 4), u16::MAX) {
+        anyhow::bail!("8x4 frame matched an 8x6 one");
+    }
+
+    let mut other_black = original.clone();
+    other_black.blacklevels[2] = 512;
+    if original.approx_eq(&other_black, u16::MAX) {
+        anyhow::bail!("Frame with another black level matched");
+    }
+
+    println!("approx_eq matches equal and near-equal frames only");
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Checks that the Reinhard tone curve compresses highlights without clipping.
+//!
+//! `ToneCurve::Reinhard` is sampled from 0.0 to 1000.0: it must map 0.0 to 0.0, never
+//! fall as its input rises, stay below 1.0 and pass 0.99 by the end of the sweep. A flat
+//! frame at the white level, converted with a high exposure, must clip to 65535 with
+//! `Linear` but stay below it with `Reinhard`. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example reinhard_tone_curve`.
+
+use std::io::Cursor;
+
+use tiff::decoder::{Decoder, DecodingResult};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader, RawToTiffPipeline, Result,
+    StandardTiffWriter, ToneCurve,
+};
+
+const WIDTH: usize = 16;
+const HEIGHT: usize = 16;
+const EXPOSURE: f32 = 8.0;
+
+/// Returns a flat 12-bit frame at the white level
+struct WhiteReader;
+
+impl RawImageReader for WhiteReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: vec![4095; WIDTH * HEIGHT],
+            bits_per_sample: 12,
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+fn brightest(curve: ToneCurve) -> anyhow::Result<u16> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .exposure(EXPOSURE)
+        .tone_curve(Some(curve))
+        .build();
+    let tiff = RawToTiffPipeline::with_custom(WhiteReader, StandardTiffWriter, config)?.convert_to_vec(&[])?;
+    match Decoder::new(Cursor::new(tiff))?.read_image()? {
+        DecodingResult::U16(samples) => Ok(samples.into_iter().max().unwrap_or(0)),
+        _ => anyhow::bail!("Output is not 16-bit"),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let curve = ToneCurve::Reinhard;
+    if curve.apply(0.0) != 0.0 {
+        anyhow::bail!("Reinhard maps 0.0 to {}", curve.apply(0.0));
+    }
+
+    let mut previous = 0.0;
+    for step in 1..=100_000 {
+        let x = step as f32 * 0.01;
+        let y = curve.apply(x);
+        if y < previous {
+            anyhow::bail!("Reinhard decreases at {}: {} after {}", x, y, previous);
+        }
+        if y >= 1.0 {
+            anyhow::bail!("Reinhard clips at {}: {}", x, y);
+        }
+        previous = y;
+    }
+    if previous < 0.99 {
+        anyhow::bail!("Reinhard only reaches {} at 1000.0", previous);
+    }
+    println!("Reinhard rises from 0.0 to {} over 0.0..=1000.0", previous);
+
+    let linear = brightest(ToneCurve::Linear)?;
+    let reinhard = brightest(ToneCurve::Reinhard)?;
+    println!("White frame at exposure {}: Linear {}, Reinhard {}", EXPOSURE, linear, reinhard);
+    if linear != u16::MAX {
+        anyhow::bail!("Linear output peaks at {}, expected it to clip at {}", linear, u16::MAX);
+    }
+    if reinhard == u16::MAX {
+        anyhow::bail!("Reinhard output clips at {}", u16::MAX);
+    }
+
+    println!("Reinhard compresses highlights toward 1.0 without clipping");
+    Ok(())
+}
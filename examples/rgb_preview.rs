@@ -0,0 +1,93 @@
+//! Checks that `RawImageData::to_rgb_preview` bins RGGB cells into a plausible preview.
+//!
+//! A synthetic 12-bit mosaic of a reddish scene, brighter at the bottom, must yield a
+//! preview with a quarter of the pixels where red dominates green and green dominates
+//! blue, rows get brighter downwards, and a neutral grey card (equal after white balance)
+//! comes out grey. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example rgb_preview`.
+
+use ffed_protosat_rs::image_pipeline::RawImageData;
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+const BLACK: u16 = 256;
+const WB: [f32; 4] = [2.0, 1.0, 1.5, 1.0];
+
+/// RGGB mosaic whose linear [R, G, B] scene color is scaled by `brightness(y)`
+fn mosaic(scene: [f32; 3], brightness: impl Fn(usize) -> f32) -> RawImageData {
+    let data = (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let (x, y) = (i % WIDTH, i / WIDTH);
+            let channel = match (y % 2, x % 2) {
+                (0, 0) => 0,
+                (1, 1) => 2,
+                _ => 1,
+            };
+            // Undo the white balance the camera would record, so the preview restores `scene`
+            let wb = [WB[0], WB[1], WB[2]][channel];
+            let signal = scene[channel] * brightness(y / 2 * 2) / wb * (4095 - BLACK) as f32;
+            BLACK + signal.round() as u16
+        })
+        .collect();
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data,
+        bits_per_sample: 12,
+        wb_coeffs: WB,
+        blacklevels: [BLACK; 4],
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let raw = mosaic([0.8, 0.4, 0.1], |y| 0.25 + 0.75 * y as f32 / HEIGHT as f32);
+    let preview = raw.to_rgb_preview();
+
+    if (preview.width, preview.height) != (WIDTH / 2, HEIGHT / 2) {
+        anyhow::bail!(
+            "Preview is {}x{}, expected {}x{}",
+            preview.width,
+            preview.height,
+            WIDTH / 2,
+            HEIGHT / 2
+        );
+    }
+    if preview.data.len() * 4 != WIDTH * HEIGHT * 3 || preview.bits_per_sample != 8 {
+        anyhow::bail!("Preview should hold a quarter of the pixels at 8 bits");
+    }
+    if let Some(pixel) = preview
+        .data
+        .chunks_exact(3)
+        .find(|p| !(p[0] > p[1] && p[1] > p[2]))
+    {
+        anyhow::bail!("Reddish scene gave preview pixel {:?}", pixel);
+    }
+    let row_red = |y: usize| preview.data[y * preview.width * 3];
+    if (1..preview.height).any(|y| row_red(y) < row_red(y - 1))
+        || row_red(preview.height - 1) <= row_red(0)
+    {
+        anyhow::bail!("Preview does not brighten towards the bottom");
+    }
+    println!(
+        "Reddish scene: top {:?}, bottom {:?}",
+        &preview.data[..3],
+        &preview.data[preview.data.len() - 3..]
+    );
+
+    let grey = mosaic([0.18; 3], |_| 1.0).to_rgb_preview();
+    if let Some(pixel) = grey
+        .data
+        .chunks_exact(3)
+        .find(|p| p.iter().max().unwrap() - p.iter().min().unwrap() > 1)
+    {
+        anyhow::bail!("Grey card gave preview pixel {:?}", pixel);
+    }
+    println!("Grey card: {:?}", &grey.data[..3]);
+
+    println!("Preview bins RGGB cells into plausible colors");
+    Ok(())
+}
@@ -0,0 +1,137 @@
+//! Checks that `set_exposure` and `set_white_balance` retune a live pipeline.
+//!
+//! A flat gray mosaic is converted with `convert_raw_image`, then again after doubling the
+//! exposure and after switching to a 3000 K white balance, all on one pipeline. The
+//! debayer is a `CpuDebayer` wrapped to count frames, so every conversion must go through
+//! the same instance. Doubled exposure must double the mean output, the new white balance
+//! must shift red against blue, and an invalid exposure must be rejected without changing
+//! the config. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example runtime_color`.
+
+use std::io::Cursor;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ffed_protosat_rs::image_pipeline::debayer::XYZ_TO_SRGB;
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, CpuDebayer, Debayer, OutputMode, RawImageData,
+    RawImageReader, RawToTiffPipeline, Result, RgbImageData, RgbImageDataF32, StandardTiffWriter,
+    WhiteBalance,
+};
+use tiff::decoder::{Decoder, DecodingResult};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 32;
+
+/// `CpuDebayer` counting the frames it processes
+struct CountingDebayer {
+    inner: CpuDebayer,
+    frames: Arc<AtomicUsize>,
+}
+
+impl Debayer for CountingDebayer {
+    fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        self.frames.fetch_add(1, Ordering::SeqCst);
+        self.inner.process(raw_image)
+    }
+
+    fn process_f32(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageDataF32> {
+        self.frames.fetch_add(1, Ordering::SeqCst);
+        self.inner.process_f32(raw_image)
+    }
+
+    fn set_color(&mut self, exposure: f32, white_balance: WhiteBalance) {
+        Debayer::set_color(&mut self.inner, exposure, white_balance);
+    }
+}
+
+/// Unused, frames are handed over already decoded
+struct NoReader;
+
+impl RawImageReader for NoReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        unreachable!("convert_raw_image skips the reader")
+    }
+}
+
+/// 12-bit mosaic with every site at 800 above black
+fn flat_gray() -> RawImageData {
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: vec![256 + 800; WIDTH * HEIGHT],
+        bits_per_sample: 12,
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        // XYZ to sRGB, for the camera response to a `Temperature` white point
+        xyz_to_cam: [XYZ_TO_SRGB[0], XYZ_TO_SRGB[1], XYZ_TO_SRGB[2], [0.0; 3]],
+        ..Default::default()
+    }
+}
+
+/// Mean R, G and B of the converted frame
+fn convert(pipeline: &RawToTiffPipeline<NoReader, StandardTiffWriter>) -> anyhow::Result<[f64; 3]> {
+    let mut tiff = Vec::new();
+    pipeline.convert_raw_image(flat_gray(), &mut tiff)?;
+    let DecodingResult::U16(samples) = Decoder::new(Cursor::new(&tiff))?.read_image()? else {
+        anyhow::bail!("Expected 16-bit samples");
+    };
+    let mut sums = [0.0f64; 3];
+    for px in samples.chunks_exact(3) {
+        for (sum, &value) in sums.iter_mut().zip(px) {
+            *sum += f64::from(value);
+        }
+    }
+    let pixels = (samples.len() / 3) as f64;
+    Ok(sums.map(|sum| sum / pixels))
+}
+
+fn mean(rgb: [f64; 3]) -> f64 {
+    rgb.iter().sum::<f64>() / 3.0
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .exposure(1.0)
+        .build();
+    let frames = Arc::new(AtomicUsize::new(0));
+    let debayer = CountingDebayer {
+        inner: CpuDebayer::with_config(&config)?,
+        frames: Arc::clone(&frames),
+    };
+    let mut pipeline = RawToTiffPipeline::with_debayer(NoReader, StandardTiffWriter, Box::new(debayer), config)?;
+
+    let base = convert(&pipeline)?;
+    pipeline.set_exposure(2.0)?;
+    let brighter = convert(&pipeline)?;
+    let ratio = mean(brighter) / mean(base);
+    println!("Mean {:.1} at exposure 1.0, {:.1} at 2.0 ({:.3}x)", mean(base), mean(brighter), ratio);
+    if (ratio - 2.0).abs() > 0.01 {
+        anyhow::bail!("Doubling the exposure scaled the output by {:.3}", ratio);
+    }
+
+    pipeline.set_white_balance(WhiteBalance::Temperature { kelvin: 3000.0, tint: 0.0 });
+    let tungsten = convert(&pipeline)?;
+    let (before, after) = (brighter[0] / brighter[2], tungsten[0] / tungsten[2]);
+    println!("R/B {:.3} as shot, {:.3} at 3000 K", before, after);
+    if (before - after).abs() < 0.05 {
+        anyhow::bail!("Switching white balance left R/B at {:.3}", after);
+    }
+
+    if pipeline.set_exposure(0.0).is_ok() {
+        anyhow::bail!("set_exposure accepted a gain of 0.0");
+    }
+    if pipeline.config().exposure != 2.0 {
+        anyhow::bail!("Rejected exposure changed the config to {}", pipeline.config().exposure);
+    }
+
+    let processed = frames.load(Ordering::SeqCst);
+    if processed != 3 {
+        anyhow::bail!("Wrapped debayer saw {} frames, expected all 3", processed);
+    }
+
+    println!("Exposure and white balance change without rebuilding the debayer");
+    Ok(())
+}
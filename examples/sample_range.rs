@@ -0,0 +1,137 @@
+//! Checks the bit-depth tags written for sub-16-bit grayscale output.
+//!
+//! A 12-bit Bayer frame goes through the pipeline as `BayerGray`. Unscaled, the TIFF
+//! must read back as unsigned 16-bit samples tagged `MaxSampleValue=4095`, the white
+//! level; with `normalize_bayer` the white level becomes 65535 and the tag is left out.
+//! Both the serial and the parallel-strip path are covered, and `estimate_output_size`
+//! must still match the written size. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example sample_range`.
+
+use std::io::Cursor;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, StandardTiffWriter,
+};
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+const BITS: u32 = 12;
+
+/// Ignores the input bytes and returns a 12-bit RGGB ramp reaching the white level
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: (0..WIDTH * HEIGHT)
+                .map(|i| (i * 4095 / (WIDTH * HEIGHT - 1)) as u16)
+                .collect(),
+            bits_per_sample: BITS,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            whitelevels: [4095; 4],
+            cam_to_xyz: [[0.0; 4]; 3],
+            ..Default::default()
+        })
+    }
+}
+
+struct Tags {
+    bits_per_sample: u16,
+    sample_format: u16,
+    max_sample_value: Option<u32>,
+    samples: Vec<u16>,
+}
+
+fn read_back(encoded: &[u8]) -> anyhow::Result<Tags> {
+    let mut decoder = Decoder::new(Cursor::new(encoded))?;
+    let bits_per_sample = decoder.get_tag_unsigned(Tag::BitsPerSample)?;
+    let sample_format = decoder.get_tag_unsigned(Tag::SampleFormat)?;
+    let max_sample_value = decoder
+        .find_tag(Tag::MaxSampleValue)?
+        .map(|value| value.into_u32())
+        .transpose()?;
+    let DecodingResult::U16(samples) = decoder.read_image()? else {
+        anyhow::bail!("Expected 16-bit samples");
+    };
+    Ok(Tags {
+        bits_per_sample,
+        sample_format,
+        max_sample_value,
+        samples,
+    })
+}
+
+fn main() -> anyhow::Result<()> {
+    for parallel_strips in [false, true] {
+        for normalize in [false, true] {
+            let name = format!(
+                "{} {}",
+                if parallel_strips {
+                    "parallel"
+                } else {
+                    "serial"
+                },
+                if normalize { "normalized" } else { "unscaled" }
+            );
+            let config = ConversionConfig::builder()
+                .output(OutputMode::BayerGray)
+                .normalize_bayer(normalize)
+                .parallel_strips(parallel_strips)
+                .build();
+            let pipeline =
+                RawToTiffPipeline::with_custom(SyntheticReader, StandardTiffWriter, config)?;
+
+            let encoded = pipeline.convert_to_vec(&[])?;
+            let tags = read_back(&encoded)?;
+            if tags.bits_per_sample != 16 || tags.sample_format != 1 {
+                anyhow::bail!(
+                    "{}: BitsPerSample={} SampleFormat={}, expected 16 and unsigned",
+                    name,
+                    tags.bits_per_sample,
+                    tags.sample_format
+                );
+            }
+
+            let peak = tags.samples.iter().copied().max().unwrap_or(0);
+            let expected_max = (!normalize).then_some((1u32 << BITS) - 1);
+            if tags.max_sample_value != expected_max {
+                anyhow::bail!(
+                    "{}: MaxSampleValue={:?}, expected {:?}",
+                    name,
+                    tags.max_sample_value,
+                    expected_max
+                );
+            }
+            if u32::from(peak) != expected_max.unwrap_or(u16::MAX as u32) {
+                anyhow::bail!(
+                    "{}: peak sample {} does not match the tagged range",
+                    name,
+                    peak
+                );
+            }
+
+            let estimate = pipeline.estimate(&[])?;
+            if estimate != encoded.len() {
+                anyhow::bail!(
+                    "{}: estimated {} bytes, wrote {}",
+                    name,
+                    estimate,
+                    encoded.len()
+                );
+            }
+            println!(
+                "{}: BitsPerSample=16, MaxSampleValue={:?}, peak {}",
+                name, tags.max_sample_value, peak
+            );
+        }
+    }
+
+    println!("OK");
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Checks the luminance-preserving `saturation` adjustment of the CPU debayer.
+//!
+//! A synthetic mosaic of colored patches is debayered at saturation 1.0, 0.0 and 2.0.
+//! At 0.0 every pixel must be gray with the neutral result's luminance; at 2.0 every
+//! pixel's distance from gray must double while its luminance stays put. The float
+//! output is compared, before quantization clamps anything. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example saturation`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, CpuDebayer, DebayerQuality, OutputMode,
+    RawImageData,
+};
+
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// 12-bit RGGB mosaic of 16x16 patches whose R, G and B sites sit at different levels
+fn patches() -> RawImageData {
+    let (width, height) = (96, 64);
+    let data = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            let patch = (x / 16 + 6 * (y / 16)) as u16;
+            let site = match (y % 2, x % 2) {
+                (0, 0) => 0,
+                (1, 1) => 2,
+                _ => 1,
+            };
+            256 + 300 + (patch * (37 + 53 * site)) % 1500
+        })
+        .collect();
+    RawImageData {
+        width,
+        height,
+        data,
+        bits_per_sample: 12,
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+/// Float RGB pixels debayered at `saturation`
+fn debayer(raw: &RawImageData, saturation: f32) -> anyhow::Result<Vec<[f32; 3]>> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .debayer_quality(DebayerQuality::MalvarHeCutler)
+        .exposure(1.0)
+        .saturation(saturation)
+        .build();
+    let image = CpuDebayer::with_config(&config)?.process_f32(raw)?;
+    Ok(image
+        .data
+        .chunks_exact(3)
+        .map(|px| [px[0], px[1], px[2]])
+        .collect())
+}
+
+fn luma(px: [f32; 3]) -> f32 {
+    LUMA[0] * px[0] + LUMA[1] * px[1] + LUMA[2] * px[2]
+}
+
+fn main() -> anyhow::Result<()> {
+    let raw = patches();
+    let neutral = debayer(&raw, 1.0)?;
+    let gray = debayer(&raw, 0.0)?;
+    let vivid = debayer(&raw, 2.0)?;
+
+    let tolerance = 1e-4;
+    let mut chroma = [0.0f32; 2];
+    for ((n, g), v) in neutral.iter().zip(&gray).zip(&vivid) {
+        let y = luma(*n);
+        if g.iter().any(|c| (c - y).abs() > tolerance) {
+            anyhow::bail!("Saturation 0.0 left color: {:?}, expected gray {}", g, y);
+        }
+        if (luma(*v) - y).abs() > tolerance {
+            anyhow::bail!("Saturation 2.0 moved luminance from {} to {}", y, luma(*v));
+        }
+        for c in 0..3 {
+            if ((v[c] - y) - 2.0 * (n[c] - y)).abs() > tolerance {
+                anyhow::bail!("Saturation 2.0 did not double {:?} away from gray {}", n, y);
+            }
+        }
+        chroma[0] += n.iter().map(|c| (c - y).abs()).sum::<f32>();
+        chroma[1] += v.iter().map(|c| (c - y).abs()).sum::<f32>();
+    }
+
+    println!(
+        "Mean chroma: {:.5} neutral, {:.5} at 2.0",
+        chroma[0] / neutral.len() as f32,
+        chroma[1] / neutral.len() as f32
+    );
+    if chroma[0] <= 0.0 {
+        anyhow::bail!("Test image has no color");
+    }
+
+    println!("Saturation scales chroma and keeps luminance");
+    Ok(())
+}
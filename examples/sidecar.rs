@@ -0,0 +1,71 @@
+//! Checks that `write_sidecar` produces a parseable JSON report next to the output TIFF.
+//!
+//! A synthetic reader stands in for rawloader so no sample files are needed. The sidecar
+//! must exist at `<output>.json` and carry the bit depth, white balance, exposure, levels
+//! and timing breakdown. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example sidecar`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, StandardTiffWriter,
+};
+
+const EXPECTED_KEYS: [&str; 7] = [
+    "bits_per_sample",
+    "wb_coeffs",
+    "white_balance",
+    "exposure",
+    "black_levels",
+    "white_levels",
+    "timings",
+];
+
+/// Ignores the input bytes and returns a small 12-bit RGGB gradient
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        let (width, height) = (64, 48);
+        Ok(RawImageData {
+            width,
+            height,
+            data: (0..width * height).map(|i| (i % 3840 + 256) as u16).collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let input = dir.path().join("frame.raw");
+    let output = dir.path().join("frame.tiff");
+    std::fs::write(&input, [0u8])?;
+
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .write_sidecar(true)
+        .build();
+    let pipeline = RawToTiffPipeline::with_custom(SyntheticReader, StandardTiffWriter, config)?;
+    pipeline.convert_file(&input, &output)?;
+
+    let sidecar = std::fs::read_to_string(output.with_extension("json"))?;
+    let report: serde_json::Value = serde_json::from_str(&sidecar)?;
+    println!("{}", sidecar);
+
+    for key in EXPECTED_KEYS {
+        if report.get(key).is_none() {
+            anyhow::bail!("Sidecar is missing `{}`", key);
+        }
+    }
+    if report.get("bits_per_sample").and_then(|v| v.as_u64()) != Some(12) {
+        anyhow::bail!("Sidecar bits_per_sample does not match the decoded image");
+    }
+
+    println!("Sidecar written with all expected keys");
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Parity check and micro-benchmark for the SIMD color math of the CPU debayer.
+//!
+//! Runs the scalar and `wide::f32x8` paths over the same synthetic frame, requires the
+//! outputs to be bit-identical, and reports the time each path takes.
+//!
+//! Run with `cargo run --release --features simd --example simd_parity`.
+
+use std::time::Instant;
+
+use ffed_protosat_rs::image_pipeline::debayer::color_math::{
+    ColorTransform, to_linear_rgb_scalar, to_linear_rgb_simd,
+};
+
+const WIDTH: usize = 4000;
+const HEIGHT: usize = 3000;
+const RUNS: u32 = 5;
+
+fn main() -> anyhow::Result<()> {
+    // Odd pixel count so the scalar remainder path is exercised as well
+    let pixels: Vec<u16> = (0..WIDTH * HEIGHT * 3 + 3 * 5)
+        .map(|i| ((i * 7919) % 16384) as u16)
+        .collect();
+
+    let transform = ColorTransform {
+        black_level: 512.0,
+        range: 16383.0 - 512.0,
+        white_balance: [2.1, 1.0, 1.6],
+        matrix: [
+            [5.6, -1.9, -0.2, 0.01],
+            [-0.6, 4.8, -0.7, 0.0],
+            [0.1, -1.2, 4.6, -0.01],
+        ],
+    };
+
+    let scalar = to_linear_rgb_scalar(&pixels, &transform);
+    let simd = to_linear_rgb_simd(&pixels, &transform);
+
+    if let Some(index) = scalar.iter().zip(&simd).position(|(a, b)| a.to_bits() != b.to_bits()) {
+        anyhow::bail!(
+            "SIMD output differs at sample {}: scalar {} vs simd {}",
+            index,
+            scalar[index],
+            simd[index]
+        );
+    }
+    if scalar.len() != simd.len() {
+        anyhow::bail!("SIMD produced {} samples, scalar {}", simd.len(), scalar.len());
+    }
+    println!("Scalar and SIMD outputs are bit-identical ({} samples)", scalar.len());
+
+    for (name, path) in [
+        ("scalar", to_linear_rgb_scalar as fn(&[u16], &ColorTransform) -> Vec<f32>),
+        ("simd", to_linear_rgb_simd),
+    ] {
+        let start = Instant::now();
+        for _ in 0..RUNS {
+            std::hint::black_box(path(std::hint::black_box(&pixels), &transform));
+        }
+        println!("{:>6}: {:?} per frame", name, start.elapsed() / RUNS);
+    }
+
+    Ok(())
+}
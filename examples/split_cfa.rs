@@ -0,0 +1,88 @@
+//! Checks that `split_cfa` separates an RGGB mosaic into its four CFA planes.
+//!
+//! Each sample of a synthetic mosaic encodes its site and cell position, and each site
+//! has its own black level. The R, G1, G2 and B planes must be half the size of the
+//! mosaic, with the odd last row and column dropped. Each must hold exactly its site's
+//! samples in order and carry that site's black level. An RGB raw must be rejected.
+//! Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example split_cfa`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionError, RawImageData, split_cfa,
+};
+
+const WIDTH: usize = 13;
+const HEIGHT: usize = 9;
+
+/// Sample at a site: thousands give the site (1 R, 2 G1, 3 G2, 4 B), the rest the cell
+fn sample(x: usize, y: usize) -> u16 {
+    let site = match (y % 2, x % 2) {
+        (0, 0) => 1,
+        (0, _) => 2,
+        (_, 0) => 3,
+        _ => 4,
+    };
+    (site * 1000 + (y / 2) * 10 + x / 2) as u16
+}
+
+fn mosaic(is_bayer: bool) -> RawImageData {
+    RawImageData {
+        width: WIDTH,
+        height: HEIGHT,
+        data: (0..WIDTH * HEIGHT * if is_bayer { 1 } else { 3 })
+            .map(|i| sample(i % WIDTH, i / WIDTH % HEIGHT))
+            .collect(),
+        is_bayer,
+        bits_per_sample: 14,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        // [R, G, B, E], E being the blue-row green
+        blacklevels: [510, 512, 514, 516],
+        whitelevels: [16383; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        ..Default::default()
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let planes = split_cfa(&mosaic(true))?;
+    let names = ["R", "G1", "G2", "B"];
+    let black_levels = [510, 512, 516, 514];
+    for (site, plane) in planes.iter().enumerate() {
+        let name = names[site];
+        if (plane.width, plane.height) != (WIDTH / 2, HEIGHT / 2) {
+            anyhow::bail!(
+                "{} plane is {}x{}, expected {}x{}",
+                name,
+                plane.width,
+                plane.height,
+                WIDTH / 2,
+                HEIGHT / 2
+            );
+        }
+        let expected: Vec<u16> = (0..plane.width * plane.height)
+            .map(|i| ((site + 1) * 1000 + (i / plane.width) * 10 + i % plane.width) as u16)
+            .collect();
+        if plane.data != expected {
+            anyhow::bail!("{} plane holds {:?}, expected {:?}", name, plane.data, expected);
+        }
+        if plane.blacklevels != [black_levels[site]; 4] {
+            anyhow::bail!(
+                "{} plane has black levels {:?}, expected {}",
+                name,
+                plane.blacklevels,
+                black_levels[site]
+            );
+        }
+        println!("{}: {}x{}, black {}", name, plane.width, plane.height, plane.blacklevels[0]);
+    }
+
+    match split_cfa(&mosaic(false)) {
+        Err(ConversionError::UnsupportedFormat(reason)) => println!("RGB raw rejected: {}", reason),
+        Err(e) => anyhow::bail!("RGB raw failed with {}, expected UnsupportedFormat", e),
+        Ok(_) => anyhow::bail!("RGB raw was split into CFA planes"),
+    }
+
+    println!("The mosaic splits into its four CFA planes");
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! Checks `stack_average` on frames with known values.
+//!
+//! Three 12-bit frames hold 1000, 2000 and 4000 plus a per-sample ramp, so every sample
+//! of the stack must be the rounded mean 2333 plus the ramp. Frames at the top of the
+//! 16-bit range must average without overflow, repeated runs must be identical, and a
+//! frame of a different size or an empty input must be rejected. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example stack_average`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionError, Orientation, RawImageData, stack_average,
+};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 24;
+
+fn frame(width: usize, height: usize, value: impl Fn(usize) -> u16) -> RawImageData {
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height).map(value).collect(),
+        bits_per_sample: 12,
+        wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        cam_to_xyz: [[0.0; 4]; 3],
+        orientation: Orientation::Rotate90,
+        ..Default::default()
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let ramp = |i: usize| (i % 50) as u16;
+    let frames: Vec<RawImageData> = [1000u16, 2000, 4000]
+        .into_iter()
+        .map(|base| frame(WIDTH, HEIGHT, move |i| base + ramp(i)))
+        .collect();
+
+    let stacked = stack_average(&frames)?;
+    if (stacked.width, stacked.height) != (WIDTH, HEIGHT)
+        || stacked.orientation != Orientation::Rotate90
+        || stacked.blacklevels != [256; 4]
+    {
+        anyhow::bail!("Stack did not keep the first frame's dimensions and metadata");
+    }
+    // (1000 + 2000 + 4000 + 3 * ramp) / 3 = 2333.33.. + ramp
+    if let Some(i) = (0..WIDTH * HEIGHT).find(|&i| stacked.data[i] != 2333 + ramp(i)) {
+        anyhow::bail!(
+            "Sample {} is {}, expected {}",
+            i,
+            stacked.data[i],
+            2333 + ramp(i)
+        );
+    }
+    println!("Three frames average to 2333 + ramp");
+
+    let bright: Vec<RawImageData> = (0..300)
+        .map(|_| frame(WIDTH, HEIGHT, |_| u16::MAX))
+        .collect();
+    if stack_average(&bright)?.data.iter().any(|&v| v != u16::MAX) {
+        anyhow::bail!("Stacking saturated frames overflowed");
+    }
+    println!("300 saturated frames stay at {}", u16::MAX);
+
+    if stack_average(&frames)?.data != stacked.data {
+        anyhow::bail!("Repeated stack differs");
+    }
+
+    let mut mismatched = frames.clone();
+    mismatched.push(frame(WIDTH, HEIGHT + 2, ramp));
+    match stack_average(&mismatched) {
+        Err(ConversionError::InvalidDimensions(w, h)) => {
+            println!("Mismatched frame rejected: {}x{}", w, h)
+        }
+        Err(e) => anyhow::bail!("Mismatched frame failed with {}", e),
+        Ok(_) => anyhow::bail!("Mismatched frame was stacked"),
+    }
+
+    if stack_average(&[]).is_ok() {
+        anyhow::bail!("Empty input was stacked");
+    }
+
+    println!("OK");
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! Checks that the streaming RGB write path produces the same bytes as the buffered one.
+//!
+//! Synthetic RGB frames are written once from an `RgbImageData` and once row by row,
+//! covering single- and multi-strip sizes, an EXIF IFD and the compressed fallback.
+//! A short row must be rejected. Exits non-zero on the first mismatch.
+//!
+//! Run with `cargo run --example streaming_writer`.
+
+use std::io::Cursor;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, ExifMetadata, RgbImageData, StandardTiffWriter, TiffCompression, TiffWriter,
+};
+
+fn gradient(width: usize, height: usize, exif: ExifMetadata) -> RgbImageData {
+    RgbImageData {
+        width,
+        height,
+        data: (0..width * height * 3).map(|i| (i * 37 % 65536) as u16).collect(),
+        bits_per_sample: 16,
+        exif,
+    }
+}
+
+fn compare(image: &RgbImageData, config: &ConversionConfig) -> anyhow::Result<()> {
+    let mut buffered = Vec::new();
+    StandardTiffWriter.write_rgb_tiff(image, &mut buffered, config)?;
+
+    let mut streamed = Cursor::new(Vec::new());
+    let rows = image.data.chunks_exact(image.width * 3);
+    StandardTiffWriter.write_rgb_tiff_streaming(
+        image.width,
+        image.height,
+        rows,
+        &image.exif,
+        &mut streamed,
+        config,
+    )?;
+
+    let streamed = streamed.into_inner();
+    println!(
+        "{}x{} {:?} (exif: {}): buffered {} bytes, streamed {} bytes",
+        image.width,
+        image.height,
+        config.compression,
+        config.preserve_exif,
+        buffered.len(),
+        streamed.len()
+    );
+    if streamed != buffered {
+        anyhow::bail!("Streamed output differs from the buffered output");
+    }
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let exif = ExifMetadata {
+        exposure_time: Some((1, 125)),
+        f_number: Some((56, 10)),
+        iso: Some(800),
+        focal_length: Some((50, 1)),
+    };
+
+    for (width, height) in [(64, 48), (400, 1000)] {
+        for preserve_exif in [false, true] {
+            for compression in [TiffCompression::None, TiffCompression::DeflateFast] {
+                let config = ConversionConfig::builder()
+                    .compression(compression)
+                    .preserve_exif(preserve_exif)
+                    .build();
+                compare(&gradient(width, height, exif), &config)?;
+            }
+        }
+    }
+
+    let image = gradient(16, 4, ExifMetadata::default());
+    let short_rows = image.data.chunks_exact(image.width * 3).map(|row| &row[1..]);
+    let result = StandardTiffWriter.write_rgb_tiff_streaming(
+        image.width,
+        image.height,
+        short_rows,
+        &image.exif,
+        Cursor::new(Vec::new()),
+        &ConversionConfig::default(),
+    );
+    match result {
+        Err(e) => println!("Short rows rejected: {}", e),
+        Ok(()) => anyhow::bail!("Short rows were accepted"),
+    }
+
+    println!("Streaming and buffered writes match");
+    Ok(())
+}
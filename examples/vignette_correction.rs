@@ -0,0 +1,84 @@
+//! Checks the radial vignetting correction against its polynomial.
+//!
+//! A flat frame with odd dimensions, so one pixel sits exactly on the center, is
+//! converted to a Bayer TIFF with `vignette_correction` set. The center must keep its
+//! signal and each corner, at `r = 1`, must be boosted by `1 + k1 + k2` relative to it.
+//! A pixel halfway along the center row must get `1 + k1*r^2 + k2*r^4`. Exits non-zero
+//! otherwise.
+//!
+//! Run with `cargo run --example vignette_correction`.
+
+use std::io::Cursor;
+
+use tiff::decoder::{Decoder, DecodingResult};
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader, RawToTiffPipeline, Result,
+    StandardTiffWriter,
+};
+
+const WIDTH: usize = 33;
+const HEIGHT: usize = 25;
+const BLACK: u16 = 200;
+const SIGNAL: u16 = 1000;
+
+/// Returns a flat frame `SIGNAL` above the black level
+struct FlatReader;
+
+impl RawImageReader for FlatReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: vec![BLACK + SIGNAL; WIDTH * HEIGHT],
+            blacklevels: [BLACK; 4],
+            ..Default::default()
+        })
+    }
+}
+
+fn corrected(coefficients: [f32; 2]) -> anyhow::Result<Vec<u16>> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::BayerGray)
+        .vignette_correction(Some(coefficients))
+        .build();
+    let tiff = RawToTiffPipeline::with_custom(FlatReader, StandardTiffWriter, config)?.convert_to_vec(&[])?;
+    match Decoder::new(Cursor::new(tiff))?.read_image()? {
+        DecodingResult::U16(samples) => Ok(samples),
+        _ => anyhow::bail!("Output is not 16-bit"),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let (cx, cy) = (WIDTH / 2, HEIGHT / 2);
+    for [k1, k2] in [[0.5, 0.0], [0.3, 0.2], [-0.25, 0.0]] {
+        let samples = corrected([k1, k2])?;
+        let signal = |x: usize, y: usize| samples[y * WIDTH + x] as f32 - BLACK as f32;
+
+        let center = signal(cx, cy);
+        if center != SIGNAL as f32 {
+            anyhow::bail!("k=[{}, {}]: center signal is {}, expected {}", k1, k2, center, SIGNAL);
+        }
+
+        let expected = 1.0 + k1 + k2;
+        for (x, y) in [(0, 0), (WIDTH - 1, 0), (0, HEIGHT - 1), (WIDTH - 1, HEIGHT - 1)] {
+            let gain = signal(x, y) / center;
+            if (gain - expected).abs() > 1e-3 {
+                anyhow::bail!("k=[{}, {}]: corner ({}, {}) gain is {}, expected {}", k1, k2, x, y, gain, expected);
+            }
+        }
+
+        let half_x = cx / 2;
+        let r_sq = (cx - half_x).pow(2) as f32 / (cx * cx + cy * cy) as f32;
+        let expected_mid = 1.0 + k1 * r_sq + k2 * r_sq * r_sq;
+        let mid = signal(half_x, cy) / center;
+        if (mid - expected_mid).abs() > 1e-3 {
+            anyhow::bail!("k=[{}, {}]: gain at r^2={} is {}, expected {}", k1, k2, r_sq, mid, expected_mid);
+        }
+
+        println!("k=[{}, {}]: corner gain {}, gain {} at r^2={:.3}", k1, k2, expected, mid, r_sq);
+    }
+
+    println!("Vignetting correction follows 1 + k1*r^2 + k2*r^4");
+    Ok(())
+}
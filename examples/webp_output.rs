@@ -0,0 +1,102 @@
+//! Checks `WebpWriter` output from `convert_multi`.
+//!
+//! The pipeline writes a TIFF and a WebP of the same debayered frame, once lossy at the
+//! default `webp_quality` and once lossless. Both WebPs must decode to RGB at the frame's
+//! dimensions; the lossless one must hold exactly the TIFF samples scaled to 8 bits and
+//! the lossy one must stay close to them. A quality above 100 must fail validation.
+//! Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example webp_output --features webp`.
+
+use std::io::Cursor;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, RawImageData, RawImageReader,
+    RawToTiffPipeline, Result, StandardTiffWriter, WebpWriter,
+};
+use tiff::decoder::{Decoder, DecodingResult};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+
+/// Ignores the input bytes and returns a 12-bit RGGB gradient
+struct SyntheticReader;
+
+impl RawImageReader for SyntheticReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: (0..WIDTH * HEIGHT)
+                .map(|i| ((i % WIDTH) * 50 + (i / WIDTH) * 20 + 256) as u16)
+                .collect(),
+            bits_per_sample: 12,
+            wb_coeffs: [2.0, 1.0, 1.5, 1.0],
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+/// 8-bit samples of the TIFF and the decoded WebP for `quality`
+fn convert(quality: Option<f32>) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .apply_srgb_gamma(true)
+        .webp_quality(quality)
+        .build();
+    let mut pipeline = RawToTiffPipeline::with_custom(SyntheticReader, StandardTiffWriter, config)?;
+    pipeline.add_writer(Box::new(StandardTiffWriter));
+    pipeline.add_writer(Box::new(WebpWriter));
+
+    let (mut tiff_out, mut webp_out) = (Vec::new(), Vec::new());
+    pipeline.convert_multi(&[], &mut [&mut tiff_out, &mut webp_out])?;
+
+    let DecodingResult::U16(samples) = Decoder::new(Cursor::new(tiff_out))?.read_image()? else {
+        anyhow::bail!("TIFF output is not 16-bit");
+    };
+    let expected = samples
+        .iter()
+        .map(|&v| (f32::from(v) / 65535.0 * 255.0).round() as u8)
+        .collect();
+
+    let Some(decoded) = webp::Decoder::new(&webp_out).decode() else {
+        anyhow::bail!("WebP output ({} bytes) does not decode", webp_out.len());
+    };
+    if (decoded.width() as usize, decoded.height() as usize) != (WIDTH, HEIGHT) {
+        anyhow::bail!("WebP is {}x{}, expected {}x{}", decoded.width(), decoded.height(), WIDTH, HEIGHT);
+    }
+    if decoded.is_alpha() {
+        anyhow::bail!("WebP output has an alpha channel, expected RGB");
+    }
+    println!("{:?}: {} byte WebP, {}x{}", quality, webp_out.len(), decoded.width(), decoded.height());
+    Ok((expected, decoded.to_vec()))
+}
+
+fn main() -> anyhow::Result<()> {
+    let (expected, lossless) = convert(None)?;
+    if lossless != expected {
+        anyhow::bail!("Lossless WebP differs from the TIFF scaled to 8 bits");
+    }
+
+    let (expected, lossy) = convert(Some(80.0))?;
+    let mean_error = expected
+        .iter()
+        .zip(&lossy)
+        .map(|(&a, &b)| f64::from(a.abs_diff(b)))
+        .sum::<f64>()
+        / expected.len() as f64;
+    println!("Lossy mean error: {:.2} levels", mean_error);
+    if mean_error > 6.0 {
+        anyhow::bail!("Lossy WebP is off by {:.2} levels on average", mean_error);
+    }
+
+    let invalid = ConversionConfig::builder().webp_quality(Some(150.0)).build();
+    if invalid.validate().is_ok() {
+        anyhow::bail!("validate accepted webp_quality 150");
+    }
+
+    println!("WebP output decodes at the frame's dimensions");
+    Ok(())
+}
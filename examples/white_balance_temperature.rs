@@ -0,0 +1,50 @@
+//! Checks the multipliers of `WhiteBalance::Temperature` on a camera whose RGB is sRGB.
+//!
+//! The default frame's color matrices are sRGB's, whose white is D65: 6500K shifted
+//! off the Planckian locus by D65's tint. There the multipliers must be neutral to within
+//! `NEUTRAL_TOLERANCE`. A lower temperature is warmer light, redder and less blue, which
+//! the multipliers compensate: at each step down from 6500K the red multiplier must fall
+//! and the blue one rise, while green stays at 1.0. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example white_balance_temperature`.
+
+use ffed_protosat_rs::image_pipeline::{RawImageData, WhiteBalance};
+
+/// D65 lies 0.0032 Duv on the green side of the Planckian locus, in Adobe tint units
+const D65_TINT: f32 = 9.6;
+const NEUTRAL_TOLERANCE: f32 = 0.005;
+
+fn multipliers(raw: &RawImageData, kelvin: f32) -> [f32; 3] {
+    WhiteBalance::Temperature { kelvin, tint: D65_TINT }.multipliers(raw)
+}
+
+fn main() -> anyhow::Result<()> {
+    let raw = RawImageData::default();
+
+    let neutral = multipliers(&raw, 6500.0);
+    println!("6500K: {:?}", neutral);
+    if neutral.iter().any(|m| (m - 1.0).abs() > NEUTRAL_TOLERANCE) {
+        anyhow::bail!("6500K multipliers {:?} are not neutral within {}", neutral, NEUTRAL_TOLERANCE);
+    }
+
+    let mut previous = neutral;
+    for kelvin in [5000.0, 4000.0, 3200.0, 2500.0] {
+        let current = multipliers(&raw, kelvin);
+        println!("{}K: {:?}", kelvin, current);
+        if current[1] != 1.0 {
+            anyhow::bail!("{}K green multiplier is {}, expected 1.0", kelvin, current[1]);
+        }
+        if current[0] >= previous[0] || current[2] <= previous[2] {
+            anyhow::bail!(
+                "{}K multipliers {:?} do not compensate warmer light than {:?}",
+                kelvin,
+                current,
+                previous
+            );
+        }
+        previous = current;
+    }
+
+    println!("Temperature white balance is neutral at 6500K and warms toward lower kelvin");
+    Ok(())
+}
@@ -0,0 +1,107 @@
+//! Checks that `xyz_to_rgb` replaces the XYZ to sRGB matrix of the debayers.
+//!
+//! A flat RGGB mosaic with distinct red, green and blue levels carries an sRGB to XYZ
+//! camera matrix, so the default output is the camera RGB itself. With the identity as
+//! `xyz_to_rgb` every pixel must instead come out as the XYZ of that camera RGB, and the
+//! combined matrix must be the camera matrix alone. Passing `XYZ_TO_SRGB` explicitly must
+//! match the default exactly, and a non-finite matrix must fail validation. Exits non-zero
+//! otherwise.
+//!
+//! Run with `cargo run --example xyz_to_rgb`.
+
+use ffed_protosat_rs::image_pipeline::debayer::XYZ_TO_SRGB;
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, CpuDebayer, OutputMode, RawImageData,
+    compute_color_matrix,
+};
+
+const SRGB_TO_XYZ: [[f32; 4]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375, 0.0],
+    [0.2126729, 0.7151522, 0.0721750, 0.0],
+    [0.0193339, 0.119192, 0.9503041, 0.0],
+];
+
+const IDENTITY: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+const BLACK: u16 = 256;
+const WHITE: u16 = 4095;
+/// Levels above black of the R, G and B sites
+const LEVELS: [u16; 3] = [600, 1200, 1800];
+
+fn flat_mosaic() -> RawImageData {
+    let (width, height) = (32, 32);
+    RawImageData {
+        width,
+        height,
+        data: (0..width * height)
+            .map(|i| {
+                let site = match ((i / width) % 2, (i % width) % 2) {
+                    (0, 0) => 0,
+                    (1, 1) => 2,
+                    _ => 1,
+                };
+                BLACK + LEVELS[site]
+            })
+            .collect(),
+        bits_per_sample: 12,
+        blacklevels: [BLACK; 4],
+        whitelevels: [WHITE; 4],
+        ..Default::default()
+    }
+}
+
+fn config(xyz_to_rgb: Option<[[f32; 3]; 3]>) -> ConversionConfig {
+    ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .exposure(1.0)
+        .xyz_to_rgb(xyz_to_rgb)
+        .build()
+}
+
+/// Float output of the CPU debayer under `xyz_to_rgb`
+fn debayer(raw: &RawImageData, xyz_to_rgb: Option<[[f32; 3]; 3]>) -> anyhow::Result<Vec<f32>> {
+    Ok(CpuDebayer::with_config(&config(xyz_to_rgb))?.process_f32(raw)?.data)
+}
+
+fn check_pixels(output: &[f32], expected: [f32; 3], label: &str) -> anyhow::Result<()> {
+    for px in output.chunks_exact(3) {
+        for (c, (&value, &want)) in px.iter().zip(&expected).enumerate() {
+            if (value - want).abs() > 1e-3 {
+                anyhow::bail!("{}: channel {} is {}, expected {}", label, c, value, want);
+            }
+        }
+    }
+    println!("{}: {:?}", label, expected);
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let raw = flat_mosaic();
+    let range = f32::from(WHITE - BLACK);
+    let camera = LEVELS.map(|level| f32::from(level) / range);
+    let xyz: [f32; 3] = std::array::from_fn(|r| (0..3).map(|k| SRGB_TO_XYZ[r][k] * camera[k]).sum());
+
+    let default = debayer(&raw, None)?;
+    check_pixels(&default, camera, "Default (sRGB)")?;
+
+    let identity = debayer(&raw, Some(IDENTITY))?;
+    check_pixels(&identity, xyz, "Identity (XYZ)")?;
+
+    let matrix = compute_color_matrix(&config(Some(IDENTITY)), &raw);
+    if matrix != SRGB_TO_XYZ {
+        anyhow::bail!("Identity xyz_to_rgb combined to {:?}, expected the camera matrix", matrix);
+    }
+
+    if debayer(&raw, Some(XYZ_TO_SRGB))? != default {
+        anyhow::bail!("Passing XYZ_TO_SRGB explicitly differs from the default");
+    }
+
+    let mut broken = IDENTITY;
+    broken[1][1] = f32::NAN;
+    if config(Some(broken)).validate().is_ok() {
+        anyhow::bail!("validate accepted a non-finite xyz_to_rgb");
+    }
+
+    println!("xyz_to_rgb replaces the XYZ to sRGB matrix");
+    Ok(())
+}
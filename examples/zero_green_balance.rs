@@ -0,0 +1,53 @@
+//! Checks that a missing green white balance coefficient doesn't poison the output.
+//!
+//! With `wb_coeffs[1]` zeroed or `NaN`, as-shot multipliers must be normalized by a green
+//! of 1.0 instead of dividing by it, and `CpuDebayer` float output of a mid-gray mosaic
+//! must be finite and not black. Exits non-zero otherwise.
+//!
+//! Run with `cargo run --example zero_green_balance`.
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, CpuDebayer, RawImageData, WhiteBalance,
+};
+
+fn gray_frame(wb_coeffs: [f32; 4]) -> RawImageData {
+    RawImageData {
+        width: 16,
+        height: 16,
+        data: vec![2048; 256],
+        bits_per_sample: 12,
+        wb_coeffs,
+        blacklevels: [256; 4],
+        whitelevels: [4095; 4],
+        ..Default::default()
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let debayer = CpuDebayer::with_config(&ConversionConfig::default())?;
+
+    for (wb_coeffs, expected) in [
+        ([0.0; 4], [0.0, 1.0, 0.0]),
+        ([2.0, 0.0, 1.5, 0.0], [2.0, 1.0, 1.5]),
+        ([2.0, f32::NAN, 1.5, f32::NAN], [2.0, 1.0, 1.5]),
+    ] {
+        let frame = gray_frame(wb_coeffs);
+        let multipliers = WhiteBalance::AsShot.multipliers(&frame);
+        if multipliers != expected {
+            anyhow::bail!("wb_coeffs {:?} gave multipliers {:?}, expected {:?}", wb_coeffs, multipliers, expected);
+        }
+
+        let image = debayer.process_f32(&frame)?;
+        if let Some(value) = image.data.iter().find(|v| !v.is_finite()) {
+            anyhow::bail!("wb_coeffs {:?} produced a non-finite sample {}", wb_coeffs, value);
+        }
+        let mean = image.data.iter().sum::<f32>() / image.data.len() as f32;
+        if mean <= 0.0 {
+            anyhow::bail!("wb_coeffs {:?} produced a black image", wb_coeffs);
+        }
+        println!("wb_coeffs {:?}: multipliers {:?}, mean {:.4}", wb_coeffs, multipliers, mean);
+    }
+
+    println!("Missing green coefficients fall back to 1.0");
+    Ok(())
+}
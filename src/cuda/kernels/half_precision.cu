@@ -0,0 +1,35 @@
+#include <cuda_runtime.h>
+#include <cuda_fp16.h>
+
+// Convert debayered u16 samples to half floats for the F16 color pipeline.
+// Samples are raised to `floor` (the black point raised by black_clip, so clipping needs
+// no separate pass, or 0) and multiplied by `scale`, which keeps 16-bit codes inside the
+// half range.
+extern "C" __global__ void u16_to_half(
+    const unsigned short* __restrict__ src,
+    __half* __restrict__ dst,
+    int count,
+    float floor,
+    float scale
+) {
+    int i = blockDim.x * blockIdx.x + threadIdx.x;
+
+    if (i >= count)
+        return;
+
+    dst[i] = __float2half_rn(fmaxf((float)src[i], floor) * scale);
+}
+
+// Widen the F16 color pipeline's output back to float.
+extern "C" __global__ void half_to_float(
+    const __half* __restrict__ src,
+    float* __restrict__ dst,
+    int count
+) {
+    int i = blockDim.x * blockIdx.x + threadIdx.x;
+
+    if (i >= count)
+        return;
+
+    dst[i] = __half2float(src[i]);
+}
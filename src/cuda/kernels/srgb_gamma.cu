@@ -0,0 +1,21 @@
+#include <cuda_runtime.h>
+
+// Encode linear samples in place with the sRGB transfer function (IEC 61966-2.1).
+// Negative values are encoded mirrored so out-of-gamut samples keep their sign,
+// matching tone_curve::srgb_encode on the CPU.
+extern "C" __global__ void srgb_encode(
+    float* __restrict__ data,
+    int count
+) {
+    int i = blockDim.x * blockIdx.x + threadIdx.x;
+
+    if (i >= count)
+        return;
+
+    float x = data[i];
+    float magnitude = fabsf(x);
+    float encoded = magnitude <= 0.0031308f
+        ? magnitude * 12.92f
+        : __fmaf_rn(1.055f, powf(magnitude, 1.0f / 2.4f), -0.055f);
+    data[i] = copysignf(encoded, x);
+}
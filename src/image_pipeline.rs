@@ -11,29 +11,77 @@ pub mod debayer;
 
 pub use common::{
     ConversionError,
+    PipelineStage,
+    StageContext,
     Result,
 };
 
 pub use raw::{
     RawImageData,
+    GainMap,
+    RawImageInfo,
+    ExifMetadata,
+    Orientation,
+    CameraProfiles,
     RawImageReader,
     RawLoaderReader,
+    stack_average,
+    split_cfa,
+    extract_preview,
 };
 
 pub use tiff::{
     TiffCompression,
+    OutputMode,
+    GrayPhotometric,
+    ChannelOrder,
+    ByteOrder,
     ConversionConfig,
     ConversionConfigBuilder,
     TiffWriter,
+    ImageWriter,
+    ImageKind,
     StandardTiffWriter,
+    MultiPageTiffWriter,
+    config_for_model,
 };
 
+#[cfg(feature = "webp")]
+pub use tiff::WebpWriter;
+
 pub use conversions::{
     RawToTiffPipeline,
+    tiff_output_path,
+    PipelineBuilder,
+    FrameProcessor,
+    Stage,
+    ConversionReport,
+    DirSummary,
+    PipelineTimings,
 };
 
 pub use debayer::{
     RgbImageData,
+    RgbImageDataF32,
+    GrayImageData,
+    DebayerQuality,
+    DebayerBackend,
+    ColorTransform,
+    NppInterpolation,
+    WorkingPrecision,
+    WhiteBalance,
+    ToneCurve,
+    DenoiseStrength,
+    BorderMode,
+    OverflowMode,
+    ClipStats,
     CudaDebayer,
+    NppDebayer,
     CpuDebayer,
+    Debayer,
+    compute_color_matrix,
+    hdr_merge,
+    contact_sheet,
+    CONTACT_SHEET_TILE_EDGE,
+    wait_with_timeout,
 };
\ No newline at end of file
@@ -2,6 +2,9 @@
 //!
 //! This module contains shared utilities used across the image pipeline.
 
+pub mod concurrency;
 pub mod error;
+pub mod input;
 
-pub use error::{ConversionError, Result};
+pub use error::{ConversionError, PipelineStage, Result, StageContext};
+pub use input::{InputData, read_input};
@@ -0,0 +1,42 @@
+//! Concurrency helpers shared by the pipeline stages
+
+use std::sync::{Condvar, Mutex};
+
+/// Counting semaphore bounding how many threads may run a stage at once
+pub struct ConcurrencyLimit {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Held while inside the limited stage, returns its slot on drop
+pub struct ConcurrencyPermit<'a> {
+    limit: &'a ConcurrencyLimit,
+}
+
+impl ConcurrencyLimit {
+    /// Creates a limit allowing `permits` concurrent holders (at least one)
+    pub fn new(permits: usize) -> Self {
+        Self {
+            available: Mutex::new(permits.max(1)),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a slot is free
+    pub fn acquire(&self) -> ConcurrencyPermit<'_> {
+        let mut available = self.available.lock().unwrap_or_else(|e| e.into_inner());
+        while *available == 0 {
+            available = self.released.wait(available).unwrap_or_else(|e| e.into_inner());
+        }
+        *available -= 1;
+        ConcurrencyPermit { limit: self }
+    }
+}
+
+impl Drop for ConcurrencyPermit<'_> {
+    fn drop(&mut self) {
+        let mut available = self.limit.available.lock().unwrap_or_else(|e| e.into_inner());
+        *available += 1;
+        self.limit.released.notify_one();
+    }
+}
@@ -1,5 +1,43 @@
+use std::fmt;
 use thiserror::Error;
 
+/// Pipeline stage an error was raised in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineStage {
+    /// Reading the input file
+    Read,
+    /// Decoding the RAW container
+    Decode,
+    /// Checking dimensions, buffer length and bit depth
+    Validate,
+    /// User stages added through `PipelineBuilder`
+    Custom,
+    /// Demosaic and color pipeline
+    Debayer,
+    /// TIFF encoding
+    Encode,
+    /// Read-back check of the encoded output
+    Verify,
+    /// Writing the output file or stream
+    Write,
+}
+
+impl fmt::Display for PipelineStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PipelineStage::Read => "read",
+            PipelineStage::Decode => "decode",
+            PipelineStage::Validate => "validate",
+            PipelineStage::Custom => "custom stage",
+            PipelineStage::Debayer => "debayer",
+            PipelineStage::Encode => "encode",
+            PipelineStage::Verify => "verify",
+            PipelineStage::Write => "write",
+        };
+        f.write_str(name)
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ConversionError {
     #[error("Failed to read input file: {0}")]
@@ -11,20 +49,117 @@ pub enum ConversionError {
     #[error("Failed to decode ARW image: {0}")]
     DecodeError(String),
     
+    /// rawloader could not decode the container, keeping its error as the source
+    #[error("Failed to decode RAW image: {context}: {source}")]
+    RawDecode {
+        context: String,
+        #[source]
+        source: rawloader::RawLoaderError,
+    },
+    
     #[error("Failed to encode TIFF image: {0}")]
     EncodeError(String),
     
     #[error("Invalid image dimensions: width={0}, height={1}")]
     InvalidDimensions(usize, usize),
     
+    /// The pixel buffer's length doesn't match the dimensions it claims
+    #[error("Pixel buffer holds {actual} samples, expected {expected}")]
+    DataLengthMismatch { expected: usize, actual: usize },
+    
     #[error("Unsupported format: {0}")]
     UnsupportedFormat(String),
     
+    /// `ConversionConfig::validate` rejected the configuration
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+    
     #[error("CUDA error: {0}")]
     CudaError(String),
     
+    /// The debayer backend failed, keeping its error and that error's chain as the source
+    #[error("{context}: {source}")]
+    Debayer {
+        context: String,
+        #[source]
+        source: anyhow::Error,
+    },
+    
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+    
+    #[error("Conversion cancelled")]
+    Cancelled,
+    
+    #[error("{stage} failed: {source}")]
+    Stage {
+        stage: PipelineStage,
+        #[source]
+        source: Box<ConversionError>,
+    },
+}
+
+impl From<rawloader::RawLoaderError> for ConversionError {
+    fn from(source: rawloader::RawLoaderError) -> Self {
+        ConversionError::RawDecode {
+            context: "rawloader".to_string(),
+            source,
+        }
+    }
+}
+
+/// A `ConversionError` the backend raised itself, such as a GPU timeout, is unwrapped
+/// rather than nested in `Debayer`, so callers can still match on it
+impl From<anyhow::Error> for ConversionError {
+    fn from(source: anyhow::Error) -> Self {
+        match source.downcast::<ConversionError>() {
+            Ok(error) => error,
+            Err(source) => ConversionError::Debayer {
+                context: "Debayering failed".to_string(),
+                source,
+            },
+        }
+    }
+}
+
+impl ConversionError {
+    /// Stage the error was raised in, if it was tagged by the pipeline
+    pub fn stage(&self) -> Option<PipelineStage> {
+        match self {
+            ConversionError::Stage { stage, .. } => Some(*stage),
+            _ => None,
+        }
+    }
+
+    /// The underlying error with any stage tag removed
+    pub fn without_stage(&self) -> &ConversionError {
+        match self {
+            ConversionError::Stage { source, .. } => source.without_stage(),
+            other => other,
+        }
+    }
+
+    /// Tags the error with `stage`, keeping the innermost tag if it already has one
+    pub fn at_stage(self, stage: PipelineStage) -> Self {
+        match self {
+            tagged @ ConversionError::Stage { .. } => tagged,
+            other => ConversionError::Stage {
+                stage,
+                source: Box::new(other),
+            },
+        }
+    }
+}
+
+/// Extension for tagging a `Result`'s error with the stage that produced it
+pub trait StageContext<T> {
+    fn at_stage(self, stage: PipelineStage) -> Result<T>;
+}
+
+impl<T> StageContext<T> for Result<T> {
+    fn at_stage(self, stage: PipelineStage) -> Result<T> {
+        self.map_err(|e| e.at_stage(stage))
+    }
 }
 
 pub type Result<T> = std::result::Result<T, ConversionError>;
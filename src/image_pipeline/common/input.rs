@@ -0,0 +1,51 @@
+//! Input file access for the file-based conversions
+
+use std::ops::Deref;
+use std::path::Path;
+
+#[cfg(feature = "mmap")]
+use tracing::warn;
+
+/// Contents of an input file, memory-mapped or read onto the heap
+pub enum InputData {
+    /// Pages of the file mapped read-only, nothing copied up front
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+    /// The whole file read with `std::fs::read`
+    Owned(Vec<u8>),
+}
+
+impl Deref for InputData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(feature = "mmap")]
+            InputData::Mapped(map) => map,
+            InputData::Owned(data) => data,
+        }
+    }
+}
+
+/// Reads `path` for decoding
+///
+/// With the `mmap` feature the file is memory-mapped, so huge RAWs are paged in by the
+/// decoder instead of being copied to the heap first. Empty files, which some platforms
+/// refuse to map, are read instead, and if the mapping fails (e.g. on a filesystem
+/// without mmap support) it falls back to `std::fs::read`.
+pub fn read_input(path: &Path) -> std::io::Result<InputData> {
+    #[cfg(feature = "mmap")]
+    {
+        let file = std::fs::File::open(path)?;
+        if file.metadata()?.len() > 0 {
+            // SAFETY: the map is read-only and only lives for one conversion; like any
+            // mmap reader we rely on nobody truncating or rewriting the input meanwhile
+            match unsafe { memmap2::Mmap::map(&file) } {
+                Ok(map) => return Ok(InputData::Mapped(map)),
+                Err(e) => warn!("Memory-mapping {} failed, reading it instead: {}", path.display(), e),
+            }
+        }
+    }
+
+    std::fs::read(path).map(InputData::Owned)
+}
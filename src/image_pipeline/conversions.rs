@@ -2,6 +2,14 @@
 //!
 //! This module contains orchestration logic for various image format conversions.
 
+mod builder;
+mod frames;
 mod raw_to_tiff;
+mod report;
+mod stage;
 
-pub use raw_to_tiff::RawToTiffPipeline;
+pub use builder::PipelineBuilder;
+pub use frames::FrameProcessor;
+pub use raw_to_tiff::{RawToTiffPipeline, tiff_output_path};
+pub use stage::Stage;
+pub use report::{ConversionReport, DirSummary, PipelineTimings};
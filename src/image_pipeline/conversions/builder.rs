@@ -0,0 +1,88 @@
+//! Step-by-step construction of a `RawToTiffPipeline` with optional custom stages
+
+use crate::image_pipeline::{
+    common::error::Result,
+    conversions::{RawToTiffPipeline, Stage},
+    debayer::Debayer,
+    raw::{RawImageReader, RawLoaderReader},
+    tiff::{ConversionConfig, StandardTiffWriter, TiffWriter},
+};
+
+/// Builds a `RawToTiffPipeline`, starting from the standard reader and writer
+///
+/// ```ignore
+/// let pipeline = PipelineBuilder::new(config)
+///     .stage(HotPixelFilter)
+///     .stage(Watermark::new(logo))
+///     .build()?;
+/// ```
+pub struct PipelineBuilder<R: RawImageReader, W: TiffWriter> {
+    reader: R,
+    writer: W,
+    config: ConversionConfig,
+    debayer: Option<Box<dyn Debayer>>,
+    stages: Vec<Box<dyn Stage>>,
+}
+
+impl PipelineBuilder<RawLoaderReader, StandardTiffWriter> {
+    pub fn new(config: ConversionConfig) -> Self {
+        Self {
+            reader: RawLoaderReader,
+            writer: StandardTiffWriter,
+            config,
+            debayer: None,
+            stages: Vec::new(),
+        }
+    }
+}
+
+impl<R: RawImageReader, W: TiffWriter> PipelineBuilder<R, W> {
+    /// Replaces the RAW reader
+    pub fn reader<R2: RawImageReader>(self, reader: R2) -> PipelineBuilder<R2, W> {
+        PipelineBuilder {
+            reader,
+            writer: self.writer,
+            config: self.config,
+            debayer: self.debayer,
+            stages: self.stages,
+        }
+    }
+
+    /// Replaces the TIFF writer
+    pub fn writer<W2: TiffWriter>(self, writer: W2) -> PipelineBuilder<R, W2> {
+        PipelineBuilder {
+            reader: self.reader,
+            writer,
+            config: self.config,
+            debayer: self.debayer,
+            stages: self.stages,
+        }
+    }
+
+    /// Debayers with `debayer` instead of the one selected by `config.backend`, as
+    /// `RawToTiffPipeline::with_debayer`
+    pub fn debayer(mut self, debayer: Box<dyn Debayer>) -> Self {
+        self.debayer = Some(debayer);
+        self
+    }
+
+    /// Appends `stage`, which runs after every stage added before it
+    pub fn stage(mut self, stage: impl Stage + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Fails with `InvalidConfig` if the config does, or if stages were added and the
+    /// config asks for `output_float` RGB output, which stages do not run on
+    pub fn build(self) -> Result<RawToTiffPipeline<R, W>> {
+        let mut pipeline = match self.debayer {
+            Some(debayer) => RawToTiffPipeline::with_debayer(self.reader, self.writer, debayer, self.config)?,
+            None => RawToTiffPipeline::with_custom(self.reader, self.writer, self.config)?,
+        };
+        for stage in self.stages {
+            pipeline.add_stage(stage);
+        }
+        pipeline.check_stages(pipeline.config())?;
+        Ok(pipeline)
+    }
+}
@@ -0,0 +1,40 @@
+//! Frame-by-frame conversion for continuous sources such as capture cards
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::image_pipeline::{
+    common::error::Result,
+    conversions::RawToTiffPipeline,
+    raw::RawImageReader,
+    tiff::TiffWriter,
+};
+
+/// Converts a stream of RAW frames to TIFF with one pipeline kept warm between them
+///
+/// The pipeline's debayer, GPU context and concurrency limits are set up once and reused
+/// for every frame. Each output buffer is preallocated to the largest frame seen so far,
+/// so a steady stream of same-sized frames encodes without reallocating. Frames are
+/// independent: a failed frame returns its error and the next one converts as usual.
+/// Created by `RawToTiffPipeline::frame_processor`.
+pub struct FrameProcessor<'a, R: RawImageReader, W: TiffWriter> {
+    pipeline: &'a RawToTiffPipeline<R, W>,
+    /// Largest encoded frame so far, the capacity of the next output buffer
+    capacity: AtomicUsize,
+}
+
+impl<'a, R: RawImageReader, W: TiffWriter> FrameProcessor<'a, R, W> {
+    pub(crate) fn new(pipeline: &'a RawToTiffPipeline<R, W>) -> Self {
+        Self {
+            pipeline,
+            capacity: AtomicUsize::new(0),
+        }
+    }
+
+    /// Converts one frame of RAW bytes to an encoded TIFF, as `convert_to_vec` would
+    pub fn process_frame(&self, frame: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(self.capacity.load(Ordering::Relaxed));
+        self.pipeline.convert(frame, &mut output)?;
+        self.capacity.fetch_max(output.len(), Ordering::Relaxed);
+        Ok(output)
+    }
+}
@@ -1,46 +1,258 @@
-use tracing::{info, instrument};
-use std::io::Write;
-use std::path::Path;
+use rayon::prelude::*;
+use tracing::{info, instrument, warn};
+use std::io::{Cursor, Write};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use crate::image_pipeline::{
-    common::error::{ConversionError, Result},
+    common::concurrency::{ConcurrencyLimit, ConcurrencyPermit},
+    common::input::read_input,
+    common::error::{ConversionError, PipelineStage, Result, StageContext},
     raw::{RawImageReader, RawLoaderReader},
-    tiff::{TiffWriter, StandardTiffWriter, ConversionConfig},
+    raw::{RawImageData, Orientation, corrections, types::SUPPORTED_BITS_PER_SAMPLE},
+    debayer::{CpuDebayer, Debayer, DebayerBackend, NppDebayer, RgbImageData, WhiteBalance},
+    debayer::border::{BORDER, BorderMode},
+    debayer::color_math,
+    tiff::{ImageKind, ImageWriter, TiffWriter, StandardTiffWriter, ConversionConfig, OutputMode},
+    tiff::verify::verify_tiff,
+    tiff::estimate::estimate_tiff_size,
+    tiff::max_sample_value,
+    tiff::thumbnail::thumbnail_dimensions,
+    conversions::report::{ConversionReport, DirSummary, PipelineTimings, timed},
+    conversions::{FrameProcessor, PipelineBuilder, Stage},
 };
 
-#[cfg(jetson_cuda)]
-use crate::image_pipeline::debayer::NppDebayer as Debayer;
-#[cfg(not(jetson_cuda))]
-use crate::image_pipeline::debayer::CpuDebayer as Debayer;
+/// Debayer for the backend selected by `ConversionConfig::backend`
+fn default_debayer(config: &ConversionConfig) -> anyhow::Result<Box<dyn Debayer>> {
+    match config.backend.resolve() {
+        DebayerBackend::Npp if !cfg!(jetson_cuda) => {
+            anyhow::bail!("NPP backend requires a Jetson (jetson_cuda) build")
+        }
+        DebayerBackend::Npp => Ok(Box::new(NppDebayer::with_config(config)?)),
+        _ => Ok(Box::new(CpuDebayer::with_config(config)?)),
+    }
+}
+
+/// Output file created on its first write, so a `max_open_files` slot is only taken
+/// once the image is encoded and is returned as soon as the file is closed
+struct DeferredOutput<'a> {
+    path: &'a Path,
+    limit: &'a ConcurrencyLimit,
+    open: Option<(std::fs::File, ConcurrencyPermit<'a>)>,
+}
+
+impl Write for DeferredOutput<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let (file, _) = match &mut self.open {
+            Some(open) => open,
+            None => {
+                let permit = self.limit.acquire();
+                let file = std::fs::File::create(self.path).map_err(|e| {
+                    std::io::Error::new(e.kind(), format!("{}: {}", self.path.display(), e))
+                })?;
+                self.open.insert((file, permit))
+            }
+        };
+        file.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.open {
+            Some((file, _)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Output path of `input` in `output_dir`: its whole file stem with a `.tiff` extension
+///
+/// `a.v1.dng` becomes `a.v1.tiff`; `Path::with_extension` would cut it to `a.tiff`.
+pub fn tiff_output_path(input: &Path, output_dir: &Path) -> PathBuf {
+    let mut name = input.file_stem().unwrap_or_default().to_owned();
+    name.push(".tiff");
+    output_dir.join(name)
+}
 
+/// Fails with `Cancelled` tagged at `stage` once `cancel` is set
+fn check_cancelled(cancel: Option<&AtomicBool>, stage: PipelineStage) -> Result<()> {
+    match cancel {
+        Some(flag) if flag.load(Ordering::Relaxed) => Err(ConversionError::Cancelled.at_stage(stage)),
+        _ => Ok(()),
+    }
+}
+
+/// Converts RAW input to TIFF through a reader, an optional debayer and a writer
+///
+/// # Reproducibility
+///
+/// On the CPU backend, the same input bytes and `ConversionConfig` produce byte-identical
+/// TIFF output on every run of the same build and platform, independent of
+/// `batch_threads`, `parallel_strips` and how many conversions run concurrently: strips
+/// are assembled in image order, and nothing time- or host-dependent is written to the
+/// file. Only the timings in `ConversionReport` vary between runs. GPU backends are not
+/// covered. `examples/golden_cpu.rs` pins the current CPU output.
 pub struct RawToTiffPipeline<R: RawImageReader, W: TiffWriter> {
     reader: R,
     writer: W,
     config: ConversionConfig,
-    debayer: Option<Debayer>,
+    debayer: Option<Box<dyn Debayer>>,
+    /// Bounds concurrent use of the shared GPU debayer, `None` on the CPU backend
+    gpu_limit: Option<ConcurrencyLimit>,
+    /// Takes over frames the GPU debayer failed on twice, see `gpu_fallback`
+    cpu_fallback: Option<CpuDebayer>,
+    /// Writers `convert_multi` fans the debayered image out to, in registration order
+    writers: Vec<Box<dyn ImageWriter>>,
+    /// Custom stages from `PipelineBuilder`, in the order they run
+    stages: Vec<Box<dyn Stage>>,
+    /// Bounds files open across `convert_file` calls, see `max_open_files`
+    file_limit: Option<ConcurrencyLimit>,
 }
 
 impl RawToTiffPipeline<RawLoaderReader, StandardTiffWriter> {
     pub fn new(config: ConversionConfig) -> Result<Self> {
         Self::with_custom(RawLoaderReader, StandardTiffWriter, config)
     }
+
+    /// Starts a `PipelineBuilder` for adding custom stages or swapping components
+    pub fn builder(config: ConversionConfig) -> PipelineBuilder<RawLoaderReader, StandardTiffWriter> {
+        PipelineBuilder::new(config)
+    }
 }
 
 impl<R: RawImageReader, W: TiffWriter> RawToTiffPipeline<R, W> {
+    /// Fails with `InvalidConfig` if `config.validate()` does
     pub fn with_custom(reader: R, writer: W, config: ConversionConfig) -> Result<Self> {
-        let debayer = if config.debayer {
-            Some(Debayer::new()
-                .map_err(|e| ConversionError::CudaError(format!("Failed to initialize NPP: {}", e)))?)
+        config.validate()?;
+        let debayer = if config.output.requires_debayer() {
+            Some(default_debayer(&config)
+                .map_err(|source| ConversionError::Debayer {
+                    context: "Failed to initialize debayer".to_string(),
+                    source,
+                })?)
+        } else {
+            None
+        };
+
+        Ok(Self::assemble(reader, writer, debayer, config))
+    }
+
+    /// Like `with_custom`, but debayers with `debayer` instead of the one selected by
+    /// `config.backend`
+    ///
+    /// The debayer-affecting fields of `config` only reach `debayer` if it was built
+    /// from the same config.
+    pub fn with_debayer(reader: R, writer: W, debayer: Box<dyn Debayer>, config: ConversionConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self::assemble(reader, writer, Some(debayer), config))
+    }
+
+    fn assemble(reader: R, writer: W, debayer: Option<Box<dyn Debayer>>, config: ConversionConfig) -> Self {
+        let gpu_limit = debayer
+            .as_ref()
+            .filter(|debayer| debayer.uses_gpu())
+            .map(|_| ConcurrencyLimit::new(config.max_gpu_concurrency));
+        let file_limit = config.max_open_files.map(ConcurrencyLimit::new);
+        let cpu_fallback = if gpu_limit.is_some() && config.gpu_fallback {
+            CpuDebayer::with_config(&config)
+                .inspect_err(|e| warn!("No CPU fallback for the GPU debayer: {}", e))
+                .ok()
         } else {
             None
         };
-        
-        Ok(Self {
+
+        Self {
             reader,
             writer,
             config,
             debayer,
-        })
+            gpu_limit,
+            cpu_fallback,
+            writers: Vec::new(),
+            stages: Vec::new(),
+            file_limit,
+        }
+    }
+
+    /// Registers `writer` as the next output of `convert_multi`
+    pub fn add_writer(&mut self, writer: Box<dyn ImageWriter>) {
+        self.writers.push(writer);
+    }
+
+    pub(super) fn add_stage(&mut self, stage: Box<dyn Stage>) {
+        self.stages.push(stage);
+    }
+
+    /// Fails with `InvalidConfig` if custom stages are set and `config` asks for float RGB
+    /// output, which `Stage::apply` cannot run on
+    pub(super) fn check_stages(&self, config: &ConversionConfig) -> Result<()> {
+        if !self.stages.is_empty() && matches!(config.output, OutputMode::Rgb) && config.output_float {
+            return Err(ConversionError::InvalidConfig(format!(
+                "{} custom stage(s) set, but stages do not run on output_float RGB output",
+                self.stages.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Runs each custom stage's raw hook on `raw_image`
+    fn run_raw_stages(&self, raw_image: &mut RawImageData, timings: &mut PipelineTimings) -> Result<()> {
+        for stage in &self.stages {
+            let _span = tracing::info_span!("custom_stage", name = stage.name()).entered();
+            timed(&mut timings.stages, || stage.apply_raw(raw_image)).at_stage(PipelineStage::Custom)?;
+        }
+        Ok(())
+    }
+
+    /// Runs each custom stage's RGB hook on `rgb_image`
+    fn run_rgb_stages(&self, rgb_image: &mut RgbImageData, timings: &mut PipelineTimings) -> Result<()> {
+        for stage in &self.stages {
+            let _span = tracing::info_span!("custom_stage", name = stage.name()).entered();
+            timed(&mut timings.stages, || stage.apply(rgb_image)).at_stage(PipelineStage::Custom)?;
+        }
+        Ok(())
+    }
+
+    /// Runs `stage` on the shared debayer backend
+    ///
+    /// On the GPU backend this waits for one of the `max_gpu_concurrency` slots first,
+    /// so parallel batch workers serialize here instead of each needing a CUDA context.
+    /// With `gpu_fallback`, a failed GPU run is retried once and then handed to the CPU
+    /// debayer, outside the GPU slot.
+    fn run_debayer<T>(
+        &self,
+        output: OutputMode,
+        stage: impl Fn(&dyn Debayer) -> anyhow::Result<T>,
+    ) -> Result<T> {
+        let debayer = self.debayer.as_ref().ok_or_else(|| {
+            ConversionError::CudaError(format!(
+                "No debayer initialized for {:?} output",
+                output
+            ))
+        }).at_stage(PipelineStage::Debayer)?;
+
+        let result = {
+            let _permit = self.gpu_limit.as_ref().map(ConcurrencyLimit::acquire);
+            stage(debayer.as_ref()).or_else(|first| match &self.cpu_fallback {
+                Some(_) => {
+                    warn!("GPU debayer failed, retrying once: {}", first);
+                    stage(debayer.as_ref())
+                }
+                None => Err(first),
+            })
+        };
+        let result = match (result, &self.cpu_fallback) {
+            (Err(e), Some(cpu)) => {
+                warn!("GPU debayer failed again, falling back to the CPU for this frame: {}", e);
+                stage(cpu)
+            }
+            (result, _) => result,
+        };
+
+        result
+            .map_err(ConversionError::from)
+            .at_stage(PipelineStage::Debayer)
     }
 
     fn validate_dimensions(&self, width: usize, height: usize) -> Result<()> {
@@ -52,64 +264,585 @@ impl<R: RawImageReader, W: TiffWriter> RawToTiffPipeline<R, W> {
             return Err(ConversionError::InvalidDimensions(width, height));
         }
 
+        if let Some(max) = self.config.max_dimension
+            && (width > max || height > max)
+        {
+            warn!("Image dimensions {}x{} exceed maximum of {}", width, height, max);
+            return Err(ConversionError::InvalidDimensions(width, height));
+        }
+
         Ok(())
     }
 
-    #[instrument(skip(self, input_data, output), fields(input_size = input_data.len()))]
-    pub fn convert(&self, input_data: &[u8], output: &mut dyn Write) -> Result<()> {
-        info!("Starting RAW to TIFF conversion");
+    fn validate_data_length(&self, image: &RawImageData) -> Result<()> {
+        // A short buffer would make the debayer or writer index out of bounds,
+        // so this check runs regardless of `validate_dimensions`
+        let expected = image.width * image.height * image.samples_per_pixel();
+        if image.data.len() != expected {
+            warn!(
+                "Pixel buffer holds {} samples, expected {} for {}x{}",
+                image.data.len(),
+                expected,
+                image.width,
+                image.height
+            );
+            return Err(ConversionError::DataLengthMismatch {
+                expected,
+                actual: image.data.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_bit_depth(&self, image: &RawImageData) -> Result<()> {
+        if !SUPPORTED_BITS_PER_SAMPLE.contains(&image.bits_per_sample) {
+            return Err(ConversionError::UnsupportedFormat(format!(
+                "{} bits per sample, expected {}..={}",
+                image.bits_per_sample,
+                SUPPORTED_BITS_PER_SAMPLE.start(),
+                SUPPORTED_BITS_PER_SAMPLE.end()
+            )));
+        }
+
+        Ok(())
+    }
 
+    /// Decodes the RAW input, validates the result and applies raw-domain corrections
+    fn decode(&self, input_data: &[u8], timings: &mut PipelineTimings) -> Result<RawImageData> {
         let raw_image = {
             let _span = tracing::info_span!("decode_raw").entered();
-            self.reader.read_raw(input_data)?
+            timed(&mut timings.decode, || self.reader.read_raw(input_data))
+                .at_stage(PipelineStage::Decode)?
         };
+        self.validate_and_correct(raw_image, timings)
+    }
 
+    /// Validation, raw-domain corrections and custom raw stages for a decoded image
+    fn validate_and_correct(&self, mut raw_image: RawImageData, timings: &mut PipelineTimings) -> Result<RawImageData> {
         {
             let _span = tracing::info_span!("validate_dimensions", 
                 width = raw_image.width, 
                 height = raw_image.height
             ).entered();
-            self.validate_dimensions(raw_image.width, raw_image.height)?;
+            timed(&mut timings.validate, || {
+                self.validate_dimensions(raw_image.width, raw_image.height)
+                    .and_then(|_| self.validate_data_length(&raw_image))
+                    .and_then(|_| self.validate_bit_depth(&raw_image))
+            })
+            .at_stage(PipelineStage::Validate)?;
         }
 
-        // Debayer if configured
-        if let Some(ref debayer) = self.debayer {
-            let rgb_image = {
-                let _span = tracing::info_span!("debayer").entered();
-                debayer.process(&raw_image)
-                    .map_err(|e| ConversionError::CudaError(format!("Debayering failed: {}", e)))?
-            };
-            
-            let _span = tracing::info_span!("encode_tiff").entered();
-            self.writer.write_rgb_tiff(&rgb_image, output, &self.config)?;
-            
+        {
+            let _span = tracing::info_span!("estimate_missing_levels").entered();
+            timed(&mut timings.corrections, || corrections::estimate_missing_levels(&mut raw_image));
+        }
+
+        if !self.config.bad_columns.is_empty() {
+            let _span = tracing::info_span!("bad_columns").entered();
+            timed(&mut timings.corrections, || {
+                corrections::correct_bad_columns(&mut raw_image, &self.config.bad_columns)
+            });
+        }
+
+        if self.config.apply_gain_map && raw_image.gain_map.is_some() {
+            let _span = tracing::info_span!("gain_map").entered();
+            timed(&mut timings.corrections, || corrections::apply_gain_map(&mut raw_image));
+        }
+
+        if let Some(coefficients) = self.config.vignette_correction {
+            let _span = tracing::info_span!("vignette_correction").entered();
+            timed(&mut timings.corrections, || {
+                corrections::correct_vignetting(&mut raw_image, coefficients)
+            });
+        }
+
+        self.run_raw_stages(&mut raw_image, timings)?;
+        Ok(raw_image)
+    }
+
+    /// Raw-domain corrections that only make sense ahead of the debayer, then the
+    /// `log_color_pipeline` diagnostics for the corrected image
+    fn prepare_for_debayer(&self, raw_image: &mut RawImageData, timings: &mut PipelineTimings) {
+        if let Some(matrix) = self.config.color_matrix {
+            raw_image.cam_to_xyz = matrix;
+        }
+        if self.config.balance_green_sites {
+            let _span = tracing::info_span!("balance_green_sites").entered();
+            timed(&mut timings.corrections, || corrections::balance_green_sites(raw_image));
+        }
+        if self.config.green_equalization {
+            let _span = tracing::info_span!("green_equalization").entered();
+            timed(&mut timings.corrections, || corrections::equalize_green_sites(raw_image));
+        }
+        if self.config.log_color_pipeline {
+            let transform = color_math::ColorTransform::for_raw(&self.config, raw_image);
             info!(
-                width = rgb_image.width,
-                height = rgb_image.height,
-                format = "RGB",
-                "Conversion complete"
+                white_balance = ?transform.white_balance,
+                black_level = transform.black_level,
+                white_level = transform.black_level + transform.range,
+                matrix = ?transform.matrix,
+                "Color pipeline"
             );
+        }
+    }
+
+    #[instrument(skip(self, input_data, output), fields(input_size = input_data.len()))]
+    pub fn convert(&self, input_data: &[u8], output: &mut dyn Write) -> Result<()> {
+        self.convert_with_config(input_data, output, &self.config)
+    }
+
+    /// Converts using `config` for output selection and encoding instead of the pipeline's own
+    ///
+    /// Decoding, validation and the debayer instance stay as configured at construction,
+    /// so debayer-affecting fields of `config` (`debayer_quality`, `npp_interpolation`)
+    /// are ignored. Fails if `config.output` needs a debayer the pipeline was not built
+    /// with, or with `InvalidConfig` if it asks for float RGB output while custom stages
+    /// are set.
+    #[instrument(skip(self, input_data, output, config), fields(input_size = input_data.len()))]
+    pub fn convert_with_config(
+        &self,
+        input_data: &[u8],
+        output: &mut dyn Write,
+        config: &ConversionConfig,
+    ) -> Result<()> {
+        self.convert_with_report(input_data, output, config).map(|_| ())
+    }
+
+    /// Same as `convert_with_config`, returning what was detected and applied along with
+    /// per-stage timings. The `read` timing is left at zero since the input is already in memory.
+    #[instrument(skip(self, input_data, output, config), fields(input_size = input_data.len()))]
+    pub fn convert_with_report(
+        &self,
+        input_data: &[u8],
+        output: &mut dyn Write,
+        config: &ConversionConfig,
+    ) -> Result<ConversionReport> {
+        info!("Starting RAW to TIFF conversion");
+
+        let mut timings = PipelineTimings::default();
+        let raw_image = self.decode(input_data, &mut timings)?;
+        self.write_decoded(raw_image, output, config, timings, None)
+    }
+
+    /// Same as `convert`, starting from an already decoded image instead of RAW bytes
+    ///
+    /// Validation, corrections and everything after run as in `convert`; only the
+    /// reader is skipped. Useful for benchmarking and for frames that do not come from
+    /// a RAW container.
+    #[instrument(skip(self, raw_image, output), fields(width = raw_image.width, height = raw_image.height))]
+    pub fn convert_raw_image(&self, raw_image: RawImageData, output: &mut dyn Write) -> Result<()> {
+        info!("Starting decoded RAW to TIFF conversion");
+
+        let mut timings = PipelineTimings::default();
+        let raw_image = self.validate_and_correct(raw_image, &mut timings)?;
+        self.write_decoded(raw_image, output, &self.config, timings, None)
+            .map(|_| ())
+    }
+
+    /// Same as `convert`, but gives up with `ConversionError::Cancelled` once `cancel` is set
+    ///
+    /// The flag is checked before decoding and between the decode, debayer and encode
+    /// stages, so a stage that is already running finishes first. Output already written
+    /// to `output` when the flag is seen is left as is.
+    #[instrument(skip(self, input_data, output, cancel), fields(input_size = input_data.len()))]
+    pub fn convert_cancellable(
+        &self,
+        input_data: &[u8],
+        output: &mut dyn Write,
+        cancel: &AtomicBool,
+    ) -> Result<()> {
+        info!("Starting cancellable RAW to TIFF conversion");
+
+        check_cancelled(Some(cancel), PipelineStage::Decode)?;
+        let mut timings = PipelineTimings::default();
+        let raw_image = self.decode(input_data, &mut timings)?;
+        self.write_decoded(raw_image, output, &self.config, timings, Some(cancel))
+            .map(|_| ())
+    }
+
+    /// Writes an already decoded image as `config.output`, debayering first if it needs to,
+    /// and checking `cancel` ahead of each remaining stage
+    fn write_decoded(
+        &self,
+        mut raw_image: RawImageData,
+        output: &mut dyn Write,
+        config: &ConversionConfig,
+        mut timings: PipelineTimings,
+        cancel: Option<&AtomicBool>,
+    ) -> Result<ConversionReport> {
+        self.check_stages(config)?;
+        check_cancelled(cancel, PipelineStage::Debayer)?;
+        if config.output.requires_debayer() {
+            self.prepare_for_debayer(&mut raw_image, &mut timings);
+        }
+
+        // The debayer was built from the pipeline's own config, so report what it applies
+        let debayered = config.output.requires_debayer();
+        let mut report = ConversionReport {
+            width: raw_image.width,
+            height: raw_image.height,
+            output: format!("{:?}", config.output),
+            bits_per_sample: raw_image.bits_per_sample,
+            wb_coeffs: raw_image.wb_coeffs,
+            white_balance: debayered.then(|| self.config.white_balance.multipliers(&raw_image)),
+            exposure: debayered.then_some(self.config.exposure),
+            black_levels: raw_image.blacklevels,
+            white_levels: raw_image.whitelevels,
+            clipping: None,
+            timings,
+        };
+        let timings = &mut report.timings;
+
+        // With verification on, encode into memory first so the result can be read back
+        let mut encoded = Vec::new();
+        let sink: &mut dyn Write = if config.verify_output { &mut encoded } else { &mut *output };
+
+        let orientation = if config.apply_orientation {
+            raw_image.orientation
         } else {
-            let _span = tracing::info_span!("encode_tiff").entered();
-            self.writer.write_tiff(&raw_image, output, &self.config)?;
-            
-            info!(
-                width = raw_image.width,
-                height = raw_image.height,
-                format = "Grayscale Bayer",
-                "Conversion complete"
-            );
+            Orientation::Normal
+        };
+
+        let (width, height, color_type) = match config.output {
+            OutputMode::BayerGray => {
+                if !raw_image.is_bayer {
+                    return Err(ConversionError::UnsupportedFormat(
+                        "BayerGray output needs a Bayer mosaic, the raw is already RGB".to_string(),
+                    )
+                    .at_stage(PipelineStage::Encode));
+                }
+
+                let raw_image = if config.normalize_bayer {
+                    let _span = tracing::info_span!("normalize_bayer").entered();
+                    RawImageData {
+                        data: raw_image.normalized_to_16_bit(),
+                        bits_per_sample: 16,
+                        ..raw_image
+                    }
+                } else {
+                    raw_image
+                };
+                
+                check_cancelled(cancel, PipelineStage::Encode)?;
+                let _span = tracing::info_span!("encode_tiff").entered();
+                timed(&mut timings.encode, || self.writer.write_tiff(&raw_image, sink, config))
+                    .at_stage(PipelineStage::Encode)?;
+                
+                info!(
+                    width = raw_image.width,
+                    height = raw_image.height,
+                    format = "Grayscale Bayer",
+                    "Conversion complete"
+                );
+                (raw_image.width, raw_image.height, ::tiff::ColorType::Gray(16))
+            }
+            OutputMode::Rgb if config.output_float => {
+                let rgb_image = {
+                    let _span = tracing::info_span!("debayer").entered();
+                    timed(&mut timings.debayer, || {
+                        self.run_debayer(config.output, |debayer| debayer.process_f32(&raw_image))
+                    })?
+                };
+                let rgb_image = {
+                    let _span = tracing::info_span!("denoise").entered();
+                    timed(&mut timings.denoise, || {
+                        rgb_image.denoised(config.denoise).chroma_denoised(config.chroma_denoise)
+                    })
+                };
+                let mut rgb_image = rgb_image.oriented(orientation);
+                config.channel_order.apply(&mut rgb_image.data);
+                
+                check_cancelled(cancel, PipelineStage::Encode)?;
+                let _span = tracing::info_span!("encode_tiff").entered();
+                timed(&mut timings.encode, || self.writer.write_rgb_tiff_f32(&rgb_image, sink, config))
+                    .at_stage(PipelineStage::Encode)?;
+                
+                info!(
+                    width = rgb_image.width,
+                    height = rgb_image.height,
+                    format = "RGB float",
+                    "Conversion complete"
+                );
+                (rgb_image.width, rgb_image.height, ::tiff::ColorType::RGB(32))
+            }
+            OutputMode::Rgb => {
+                let (rgb_image, clipping) = {
+                    let _span = tracing::info_span!("debayer").entered();
+                    timed(&mut timings.debayer, || {
+                        self.run_debayer(config.output, |debayer| debayer.process_with_stats(&raw_image))
+                    })?
+                };
+                report.clipping = clipping;
+                let mut rgb_image = {
+                    let _span = tracing::info_span!("denoise").entered();
+                    timed(&mut timings.denoise, || {
+                        rgb_image.denoised(config.denoise).chroma_denoised(config.chroma_denoise)
+                    })
+                };
+                self.run_rgb_stages(&mut rgb_image, timings)?;
+                let mut rgb_image = rgb_image.oriented(orientation);
+                config.channel_order.apply(&mut rgb_image.data);
+                
+                check_cancelled(cancel, PipelineStage::Encode)?;
+                let _span = tracing::info_span!("encode_tiff").entered();
+                timed(&mut timings.encode, || self.writer.write_rgb_tiff(&rgb_image, sink, config))
+                    .at_stage(PipelineStage::Encode)?;
+                
+                info!(
+                    width = rgb_image.width,
+                    height = rgb_image.height,
+                    format = "RGB",
+                    "Conversion complete"
+                );
+                (rgb_image.width, rgb_image.height, ::tiff::ColorType::RGB(16))
+            }
+            OutputMode::Luminance => {
+                let (rgb_image, clipping) = {
+                    let _span = tracing::info_span!("debayer").entered();
+                    timed(&mut timings.debayer, || {
+                        self.run_debayer(config.output, |debayer| debayer.process_with_stats(&raw_image))
+                    })?
+                };
+                report.clipping = clipping;
+                let mut rgb_image = {
+                    let _span = tracing::info_span!("denoise").entered();
+                    timed(&mut timings.denoise, || rgb_image.denoised(config.denoise))
+                };
+                self.run_rgb_stages(&mut rgb_image, timings)?;
+                let rgb_image = rgb_image.oriented(orientation);
+                
+                let luminance = {
+                    let _span = tracing::info_span!("luminance").entered();
+                    rgb_image.luminance_image()
+                };
+                
+                check_cancelled(cancel, PipelineStage::Encode)?;
+                let _span = tracing::info_span!("encode_tiff").entered();
+                timed(&mut timings.encode, || self.writer.write_gray_tiff(&luminance, sink, config))
+                    .at_stage(PipelineStage::Encode)?;
+                
+                info!(
+                    width = luminance.width,
+                    height = luminance.height,
+                    format = "Luminance",
+                    "Conversion complete"
+                );
+                (luminance.width, luminance.height, ::tiff::ColorType::Gray(16))
+            }
+        };
+
+        if config.verify_output {
+            {
+                let _span = tracing::info_span!("verify_output").entered();
+                timed(&mut timings.verify, || verify_tiff(&encoded, width, height, color_type))
+                    .at_stage(PipelineStage::Verify)?;
+            }
+            check_cancelled(cancel, PipelineStage::Write)?;
+            timed(&mut timings.write, || output.write_all(&encoded))
+                .map_err(ConversionError::from)
+                .at_stage(PipelineStage::Write)?;
         }
 
+        report.width = width;
+        report.height = height;
+        Ok(report)
+    }
+
+    /// Decodes the input once and writes both the grayscale Bayer mosaic and the debayered RGB image
+    ///
+    /// Both writes use the pipeline's config apart from the output mode, so `normalize_bayer`
+    /// applies to the Bayer output and `output_float` to the RGB one. The pipeline must have
+    /// been built with a debayer (any output mode other than `BayerGray`).
+    #[instrument(skip(self, input_data, bayer_out, rgb_out), fields(input_size = input_data.len()))]
+    pub fn convert_both(
+        &self,
+        input_data: &[u8],
+        bayer_out: &mut dyn Write,
+        rgb_out: &mut dyn Write,
+    ) -> Result<()> {
+        info!("Starting RAW to Bayer and RGB TIFF conversion");
+
+        let mut timings = PipelineTimings::default();
+        let raw_image = self.decode(input_data, &mut timings)?;
+
+        let bayer_config = ConversionConfig {
+            output: OutputMode::BayerGray,
+            ..self.config.clone()
+        };
+        let rgb_config = ConversionConfig {
+            output: OutputMode::Rgb,
+            ..self.config.clone()
+        };
+        self.check_stages(&rgb_config)?;
+
+        self.write_decoded(raw_image.clone(), bayer_out, &bayer_config, timings, None)?;
+        self.write_decoded(raw_image, rgb_out, &rgb_config, timings, None)?;
         Ok(())
     }
 
-    #[instrument(skip(self, input_path, output_path))]
+    /// Decodes and debayers the input once and writes the image with every writer
+    /// registered through `add_writer`, `outputs[i]` receiving `writers[i]`
+    ///
+    /// The image is the one `debayer_only` returns. Fails before decoding if the number
+    /// of outputs does not match the number of writers, and stops at the first writer
+    /// that fails.
+    #[instrument(skip(self, input_data, outputs), fields(input_size = input_data.len(), outputs = outputs.len()))]
+    pub fn convert_multi(&self, input_data: &[u8], outputs: &mut [&mut dyn Write]) -> Result<()> {
+        if outputs.len() != self.writers.len() {
+            return Err(ConversionError::OutputWriteError(format!(
+                "{} outputs given for {} registered writers",
+                outputs.len(),
+                self.writers.len()
+            )));
+        }
+
+        let rgb_image = self.debayer_only(input_data)?;
+
+        let _span = tracing::info_span!("encode_outputs").entered();
+        for (writer, output) in self.writers.iter().zip(outputs.iter_mut()) {
+            writer.write(ImageKind::Rgb16(&rgb_image), &mut **output, &self.config)
+                .at_stage(PipelineStage::Encode)?;
+        }
+
+        info!(
+            width = rgb_image.width,
+            height = rgb_image.height,
+            outputs = self.writers.len(),
+            "Conversion complete"
+        );
+        Ok(())
+    }
+
+    /// Decodes and debayers the input with the configured backend, skipping the writer
+    ///
+    /// Useful for callers that post-process the RGB image themselves. `denoise`,
+    /// `chroma_denoise` and `apply_orientation` are honored as in `convert`.
+    #[instrument(skip(self, input_data), fields(input_size = input_data.len()))]
+    pub fn debayer_only(&self, input_data: &[u8]) -> Result<RgbImageData> {
+        let mut timings = PipelineTimings::default();
+        let mut raw_image = self.decode(input_data, &mut timings)?;
+        self.prepare_for_debayer(&mut raw_image, &mut timings);
+
+        let rgb_image = {
+            let _span = tracing::info_span!("debayer").entered();
+            self.run_debayer(self.config.output, |debayer| debayer.process(&raw_image))?
+        };
+        let mut rgb_image = {
+            let _span = tracing::info_span!("denoise").entered();
+            rgb_image
+                .denoised(self.config.denoise)
+                .chroma_denoised(self.config.chroma_denoise)
+        };
+        self.run_rgb_stages(&mut rgb_image, &mut timings)?;
+
+        Ok(if self.config.apply_orientation {
+            rgb_image.oriented(raw_image.orientation)
+        } else {
+            rgb_image
+        })
+    }
+
+    /// Expected size in bytes of the TIFF `convert` would write for `raw_image`
+    ///
+    /// Exact for uncompressed output from `StandardTiffWriter`; with compression the pixel
+    /// data is scaled by `TiffCompression::estimated_ratio`. Custom writers and debayers may differ.
+    pub fn estimate_output_size(&self, raw_image: &RawImageData) -> usize {
+        let config = &self.config;
+        let (channels, bytes_per_sample) = match config.output {
+            OutputMode::Rgb if config.output_float => (3, 4),
+            OutputMode::Rgb => (3, 2),
+            OutputMode::BayerGray | OutputMode::Luminance => (1, 2),
+        };
+        let exif = (config.preserve_exif && !raw_image.exif.is_empty()).then_some(&raw_image.exif);
+        let (width, height) = if config.output.requires_debayer()
+            && config.border_mode == BorderMode::Crop
+            && raw_image.is_bayer
+        {
+            (
+                raw_image.width.saturating_sub(2 * BORDER),
+                raw_image.height.saturating_sub(2 * BORDER),
+            )
+        } else {
+            (raw_image.width, raw_image.height)
+        };
+        let (width, height) = if config.apply_orientation && config.output.requires_debayer() {
+            raw_image.orientation.oriented_dimensions(width, height)
+        } else {
+            (width, height)
+        };
+        let thumbnail = match (config.output, config.embed_thumbnail) {
+            (OutputMode::Rgb, Some(max_edge)) => thumbnail_dimensions(width, height, max_edge),
+            _ => None,
+        };
+
+        // MaxSampleValue fits in its IFD entry
+        let bits = if config.normalize_bayer { 16 } else { raw_image.bits_per_sample };
+        let range_tag = config.output == OutputMode::BayerGray && max_sample_value(bits).is_some();
+
+        estimate_tiff_size(
+            width,
+            height,
+            channels,
+            bytes_per_sample,
+            exif,
+            thumbnail,
+            config.compression,
+        ) + 12 * usize::from(range_tag)
+    }
+
+    /// Decodes the input and returns `estimate_output_size` without debayering or encoding
+    #[instrument(skip(self, input_data), fields(input_size = input_data.len()))]
+    pub fn estimate(&self, input_data: &[u8]) -> Result<usize> {
+        let raw_image = self.decode(input_data, &mut PipelineTimings::default())?;
+        Ok(self.estimate_output_size(&raw_image))
+    }
+
+    /// Runs `convert` into an in-memory buffer and returns the encoded TIFF bytes
+    pub fn convert_to_vec(&self, input_data: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Cursor::new(Vec::new());
+        self.convert(input_data, &mut output)?;
+        Ok(output.into_inner())
+    }
+
+    /// A `FrameProcessor` converting frames one at a time with this pipeline
+    pub fn frame_processor(&self) -> FrameProcessor<'_, R, W> {
+        FrameProcessor::new(self)
+    }
+
+    /// Converts each of `frames` in turn as it is pulled from the iterator, yielding the
+    /// encoded TIFFs in order
+    ///
+    /// Frames go through one `FrameProcessor`, so the pipeline stays warm and output
+    /// buffers are sized from earlier frames. A frame that fails yields its error and the
+    /// iteration goes on with the next.
+    pub fn convert_all<'a, I>(&'a self, frames: I) -> impl Iterator<Item = Result<Vec<u8>>> + 'a
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+        I::IntoIter: 'a,
+    {
+        let processor = self.frame_processor();
+        frames
+            .into_iter()
+            .map(move |frame| processor.process_frame(frame.as_ref()))
+    }
+
     pub fn convert_file<P: AsRef<Path>, Q: AsRef<Path>>(
         &self,
         input_path: P,
         output_path: Q,
     ) -> Result<()> {
+        self.convert_file_with_report(input_path, output_path).map(|_| ())
+    }
+
+    /// Same as `convert_file`, returning the conversion report with the `read` timing set
+    #[instrument(skip(self, input_path, output_path))]
+    pub fn convert_file_with_report<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input_path: P,
+        output_path: Q,
+    ) -> Result<ConversionReport> {
         let input_path = input_path.as_ref();
         let output_path = output_path.as_ref();
 
@@ -119,23 +852,193 @@ impl<R: RawImageReader, W: TiffWriter> RawToTiffPipeline<R, W> {
             "Converting file"
         );
 
+        let mut read_time = Duration::ZERO;
         let input_data = {
             let _span = tracing::info_span!("read_input_file").entered();
-            std::fs::read(input_path).map_err(|e| {
+            let _permit = self.file_limit.as_ref().map(ConcurrencyLimit::acquire);
+            timed(&mut read_time, || read_input(input_path)).map_err(|e| {
                 ConversionError::InputReadError(format!("{}: {}", input_path.display(), e))
-            })?
+            }).at_stage(PipelineStage::Read)?
         };
 
-        let mut output_file = {
-            let _span = tracing::info_span!("create_output_file").entered();
-            std::fs::File::create(output_path).map_err(|e| {
-                ConversionError::OutputWriteError(format!("{}: {}", output_path.display(), e))
-            })?
+        let mut report = match &self.file_limit {
+            Some(limit) => {
+                let mut output = DeferredOutput { path: output_path, limit, open: None };
+                self.convert_with_report(&input_data, &mut output, &self.config)?
+            }
+            None => {
+                let mut output_file = {
+                    let _span = tracing::info_span!("create_output_file").entered();
+                    std::fs::File::create(output_path).map_err(|e| {
+                        ConversionError::OutputWriteError(format!("{}: {}", output_path.display(), e))
+                    }).at_stage(PipelineStage::Write)?
+                };
+                self.convert_with_report(&input_data, &mut output_file, &self.config)?
+            }
         };
+        report.timings.read = read_time;
 
-        self.convert(&input_data, &mut output_file)?;
+        if self.config.write_sidecar {
+            let _span = tracing::info_span!("write_sidecar").entered();
+            let _permit = self.file_limit.as_ref().map(ConcurrencyLimit::acquire);
+            Self::write_sidecar(&report, &output_path.with_extension("json"))
+                .at_stage(PipelineStage::Write)?;
+        }
 
-        Ok(())
+        Ok(report)
+    }
+
+    fn write_sidecar(report: &ConversionReport, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(report).map_err(|e| {
+            ConversionError::OutputWriteError(format!("{}: {}", path.display(), e))
+        })?;
+        std::fs::write(path, json).map_err(|e| {
+            ConversionError::OutputWriteError(format!("{}: {}", path.display(), e))
+        })
+    }
+
+    /// Converts many files in parallel, returning one result per `(input, output)` pair
+    ///
+    /// Each job reads, decodes and encodes on its own rayon worker, using
+    /// `batch_threads` workers. All jobs share this pipeline's single debayer instance;
+    /// on the GPU backend at most `max_gpu_concurrency` frames are debayered at once.
+    /// With `max_open_files` set, at most that many input and output files are open at once.
+    /// A failing job does not stop the others.
+    pub fn convert_batch<P, Q>(&self, jobs: &[(P, Q)]) -> Vec<Result<()>>
+    where
+        P: AsRef<Path> + Sync,
+        Q: AsRef<Path> + Sync,
+        Self: Sync,
+    {
+        self.convert_batch_with_reports(jobs)
+            .into_iter()
+            .map(|result| result.map(|_| ()))
+            .collect()
+    }
+
+    /// Same as `convert_batch`, returning each successful job's conversion report
+    #[instrument(skip(self, jobs), fields(jobs = jobs.len()))]
+    pub fn convert_batch_with_reports<P, Q>(&self, jobs: &[(P, Q)]) -> Vec<Result<ConversionReport>>
+    where
+        P: AsRef<Path> + Sync,
+        Q: AsRef<Path> + Sync,
+        Self: Sync,
+    {
+        let run = || {
+            jobs.par_iter()
+                .map(|(input, output)| self.convert_file_with_report(input, output))
+                .collect::<Vec<_>>()
+        };
+
+        let Some(threads) = self.config.batch_threads else {
+            return run();
+        };
+
+        match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+            Ok(pool) => pool.install(run),
+            Err(e) => {
+                let message = format!("Failed to start {} batch workers: {}", threads, e);
+                jobs.iter()
+                    .map(|_| Err(ConversionError::IoError(std::io::Error::other(message.clone()))))
+                    .collect()
+            }
+        }
+    }
+
+    /// Converts every file in `input_dir` whose name matches one of `extension_filter`
+    /// to `<output_dir>/<stem>.tiff` (see `tiff_output_path`), creating `output_dir` if
+    /// needed
+    ///
+    /// The filter holds glob patterns such as `*.arw`, matched case-insensitively against
+    /// file names; an empty filter matches every file. Subdirectories are not descended
+    /// into. Files run through `convert_batch`, and any that fail to read or convert, or
+    /// share their output path with an earlier file, are logged and listed in the summary
+    /// instead of aborting the rest. Only an invalid pattern, an unreadable `input_dir` or
+    /// an uncreatable `output_dir` fail the call.
+    #[instrument(skip(self, input_dir, output_dir))]
+    pub fn convert_dir<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input_dir: P,
+        output_dir: Q,
+        extension_filter: &[&str],
+    ) -> Result<DirSummary>
+    where
+        Self: Sync,
+    {
+        let input_dir = input_dir.as_ref();
+        let output_dir = output_dir.as_ref();
+
+        let patterns = extension_filter
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern).map_err(|e| {
+                    ConversionError::InputReadError(format!("Invalid pattern {}: {}", pattern, e))
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .at_stage(PipelineStage::Read)?;
+        let options = glob::MatchOptions {
+            case_sensitive: false,
+            ..glob::MatchOptions::new()
+        };
+
+        let entries = std::fs::read_dir(input_dir).map_err(|e| {
+            ConversionError::InputReadError(format!("{}: {}", input_dir.display(), e))
+        }).at_stage(PipelineStage::Read)?;
+
+        let mut inputs = Vec::new();
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    warn!("Skipping unreadable entry in {}: {}", input_dir.display(), e);
+                    continue;
+                }
+            };
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let matches = patterns.is_empty()
+                || patterns.iter().any(|pattern| pattern.matches_with(&name, options));
+            if matches && path.is_file() {
+                inputs.push(path);
+            }
+        }
+        inputs.sort();
+
+        std::fs::create_dir_all(output_dir).map_err(|e| {
+            ConversionError::OutputWriteError(format!("{}: {}", output_dir.display(), e))
+        }).at_stage(PipelineStage::Write)?;
+
+        let mut summary = DirSummary::default();
+        let mut targets = HashSet::new();
+        let mut jobs: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for input in inputs {
+            let target = tiff_output_path(&input, output_dir);
+            if targets.insert(target.clone()) {
+                jobs.push((input, target));
+            } else {
+                let e = ConversionError::OutputWriteError(format!(
+                    "{} is already the output of another input",
+                    target.display()
+                ));
+                warn!("Skipping {}: {}", input.display(), e);
+                summary.failed.push((input, e.at_stage(PipelineStage::Write)));
+            }
+        }
+
+        info!(files = jobs.len(), input = %input_dir.display(), "Converting directory");
+
+        let results = self.convert_batch(&jobs);
+        for ((input, output), result) in jobs.into_iter().zip(results) {
+            match result {
+                Ok(()) => summary.converted.push(output),
+                Err(e) => {
+                    warn!("Skipping {}: {}", input.display(), e);
+                    summary.failed.push((input, e));
+                }
+            }
+        }
+
+        Ok(summary)
     }
 
     pub fn config(&self) -> &ConversionConfig {
@@ -145,4 +1048,60 @@ impl<R: RawImageReader, W: TiffWriter> RawToTiffPipeline<R, W> {
     pub fn set_config(&mut self, config: ConversionConfig) {
         self.config = config;
     }
+
+    /// Changes the exposure gain for the following conversions
+    ///
+    /// Only the color stage of the debayer and its CPU fallback is updated; the backend,
+    /// and any CUDA context it holds, stays as it is. Fails with `InvalidConfig` for a
+    /// gain `validate` would reject, leaving the pipeline unchanged.
+    pub fn set_exposure(&mut self, exposure: f32) -> Result<()> {
+        ConversionConfig { exposure, ..self.config.clone() }.validate()?;
+        self.config.exposure = exposure;
+        self.update_color();
+        Ok(())
+    }
+
+    /// Changes the white balance for the following conversions, like `set_exposure`
+    pub fn set_white_balance(&mut self, white_balance: WhiteBalance) {
+        self.config.white_balance = white_balance;
+        self.update_color();
+    }
+
+    /// Hands the config's exposure and white balance to the debayers
+    fn update_color(&mut self) {
+        let (exposure, white_balance) = (self.config.exposure, self.config.white_balance);
+        if let Some(debayer) = self.debayer.as_mut() {
+            debayer.set_color(exposure, white_balance);
+        }
+        if let Some(fallback) = self.cpu_fallback.as_mut() {
+            fallback.set_color(exposure, white_balance);
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R, W> RawToTiffPipeline<R, W>
+where
+    R: RawImageReader + Send + Sync + 'static,
+    W: TiffWriter + Send + Sync + 'static,
+{
+    /// Async variant of `convert_file` for use inside a tokio runtime
+    ///
+    /// The whole of `convert_file_with_report`, file IO included, runs on the blocking
+    /// thread pool, so the calling task never stalls the runtime and `mmap` input,
+    /// `max_open_files` and `write_sidecar` behave as in the synchronous call.
+    #[instrument(skip(self, input_path, output_path))]
+    pub async fn convert_file_async<P: AsRef<Path>, Q: AsRef<Path>>(
+        self: std::sync::Arc<Self>,
+        input_path: P,
+        output_path: Q,
+    ) -> Result<()> {
+        let input_path = input_path.as_ref().to_path_buf();
+        let output_path = output_path.as_ref().to_path_buf();
+
+        tokio::task::spawn_blocking(move || self.convert_file_with_report(&input_path, &output_path))
+            .await
+            .map_err(|e| ConversionError::IoError(std::io::Error::other(e)))?
+            .map(|_| ())
+    }
 }
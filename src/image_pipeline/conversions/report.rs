@@ -0,0 +1,137 @@
+//! Per-conversion metadata and stage timings, optionally written as a JSON sidecar
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use serde::{Serialize, Serializer};
+
+use crate::image_pipeline::common::error::ConversionError;
+use crate::image_pipeline::debayer::quantize::ClipStats;
+
+/// Wall-clock time spent in each stage of one conversion
+///
+/// Stages that did not run stay at zero. Serialized as milliseconds.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PipelineTimings {
+    #[serde(serialize_with = "as_millis")]
+    pub read: Duration,
+    #[serde(serialize_with = "as_millis")]
+    pub decode: Duration,
+    #[serde(serialize_with = "as_millis")]
+    pub validate: Duration,
+    #[serde(serialize_with = "as_millis")]
+    pub corrections: Duration,
+    /// User stages added through `PipelineBuilder`, raw and RGB together
+    #[serde(serialize_with = "as_millis")]
+    pub stages: Duration,
+    #[serde(serialize_with = "as_millis")]
+    pub debayer: Duration,
+    #[serde(serialize_with = "as_millis")]
+    pub denoise: Duration,
+    #[serde(serialize_with = "as_millis")]
+    pub encode: Duration,
+    #[serde(serialize_with = "as_millis")]
+    pub verify: Duration,
+    #[serde(serialize_with = "as_millis")]
+    pub write: Duration,
+}
+
+impl PipelineTimings {
+    /// Sum of all stages
+    pub fn total(&self) -> Duration {
+        self.read
+            + self.decode
+            + self.validate
+            + self.corrections
+            + self.stages
+            + self.debayer
+            + self.denoise
+            + self.encode
+            + self.verify
+            + self.write
+    }
+
+    /// Stages with their timings in pipeline order
+    fn stages(&self) -> [(&'static str, Duration); 10] {
+        [
+            ("read", self.read),
+            ("decode", self.decode),
+            ("validate", self.validate),
+            ("corrections", self.corrections),
+            ("stages", self.stages),
+            ("debayer", self.debayer),
+            ("denoise", self.denoise),
+            ("encode", self.encode),
+            ("verify", self.verify),
+            ("write", self.write),
+        ]
+    }
+
+    /// The timings in the folded-stack format of `inferno` and flamegraph.pl
+    ///
+    /// One `convert;<stage> <microseconds>` line per stage that ran, in pipeline order,
+    /// so each stage is a frame under a common `convert` root. Stages that did not run
+    /// are left out. Reports of several conversions can be concatenated; the tools sum
+    /// identical stacks.
+    pub fn to_folded_stacks(&self) -> String {
+        self.stages()
+            .iter()
+            .filter(|(_, duration)| !duration.is_zero())
+            .map(|(stage, duration)| format!("convert;{} {}\n", stage, duration.as_micros()))
+            .collect()
+    }
+}
+
+/// What a conversion detected and applied, enough to reproduce the output later
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversionReport {
+    /// Output width in pixels
+    pub width: usize,
+    /// Output height in pixels
+    pub height: usize,
+    /// Output kind, e.g. `Rgb` or `BayerGray`
+    pub output: String,
+    /// Sensor bit depth detected from the white level
+    pub bits_per_sample: u32,
+    /// Camera as-shot white balance coefficients [R, G, B, E]
+    pub wb_coeffs: [f32; 4],
+    /// [R, G, B] multipliers the debayer applied, `None` when the output was not debayered
+    pub white_balance: Option<[f32; 3]>,
+    /// Exposure gain the debayer applied, `None` when the output was not debayered
+    pub exposure: Option<f32>,
+    /// Black levels [R, G, B, E], estimated from the histogram if the metadata was invalid
+    pub black_levels: [u16; 4],
+    /// White levels [R, G, B, E], estimated from the histogram if the metadata was invalid
+    pub white_levels: [u16; 4],
+    /// Out-of-range pixels in the 16-bit RGB or luminance quantization, `None` when the
+    /// output was not quantized from float or the debayer doesn't report it
+    pub clipping: Option<ClipStats>,
+    pub timings: PipelineTimings,
+}
+
+/// Outcome of `RawToTiffPipeline::convert_dir`
+#[derive(Debug, Default)]
+pub struct DirSummary {
+    /// Output paths of the files that converted, in input name order
+    pub converted: Vec<PathBuf>,
+    /// Input paths that were skipped, with the reason
+    pub failed: Vec<(PathBuf, ConversionError)>,
+}
+
+impl DirSummary {
+    /// Whether every matching file converted
+    pub fn all_succeeded(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+fn as_millis<S: Serializer>(duration: &Duration, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+/// Runs `stage`, adding its wall-clock time to `elapsed`
+pub(crate) fn timed<T>(elapsed: &mut Duration, stage: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = stage();
+    *elapsed += start.elapsed();
+    result
+}
@@ -0,0 +1,33 @@
+//! User-supplied processing steps run by `RawToTiffPipeline`, see `PipelineBuilder`
+
+use crate::image_pipeline::{
+    common::error::Result,
+    debayer::RgbImageData,
+    raw::RawImageData,
+};
+
+/// A processing step inserted into the pipeline through `PipelineBuilder::stage`
+///
+/// Stages run in the order they were added. Both hooks default to doing nothing, so a
+/// stage only implements the domain it works in. Errors abort the conversion and are
+/// tagged with `PipelineStage::Custom`.
+pub trait Stage: Send + Sync {
+    /// Name of the stage's tracing span
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Runs on the decoded raw image, after the built-in raw corrections and before the
+    /// debayer, for every output mode
+    fn apply_raw(&self, _image: &mut RawImageData) -> Result<()> {
+        Ok(())
+    }
+
+    /// Runs on the debayered 16-bit RGB image, after denoising and before orientation
+    ///
+    /// Not called for `BayerGray` output. Float RGB output has no 16-bit image to run on,
+    /// so a pipeline with stages rejects `output_float` with `InvalidConfig` instead.
+    fn apply(&self, _image: &mut RgbImageData) -> Result<()> {
+        Ok(())
+    }
+}
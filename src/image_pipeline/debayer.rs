@@ -1,12 +1,25 @@
 //! Debayering module for converting Bayer pattern RAW images to RGB
 
 
+#[cfg(jetson_cuda)]
+pub mod cuda_context;
 #[cfg(jetson_cuda)]
 pub mod cuda_debayer;
 #[cfg(jetson_cuda)]
 pub mod npp_debayer;
 pub mod cpu_debayer;
+mod processor;
+pub mod border;
+pub mod color_math;
+pub mod contact_sheet;
+pub mod denoise;
+pub mod gpu_timeout;
+pub mod hdr;
+pub mod npp_status;
+pub mod quantize;
 pub mod types;
+pub mod tone_curve;
+pub mod white_balance;
 
 // Fallback CPU implementations when NOT on Jetson
 #[cfg(not(jetson_cuda))]
@@ -21,16 +34,47 @@ impl CudaDebayer {
     }
 }
 
+#[cfg(not(jetson_cuda))]
+impl Debayer for CudaDebayer {
+    fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        CudaDebayer::process(self, raw_image)
+    }
+
+    fn uses_gpu(&self) -> bool {
+        true
+    }
+}
+
 #[cfg(not(jetson_cuda))]
 pub struct NppDebayer;
 
 #[cfg(not(jetson_cuda))]
 impl NppDebayer {
     pub fn new() -> anyhow::Result<Self> { Ok(Self) }
+    pub fn with_config(_config: &ConversionConfig) -> anyhow::Result<Self> { Ok(Self) }
     #[allow(unused)]
     pub fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
         panic!("NPP debayer is not available on this platform.");
     }
+    #[allow(unused)]
+    pub fn process_f32(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageDataF32> {
+        anyhow::bail!("NPP debayer is not available on this platform")
+    }
+}
+
+#[cfg(not(jetson_cuda))]
+impl Debayer for NppDebayer {
+    fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        NppDebayer::process(self, raw_image)
+    }
+
+    fn process_f32(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageDataF32> {
+        NppDebayer::process_f32(self, raw_image)
+    }
+
+    fn uses_gpu(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(jetson_cuda)]
@@ -38,7 +82,17 @@ pub use cuda_debayer::CudaDebayer;
 #[cfg(jetson_cuda)]
 pub use npp_debayer::NppDebayer;
 pub use cpu_debayer::CpuDebayer;
-pub use types::RgbImageData;
+pub use processor::Debayer;
+pub use types::{RgbImageData, RgbImageDataF32, GrayImageData, DebayerQuality, DebayerBackend, NppInterpolation, WorkingPrecision, ColorTransform};
+pub use white_balance::WhiteBalance;
+pub use tone_curve::ToneCurve;
+pub use denoise::DenoiseStrength;
+pub use border::BorderMode;
+pub use quantize::{ClipStats, OverflowMode};
+pub use color_math::{compute_color_matrix, SRGB_TO_XYZ, XYZ_TO_SRGB};
+pub use hdr::hdr_merge;
+pub use contact_sheet::{contact_sheet, CONTACT_SHEET_TILE_EDGE};
+pub use gpu_timeout::wait_with_timeout;
 
 #[cfg(not(jetson_cuda))]
-use crate::image_pipeline::RawImageData;
+use crate::image_pipeline::{RawImageData, ConversionConfig};
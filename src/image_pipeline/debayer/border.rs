@@ -0,0 +1,161 @@
+//! Edge handling for demosaicing
+
+use std::borrow::Cow;
+
+use crate::image_pipeline::RawImageData;
+use crate::image_pipeline::common::error::ConversionError;
+use crate::image_pipeline::debayer::types::RgbImageDataF32;
+
+/// Pixels added around a mosaic before demosaicing, or trimmed from the result by
+/// `BorderMode::Crop`. Covers the 5x5 Malvar-He-Cutler window, and is even so the CFA
+/// phase is kept
+pub const BORDER: usize = 2;
+
+/// How the debayers treat pixels whose demosaic window runs past the image edge
+///
+/// `Replicate` and `Mirror` extend the mosaic by `BORDER` pixels before demosaicing and
+/// drop them afterwards, so every algorithm sees the same edges and the output keeps the
+/// input dimensions. `Crop` demosaics the mosaic as is and trims `BORDER` pixels from
+/// each side, leaving only pixels with a full window. RGB raws are never padded or trimmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BorderMode {
+    /// Repeat the outermost row or column of each CFA color
+    Replicate,
+    /// Reflect across the edge without repeating it (default, what `MalvarHeCutler` and
+    /// `EdgeDirected` already did on their own)
+    #[default]
+    Mirror,
+    /// Trim `BORDER` pixels from every side of the output
+    Crop,
+}
+
+impl BorderMode {
+    /// Source index for position `i` of a row or column of `len` sites, `i` being up to
+    /// `BORDER` past either end. Always lands on a site of the same CFA color
+    fn source(self, i: isize, len: usize) -> usize {
+        let last = len as isize - 1;
+        let i = match self {
+            BorderMode::Mirror if i < 0 => -i,
+            BorderMode::Mirror if i > last => 2 * last - i,
+            _ => i,
+        };
+        // Replicate, or a reflection that overshoots a tiny image: nearest same-color site
+        let clamped = i.clamp(0, last);
+        let clamped = if (i - clamped) % 2 == 0 {
+            clamped
+        } else if i < 0 {
+            clamped + 1
+        } else {
+            clamped - 1
+        };
+        clamped.clamp(0, last) as usize
+    }
+
+    /// The mosaic the debayer should demosaic: `raw_image` extended by `BORDER` pixels
+    /// on each side for `Replicate` and `Mirror`, unchanged for `Crop` and RGB raws
+    pub(crate) fn pad(self, raw_image: &RawImageData) -> Cow<'_, RawImageData> {
+        if self == BorderMode::Crop || !raw_image.is_bayer {
+            return Cow::Borrowed(raw_image);
+        }
+        let (width, height) = (raw_image.width, raw_image.height);
+        let columns: Vec<usize> = (0..width + 2 * BORDER)
+            .map(|x| self.source(x as isize - BORDER as isize, width))
+            .collect();
+        let rows: Vec<usize> = (0..height + 2 * BORDER)
+            .map(|y| self.source(y as isize - BORDER as isize, height))
+            .collect();
+        Cow::Owned(remapped(raw_image, &columns, &rows))
+    }
+}
+
+/// `raw_image` extended by one column and/or row to even dimensions, so the demosaic
+/// only sees whole RGGB cells; unchanged when both are already even or the raw is RGB
+///
+/// The added column or row repeats the nearest one of the same CFA color, as
+/// `BorderMode::Replicate` would. `crop_to` removes it again after demosaicing.
+pub(crate) fn pad_to_even(raw_image: &RawImageData) -> Cow<'_, RawImageData> {
+    let (width, height) = (raw_image.width, raw_image.height);
+    if !raw_image.is_bayer || (width.is_multiple_of(2) && height.is_multiple_of(2)) {
+        return Cow::Borrowed(raw_image);
+    }
+    let even = |len: usize| -> Vec<usize> {
+        (0..len.next_multiple_of(2))
+            .map(|i| BorderMode::Replicate.source(i as isize, len))
+            .collect()
+    };
+    Cow::Owned(remapped(raw_image, &even(width), &even(height)))
+}
+
+/// The top-left `width`x`height` pixels of `image`, dropping what `pad_to_even` added
+pub(crate) fn crop_to(image: RgbImageDataF32, width: usize, height: usize) -> RgbImageDataF32 {
+    if (image.width, image.height) == (width, height) {
+        return image;
+    }
+    let data = image
+        .data
+        .chunks_exact(image.width * 3)
+        .take(height)
+        .flat_map(|row| &row[..width * 3])
+        .copied()
+        .collect();
+    RgbImageDataF32 {
+        width,
+        height,
+        data,
+        ..image
+    }
+}
+
+/// Mosaic whose pixel (x, y) is `raw_image`'s (`columns[x]`, `rows[y]`), with the same
+/// metadata. Both lists must map to sites of the same CFA color
+fn remapped(raw_image: &RawImageData, columns: &[usize], rows: &[usize]) -> RawImageData {
+    let width = raw_image.width;
+    let data = rows
+        .iter()
+        .flat_map(|&y| columns.iter().map(move |&x| raw_image.data[y * width + x]))
+        .collect();
+    RawImageData {
+        width: columns.len(),
+        height: rows.len(),
+        data,
+        is_bayer: true,
+        bits_per_sample: raw_image.bits_per_sample,
+        wb_coeffs: raw_image.wb_coeffs,
+        blacklevels: raw_image.blacklevels,
+        whitelevels: raw_image.whitelevels,
+        cam_to_xyz: raw_image.cam_to_xyz,
+        xyz_to_cam: raw_image.xyz_to_cam,
+        exif: raw_image.exif,
+        orientation: raw_image.orientation,
+        gain_map: None,
+    }
+}
+
+/// Trims `BORDER` pixels from every side of the demosaiced `image` of `raw_image`: the
+/// padding `BorderMode::pad` added, or for `Crop` the pixels without a full window
+///
+/// Fails with `InvalidDimensions` when `Crop` would leave nothing.
+pub(crate) fn trim(raw_image: &RawImageData, image: RgbImageDataF32) -> Result<RgbImageDataF32, ConversionError> {
+    if !raw_image.is_bayer {
+        return Ok(image);
+    }
+    if image.width <= 2 * BORDER || image.height <= 2 * BORDER {
+        return Err(ConversionError::InvalidDimensions(image.width, image.height));
+    }
+    let width = image.width - 2 * BORDER;
+    let height = image.height - 2 * BORDER;
+    let data = image
+        .data
+        .chunks_exact(image.width * 3)
+        .skip(BORDER)
+        .take(height)
+        .flat_map(|row| &row[BORDER * 3..(BORDER + width) * 3])
+        .copied()
+        .collect();
+    Ok(RgbImageDataF32 {
+        width,
+        height,
+        data,
+        ..image
+    })
+}
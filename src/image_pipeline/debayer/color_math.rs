@@ -0,0 +1,193 @@
+//! Per-pixel color math of the CPU debayer: black level, normalization, white balance
+//! and the camera to sRGB matrix.
+//!
+//! With the `simd` feature the hot loop runs eight pixels per step using `wide::f32x8`;
+//! otherwise (and for the remainder pixels) the scalar path is used. Both evaluate the
+//! same operations in the same order, so their outputs are bit-identical.
+
+use super::types;
+use crate::image_pipeline::raw::types::RawImageData;
+use crate::image_pipeline::tiff::types::ConversionConfig;
+
+/// Constants for mapping demosaiced camera RGB to linear sRGB
+#[derive(Debug, Clone, Copy)]
+pub struct ColorTransform {
+    /// Sensor black level subtracted from every sample
+    pub black_level: f32,
+    /// White minus black level, used to normalize to 0.0..=1.0
+    pub range: f32,
+    /// [R, G, B] white balance multipliers
+    pub white_balance: [f32; 3],
+    /// Camera to sRGB matrix with exposure folded in; the 4th column is a constant offset
+    pub matrix: [[f32; 4]; 3],
+}
+
+impl ColorTransform {
+    /// The transform the CPU debayer applies to `raw` under `config`
+    ///
+    /// The black level is raised by `black_clip` and the range shrunk to match, the white
+    /// balance multipliers include the `baseline_exposure` gain, and the matrix is
+    /// `compute_color_matrix`.
+    pub fn for_raw(config: &ConversionConfig, raw: &RawImageData) -> Self {
+        // Black clip raises the black point and shrinks the range by the same amount, so
+        // white still normalizes to 1.0; baseline exposure scales the balanced values
+        let black_level = raw.blacklevels[0] as f32;
+        let white_level = raw.whitelevels[0] as f32;
+        let black_clip = (white_level - black_level) * config.black_clip.clamp(0.0, 1.0);
+        let baseline_gain = config.baseline_exposure.exp2();
+        ColorTransform {
+            black_level: black_level + black_clip,
+            range: (white_level - black_level - black_clip).max(1.0),
+            white_balance: config.white_balance.multipliers(raw).map(|m| m * baseline_gain),
+            matrix: compute_color_matrix(config, raw),
+        }
+    }
+
+    /// Transforms one camera RGB pixel
+    #[inline]
+    pub fn apply(&self, [r, g, b]: [f32; 3]) -> [f32; 3] {
+        let [wb_r, wb_g, wb_b] = self.white_balance;
+        let r_lin = ((r - self.black_level).max(0.0) / self.range) * wb_r;
+        let g_lin = ((g - self.black_level).max(0.0) / self.range) * wb_g;
+        let b_lin = ((b - self.black_level).max(0.0) / self.range) * wb_b;
+
+        let m = &self.matrix;
+        [
+            m[0][0] * r_lin + m[0][1] * g_lin + m[0][2] * b_lin + m[0][3],
+            m[1][0] * r_lin + m[1][1] * g_lin + m[1][2] * b_lin + m[1][3],
+            m[2][0] * r_lin + m[2][1] * g_lin + m[2][2] * b_lin + m[2][3],
+        ]
+    }
+}
+
+/// Rec.709 luminance weights of linear sRGB
+const LUMINANCE: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// Standard XYZ to linear sRGB matrix for the D65 illuminant, used by the CPU and NPP
+/// debayers unless `ConversionConfig::xyz_to_rgb` replaces it
+#[allow(clippy::excessive_precision)]
+pub const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+    [ 3.2404542, -1.5371385, -0.4985314],
+    [-0.9692660,  1.8760108,  0.0415560],
+    [ 0.0556434, -0.2040259,  1.0572252],
+];
+
+/// Linear sRGB (D65) to XYZ matrix in the layout of `RawImageData::cam_to_xyz`, the
+/// inverse of `XYZ_TO_SRGB`; a raw with it renders its camera RGB as sRGB
+#[allow(clippy::excessive_precision)]
+pub const SRGB_TO_XYZ: [[f32; 4]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375, 0.0],
+    [0.2126729, 0.7151522, 0.0721750, 0.0],
+    [0.0193339, 0.1191920, 0.9503041, 0.0],
+];
+
+/// The 3x4 matrix the CPU and NPP debayers apply to white-balanced camera RGB of `raw`
+///
+/// Combines the camera to XYZ matrix (`config.color_matrix` if set, else the raw's
+/// `cam_to_xyz`) with XYZ to RGB (`config.xyz_to_rgb` if set, else `XYZ_TO_SRGB`), then
+/// folds in `config.exposure` and `config.saturation`. The 4th column is a constant
+/// offset. With `ColorTransform::CameraNative` it is the identity scaled by the exposure.
+pub fn compute_color_matrix(config: &ConversionConfig, raw: &RawImageData) -> [[f32; 4]; 3] {
+    let cam_to_xyz = config.color_matrix.unwrap_or(raw.cam_to_xyz);
+    let xyz_to_rgb = config.xyz_to_rgb.unwrap_or(XYZ_TO_SRGB);
+    let (exposure, saturation) = (config.exposure, config.saturation);
+    let mut matrix = [[0.0f32; 4]; 3];
+    match config.color_transform {
+        types::ColorTransform::CameraToSrgb => {
+            for r in 0..3 {
+                for c in 0..4 {
+                    matrix[r][c] = (0..3).map(|k| xyz_to_rgb[r][k] * cam_to_xyz[k][c]).sum::<f32>() * exposure;
+                }
+            }
+            saturate_matrix(&mut matrix, saturation);
+        }
+        types::ColorTransform::CameraNative => {
+            for (r, row) in matrix.iter_mut().enumerate() {
+                row[r] = exposure;
+            }
+        }
+    }
+    matrix
+}
+
+/// Folds a saturation adjustment into a camera to sRGB matrix
+///
+/// The adjusted matrix yields `Y + saturation * (rgb - Y)`, where `Y` is the Rec.709
+/// luminance of the original output, so luminance is kept while the distance from gray
+/// scales: `0.0` gives grayscale and `1.0` leaves the matrix untouched.
+pub fn saturate_matrix(matrix: &mut [[f32; 4]; 3], saturation: f32) {
+    if saturation == 1.0 {
+        return;
+    }
+    let rows = *matrix;
+    for c in 0..4 {
+        let luma: f32 = LUMINANCE.iter().zip(&rows).map(|(w, row)| w * row[c]).sum();
+        for (out, row) in matrix.iter_mut().zip(&rows) {
+            out[c] = luma + saturation * (row[c] - luma);
+        }
+    }
+}
+
+/// Transforms interleaved camera RGB samples to interleaved linear sRGB, one pixel at a time
+pub fn to_linear_rgb_scalar(pixels: &[u16], transform: &ColorTransform) -> Vec<f32> {
+    pixels
+        .chunks_exact(3)
+        .flat_map(|px| transform.apply([px[0] as f32, px[1] as f32, px[2] as f32]))
+        .collect()
+}
+
+/// Transforms interleaved camera RGB samples to interleaved linear sRGB, eight pixels at a time
+#[cfg(feature = "simd")]
+pub fn to_linear_rgb_simd(pixels: &[u16], transform: &ColorTransform) -> Vec<f32> {
+    use wide::f32x8;
+
+    const LANES: usize = 8;
+
+    let mut out = Vec::with_capacity(pixels.len());
+
+    let black = f32x8::splat(transform.black_level);
+    let range = f32x8::splat(transform.range);
+    let zero = f32x8::splat(0.0);
+    let wb = transform.white_balance.map(f32x8::splat);
+    let m = transform.matrix.map(|row| row.map(f32x8::splat));
+
+    let mut blocks = pixels.chunks_exact(3 * LANES);
+    for block in &mut blocks {
+        // De-interleave into one vector per channel
+        let mut channels = [[0.0f32; LANES]; 3];
+        for (lane, px) in block.chunks_exact(3).enumerate() {
+            for (channel, &value) in channels.iter_mut().zip(px) {
+                channel[lane] = value as f32;
+            }
+        }
+        let [r, g, b] = channels.map(f32x8::from);
+
+        let r_lin = ((r - black).max(zero) / range) * wb[0];
+        let g_lin = ((g - black).max(zero) / range) * wb[1];
+        let b_lin = ((b - black).max(zero) / range) * wb[2];
+
+        let r_out = (m[0][0] * r_lin + m[0][1] * g_lin + m[0][2] * b_lin + m[0][3]).to_array();
+        let g_out = (m[1][0] * r_lin + m[1][1] * g_lin + m[1][2] * b_lin + m[1][3]).to_array();
+        let b_out = (m[2][0] * r_lin + m[2][1] * g_lin + m[2][2] * b_lin + m[2][3]).to_array();
+
+        for lane in 0..LANES {
+            out.extend_from_slice(&[r_out[lane], g_out[lane], b_out[lane]]);
+        }
+    }
+
+    out.extend(to_linear_rgb_scalar(blocks.remainder(), transform));
+    out
+}
+
+/// Transforms interleaved camera RGB samples to interleaved linear sRGB using the
+/// fastest path compiled in
+pub fn to_linear_rgb(pixels: &[u16], transform: &ColorTransform) -> Vec<f32> {
+    #[cfg(feature = "simd")]
+    {
+        to_linear_rgb_simd(pixels, transform)
+    }
+    #[cfg(not(feature = "simd"))]
+    {
+        to_linear_rgb_scalar(pixels, transform)
+    }
+}
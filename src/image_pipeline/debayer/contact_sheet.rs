@@ -0,0 +1,116 @@
+//! Contact-sheet montages of debayered frames for quick review
+
+use tracing::{debug, warn};
+use crate::image_pipeline::debayer::types::RgbImageData;
+use crate::image_pipeline::raw::exif::ExifMetadata;
+
+/// Longest edge of a tile in a contact sheet; larger images are box-filtered down to it
+pub const CONTACT_SHEET_TILE_EDGE: usize = 256;
+
+/// Tiles `images` left to right, top to bottom into a grid `cols` tiles wide
+///
+/// Each image is box-filtered so its longer edge is at most `CONTACT_SHEET_TILE_EDGE`,
+/// keeping its aspect ratio; smaller images are placed at their own size. Cells are as
+/// large as the largest tile, tiles sit in their cell's top-left corner, and the rest of
+/// the sheet, including the unused cells of a partial last row, is black. Samples are
+/// scaled to the highest `bits_per_sample` among the images, which the sheet carries; it
+/// has no EXIF. `cols` is clamped to 1..=`images.len()`, and no images give a 0x0 sheet.
+pub fn contact_sheet(images: &[RgbImageData], cols: usize) -> RgbImageData {
+    let bits_per_sample = images.iter().map(|image| image.bits_per_sample).max().unwrap_or(16);
+    let tiles: Vec<RgbImageData> = images
+        .iter()
+        .map(|image| downsize(image, CONTACT_SHEET_TILE_EDGE, bits_per_sample))
+        .collect();
+
+    let cols = cols.clamp(1, tiles.len().max(1));
+    let rows = tiles.len().div_ceil(cols);
+    let cell_width = tiles.iter().map(|tile| tile.width).max().unwrap_or(0);
+    let cell_height = tiles.iter().map(|tile| tile.height).max().unwrap_or(0);
+    let (width, height) = (cell_width * cols, cell_height * rows);
+
+    debug!(images = images.len(), cols, rows, width, height, "Building contact sheet");
+
+    let mut data = vec![0u16; width * height * 3];
+    for (index, tile) in tiles.iter().enumerate() {
+        if tile.width == 0 {
+            continue;
+        }
+        let (left, top) = (index % cols * cell_width, index / cols * cell_height);
+        for (y, row) in tile.data.chunks_exact(tile.width * 3).enumerate() {
+            let start = ((top + y) * width + left) * 3;
+            data[start..start + row.len()].copy_from_slice(row);
+        }
+    }
+
+    RgbImageData {
+        width,
+        height,
+        data,
+        bits_per_sample,
+        exif: ExifMetadata::default(),
+    }
+}
+
+/// Box-filters `image` so its longer edge is at most `max_edge`, rescaling samples to
+/// `bits_per_sample`
+fn downsize(image: &RgbImageData, max_edge: usize, bits_per_sample: u32) -> RgbImageData {
+    let (width, height) = (image.width, image.height);
+    let empty = RgbImageData {
+        width: 0,
+        height: 0,
+        data: Vec::new(),
+        bits_per_sample,
+        exif: ExifMetadata::default(),
+    };
+    // An image without pixels would leave every tile's box empty
+    if width == 0 || height == 0 {
+        return empty;
+    }
+    if image.data.len() != width * height * 3 {
+        warn!(
+            "Skipping {}x{} image holding {} samples in the contact sheet",
+            width,
+            height,
+            image.data.len()
+        );
+        return empty;
+    }
+
+    let long_edge = width.max(height);
+    let (tile_width, tile_height) = if long_edge <= max_edge {
+        (width, height)
+    } else {
+        let scale = |edge: usize| ((edge * max_edge) as f64 / long_edge as f64).round().max(1.0) as usize;
+        (scale(width), scale(height))
+    };
+    let shift = bits_per_sample.saturating_sub(image.bits_per_sample).min(16);
+    // Tiles are never larger than the image, so every span covers at least one pixel
+    let span = |index: usize, tile_len: usize, len: usize| index * len / tile_len..(index + 1) * len / tile_len;
+
+    let mut data = Vec::with_capacity(tile_width * tile_height * 3);
+    for ty in 0..tile_height {
+        let rows = span(ty, tile_height, height);
+        for tx in 0..tile_width {
+            let columns = span(tx, tile_width, width);
+            let mut sum = [0u64; 3];
+            for y in rows.clone() {
+                for x in columns.clone() {
+                    let pixel = (y * width + x) * 3;
+                    for (total, &value) in sum.iter_mut().zip(&image.data[pixel..pixel + 3]) {
+                        *total += u64::from(value);
+                    }
+                }
+            }
+            let count = (rows.len() * columns.len()) as u64;
+            data.extend(sum.map(|total| (((total + count / 2) / count) << shift).min(u64::from(u16::MAX)) as u16));
+        }
+    }
+
+    RgbImageData {
+        width: tile_width,
+        height: tile_height,
+        data,
+        bits_per_sample,
+        exif: ExifMetadata::default(),
+    }
+}
@@ -3,19 +3,112 @@ use tracing::info;
 use std::io::Cursor;
 use bayer::{BayerDepth, CFA, Demosaic, RasterDepth, RasterMut};
 use crate::image_pipeline::{RawImageData, debayer::RgbImageData};
+use crate::image_pipeline::debayer::border;
+use crate::image_pipeline::debayer::processor::Debayer;
+use crate::image_pipeline::debayer::quantize::ClipStats;
+use crate::image_pipeline::debayer::types::{DebayerQuality, RgbImageDataF32};
+use crate::image_pipeline::debayer::color_math::{self, ColorTransform};
+use crate::image_pipeline::debayer::tone_curve;
+use crate::image_pipeline::debayer::white_balance::WhiteBalance;
+use crate::image_pipeline::tiff::types::ConversionConfig;
 
-pub struct CpuDebayer;
+/// Rows demosaiced above and below each band for context and then discarded. Wider than
+/// any supported demosaic window, and even so every band starts on the same CFA phase
+const BAND_OVERLAP: usize = 8;
+
+pub struct CpuDebayer {
+    config: ConversionConfig,
+}
 
 impl CpuDebayer {
     pub fn new() -> Result<Self> {
-        Ok(Self)
+        Self::with_config(&ConversionConfig::default())
+    }
+
+    pub fn with_config(config: &ConversionConfig) -> Result<Self> {
+        Ok(Self {
+            config: config.clone(),
+        })
     }
 
     pub fn process(&self, raw_image: &RawImageData) -> Result<RgbImageData> {
+        Ok(self.process_f32(raw_image)?.quantize(self.config.overflow)?.0)
+    }
+
+    /// Runs demosaic and the color pipeline, returning linear float RGB without quantization
+    ///
+    /// Mosaics are padded and trimmed as `border_mode` says, so `BorderMode::Crop` output
+    /// is `2 * BORDER` pixels narrower and shorter than `raw_image`. With `cpu_band_height`
+    /// set the mosaic is demosaiced band by band, with the same result. Mosaics with an odd
+    /// width or height are demosaiced with one more column or row of the same CFA color,
+    /// cropped off again, so output keeps the input dimensions and RGGB phase.
+    pub fn process_f32(&self, raw_image: &RawImageData) -> Result<RgbImageDataF32> {
+        let padded = self.config.border_mode.pad(raw_image);
+        let even = border::pad_to_even(&padded);
+        let image = match self.config.cpu_band_height {
+            Some(band_height) if even.is_bayer && even.height > band_height => {
+                self.demosaic_in_bands(&even, band_height)?
+            }
+            _ => self.demosaic_and_correct(&even)?,
+        };
+        let image = border::crop_to(image, padded.width, padded.height);
+        Ok(border::trim(raw_image, image)?)
+    }
+
+    /// Demosaics and color corrects `raw_image` in horizontal bands of `band_height` rows
+    ///
+    /// Each band is demosaiced with `BAND_OVERLAP` extra rows on either side so rows near
+    /// its cut see the same neighbours as in a full-frame demosaic. The color pipeline is
+    /// per pixel, so bands join seamlessly. Only the output and one band's buffers are
+    /// held at a time. `band_height` is rounded up to keep the CFA phase.
+    fn demosaic_in_bands(&self, raw_image: &RawImageData, band_height: usize) -> Result<RgbImageDataF32> {
+        let (width, height) = (raw_image.width, raw_image.height);
+        let band_height = band_height.max(2).next_multiple_of(2);
+        info!("Demosaicing {}x{} in bands of {} rows", width, height, band_height);
+
+        let mut data = Vec::with_capacity(width * height * 3);
+        for top in (0..height).step_by(band_height) {
+            let bottom = (top + band_height).min(height);
+            let start = top.saturating_sub(BAND_OVERLAP);
+            let end = (bottom + BAND_OVERLAP).min(height);
+            let band = RawImageData {
+                width,
+                height: end - start,
+                data: raw_image.data[start * width..end * width].to_vec(),
+                is_bayer: true,
+                bits_per_sample: raw_image.bits_per_sample,
+                wb_coeffs: raw_image.wb_coeffs,
+                blacklevels: raw_image.blacklevels,
+                whitelevels: raw_image.whitelevels,
+                cam_to_xyz: raw_image.cam_to_xyz,
+                xyz_to_cam: raw_image.xyz_to_cam,
+                exif: raw_image.exif,
+                orientation: raw_image.orientation,
+                gain_map: None,
+            };
+            let rgb = self.demosaic_and_correct(&band)?;
+            data.extend_from_slice(&rgb.data[(top - start) * width * 3..(bottom - start) * width * 3]);
+        }
+
+        Ok(RgbImageDataF32 {
+            width,
+            height,
+            data,
+            exif: raw_image.exif,
+        })
+    }
+
+    /// Demosaics `raw_image` edge to edge and runs the color pipeline
+    fn demosaic_and_correct(&self, raw_image: &RawImageData) -> Result<RgbImageDataF32> {
         let width = raw_image.width;
         let height = raw_image.height;
         info!("Starting CPU debayering for image {}x{}", width, height);
         
+        if !raw_image.is_bayer {
+            info!("Input is already RGB, skipping demosaic");
+            return self.color_correct(raw_image, &raw_image.data);
+        }
+        
         // Determine bit depth - bayer crate only supports 8 and 16 bit
         let (bayer_depth, raster_depth, bytes_per_pixel) = if raw_image.bits_per_sample <= 8 {
             (BayerDepth::Depth8, RasterDepth::Depth8, 1)
@@ -36,111 +129,234 @@ impl CpuDebayer {
         let output_buf_size = width * height * 3 * bytes_per_pixel;
         let mut output_buf = vec![0u8; output_buf_size];
         
-        // Create cursor for reading bytes
-        let mut cursor = Cursor::new(&bayer_bytes[..]);
-        
-        info!("Running demosaic with depth={:?}, CFA=RGGB, algo=Linear", bayer_depth);
+        let quality = self.config.debayer_quality;
+        info!("Running demosaic with depth={:?}, CFA=RGGB, algo={:?}", bayer_depth, quality);
         info!("Input bytes: {}, Output buffer: {} ({}x{}x3x{})", 
               bayer_bytes.len(), output_buf_size, width, height, bytes_per_pixel);
         
-        // Create output raster
-        let mut output_raster = RasterMut::new(
-            width,
-            height,
-            raster_depth,
-            &mut output_buf
-        );
+        let algorithm = match quality {
+            DebayerQuality::NearestNeighbour => Some(Demosaic::NearestNeighbour),
+            DebayerQuality::Linear => Some(Demosaic::Linear),
+            DebayerQuality::Cubic => Some(Demosaic::Cubic),
+            DebayerQuality::MalvarHeCutler | DebayerQuality::EdgeDirected => None,
+        };
+        
+        let demosaic_span = tracing::info_span!("cpu_demosaic", width, height, algorithm = ?quality).entered();
+        if let Some(algorithm) = algorithm {
+            // Create cursor for reading bytes
+            let mut cursor = Cursor::new(&bayer_bytes[..]);
+            
+            // Create output raster
+            let mut output_raster = RasterMut::new(
+                width,
+                height,
+                raster_depth,
+                &mut output_buf
+            );
+            
+            // Run demosaicing - assuming RGGB pattern
+            bayer::run_demosaic(
+                &mut cursor,
+                bayer_depth,
+                CFA::RGGB,
+                algorithm,
+                &mut output_raster
+            ).map_err(|e| anyhow::anyhow!("Demosaic failed: {:?}", e))?;
+        } else {
+            // The bayer crate has no gradient-corrected or edge-directed algorithm, run our own
+            let max_value = if bytes_per_pixel == 1 { u8::MAX as f32 } else { u16::MAX as f32 };
+            let rgb = if quality == DebayerQuality::EdgeDirected {
+                edge_directed(&raw_image.data, width, height, max_value)
+            } else {
+                malvar_he_cutler(&raw_image.data, width, height, max_value)
+            };
+            for (dst, &val) in output_buf.chunks_exact_mut(bytes_per_pixel).zip(rgb.iter()) {
+                if bytes_per_pixel == 1 {
+                    dst[0] = val as u8;
+                } else {
+                    dst.copy_from_slice(&val.to_le_bytes());
+                }
+            }
+        }
+        
+        drop(demosaic_span);
+        
+        let samples: Vec<u16> = if bytes_per_pixel == 1 {
+            output_buf.iter().map(|&b| b as u16).collect()
+        } else {
+            output_buf.chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .collect()
+        };
+        self.color_correct(raw_image, &samples)
+    }
+
+    /// Black level, white balance, color matrix, tone curve and sRGB gamma on interleaved
+    /// camera RGB
+    fn color_correct(&self, raw_image: &RawImageData, samples: &[u16]) -> Result<RgbImageDataF32> {
+        let (width, height) = (raw_image.width, raw_image.height);
+        let quality = self.config.debayer_quality;
         
-        // Run demosaicing - assuming RGGB pattern
-        bayer::run_demosaic(
-            &mut cursor,
-            bayer_depth,
-            CFA::RGGB,
-            Demosaic::Linear,
-            &mut output_raster
-        ).map_err(|e| anyhow::anyhow!("Demosaic failed: {:?}", e))?;
+        // Span ends with the function, after the transfer curve
+        let _color_span = tracing::info_span!("cpu_color_correction", width, height, algorithm = ?quality).entered();
         
         // Convert output buffer to u16 RGB data with simple color correction (Black Level + WB)
         // This fixes the "too green" and "too dark" issues.
         
         // Full Color Pipeline: Black Level -> WB -> Color Matrix (Cam->XYZ->sRGB)
         
-        // 1. Setup Color Matrix
-        // Standard XYZ to sRGB D65 illuminant matrix
-        const XYZ_TO_SRGB: [[f32; 3]; 3] = [
-            [ 3.2404542, -1.5371385, -0.4985314],
-            [-0.9692660,  1.8760108,  0.0415560],
-            [ 0.0556434, -0.2040259,  1.0572252],
-        ];
-
-        // Compute combined matrix: Cam -> XYZ -> sRGB
-        // cam_to_xyz is 3x4 (includes offset in col 3)
-        let mut cam_to_srgb = [[0.0f32; 4]; 3];
-        for r in 0..3 {
-            for c in 0..4 {
-                let mut sum = 0.0;
-                for k in 0..3 {
-                    sum += XYZ_TO_SRGB[r][k] * raw_image.cam_to_xyz[k][c];
-                }
-                cam_to_srgb[r][c] = sum;
-            }
-        }
+        // 1-2. Setup Color Matrix: Cam -> XYZ -> sRGB (or identity for camera-native output),
+        // with exposure compensation folded in (matching NPP implementation), levels and WB
+        let transform = ColorTransform::for_raw(&self.config, raw_image);
+
+        // 3. Process Pixels
+        let mut rgb_data = color_math::to_linear_rgb(samples, &transform);
 
-        // Exposure compensation (matching NPP implementation)
-        const EXPOSURE: f32 = 3.5;
-        for r in 0..3 {
-            for c in 0..4 {
-                cam_to_srgb[r][c] *= EXPOSURE;
+        // 4. Tone curve
+        if let Some(tone_curve) = self.config.tone_curve {
+            for value in rgb_data.iter_mut() {
+                *value = tone_curve.apply(*value);
             }
         }
-
-        // 2. Setup Levels & WB
-        let black_level = raw_image.blacklevels[0] as f32;
-        let white_level = raw_image.whitelevels[0] as f32;
-        let range = (white_level - black_level).max(1.0);
         
-        let wb_r = raw_image.wb_coeffs[0] / raw_image.wb_coeffs[1];
-        let wb_g = 1.0;
-        let wb_b = raw_image.wb_coeffs[2] / raw_image.wb_coeffs[1];
-
-        // 3. Process Pixels
-        let rgb_data: Vec<u16> = output_buf.chunks_exact(bytes_per_pixel * 3)
-            .flat_map(|pixel_bytes| {
-                // Extract RGB
-                let (r_raw, g_raw, b_raw) = if bytes_per_pixel == 1 {
-                    (pixel_bytes[0] as f32, pixel_bytes[1] as f32, pixel_bytes[2] as f32)
-                } else {
-                    (
-                        u16::from_le_bytes([pixel_bytes[0], pixel_bytes[1]]) as f32,
-                        u16::from_le_bytes([pixel_bytes[2], pixel_bytes[3]]) as f32,
-                        u16::from_le_bytes([pixel_bytes[4], pixel_bytes[5]]) as f32
-                    )
-                };
-
-                // Black Level & Normalize & WB
-                let r_lin = ((r_raw - black_level).max(0.0) / range) * wb_r;
-                let g_lin = ((g_raw - black_level).max(0.0) / range) * wb_g;
-                let b_lin = ((b_raw - black_level).max(0.0) / range) * wb_b;
-
-                // Color Matrix (Cam -> sRGB)
-                let r_out = cam_to_srgb[0][0] * r_lin + cam_to_srgb[0][1] * g_lin + cam_to_srgb[0][2] * b_lin + cam_to_srgb[0][3];
-                let g_out = cam_to_srgb[1][0] * r_lin + cam_to_srgb[1][1] * g_lin + cam_to_srgb[1][2] * b_lin + cam_to_srgb[1][3];
-                let b_out = cam_to_srgb[2][0] * r_lin + cam_to_srgb[2][1] * g_lin + cam_to_srgb[2][2] * b_lin + cam_to_srgb[2][3];
-
-                // Clamp and Scale to u16
-                [
-                    (r_out * 65535.0).clamp(0.0, 65535.0) as u16,
-                    (g_out * 65535.0).clamp(0.0, 65535.0) as u16,
-                    (b_out * 65535.0).clamp(0.0, 65535.0) as u16
-                ]
-            })
-            .collect();
+        // 5. sRGB transfer curve
+        if self.config.apply_srgb_gamma {
+            for value in rgb_data.iter_mut() {
+                *value = tone_curve::srgb_encode(*value);
+            }
+        }
         
-        Ok(RgbImageData {
+        Ok(RgbImageDataF32 {
             width,
             height,
             data: rgb_data,
-            bits_per_sample: 16,
+            exif: raw_image.exif,
         })
     }
 }
+
+impl Debayer for CpuDebayer {
+    fn process(&self, raw_image: &RawImageData) -> Result<RgbImageData> {
+        CpuDebayer::process(self, raw_image)
+    }
+
+    fn process_with_stats(&self, raw_image: &RawImageData) -> Result<(RgbImageData, Option<ClipStats>)> {
+        let (image, stats) = self.process_f32(raw_image)?.quantize(self.config.overflow)?;
+        Ok((image, Some(stats)))
+    }
+
+    fn process_f32(&self, raw_image: &RawImageData) -> Result<RgbImageDataF32> {
+        CpuDebayer::process_f32(self, raw_image)
+    }
+
+    fn set_color(&mut self, exposure: f32, white_balance: WhiteBalance) {
+        self.config.exposure = exposure;
+        self.config.white_balance = white_balance;
+    }
+}
+
+/// Sample at `(x, y)` of a `width` x `height` mosaic, as `f32`.
+///
+/// Positions outside the mosaic are mirrored across the border without repeating the
+/// edge sample, which keeps the CFA phase intact.
+fn mirrored_sample(data: &[u16], width: usize, height: usize, x: isize, y: isize) -> f32 {
+    let mirror = |i: isize, len: usize| -> usize {
+        let last = len as isize - 1;
+        let i = if i < 0 { -i } else if i > last { 2 * last - i } else { i };
+        i.clamp(0, last) as usize
+    };
+    data[mirror(y, height) * width + mirror(x, width)] as f32
+}
+
+/// Malvar-He-Cutler demosaic for an RGGB mosaic.
+///
+/// Uses the 5x5 gradient-corrected linear filters from "High-Quality Linear
+/// Interpolation for Demosaicing of Bayer-Patterned Color Images" (ICASSP 2004).
+/// Edge pixels are handled by `mirrored_sample`.
+/// Returns interleaved RGB clamped to `0..=max_value`.
+fn malvar_he_cutler(data: &[u16], width: usize, height: usize, max_value: f32) -> Vec<u16> {
+    let mut rgb = vec![0u16; width * height * 3];
+
+    for y in 0..height {
+        for x in 0..width {
+            let p = |dx: isize, dy: isize| mirrored_sample(data, width, height, x as isize + dx, y as isize + dy);
+
+            let center = p(0, 0);
+            let horizontal = p(-1, 0) + p(1, 0);
+            let vertical = p(0, -1) + p(0, 1);
+            let horizontal2 = p(-2, 0) + p(2, 0);
+            let vertical2 = p(0, -2) + p(0, 2);
+            let diagonal = p(-1, -1) + p(1, -1) + p(-1, 1) + p(1, 1);
+
+            // Green at a red or blue site
+            let green_at_rb = (4.0 * center + 2.0 * (horizontal + vertical) - (horizontal2 + vertical2)) / 8.0;
+            // Red/blue at a green site whose same-row neighbours carry that color
+            let rb_at_g_row = (5.0 * center + 4.0 * horizontal - horizontal2 - diagonal + 0.5 * vertical2) / 8.0;
+            // Red/blue at a green site whose same-column neighbours carry that color
+            let rb_at_g_col = (5.0 * center + 4.0 * vertical - vertical2 - diagonal + 0.5 * horizontal2) / 8.0;
+            // Red at blue or blue at red
+            let rb_at_br = (6.0 * center + 2.0 * diagonal - 1.5 * (horizontal2 + vertical2)) / 8.0;
+
+            let (r, g, b) = match (y % 2, x % 2) {
+                (0, 0) => (center, green_at_rb, rb_at_br),
+                (0, _) => (rb_at_g_row, center, rb_at_g_col),
+                (_, 0) => (rb_at_g_col, center, rb_at_g_row),
+                _ => (rb_at_br, green_at_rb, center),
+            };
+
+            let idx = (y * width + x) * 3;
+            rgb[idx] = r.clamp(0.0, max_value) as u16;
+            rgb[idx + 1] = g.clamp(0.0, max_value) as u16;
+            rgb[idx + 2] = b.clamp(0.0, max_value) as u16;
+        }
+    }
+
+    rgb
+}
+
+/// Edge-directed demosaic for an RGGB mosaic.
+///
+/// Green at red and blue sites is interpolated along whichever of the horizontal and
+/// vertical directions has the smaller neighbour difference, averaging all four
+/// neighbours on a tie, so edges and point sources are not smeared across. Red and blue
+/// stay bilinear. Edge pixels are handled by `mirrored_sample`.
+/// Returns interleaved RGB clamped to `0..=max_value`.
+fn edge_directed(data: &[u16], width: usize, height: usize, max_value: f32) -> Vec<u16> {
+    let mut rgb = vec![0u16; width * height * 3];
+
+    for y in 0..height {
+        for x in 0..width {
+            let p = |dx: isize, dy: isize| mirrored_sample(data, width, height, x as isize + dx, y as isize + dy);
+
+            let center = p(0, 0);
+            let horizontal = p(-1, 0) + p(1, 0);
+            let vertical = p(0, -1) + p(0, 1);
+            let diagonal = p(-1, -1) + p(1, -1) + p(-1, 1) + p(1, 1);
+
+            // Green at a red or blue site, along the smoother direction
+            let horizontal_gradient = (p(-1, 0) - p(1, 0)).abs();
+            let vertical_gradient = (p(0, -1) - p(0, 1)).abs();
+            let green_at_rb = if horizontal_gradient < vertical_gradient {
+                horizontal / 2.0
+            } else if vertical_gradient < horizontal_gradient {
+                vertical / 2.0
+            } else {
+                (horizontal + vertical) / 4.0
+            };
+
+            let (r, g, b) = match (y % 2, x % 2) {
+                (0, 0) => (center, green_at_rb, diagonal / 4.0),
+                (0, _) => (horizontal / 2.0, center, vertical / 2.0),
+                (_, 0) => (vertical / 2.0, center, horizontal / 2.0),
+                _ => (diagonal / 4.0, green_at_rb, center),
+            };
+
+            let idx = (y * width + x) * 3;
+            rgb[idx] = r.clamp(0.0, max_value) as u16;
+            rgb[idx + 1] = g.clamp(0.0, max_value) as u16;
+            rgb[idx + 2] = b.clamp(0.0, max_value) as u16;
+        }
+    }
+
+    rgb
+}
@@ -0,0 +1,39 @@
+//! Process-wide CUDA context shared by the GPU debayers
+//!
+//! All `NppDebayer` and `CudaDebayer` instances run on a single context for device 0,
+//! created on first use and kept alive until `release_shared_context` is called. Each
+//! debayer holds an `Arc` to the context's default stream, so building and dropping
+//! pipelines in a long-running service reuses the same context instead of creating one
+//! per instance. NPP's legacy API runs on the default stream as well, so every GPU stage
+//! in the process is serialized on that one stream.
+
+use cudarc::driver::safe::{CudaContext, CudaStream};
+use std::sync::{Arc, Mutex};
+
+static SHARED_CONTEXT: Mutex<Option<Arc<CudaContext>>> = Mutex::new(None);
+
+/// Returns the shared context, creating it on the first call
+pub fn shared_context() -> anyhow::Result<Arc<CudaContext>> {
+    let mut shared = SHARED_CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(ctx) = shared.as_ref() {
+        return Ok(ctx.clone());
+    }
+
+    let ctx = CudaContext::new(0)?;
+    *shared = Some(ctx.clone());
+    Ok(ctx)
+}
+
+/// Returns the default stream of the shared context
+pub fn shared_stream() -> anyhow::Result<Arc<CudaStream>> {
+    Ok(shared_context()?.default_stream())
+}
+
+/// Drops the process-wide reference to the shared context
+///
+/// The context is destroyed once every debayer holding one of its streams is dropped as
+/// well; the next `shared_context` call then creates a fresh one. Intended for tests and
+/// for services that want to hand the GPU back while idle.
+pub fn release_shared_context() {
+    SHARED_CONTEXT.lock().unwrap_or_else(|e| e.into_inner()).take();
+}
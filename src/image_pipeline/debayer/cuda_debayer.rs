@@ -2,7 +2,10 @@ use cudarc::driver::safe::*;
 use cudarc::nvrtc::Ptx;
 use std::sync::Arc;
 
+use super::cuda_context::shared_stream;
+use super::processor::Debayer;
 use super::types::RgbImageData;
+use super::white_balance::green_coefficient;
 use crate::image_pipeline::raw::types::RawImageData;
 
 /// CUDA Debayer + White Balance + Camera→XYZ
@@ -12,14 +15,14 @@ pub struct CudaDebayer {
 }
 
 impl CudaDebayer {
-    /// Initialize CUDA context and load kernel
+    /// Attach to the shared CUDA context and load kernel
     pub fn new() -> anyhow::Result<Self> {
         // Include compiled PTX from build.rs
         let ptx = include_str!(concat!(env!("OUT_DIR"), "/debayer_rggb_bilinear.ptx"));
         let kernel_name = "debayer16_to_xyz";
 
-        let ctx = CudaContext::new(0)?;
-        let stream = ctx.default_stream();
+        let stream = shared_stream()?;
+        let ctx = stream.context();
         let module = ctx.load_module(Ptx::from_src(ptx))?;
         let kernel = module.load_function(kernel_name)?;
 
@@ -28,6 +31,11 @@ impl CudaDebayer {
 
     /// Process RAW image into linear XYZ
     pub fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        // The kernel always demosaics, it has no path for already-RGB input
+        if !raw_image.is_bayer {
+            anyhow::bail!("CUDA debayer only accepts Bayer mosaics");
+        }
+
         // Copy RAW Bayer data to GPU
         let mut d_bayer = self.stream.clone_htod(&raw_image.data)?;
 
@@ -36,9 +44,10 @@ impl CudaDebayer {
         let mut d_xyz = self.stream.alloc_zeros::<f32>(num_pixels * 3)?;
 
         // Prepare white balance multipliers (normalize by green)
-        let wb_r = raw_image.wb_coeffs[0] / raw_image.wb_coeffs[1];
+        let green = green_coefficient(&raw_image.wb_coeffs);
+        let wb_r = raw_image.wb_coeffs[0] / green;
         let wb_g = 1.0f32;
-        let wb_b = raw_image.wb_coeffs[2] / raw_image.wb_coeffs[1];
+        let wb_b = raw_image.wb_coeffs[2] / green;
         
         // Black and white levels (use first channel, assuming they're the same for RGGB)
         let black_level = raw_image.blacklevels[0] as i32;
@@ -99,6 +108,17 @@ impl CudaDebayer {
             height: raw_image.height,
             data: rgb_data_u16,
             bits_per_sample: 16,
+            exif: raw_image.exif,
         })
     }
 }
+
+impl Debayer for CudaDebayer {
+    fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        CudaDebayer::process(self, raw_image)
+    }
+
+    fn uses_gpu(&self) -> bool {
+        true
+    }
+}
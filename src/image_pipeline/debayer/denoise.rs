@@ -0,0 +1,139 @@
+//! Median noise reduction applied to debayered RGB on the CPU
+
+use rayon::prelude::*;
+
+/// Window size of the median filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DenoiseStrength {
+    /// 3x3 median: removes hot/dead pixels and fine grain while keeping edges sharp
+    Light,
+    /// 5x5 median: stronger smoothing for high-ISO frames, at the cost of fine texture
+    Strong,
+}
+
+impl DenoiseStrength {
+    /// Distance from the center pixel to the window edge
+    pub fn radius(self) -> usize {
+        match self {
+            DenoiseStrength::Light => 1,
+            DenoiseStrength::Strong => 2,
+        }
+    }
+}
+
+/// Applies a per-channel median filter to interleaved pixel data
+///
+/// Each output sample is the median of the same channel over the `(2r+1)x(2r+1)`
+/// window around it, with edge pixels replicated past the border. A median never
+/// moves a flat region's level, so overall brightness is kept while isolated
+/// outliers are replaced by their neighbours. Rows are filtered in parallel.
+pub fn median_filter<T>(data: &[T], width: usize, height: usize, channels: usize, radius: usize) -> Vec<T>
+where
+    T: Copy + PartialOrd + Send + Sync,
+{
+    if radius == 0 || width == 0 || height == 0 {
+        return data.to_vec();
+    }
+
+    let side = 2 * radius + 1;
+    let mut out = data.to_vec();
+    out.par_chunks_mut(width * channels)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let mut window = Vec::with_capacity(side * side);
+            for x in 0..width {
+                for c in 0..channels {
+                    window.clear();
+                    for sy in clamped_window(y, radius, height) {
+                        for sx in clamped_window(x, radius, width) {
+                            window.push(data[(sy * width + sx) * channels + c]);
+                        }
+                    }
+                    let mid = window.len() / 2;
+                    let (_, median, _) = window.select_nth_unstable_by(mid, |a, b| {
+                        a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+                    });
+                    row[x * channels + c] = *median;
+                }
+            }
+        });
+    out
+}
+
+/// Coordinates `center - radius..=center + radius`, clamped to `0..len`
+fn clamped_window(center: usize, radius: usize, len: usize) -> impl Iterator<Item = usize> {
+    (center..=center + 2 * radius).map(move |i| i.saturating_sub(radius).min(len - 1))
+}
+
+/// Rec.709 luma weights, matching `RgbImageData::luminance`
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// Blurs the chroma of interleaved RGB while keeping its luma
+///
+/// Pixels are split into Rec.709 Y'CbCr, Cb and Cr are smoothed with a Gaussian of
+/// standard deviation `radius` pixels (edge pixels replicated past the border), and the
+/// result is converted back. Y is carried through untouched, so detail survives while
+/// color blotches spread out. Rows are filtered in parallel.
+pub fn chroma_blur(data: &[f32], width: usize, height: usize, radius: f32) -> Vec<f32> {
+    if radius.is_nan() || radius <= 0.0 || width == 0 || height == 0 {
+        return data.to_vec();
+    }
+
+    let luma: Vec<f32> = data
+        .chunks_exact(3)
+        .map(|px| LUMA[0] * px[0] + LUMA[1] * px[1] + LUMA[2] * px[2])
+        .collect();
+    let cb: Vec<f32> = data.chunks_exact(3).zip(&luma).map(|(px, y)| px[2] - y).collect();
+    let cr: Vec<f32> = data.chunks_exact(3).zip(&luma).map(|(px, y)| px[0] - y).collect();
+
+    let kernel = gaussian_kernel(radius);
+    let cb = gaussian_blur(&cb, width, height, &kernel);
+    let cr = gaussian_blur(&cr, width, height, &kernel);
+
+    let mut out = vec![0.0; data.len()];
+    out.par_chunks_mut(3).enumerate().for_each(|(i, px)| {
+        let (y, r, b) = (luma[i], luma[i] + cr[i], luma[i] + cb[i]);
+        px[0] = r;
+        px[1] = (y - LUMA[0] * r - LUMA[2] * b) / LUMA[1];
+        px[2] = b;
+    });
+    out
+}
+
+/// Normalized Gaussian weights for offsets `-3σ..=3σ`
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let half = (3.0 * sigma).ceil() as isize;
+    let weights: Vec<f32> = (-half..=half)
+        .map(|d| (-(d * d) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / sum).collect()
+}
+
+/// Separable blur of a single-channel plane, horizontal then vertical
+fn gaussian_blur(plane: &[f32], width: usize, height: usize, kernel: &[f32]) -> Vec<f32> {
+    let half = kernel.len() / 2;
+    let mut horizontal = vec![0.0; plane.len()];
+    horizontal
+        .par_chunks_mut(width)
+        .zip(plane.par_chunks(width))
+        .for_each(|(out, row)| {
+            for (x, value) in out.iter_mut().enumerate() {
+                *value = clamped_window(x, half, width)
+                    .zip(kernel)
+                    .map(|(sx, w)| row[sx] * w)
+                    .sum();
+            }
+        });
+
+    let mut out = vec![0.0; plane.len()];
+    out.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+        for (x, value) in row.iter_mut().enumerate() {
+            *value = clamped_window(y, half, height)
+                .zip(kernel)
+                .map(|(sy, w)| horizontal[sy * width + x] * w)
+                .sum();
+        }
+    });
+    out
+}
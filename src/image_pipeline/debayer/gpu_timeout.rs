@@ -0,0 +1,29 @@
+//! Bounded waits for GPU work, so a hung device fails a frame instead of blocking forever
+
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::image_pipeline::common::error::{ConversionError, Result};
+
+/// Pause between completion checks
+const POLL_INTERVAL: Duration = Duration::from_micros(200);
+
+/// Polls `is_complete` until it reports true, failing once `timeout` has passed
+///
+/// The GPU debayers call this with a CUDA event recorded after a frame's work, instead
+/// of synchronizing on the stream. Fails with `ConversionError::CudaError` starting with
+/// "timeout" when the deadline passes first, and passes on any error from `is_complete`.
+pub fn wait_with_timeout(timeout: Duration, mut is_complete: impl FnMut() -> anyhow::Result<bool>) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if is_complete()? {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(ConversionError::CudaError(format!(
+                "timeout: GPU work did not finish within {:?}",
+                timeout
+            )));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,158 @@
+//! Exposure bracket merging into one extended-range linear image
+
+use rayon::prelude::*;
+use tracing::{debug, warn};
+use crate::image_pipeline::common::error::{ConversionError, Result};
+use crate::image_pipeline::debayer::cpu_debayer::CpuDebayer;
+use crate::image_pipeline::debayer::types::RgbImageDataF32;
+use crate::image_pipeline::raw::types::RawImageData;
+use crate::image_pipeline::tiff::types::ConversionConfig;
+
+/// Normalized raw level from which a sample counts as clipped and gets no weight
+const CLIP_LEVEL: f32 = 0.98;
+
+/// Weight of samples at the noise floor, so pixels dark in every frame still merge
+const MIN_WEIGHT: f32 = 1e-3;
+
+/// Merges a bracket of aligned exposures into linear sRGB at the scale of 0 EV
+///
+/// `ev_steps[i]` is frame `i`'s exposure relative to the reference, e.g.
+/// `[-2.0, 0.0, 2.0]` for a 3-stop bracket around it. Every frame is demosaiced on the
+/// CPU at unit exposure with no tone curve or gamma, divided by `2^ev` and averaged with
+/// a hat weight on how well exposed its raw samples are; the highest sample of the pixel's
+/// 2x2 CFA quad decides, and clipped quads get no weight. Pixels clipped in every frame
+/// take the darkest frame's value. Highlights beyond the reference frame's white come out
+/// above 1.0.
+///
+/// Frames are assumed to be pre-aligned. Metadata (EXIF) comes from the first frame. Fails
+/// if `frames` is empty, `ev_steps` does not hold one finite value per frame, or any frame
+/// is empty or differs from the first in dimensions or Bayer layout (`InvalidDimensions`)
+/// or holds the wrong number of samples (`DataLengthMismatch`).
+pub fn hdr_merge(frames: &[RawImageData], ev_steps: &[f32]) -> Result<RgbImageDataF32> {
+    let Some(first) = frames.first() else {
+        return Err(ConversionError::UnsupportedFormat("no frames to merge".to_string()));
+    };
+    if ev_steps.len() != frames.len() {
+        return Err(ConversionError::UnsupportedFormat(format!(
+            "{} EV steps for {} frames",
+            ev_steps.len(),
+            frames.len()
+        )));
+    }
+    if let Some(ev) = ev_steps.iter().find(|ev| !ev.is_finite()) {
+        return Err(ConversionError::UnsupportedFormat(format!("EV step {} is not finite", ev)));
+    }
+    if first.width == 0 || first.height == 0 {
+        return Err(ConversionError::InvalidDimensions(first.width, first.height));
+    }
+    let samples = first.width * first.height * if first.is_bayer { 1 } else { 3 };
+    for (index, frame) in frames.iter().enumerate() {
+        if frame.width != first.width || frame.height != first.height || frame.is_bayer != first.is_bayer {
+            warn!(
+                "Frame {} is {}x{} (Bayer: {}), frame 0 is {}x{} (Bayer: {})",
+                index, frame.width, frame.height, frame.is_bayer,
+                first.width, first.height, first.is_bayer
+            );
+            return Err(ConversionError::InvalidDimensions(frame.width, frame.height));
+        }
+        if frame.data.len() != samples {
+            warn!("Frame {} holds {} samples, expected {}", index, frame.data.len(), samples);
+            return Err(ConversionError::DataLengthMismatch {
+                expected: samples,
+                actual: frame.data.len(),
+            });
+        }
+    }
+
+    let config = ConversionConfig {
+        exposure: 1.0,
+        ..ConversionConfig::default()
+    };
+    let debayer = CpuDebayer::with_config(&config)?;
+    let linear = frames
+        .iter()
+        .map(|frame| debayer.process_f32(frame).map_err(ConversionError::from))
+        .collect::<Result<Vec<_>>>()?;
+
+    let gains: Vec<f32> = ev_steps.iter().map(|ev| (-ev).exp2()).collect();
+    let darkest = ev_steps
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))
+        .map_or(0, |(index, _)| index);
+
+    let (width, height) = (first.width, first.height);
+    let mut data = vec![0.0f32; width * height * 3];
+    data.par_chunks_mut(width * 3).enumerate().for_each(|(y, row)| {
+        for (x, out) in row.chunks_exact_mut(3).enumerate() {
+            let i = (y * width + x) * 3;
+            let mut sum = [0.0f32; 3];
+            let mut total = 0.0f32;
+            for ((frame, rgb), gain) in frames.iter().zip(&linear).zip(&gains) {
+                let weight = exposure_weight(frame, x, y);
+                for (acc, value) in sum.iter_mut().zip(&rgb.data[i..i + 3]) {
+                    *acc += weight * value * gain;
+                }
+                total += weight;
+            }
+            if total > 0.0 {
+                for (value, acc) in out.iter_mut().zip(sum) {
+                    *value = acc / total;
+                }
+            } else {
+                for (value, dark) in out.iter_mut().zip(&linear[darkest].data[i..i + 3]) {
+                    *value = dark * gains[darkest];
+                }
+            }
+        }
+    });
+
+    debug!("Merged {} exposures of {}x{} (EV {:?})", frames.len(), width, height, ev_steps);
+    Ok(RgbImageDataF32 {
+        width,
+        height,
+        data,
+        exif: first.exif,
+    })
+}
+
+/// Hat weight peaking at mid-range for the brightest raw sample behind pixel `(x, y)`,
+/// zero once it is clipped
+fn exposure_weight(frame: &RawImageData, x: usize, y: usize) -> f32 {
+    let peak = if frame.is_bayer {
+        let (x0, y0) = (x & !1, y & !1);
+        let (x1, y1) = ((x0 + 1).min(frame.width - 1), (y0 + 1).min(frame.height - 1));
+        [(x0, y0), (x1, y0), (x0, y1), (x1, y1)]
+            .iter()
+            .map(|&(sx, sy)| normalized(frame, sy * frame.width + sx, site_level(sx, sy)))
+            .fold(0.0, f32::max)
+    } else {
+        let i = (y * frame.width + x) * 3;
+        (0..3).map(|c| normalized(frame, i + c, c)).fold(0.0, f32::max)
+    };
+
+    if peak >= CLIP_LEVEL {
+        0.0
+    } else {
+        (1.0 - (2.0 * peak - 1.0).abs()).max(MIN_WEIGHT)
+    }
+}
+
+/// Index into `[R, G, B, E]` levels of the RGGB site at `(x, y)`
+fn site_level(x: usize, y: usize) -> usize {
+    match (y & 1, x & 1) {
+        (0, 0) => 0,
+        (0, _) => 1,
+        (_, 0) => 3,
+        _ => 2,
+    }
+}
+
+/// Sample `index` between its black (0.0) and white (1.0) level, `level` indexing
+/// `blacklevels` and `whitelevels`
+fn normalized(frame: &RawImageData, index: usize, level: usize) -> f32 {
+    let black = frame.blacklevels[level] as f32;
+    let white = frame.whitelevels[level] as f32;
+    let value = frame.data[index] as f32;
+    ((value - black) / (white - black).max(1.0)).clamp(0.0, 1.0)
+}
@@ -1,8 +1,18 @@
 use cudarc::driver::safe::*;
+use cudarc::nvrtc::Ptx;
 use std::sync::Arc;
 
-use super::types::RgbImageData;
+use super::border;
+use super::color_math;
+use super::cuda_context::shared_stream;
+use super::gpu_timeout::wait_with_timeout;
+use super::npp_status::describe_npp_status;
+use super::processor::Debayer;
+use super::quantize::ClipStats;
+use super::types::{NppInterpolation, RgbImageData, RgbImageDataF32, WorkingPrecision};
+use super::white_balance::WhiteBalance;
 use crate::image_pipeline::raw::types::RawImageData;
+use crate::image_pipeline::tiff::types::ConversionConfig;
 
 #[allow(non_upper_case_globals)]
 #[allow(non_camel_case_types)]
@@ -18,11 +28,17 @@ mod npp {
 /// image processing pipeline, replacing the previous custom CUDA color correction kernel.
 ///
 /// Pipeline stages:
-/// 1. **Debayering**: `nppiCFAToRGB_16u_C1C3R` - Converts Bayer pattern to RGB
+/// 1. **Debayering**: `nppiCFAToRGB_16u_C1C3R` - Converts Bayer pattern to RGB (skipped for non-Bayer raws)
 /// 2. **Type conversion**: `nppiConvert_16u32f_C3R` - Converts u16 to f32 for processing
 /// 3. **Black level subtraction**: `nppiSubC_32f_C3IR` - Removes sensor black level
 /// 4. **Normalization + White balance**: `nppiMulC_32f_C3IR` - Scales to 0..1 and applies WB
 /// 5. **Color matrix transform**: `nppiColorTwist_32f_C3R` - Applies camera→XYZ→sRGB transform
+/// 6. **sRGB gamma** (`apply_srgb_gamma` only): `srgb_encode` kernel - NPP has no float
+///    sRGB transfer function, so a small custom kernel encodes the samples on-device
+///
+/// With `WorkingPrecision::F16`, stages 2-5 run on half floats instead: custom kernels
+/// convert to f16 and back, and `nppiSubC_16f_C3IR`, `nppiMulC_16f_C3IR` and
+/// `nppiColorTwist32f_16f_C3R` do the arithmetic.
 ///
 /// Benefits over custom kernel:
 /// - Leverages highly optimized NPP library functions
@@ -34,19 +50,62 @@ mod npp {
 /// as NPP reads it directly during the kernel launch.
 pub struct NppDebayer {
     stream: Arc<CudaStream>,
+    config: ConversionConfig,
+    /// `srgb_encode` from `srgb_gamma.cu`, loaded only when `apply_srgb_gamma` is set
+    gamma_kernel: Option<CudaFunction>,
+    /// `u16_to_half` and `half_to_float` from `half_precision.cu`, loaded only for
+    /// `WorkingPrecision::F16`
+    half_kernels: Option<(CudaFunction, CudaFunction)>,
 }
 
 impl NppDebayer {
-    /// Initialize CUDA context
+    /// Attach to the shared CUDA context
     pub fn new() -> anyhow::Result<Self> {
-        let ctx = CudaContext::new(0)?;
-        let stream = ctx.default_stream();
+        Self::with_config(&ConversionConfig::default())
+    }
+
+    /// Attach to the shared CUDA context for the given conversion settings
+    ///
+    /// `debayer_quality` only applies to `CpuDebayer`; NPP uses `npp_interpolation` instead.
+    pub fn with_config(config: &ConversionConfig) -> anyhow::Result<Self> {
+        let stream = shared_stream()?;
+        let gamma_kernel = if config.apply_srgb_gamma {
+            let ptx = include_str!(concat!(env!("OUT_DIR"), "/srgb_gamma.ptx"));
+            let module = stream.context().load_module(Ptx::from_src(ptx))?;
+            Some(module.load_function("srgb_encode")?)
+        } else {
+            None
+        };
+        let half_kernels = if config.working_precision == WorkingPrecision::F16 {
+            let ptx = include_str!(concat!(env!("OUT_DIR"), "/half_precision.ptx"));
+            let module = stream.context().load_module(Ptx::from_src(ptx))?;
+            Some((module.load_function("u16_to_half")?, module.load_function("half_to_float")?))
+        } else {
+            None
+        };
 
-        Ok(Self { stream })
+        Ok(Self {
+            stream,
+            config: config.clone(),
+            gamma_kernel,
+            half_kernels,
+        })
     }
 
-    /// Process RAW image using NPP debayer + NPP color pipeline
-    pub fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+    /// NPP enum value for the configured interpolation mode
+    fn interpolation_mode(interpolation: NppInterpolation) -> npp::NppiInterpolationMode {
+        match interpolation {
+            NppInterpolation::Undefined => npp::NppiInterpolationMode_NPPI_INTER_UNDEFINED,
+            NppInterpolation::NearestNeighbour => npp::NppiInterpolationMode_NPPI_INTER_NN,
+            NppInterpolation::Linear => npp::NppiInterpolationMode_NPPI_INTER_LINEAR,
+            NppInterpolation::Cubic => npp::NppiInterpolationMode_NPPI_INTER_CUBIC,
+            NppInterpolation::Super => npp::NppiInterpolationMode_NPPI_INTER_SUPER,
+            NppInterpolation::Lanczos => npp::NppiInterpolationMode_NPPI_INTER_LANCZOS,
+        }
+    }
+
+    /// Demosaics the RGGB mosaic on the GPU into interleaved u16 RGB
+    fn demosaic(&self, raw_image: &RawImageData) -> anyhow::Result<CudaSlice<u16>> {
         let width = raw_image.width;
         let height = raw_image.height;
         
@@ -57,7 +116,6 @@ impl NppDebayer {
         let num_pixels = width * height;
         let mut d_rgb_u16 = self.stream.alloc_zeros::<u16>(num_pixels * 3)?;
 
-        // ---- Stage 1: NPP Debayering ----
         let src_size = npp::NppiSize { 
             width: width as i32, 
             height: height as i32 
@@ -85,16 +143,94 @@ impl NppDebayer {
                 dst_ptr as *mut npp::Npp16u,
                 dst_step,
                 npp::NppiBayerGridPosition_NPPI_BAYER_RGGB,
-                npp::NppiInterpolationMode_NPPI_INTER_UNDEFINED,
+                Self::interpolation_mode(self.config.npp_interpolation),
             );
             
             if status != 0 {
-                anyhow::bail!("NPP debayer failed with status {}", status);
+                anyhow::bail!("NPP debayer failed with {}", describe_npp_status(status));
             }
         }
 
+        Ok(d_rgb_u16)
+    }
+
+    /// Process RAW image using NPP debayer + NPP color pipeline
+    pub fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        Ok(self.process_f32(raw_image)?.quantize(self.config.overflow)?.0)
+    }
+
+    /// Process RAW image, returning the linear float result without quantization
+    ///
+    /// Mosaics are padded and trimmed as `border_mode` says, so `BorderMode::Crop` output
+    /// is `2 * BORDER` pixels narrower and shorter than `raw_image`.
+    pub fn process_f32(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageDataF32> {
+        let padded = self.config.border_mode.pad(raw_image);
+        let image = self.demosaic_and_correct(&padded)?;
+        Ok(border::trim(raw_image, image)?)
+    }
+
+    /// Runs the NPP debayer and color pipeline over `raw_image` edge to edge
+    fn demosaic_and_correct(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageDataF32> {
+        let width = raw_image.width;
+        let height = raw_image.height;
+        
+        let num_pixels = width * height;
+
+        // ---- Stage 1: NPP Debayering, skipped for input that is already RGB ----
+        let d_rgb_u16 = if raw_image.is_bayer {
+            self.demosaic(raw_image)?
+        } else {
+            self.stream.clone_htod(&raw_image.data)?
+        };
+
         // ---- Stage 2: NPP Color Pipeline ----
+        let mut d_rgb_twisted = match self.config.working_precision {
+            WorkingPrecision::F32 => self.color_pipeline_f32(raw_image, &d_rgb_u16)?,
+            WorkingPrecision::F16 => self.color_pipeline_f16(raw_image, &d_rgb_u16)?,
+        };
+
+        // sRGB transfer curve on-device, after the color matrix as in the CPU debayer
+        if let Some(kernel) = &self.gamma_kernel {
+            let count = (num_pixels * 3) as i32;
+            let mut launch_args = self.stream.launch_builder(kernel);
+            launch_args.arg(&mut d_rgb_twisted);
+            launch_args.arg(&count);
+            let cfg = LaunchConfig::for_num_elems(count as u32);
+            unsafe { launch_args.launch(cfg)? };
+        }
+
+        // Copy back from GPU, once the queued work is done
+        self.wait_for_device()?;
+        let rgb_data_f32 = self.stream.clone_dtoh(&d_rgb_twisted)?;
+
+        Ok(RgbImageDataF32 {
+            width,
+            height,
+            data: rgb_data_f32,
+            exif: raw_image.exif,
+        })
+    }
+
+    /// Waits for the work queued on the stream, failing after `gpu_timeout` if it is set
+    ///
+    /// The blocking device-to-host copy would otherwise wait on a hung GPU indefinitely,
+    /// so completion is polled through an event recorded behind the queued work.
+    fn wait_for_device(&self) -> anyhow::Result<()> {
+        let Some(timeout) = self.config.gpu_timeout else {
+            return Ok(());
+        };
+        let event = self.stream.record_event(None)?;
+        Ok(wait_with_timeout(timeout, || Ok(event.is_complete()))?)
+    }
+
+    /// Black level, white balance and color matrix on `d_rgb_u16` in 32-bit float
+    fn color_pipeline_f32(&self, raw_image: &RawImageData, d_rgb_u16: &CudaSlice<u16>) -> anyhow::Result<CudaSlice<f32>> {
+        let width = raw_image.width;
+        let height = raw_image.height;
         
+        let num_pixels = width * height;
+        let rgb_u16_step = (width * 3 * std::mem::size_of::<u16>()) as i32;
+
         // Allocate f32 workspace for color corrections
         let mut d_rgb_f32 = self.stream.alloc_zeros::<f32>(num_pixels * 3)?;
 
@@ -110,19 +246,21 @@ impl NppDebayer {
             
             let status = npp::nppiConvert_16u32f_C3R(
                 src_ptr as *const npp::Npp16u,
-                dst_step,
+                rgb_u16_step,
                 dst_ptr as *mut npp::Npp32f,
                 (width * 3 * std::mem::size_of::<f32>()) as i32,
                 roi_size,
             );
             
             if status != 0 {
-                anyhow::bail!("NPP Convert 16u→32f failed with status {}", status);
+                anyhow::bail!("NPP Convert 16u→32f failed with {}", describe_npp_status(status));
             }
         }
 
-        // Step 2.2: Subtract black level from each channel
-        let black_level = raw_image.blacklevels[0] as f32;
+        // Step 2.2: Subtract black level (raised by the configured black clip) from each channel
+        let white_level = raw_image.whitelevels[0] as f32;
+        let black_clip = (white_level - raw_image.blacklevels[0] as f32) * self.config.black_clip.clamp(0.0, 1.0);
+        let black_level = raw_image.blacklevels[0] as f32 + black_clip;
         let black_levels = [black_level, black_level, black_level];
         
         unsafe {
@@ -136,19 +274,38 @@ impl NppDebayer {
             );
             
             if status != 0 {
-                anyhow::bail!("NPP SubC (black level) failed with status {}", status);
+                anyhow::bail!("NPP SubC (black level) failed with {}", describe_npp_status(status));
+            }
+        }
+
+        // Values below the raised black point clip to zero
+        if black_clip > 0.0 {
+            let zeros = [0.0f32; 3];
+
+            unsafe {
+                let (ptr, _guard) = d_rgb_f32.device_ptr_mut(&self.stream);
+
+                let status = npp::nppiThreshold_LTVal_32f_C3IR(
+                    ptr as *mut npp::Npp32f,
+                    (width * 3 * std::mem::size_of::<f32>()) as i32,
+                    roi_size,
+                    zeros.as_ptr(),
+                    zeros.as_ptr(),
+                );
+
+                if status != 0 {
+                    anyhow::bail!("NPP Threshold (black clip) failed with {}", describe_npp_status(status));
+                }
             }
         }
 
         // Step 2.3: Normalize by (white - black) and apply white balance
-        let white_level = raw_image.whitelevels[0] as f32;
-        let range = white_level - black_level;
+        let range = (white_level - black_level).max(1.0);
         
-        // Combine normalization with white balance: (1/range) * wb_coeff
-        let wb_r = (raw_image.wb_coeffs[0] / raw_image.wb_coeffs[1]) / range;
-        let wb_g = 1.0f32 / range;
-        let wb_b = (raw_image.wb_coeffs[2] / raw_image.wb_coeffs[1]) / range;
-        let wb_multipliers = [wb_r, wb_g, wb_b];
+        // Combine normalization, white balance and baseline exposure: (1/range) * wb_coeff * 2^ev
+        let baseline_gain = self.config.baseline_exposure.exp2();
+        let [wb_r, wb_g, wb_b] = self.config.white_balance.multipliers(raw_image).map(|m| m * baseline_gain);
+        let wb_multipliers = [wb_r / range, wb_g / range, wb_b / range];
         
         unsafe {
             let (ptr, _guard) = d_rgb_f32.device_ptr_mut(&self.stream);
@@ -161,41 +318,14 @@ impl NppDebayer {
             );
             
             if status != 0 {
-                anyhow::bail!("NPP MulC (normalize + white balance) failed with status {}", status);
+                anyhow::bail!("NPP MulC (normalize + white balance) failed with {}", describe_npp_status(status));
             }
         }
 
         // Step 2.4: Apply camera-to-XYZ → XYZ-to-sRGB color matrix transformation
-        // Combine both matrices: sRGB_from_XYZ * cam_to_XYZ
-        // Standard XYZ to sRGB D65 illuminant matrix:
-        const XYZ_TO_SRGB: [[f32; 3]; 3] = [
-            [ 3.2404542, -1.5371385, -0.4985314],
-            [-0.9692660,  1.8760108,  0.0415560],
-            [ 0.0556434, -0.2040259,  1.0572252],
-        ];
-        
-        // Multiply: combined = XYZ_TO_SRGB * cam_to_xyz (3×3 result from 3×3 × 3×4)
-        // We also compute the offset (4th column) from cam_to_xyz
-        let mut combined = [[0.0f32; 4]; 3];
-        for i in 0..3 {
-            for j in 0..3 {
-                combined[i][j] = XYZ_TO_SRGB[i][0] * raw_image.cam_to_xyz[0][j]
-                               + XYZ_TO_SRGB[i][1] * raw_image.cam_to_xyz[1][j]
-                               + XYZ_TO_SRGB[i][2] * raw_image.cam_to_xyz[2][j];
-            }
-            // Offset column (4th): apply XYZ_TO_SRGB to cam_to_xyz offset
-            combined[i][3] = XYZ_TO_SRGB[i][0] * raw_image.cam_to_xyz[0][3]
-                           + XYZ_TO_SRGB[i][1] * raw_image.cam_to_xyz[1][3]
-                           + XYZ_TO_SRGB[i][2] * raw_image.cam_to_xyz[2][3];
-        }
-        
-        // Apply exposure scaling to entire matrix (including offset)
-        const EXPOSURE: f32 = 3.5;
-        for i in 0..3 {
-            for j in 0..4 {
-                combined[i][j] *= EXPOSURE;
-            }
-        }
+        // (identity for camera-native output), exposure scaling the entire matrix
+        // including the offset column, same as the CPU debayer
+        let combined = color_math::compute_color_matrix(&self.config, raw_image);
         
         // NPP ColorTwist uses a 3×4 matrix in row-major order:
         // [m00 m01 m02 m03]  where the 4th column is constant offset per channel
@@ -224,26 +354,146 @@ impl NppDebayer {
             );
             
             if status != 0 {
-                anyhow::bail!("NPP ColorTwist (color matrix) failed with status {}", status);
+                anyhow::bail!("NPP ColorTwist (color matrix) failed with {}", describe_npp_status(status));
             }
         }
 
-        // Copy back from GPU and convert to u16 (0..1 → 0..65535)
-        let rgb_data_f32 = self.stream.clone_dtoh(&d_rgb_twisted)?;
 
-        let rgb_data_u16: Vec<u16> = rgb_data_f32
-            .iter()
-            .map(|&v| {
-                let v = v.clamp(0.0, 1.0);
-                (v * 65535.0) as u16
-            })
-            .collect();
+        Ok(d_rgb_twisted)
+    }
+    /// Black level, white balance and color matrix on `d_rgb_u16` in half precision
+    ///
+    /// NPP has no u16 to f16 conversion and no f16 threshold, so the `u16_to_half` kernel
+    /// converts, raising samples to a black point raised by `black_clip` on the way so the
+    /// subtraction clips at zero as in the f32 path, and `half_to_float` widens the
+    /// color-twisted result. 65535 is past the largest
+    /// half float, so samples are scaled by `1 / 65535` on conversion and the white
+    /// balance multipliers undo it.
+    fn color_pipeline_f16(&self, raw_image: &RawImageData, d_rgb_u16: &CudaSlice<u16>) -> anyhow::Result<CudaSlice<f32>> {
+        let Some((to_half, to_float)) = &self.half_kernels else {
+            anyhow::bail!("Half-precision kernels are only loaded for WorkingPrecision::F16");
+        };
+        let width = raw_image.width;
+        let height = raw_image.height;
+        
+        let count = (width * height * 3) as i32;
+        let cfg = LaunchConfig::for_num_elems(count as u32);
+        let half_step = (width * 3 * std::mem::size_of::<u16>()) as i32;
+        let roi_size = npp::NppiSize {
+            width: width as i32,
+            height: height as i32,
+        };
 
-        Ok(RgbImageData {
-            width,
-            height,
-            data: rgb_data_u16,
-            bits_per_sample: 16,
-        })
+        // Same levels and multipliers as the f32 path, in units of the scaled samples
+        let scale = 1.0 / u16::MAX as f32;
+        let white_level = raw_image.whitelevels[0] as f32;
+        let black_clip = (white_level - raw_image.blacklevels[0] as f32) * self.config.black_clip.clamp(0.0, 1.0);
+        let black_level = raw_image.blacklevels[0] as f32 + black_clip;
+        let range = (white_level - black_level).max(1.0) * scale;
+        let baseline_gain = self.config.baseline_exposure.exp2();
+        let [wb_r, wb_g, wb_b] = self.config.white_balance.multipliers(raw_image).map(|m| m * baseline_gain);
+        let wb_multipliers = [wb_r / range, wb_g / range, wb_b / range];
+
+        // Step 2.1: Convert u16 → f16 (half floats are stored as their u16 bits); values
+        // below the raised black point clip to zero
+        let floor = if black_clip > 0.0 { black_level } else { 0.0 };
+        let mut d_rgb_f16 = self.stream.alloc_zeros::<u16>(count as usize)?;
+        let mut launch_args = self.stream.launch_builder(to_half);
+        launch_args.arg(d_rgb_u16);
+        launch_args.arg(&mut d_rgb_f16);
+        launch_args.arg(&count);
+        launch_args.arg(&floor);
+        launch_args.arg(&scale);
+        unsafe { launch_args.launch(cfg)? };
+
+        // Step 2.2: Subtract black level
+        let black_levels = [black_level * scale; 3];
+        
+        unsafe {
+            let (ptr, _guard) = d_rgb_f16.device_ptr_mut(&self.stream);
+            
+            let status = npp::nppiSubC_16f_C3IR(
+                black_levels.as_ptr(),
+                ptr as *mut npp::Npp16f,
+                half_step,
+                roi_size,
+            );
+            
+            if status != 0 {
+                anyhow::bail!("NPP SubC 16f (black level) failed with {}", describe_npp_status(status));
+            }
+        }
+
+        // Step 2.3: Normalize by (white - black) and apply white balance
+        unsafe {
+            let (ptr, _guard) = d_rgb_f16.device_ptr_mut(&self.stream);
+            
+            let status = npp::nppiMulC_16f_C3IR(
+                wb_multipliers.as_ptr(),
+                ptr as *mut npp::Npp16f,
+                half_step,
+                roi_size,
+            );
+            
+            if status != 0 {
+                anyhow::bail!("NPP MulC 16f (normalize + white balance) failed with {}", describe_npp_status(status));
+            }
+        }
+
+        // Step 2.4: Color matrix, with a float matrix on half samples
+        let twist_matrix = color_math::compute_color_matrix(&self.config, raw_image);
+        let mut d_twisted_f16 = self.stream.alloc_zeros::<u16>(count as usize)?;
+        
+        unsafe {
+            let (src_ptr, _src_guard) = d_rgb_f16.device_ptr(&self.stream);
+            let (dst_ptr, _dst_guard) = d_twisted_f16.device_ptr_mut(&self.stream);
+            
+            let status = npp::nppiColorTwist32f_16f_C3R(
+                src_ptr as *const npp::Npp16f,
+                half_step,
+                dst_ptr as *mut npp::Npp16f,
+                half_step,
+                roi_size,
+                twist_matrix.as_ptr(),
+            );
+            
+            if status != 0 {
+                anyhow::bail!("NPP ColorTwist 16f (color matrix) failed with {}", describe_npp_status(status));
+            }
+        }
+
+        // Widen to f32 for the gamma kernel and the copy back
+        let mut d_rgb_twisted = self.stream.alloc_zeros::<f32>(count as usize)?;
+        let mut launch_args = self.stream.launch_builder(to_float);
+        launch_args.arg(&d_twisted_f16);
+        launch_args.arg(&mut d_rgb_twisted);
+        launch_args.arg(&count);
+        unsafe { launch_args.launch(cfg)? };
+
+        Ok(d_rgb_twisted)
+    }
+}
+
+impl Debayer for NppDebayer {
+    fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData> {
+        NppDebayer::process(self, raw_image)
+    }
+
+    fn process_with_stats(&self, raw_image: &RawImageData) -> anyhow::Result<(RgbImageData, Option<ClipStats>)> {
+        let (image, stats) = self.process_f32(raw_image)?.quantize(self.config.overflow)?;
+        Ok((image, Some(stats)))
+    }
+
+    fn process_f32(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageDataF32> {
+        NppDebayer::process_f32(self, raw_image)
+    }
+
+    fn set_color(&mut self, exposure: f32, white_balance: WhiteBalance) {
+        self.config.exposure = exposure;
+        self.config.white_balance = white_balance;
+    }
+
+    fn uses_gpu(&self) -> bool {
+        true
     }
 }
@@ -0,0 +1,84 @@
+//! Symbolic names for `NppStatus` codes, as defined in `nppdefs.h`
+//!
+//! Kept free of the generated NPP bindings so errors can be decoded on any platform.
+
+/// Returns the `nppdefs.h` name of an NPP status code, `None` if it is not a known code
+pub fn npp_status_name(status: i32) -> Option<&'static str> {
+    let name = match status {
+        -9999 => "NPP_NOT_SUPPORTED_MODE_ERROR",
+        -1032 => "NPP_INVALID_HOST_POINTER_ERROR",
+        -1031 => "NPP_INVALID_DEVICE_POINTER_ERROR",
+        -1030 => "NPP_LUT_PALETTE_BITSIZE_ERROR",
+        -1028 => "NPP_ZC_MODE_NOT_SUPPORTED_ERROR",
+        -1027 => "NPP_NOT_SUFFICIENT_COMPUTE_CAPABILITY",
+        -1024 => "NPP_TEXTURE_BIND_ERROR",
+        -1020 => "NPP_WRONG_INTERSECTION_ROI_ERROR",
+        -1006 => "NPP_HAAR_CLASSIFIER_PIXEL_MATCH_ERROR",
+        -1005 => "NPP_MEMFREE_ERROR",
+        -1004 => "NPP_MEMSET_ERROR",
+        -1003 => "NPP_MEMCPY_ERROR",
+        -1002 => "NPP_ALIGNMENT_ERROR",
+        -1000 => "NPP_CUDA_KERNEL_EXECUTION_ERROR",
+        -213 => "NPP_ROUND_MODE_NOT_SUPPORTED_ERROR",
+        -210 => "NPP_QUALITY_INDEX_ERROR",
+        -201 => "NPP_RESIZE_NO_OPERATION_ERROR",
+        -109 => "NPP_OVERFLOW_ERROR",
+        -108 => "NPP_NOT_EVEN_STEP_ERROR",
+        -107 => "NPP_HISTOGRAM_NUMBER_OF_LEVELS_ERROR",
+        -106 => "NPP_LUT_NUMBER_OF_LEVELS_ERROR",
+        -61 => "NPP_CORRUPTED_DATA_ERROR",
+        -60 => "NPP_CHANNEL_ORDER_ERROR",
+        -59 => "NPP_ZERO_MASK_VALUE_ERROR",
+        -58 => "NPP_QUADRANGLE_ERROR",
+        -57 => "NPP_RECTANGLE_ERROR",
+        -56 => "NPP_COEFFICIENT_ERROR",
+        -53 => "NPP_NUMBER_OF_CHANNELS_ERROR",
+        -52 => "NPP_COI_ERROR",
+        -51 => "NPP_DIVISOR_ERROR",
+        -47 => "NPP_CHANNEL_ERROR",
+        -37 => "NPP_STRIDE_ERROR",
+        -34 => "NPP_ANCHOR_ERROR",
+        -33 => "NPP_MASK_SIZE_ERROR",
+        -23 => "NPP_RESIZE_FACTOR_ERROR",
+        -22 => "NPP_INTERPOLATION_ERROR",
+        -21 => "NPP_MIRROR_FLIP_ERROR",
+        -20 => "NPP_MOMENT_00_ZERO_ERROR",
+        -19 => "NPP_THRESHOLD_NEGATIVE_LEVEL_ERROR",
+        -18 => "NPP_THRESHOLD_ERROR",
+        -17 => "NPP_CONTEXT_MATCH_ERROR",
+        -16 => "NPP_FFT_FLAG_ERROR",
+        -15 => "NPP_FFT_ORDER_ERROR",
+        -14 => "NPP_STEP_ERROR",
+        -13 => "NPP_SCALE_RANGE_ERROR",
+        -12 => "NPP_DATA_TYPE_ERROR",
+        -11 => "NPP_OUT_OFF_RANGE_ERROR",
+        -10 => "NPP_DIVIDE_BY_ZERO_ERROR",
+        -9 => "NPP_MEMORY_ALLOCATION_ERR",
+        -8 => "NPP_NULL_POINTER_ERROR",
+        -7 => "NPP_RANGE_ERROR",
+        -6 => "NPP_SIZE_ERROR",
+        -5 => "NPP_BAD_ARGUMENT_ERROR",
+        -4 => "NPP_NO_MEMORY_ERROR",
+        -3 => "NPP_NOT_IMPLEMENTED_ERROR",
+        -2 => "NPP_ERROR",
+        -1 => "NPP_ERROR_RESERVED",
+        0 => "NPP_NO_ERROR",
+        1 => "NPP_NO_OPERATION_WARNING",
+        6 => "NPP_DIVIDE_BY_ZERO_WARNING",
+        28 => "NPP_AFFINE_QUAD_INCORRECT_WARNING",
+        29 => "NPP_WRONG_INTERSECTION_ROI_WARNING",
+        30 => "NPP_WRONG_INTERSECTION_QUAD_WARNING",
+        35 => "NPP_DOUBLE_SIZE_WARNING",
+        10000 => "NPP_MISALIGNED_DST_ROI_WARNING",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Formats a status for error messages, e.g. `status -6 (NPP_SIZE_ERROR)`
+pub fn describe_npp_status(status: i32) -> String {
+    match npp_status_name(status) {
+        Some(name) => format!("status {} ({})", status, name),
+        None => format!("unknown status {}", status),
+    }
+}
@@ -0,0 +1,47 @@
+use crate::image_pipeline::raw::types::RawImageData;
+use crate::image_pipeline::debayer::quantize::ClipStats;
+use crate::image_pipeline::debayer::types::{RgbImageData, RgbImageDataF32};
+use crate::image_pipeline::debayer::white_balance::WhiteBalance;
+
+/// Demosaic and color pipeline turning a Bayer `RawImageData` into RGB
+///
+/// Implemented by `CpuDebayer`, `NppDebayer` and `CudaDebayer`; pass your own to
+/// `RawToTiffPipeline::with_debayer` to plug in a different implementation. The
+/// pipeline shares one instance across batch workers, hence `Send + Sync`.
+pub trait Debayer: Send + Sync {
+    fn process(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageData>;
+
+    /// Like `process`, also returning how many pixels the 16-bit quantization clipped
+    ///
+    /// The default reports `None`, for implementations that don't quantize from float.
+    fn process_with_stats(&self, raw_image: &RawImageData) -> anyhow::Result<(RgbImageData, Option<ClipStats>)> {
+        Ok((self.process(raw_image)?, None))
+    }
+
+    /// Linear float output for `output_float`
+    ///
+    /// The default widens the 16-bit result of `process` to 0.0..=1.0, so highlights
+    /// clipped there stay clipped; implementations with a float pipeline override it.
+    fn process_f32(&self, raw_image: &RawImageData) -> anyhow::Result<RgbImageDataF32> {
+        let rgb = self.process(raw_image)?;
+        Ok(RgbImageDataF32 {
+            width: rgb.width,
+            height: rgb.height,
+            data: rgb.data.iter().map(|&v| v as f32 / 65535.0).collect(),
+            exif: rgb.exif,
+        })
+    }
+
+    /// Takes new `exposure` and `white_balance` values for the following frames, without
+    /// reinitializing anything else
+    ///
+    /// Called by `RawToTiffPipeline::set_exposure` and `set_white_balance`. The default
+    /// ignores them, for implementations whose color is fixed at construction.
+    fn set_color(&mut self, _exposure: f32, _white_balance: WhiteBalance) {}
+
+    /// Whether this runs on the GPU, in which case batch conversion limits it to
+    /// `max_gpu_concurrency` frames at once
+    fn uses_gpu(&self) -> bool {
+        false
+    }
+}
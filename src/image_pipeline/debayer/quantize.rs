@@ -0,0 +1,91 @@
+//! Final float to 16-bit quantization of debayered RGB, with clip accounting
+
+use serde::Serialize;
+
+/// How samples outside 0.0..=1.0 are handled when quantizing to 16-bit
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OverflowMode {
+    /// Clamp each sample to 0.0..=1.0 (highlights above diffuse white are lost)
+    #[default]
+    Clamp,
+    /// Divide every sample by the image's peak when it exceeds 1.0, so highlights
+    /// are compressed instead of clipped. Negative samples are still clamped to 0
+    ScaleToFit,
+    /// Clamp like `Clamp`, but fail if more than `max_clipped_percent` of the pixels
+    /// have a sample out of range
+    Error { max_clipped_percent: f32 },
+}
+
+/// Out-of-range statistics of one quantized image, measured before any scaling
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ClipStats {
+    /// Pixels in the image
+    pub total_pixels: usize,
+    /// Pixels with at least one sample below 0.0 or above 1.0
+    pub clipped_pixels: usize,
+    /// Largest sample value, 0.0 for an empty image
+    pub peak: f32,
+    /// Divisor applied to every sample, 1.0 unless `ScaleToFit` compressed the highlights
+    pub scale: f32,
+}
+
+impl ClipStats {
+    /// Measures interleaved `data` with `channels` samples per pixel
+    pub fn measure(data: &[f32], channels: usize) -> Self {
+        let mut clipped_pixels = 0;
+        let mut peak = 0.0f32;
+        for pixel in data.chunks_exact(channels) {
+            if pixel.iter().any(|&v| !(0.0..=1.0).contains(&v)) {
+                clipped_pixels += 1;
+            }
+            peak = pixel.iter().fold(peak, |peak, &v| peak.max(v));
+        }
+        ClipStats {
+            total_pixels: data.len() / channels,
+            clipped_pixels,
+            peak,
+            scale: 1.0,
+        }
+    }
+
+    /// Share of pixels with a sample out of range, in percent
+    pub fn clipped_percent(&self) -> f32 {
+        if self.total_pixels == 0 {
+            return 0.0;
+        }
+        self.clipped_pixels as f32 * 100.0 / self.total_pixels as f32
+    }
+}
+
+/// Quantizes interleaved float samples to 0..=65535 according to `mode`
+///
+/// In `Clamp` mode the result is identical to `RgbImageDataF32::to_u16`.
+pub fn quantize(data: &[f32], channels: usize, mode: OverflowMode) -> anyhow::Result<(Vec<u16>, ClipStats)> {
+    let mut stats = ClipStats::measure(data, channels);
+
+    match mode {
+        OverflowMode::Clamp => {}
+        OverflowMode::ScaleToFit => {
+            if stats.peak > 1.0 {
+                stats.scale = stats.peak;
+            }
+        }
+        OverflowMode::Error { max_clipped_percent } => {
+            if stats.clipped_percent() > max_clipped_percent {
+                anyhow::bail!(
+                    "{:.3}% of pixels are out of range, limit is {}% (peak {})",
+                    stats.clipped_percent(),
+                    max_clipped_percent,
+                    stats.peak
+                );
+            }
+        }
+    }
+
+    let scale = stats.scale;
+    let quantized = data
+        .iter()
+        .map(|&v| ((v / scale).clamp(0.0, 1.0) * 65535.0) as u16)
+        .collect();
+    Ok((quantized, stats))
+}
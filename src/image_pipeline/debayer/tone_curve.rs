@@ -0,0 +1,57 @@
+//! Tone curves applied to linear RGB after the color matrix
+
+/// Tone mapping preset applied per channel to normalized values
+///
+/// Each curve maps 0.0 to 0.0 and is monotonic; negative (out of gamut) inputs
+/// are clamped to 0.0 except by `Linear`, which passes values through unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneCurve {
+    /// Identity, equivalent to no tone curve
+    Linear,
+    /// ACES filmic fit (Narkowicz): toe plus a soft shoulder that saturates at 1.0
+    Filmic,
+    /// Reinhard `x / (1 + x)`: compresses highlights, approaching 1.0 without reaching it
+    Reinhard,
+}
+
+impl ToneCurve {
+    /// Maps a normalized linear value through the curve
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            ToneCurve::Linear => x,
+            ToneCurve::Filmic => filmic(x.max(0.0)),
+            ToneCurve::Reinhard => reinhard(x.max(0.0)),
+        }
+    }
+}
+
+/// Krzysztof Narkowicz's rational fit of the ACES reference rendering transform
+fn filmic(x: f32) -> f32 {
+    const A: f32 = 2.51;
+    const B: f32 = 0.03;
+    const C: f32 = 2.43;
+    const D: f32 = 0.59;
+    const E: f32 = 0.14;
+    ((x * (A * x + B)) / (x * (C * x + D) + E)).clamp(0.0, 1.0)
+}
+
+/// Reinhard `x / (1 + x)`, written as `1 - 1 / (1 + x)`
+///
+/// Every step of the second form is monotonic under rounding, while the quotient of
+/// two rounded values near 1.0 can step down as `x` grows.
+fn reinhard(x: f32) -> f32 {
+    1.0 - 1.0 / (1.0 + x)
+}
+
+/// Encodes a linear value with the sRGB transfer function (IEC 61966-2.1)
+///
+/// Negative values are encoded mirrored, so out-of-gamut samples keep their sign.
+pub(crate) fn srgb_encode(x: f32) -> f32 {
+    let magnitude = x.abs();
+    let encoded = if magnitude <= 0.003_130_8 {
+        magnitude * 12.92
+    } else {
+        1.055 * magnitude.powf(1.0 / 2.4) - 0.055
+    };
+    encoded.copysign(x)
+}
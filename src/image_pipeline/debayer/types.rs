@@ -1,5 +1,10 @@
 //! Types for debayering operations
 
+use crate::image_pipeline::raw::exif::ExifMetadata;
+use crate::image_pipeline::raw::orientation::Orientation;
+use crate::image_pipeline::debayer::denoise::{DenoiseStrength, chroma_blur, median_filter};
+use crate::image_pipeline::debayer::quantize::{ClipStats, OverflowMode, quantize};
+
 /// RGB image data after debayering
 #[derive(Debug, Clone)]
 pub struct RgbImageData {
@@ -11,4 +16,273 @@ pub struct RgbImageData {
     pub data: Vec<u16>,
     /// Actual bits per sample from the sensor (e.g., 12, 14, or 16)
     pub bits_per_sample: u32,
+    /// Capture settings carried over from the source RAW
+    pub exif: ExifMetadata,
+}
+
+/// Single-channel image derived from debayered RGB, such as its luminance
+#[derive(Debug, Clone)]
+pub struct GrayImageData {
+    /// Width of the image in pixels
+    pub width: usize,
+    /// Height of the image in pixels
+    pub height: usize,
+    /// One sample per pixel, row by row
+    pub data: Vec<u16>,
+    /// Bits per sample used out of the 16-bit container
+    pub bits_per_sample: u32,
+    /// Capture settings carried over from the source RAW
+    pub exif: ExifMetadata,
+}
+
+impl RgbImageData {
+    /// Collapses the RGB data to Rec.709 luminance (0.2126 R + 0.7152 G + 0.0722 B)
+    pub fn luminance(&self) -> Vec<u16> {
+        self.data
+            .chunks_exact(3)
+            .map(|px| {
+                let y = 0.2126 * px[0] as f32 + 0.7152 * px[1] as f32 + 0.0722 * px[2] as f32;
+                y.round().clamp(0.0, 65535.0) as u16
+            })
+            .collect()
+    }
+
+    /// `luminance` as a gray image with this image's dimensions, bit depth and EXIF
+    pub fn luminance_image(&self) -> GrayImageData {
+        GrayImageData {
+            width: self.width,
+            height: self.height,
+            data: self.luminance(),
+            bits_per_sample: self.bits_per_sample,
+            exif: self.exif,
+        }
+    }
+
+    /// Median-filters the image at `strength`, returning it unchanged for `None`
+    pub fn denoised(self, strength: Option<DenoiseStrength>) -> Self {
+        let Some(strength) = strength else {
+            return self;
+        };
+        RgbImageData {
+            data: median_filter(&self.data, self.width, self.height, 3, strength.radius()),
+            ..self
+        }
+    }
+
+    /// Blurs the chroma with a Gaussian of `radius` pixels, keeping luma, returning the
+    /// image unchanged for `None`
+    pub fn chroma_denoised(self, radius: Option<f32>) -> Self {
+        let Some(radius) = radius else {
+            return self;
+        };
+        let data: Vec<f32> = self.data.iter().map(|&v| v as f32).collect();
+        RgbImageData {
+            data: chroma_blur(&data, self.width, self.height, radius)
+                .into_iter()
+                .map(|v| v.round().clamp(0.0, 65535.0) as u16)
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Rotates or mirrors the image as `orientation` prescribes
+    pub fn oriented(self, orientation: Orientation) -> Self {
+        if orientation == Orientation::Normal {
+            return self;
+        }
+        let (width, height) = orientation.oriented_dimensions(self.width, self.height);
+        RgbImageData {
+            data: orientation.apply(&self.data, self.width, self.height, 3),
+            width,
+            height,
+            ..self
+        }
+    }
+
+    /// Copies the image into an `image` crate buffer, for use with its transforms
+    ///
+    /// # Panics
+    ///
+    /// If `data` does not hold `width * height` RGB pixels.
+    #[cfg(feature = "image-interop")]
+    pub fn to_image_buffer(&self) -> image::ImageBuffer<image::Rgb<u16>, Vec<u16>> {
+        image::ImageBuffer::from_raw(self.width as u32, self.height as u32, self.data.clone())
+            .expect("RGB data does not match the image dimensions")
+    }
+}
+
+/// Linear floating point RGB image data after debayering
+///
+/// Holds the color pipeline result before quantization, nominally in 0.0..=1.0
+/// but not clamped, so highlights above diffuse white are preserved.
+#[derive(Debug, Clone)]
+pub struct RgbImageDataF32 {
+    /// Width of the image in pixels
+    pub width: usize,
+    /// Height of the image in pixels
+    pub height: usize,
+    /// RGB pixel data interleaved [R, G, B, R, G, B, ...]
+    pub data: Vec<f32>,
+    /// Capture settings carried over from the source RAW
+    pub exif: ExifMetadata,
+}
+
+impl RgbImageDataF32 {
+    /// Quantizes to 16-bit RGB, clamping to 0.0..=1.0 and scaling to 0..=65535
+    pub fn to_u16(&self) -> RgbImageData {
+        RgbImageData {
+            width: self.width,
+            height: self.height,
+            data: self.data
+                .iter()
+                .map(|&v| (v.clamp(0.0, 1.0) * 65535.0) as u16)
+                .collect(),
+            bits_per_sample: 16,
+            exif: self.exif,
+        }
+    }
+
+    /// Quantizes to 16-bit RGB, handling out-of-range samples as `mode` says
+    ///
+    /// Fails only in `OverflowMode::Error` when too many pixels are out of range.
+    pub fn quantize(&self, mode: OverflowMode) -> anyhow::Result<(RgbImageData, ClipStats)> {
+        let (data, stats) = quantize(&self.data, 3, mode)?;
+        let image = RgbImageData {
+            width: self.width,
+            height: self.height,
+            data,
+            bits_per_sample: 16,
+            exif: self.exif,
+        };
+        Ok((image, stats))
+    }
+
+    /// Median-filters the image at `strength`, returning it unchanged for `None`
+    pub fn denoised(self, strength: Option<DenoiseStrength>) -> Self {
+        let Some(strength) = strength else {
+            return self;
+        };
+        RgbImageDataF32 {
+            data: median_filter(&self.data, self.width, self.height, 3, strength.radius()),
+            ..self
+        }
+    }
+
+    /// Blurs the chroma with a Gaussian of `radius` pixels, keeping luma, returning the
+    /// image unchanged for `None`
+    pub fn chroma_denoised(self, radius: Option<f32>) -> Self {
+        let Some(radius) = radius else {
+            return self;
+        };
+        RgbImageDataF32 {
+            data: chroma_blur(&self.data, self.width, self.height, radius),
+            ..self
+        }
+    }
+
+    /// Rotates or mirrors the image as `orientation` prescribes
+    pub fn oriented(self, orientation: Orientation) -> Self {
+        if orientation == Orientation::Normal {
+            return self;
+        }
+        let (width, height) = orientation.oriented_dimensions(self.width, self.height);
+        RgbImageDataF32 {
+            data: orientation.apply(&self.data, self.width, self.height, 3),
+            width,
+            height,
+            ..self
+        }
+    }
+}
+
+/// Demosaic algorithm used by the CPU debayer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebayerQuality {
+    /// Nearest neighbour replication (fastest, blocky)
+    NearestNeighbour,
+    /// Bilinear interpolation (default)
+    #[default]
+    Linear,
+    /// Bicubic interpolation (sharper, slower)
+    Cubic,
+    /// Malvar-He-Cutler 5x5 gradient-corrected interpolation (least zippering)
+    MalvarHeCutler,
+    /// Green interpolated along the smoother of the horizontal and vertical directions,
+    /// red and blue bilinear (fewest maze artifacts around point sources)
+    EdgeDirected,
+}
+
+/// Color space the CPU and NPP debayers convert camera RGB into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorTransform {
+    /// Camera to XYZ from the raw's color matrix, then XYZ to linear sRGB (D65)
+    #[default]
+    CameraToSrgb,
+    /// Black-subtracted, normalized and white-balanced camera channels with no color
+    /// matrix, for colorimetric analysis. `exposure` still applies; `saturation`, being
+    /// defined on sRGB luminance, does not
+    CameraNative,
+}
+
+/// Interpolation mode passed to NPP's `nppiCFAToRGB_16u_C1C3R`
+///
+/// Maps onto `NppiInterpolationMode`. NPP documents only `Undefined` for CFA conversion;
+/// whether other modes are honored or rejected with `NPP_INTERPOLATION_ERROR` depends on
+/// the NPP release installed on the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NppInterpolation {
+    /// `NPPI_INTER_UNDEFINED`, NPP's built-in CFA interpolation (default)
+    #[default]
+    Undefined,
+    /// `NPPI_INTER_NN`
+    NearestNeighbour,
+    /// `NPPI_INTER_LINEAR`
+    Linear,
+    /// `NPPI_INTER_CUBIC`
+    Cubic,
+    /// `NPPI_INTER_SUPER`
+    Super,
+    /// `NPPI_INTER_LANCZOS`
+    Lanczos,
+}
+
+/// Floating point precision of the NPP debayer's color pipeline
+///
+/// `F16` runs black level, white balance and the color matrix on half-precision samples,
+/// roughly halving the memory traffic of those stages on the Jetson. Half floats carry
+/// 11 significant bits, so each stage rounds to about 5e-4 of the value, and the color
+/// matrix amplifies the error of its inputs. Output differs from `F32` by up to about
+/// 4e-3 of full scale: one 8-bit step, but hundreds of 16-bit steps. Deep shadows of
+/// 14-bit and 16-bit raws also lose their lowest bits. Samples are handed back as `f32`
+/// either way. The CPU debayer always works in `f32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WorkingPrecision {
+    /// 32-bit float throughout (default)
+    #[default]
+    F32,
+    /// 16-bit half float from black level subtraction through the color matrix
+    F16,
+}
+
+/// Which debayer implementation the pipeline uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebayerBackend {
+    /// NPP on Jetson builds, CPU everywhere else
+    #[default]
+    Auto,
+    /// `CpuDebayer`
+    Cpu,
+    /// `NppDebayer`, only available on Jetson (`jetson_cuda`) builds
+    Npp,
+}
+
+impl DebayerBackend {
+    /// Resolves `Auto` to the concrete backend for this build
+    pub fn resolve(self) -> DebayerBackend {
+        match self {
+            DebayerBackend::Auto if cfg!(jetson_cuda) => DebayerBackend::Npp,
+            DebayerBackend::Auto => DebayerBackend::Cpu,
+            other => other,
+        }
+    }
 }
@@ -0,0 +1,136 @@
+//! White balance modes and the per-channel multipliers they produce
+
+use tracing::warn;
+use crate::image_pipeline::raw::types::RawImageData;
+
+/// How white balance multipliers are chosen
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WhiteBalance {
+    /// Use the camera's as-shot coefficients (`RawImageData::wb_coeffs`)
+    #[default]
+    AsShot,
+    /// Balance for a scene lit by a black body at `kelvin`, shifted by `tint`
+    ///
+    /// `kelvin` is the illuminant temperature, so as in raw developers a lower value
+    /// compensates for warmer light and renders the image cooler. Likewise a positive
+    /// `tint` compensates for a greener illuminant and renders the image more magenta
+    /// (Adobe scale, roughly 3000 units per unit of Duv).
+    Temperature { kelvin: f32, tint: f32 },
+}
+
+/// Valid range of the Kim et al. Planckian locus approximation
+const MIN_KELVIN: f32 = 1667.0;
+const MAX_KELVIN: f32 = 25000.0;
+
+/// Tint units per unit of Duv, matching the Adobe tint slider
+const TINT_SCALE: f32 = 3000.0;
+
+/// Green coefficient assumed when the camera's is zero, negative or not a number
+const DEFAULT_GREEN: f32 = 1.0;
+
+impl WhiteBalance {
+    /// Returns the [R, G, B] multipliers normalized so green is 1.0
+    pub fn multipliers(&self, raw_image: &RawImageData) -> [f32; 3] {
+        match *self {
+            WhiteBalance::AsShot => {
+                let wb = raw_image.wb_coeffs;
+                let green = green_coefficient(&wb);
+                [wb[0] / green, 1.0, wb[2] / green]
+            }
+            WhiteBalance::Temperature { kelvin, tint } => {
+                temperature_multipliers(raw_image, kelvin, tint)
+            }
+        }
+    }
+}
+
+/// `wb_coeffs[1]`, or 1.0 with a warning when the metadata left it zero, negative or
+/// `NaN`, so normalizing by green can't turn the whole image into inf or NaN
+pub(crate) fn green_coefficient(wb_coeffs: &[f32; 4]) -> f32 {
+    let green = wb_coeffs[1];
+    if green.is_finite() && green > 0.0 {
+        return green;
+    }
+    warn!("White balance green coefficient is {}, using {} instead", green, DEFAULT_GREEN);
+    DEFAULT_GREEN
+}
+
+/// Computes multipliers that map the camera response of the given illuminant to neutral.
+///
+/// The illuminant white point is taken from the Planckian locus, converted to XYZ and
+/// projected through the camera's `xyz_to_cam` matrix. Each multiplier is the reciprocal
+/// of that camera response, the same approach rawloader uses for its D65 fallback.
+fn temperature_multipliers(raw_image: &RawImageData, kelvin: f32, tint: f32) -> [f32; 3] {
+    let (x, y) = white_point_xy(kelvin, tint);
+    let white_xyz = [x / y, 1.0, (1.0 - x - y) / y];
+
+    let mut multipliers = [1.0f32; 3];
+    for (multiplier, row) in multipliers.iter_mut().zip(raw_image.xyz_to_cam.iter()) {
+        let response: f32 = row.iter().zip(white_xyz.iter()).map(|(m, v)| m * v).sum();
+        if response > 0.0 {
+            *multiplier = 1.0 / response;
+        }
+    }
+
+    let green = multipliers[1];
+    [multipliers[0] / green, 1.0, multipliers[2] / green]
+}
+
+/// CIE 1931 xy chromaticity of the black body at `kelvin`, shifted perpendicular
+/// to the locus by `tint`
+fn white_point_xy(kelvin: f32, tint: f32) -> (f32, f32) {
+    let kelvin = kelvin.clamp(MIN_KELVIN, MAX_KELVIN);
+    let (u, v) = xy_to_uv(planckian_xy(kelvin));
+
+    if tint == 0.0 {
+        return uv_to_xy(u, v);
+    }
+
+    // Tangent of the locus in CIE 1960 uv around the requested temperature
+    let t = kelvin.clamp(MIN_KELVIN + 10.0, MAX_KELVIN - 10.0);
+    let (u1, v1) = xy_to_uv(planckian_xy(t - 10.0));
+    let (u2, v2) = xy_to_uv(planckian_xy(t + 10.0));
+    let (du, dv) = (u2 - u1, v2 - v1);
+    let len = (du * du + dv * dv).sqrt();
+
+    // Unit normal pointing toward green (+v)
+    let (nu, nv) = if du < 0.0 { (dv / len, -du / len) } else { (-dv / len, du / len) };
+    let duv = tint / TINT_SCALE;
+
+    uv_to_xy(u + nu * duv, v + nv * duv)
+}
+
+/// Kim et al. cubic spline approximation of the Planckian locus (1667K..=25000K)
+fn planckian_xy(kelvin: f32) -> (f32, f32) {
+    let t = kelvin as f64;
+    let (t2, t3) = (t * t, t * t * t);
+
+    let x = if t <= 4000.0 {
+        -0.2661239e9 / t3 - 0.2343589e6 / t2 + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t3 + 2.1070379e6 / t2 + 0.2226347e3 / t + 0.240390
+    };
+
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222.0 {
+        -1.1063814 * x3 - 1.34811020 * x2 + 2.18555832 * x - 0.20219683
+    } else if t <= 4000.0 {
+        -0.9549476 * x3 - 1.37418593 * x2 + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x3 - 5.87338670 * x2 + 3.75112997 * x - 0.37001483
+    };
+
+    (x as f32, y as f32)
+}
+
+/// CIE 1931 xy to CIE 1960 uv
+fn xy_to_uv((x, y): (f32, f32)) -> (f32, f32) {
+    let d = -2.0 * x + 12.0 * y + 3.0;
+    (4.0 * x / d, 6.0 * y / d)
+}
+
+/// CIE 1960 uv to CIE 1931 xy
+fn uv_to_xy(u: f32, v: f32) -> (f32, f32) {
+    let d = 2.0 * u - 8.0 * v + 4.0;
+    (3.0 * u / d, 2.0 * v / d)
+}
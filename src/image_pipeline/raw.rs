@@ -4,8 +4,22 @@
 
 mod reader;
 mod rawloader_reader;
+mod sniff;
 pub mod types;
+pub mod corrections;
+pub mod exif;
+pub mod orientation;
+pub mod profile;
+pub mod stack;
+pub mod cfa;
+pub mod preview;
 
 pub use reader::RawImageReader;
 pub use rawloader_reader::RawLoaderReader;
-pub use types::RawImageData;
+pub use types::{GainMap, RawImageData, RawImageInfo};
+pub use exif::ExifMetadata;
+pub use orientation::Orientation;
+pub use profile::CameraProfiles;
+pub use stack::stack_average;
+pub use cfa::split_cfa;
+pub use preview::extract_preview;
@@ -0,0 +1,59 @@
+//! Per-site planes of a Bayer mosaic for sensor calibration
+
+use tracing::debug;
+use crate::image_pipeline::common::error::{ConversionError, Result};
+use crate::image_pipeline::raw::types::RawImageData;
+
+/// RGGB site of each plane `split_cfa` returns, as (row, column) within a 2x2 cell,
+/// with its index into the `[R, G, B, E]` level arrays
+const SITES: [((usize, usize), usize); 4] = [((0, 0), 0), ((0, 1), 1), ((1, 0), 3), ((1, 1), 2)];
+
+/// Splits an RGGB mosaic into its four CFA planes `[R, G1, G2, B]` without demosaicing
+///
+/// Each plane is a quarter-resolution grayscale image holding one site of every 2x2
+/// cell, G1 being the green on red rows and G2 the green on blue rows. A trailing odd row
+/// or column is dropped. Planes keep one sample per pixel (`is_bayer` stays set, so
+/// they can be written as `OutputMode::BayerGray` but are not meant to be debayered) and
+/// the source metadata, with the black and white levels of their own site in every slot.
+/// The gain map is not carried over. Fails with `UnsupportedFormat` for RGB raws,
+/// `InvalidDimensions` for mosaics without a whole 2x2 cell, and `DataLengthMismatch`
+/// when the buffer is shorter than the dimensions.
+pub fn split_cfa(image: &RawImageData) -> Result<[RawImageData; 4]> {
+    if !image.is_bayer {
+        return Err(ConversionError::UnsupportedFormat(
+            "CFA planes of an RGB raw, expected a Bayer mosaic".to_string(),
+        ));
+    }
+    let (width, height) = (image.width / 2, image.height / 2);
+    if width == 0 || height == 0 {
+        return Err(ConversionError::InvalidDimensions(image.width, image.height));
+    }
+    if image.data.len() < image.width * image.height {
+        return Err(ConversionError::DataLengthMismatch {
+            expected: image.width * image.height,
+            actual: image.data.len(),
+        });
+    }
+
+    debug!("Splitting {}x{} mosaic into {}x{} CFA planes", image.width, image.height, width, height);
+    Ok(SITES.map(|((row, column), level)| RawImageData {
+        width,
+        height,
+        data: (0..height)
+            .flat_map(|y| {
+                let start = (2 * y + row) * image.width + column;
+                image.data[start..].iter().step_by(2).take(width).copied()
+            })
+            .collect(),
+        is_bayer: true,
+        bits_per_sample: image.bits_per_sample,
+        wb_coeffs: image.wb_coeffs,
+        blacklevels: [image.blacklevels[level]; 4],
+        whitelevels: [image.whitelevels[level]; 4],
+        cam_to_xyz: image.cam_to_xyz,
+        xyz_to_cam: image.xyz_to_cam,
+        exif: image.exif,
+        orientation: image.orientation,
+        gain_map: None,
+    }))
+}
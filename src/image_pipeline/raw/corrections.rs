@@ -0,0 +1,271 @@
+//! Raw-domain corrections applied to Bayer data before demosaicing
+
+use tracing::{debug, warn};
+use crate::image_pipeline::raw::types::RawImageData;
+
+/// Subtracts the black level from a raw sample, clamping at zero
+///
+/// Samples below the black level are sensor noise around the baseline; a plain `u16`
+/// subtraction would wrap them to near 65535, so every raw-domain black subtraction
+/// goes through here.
+#[inline]
+pub fn subtract_black(value: u16, black: u16) -> u16 {
+    value.saturating_sub(black)
+}
+
+/// Applies a radial vignetting correction in place.
+///
+/// Each pixel above the black level is scaled by `1 + k1*r^2 + k2*r^4`, where `r` is the
+/// distance from the image center normalized so the corners are at `r = 1`. Results are
+/// clamped to the white level so corrected highlights saturate instead of wrapping.
+pub fn correct_vignetting(image: &mut RawImageData, [k1, k2]: [f32; 2]) {
+    let cx = (image.width as f32 - 1.0) / 2.0;
+    let cy = (image.height as f32 - 1.0) / 2.0;
+    let corner_sq = cx * cx + cy * cy;
+    if corner_sq == 0.0 {
+        return;
+    }
+
+    debug!("Correcting vignetting with k1={}, k2={}", k1, k2);
+
+    let black = image.blacklevels[0];
+    let white = match image.whitelevels[0] {
+        0 => u16::MAX as f32,
+        level => level as f32,
+    };
+
+    let samples_per_pixel = image.samples_per_pixel();
+    for (y, row) in image.data.chunks_exact_mut(image.width * samples_per_pixel).enumerate() {
+        let dy_sq = (y as f32 - cy).powi(2);
+        for (i, value) in row.iter_mut().enumerate() {
+            let x = i / samples_per_pixel;
+            let r_sq = ((x as f32 - cx).powi(2) + dy_sq) / corner_sq;
+            let gain = 1.0 + k1 * r_sq + k2 * r_sq * r_sq;
+
+            let signal = subtract_black(*value, black);
+            if signal > 0 {
+                *value = (black as f32 + signal as f32 * gain).round().clamp(0.0, white) as u16;
+            }
+        }
+    }
+}
+
+/// Applies and clears the image's lens shading gain map, if it has one.
+///
+/// Each sample above the black level has its signal scaled by the map's gain at its
+/// pixel, clamped to the white level as in `correct_vignetting`. A map without one gain
+/// per grid point is dropped with a warning. Returns whether a map was applied.
+pub fn apply_gain_map(image: &mut RawImageData) -> bool {
+    let Some(map) = image.gain_map.take() else {
+        return false;
+    };
+    if !map.is_valid() {
+        warn!(
+            "Ignoring gain map with {} gains for a {}x{} grid",
+            map.gains.len(),
+            map.points.0,
+            map.points.1
+        );
+        return false;
+    }
+
+    debug!("Applying {}x{} gain map", map.points.0, map.points.1);
+
+    let black = image.blacklevels[0];
+    let white = match image.whitelevels[0] {
+        0 => u16::MAX as f32,
+        level => level as f32,
+    };
+
+    let (width, height) = (image.width, image.height);
+    let samples_per_pixel = image.samples_per_pixel();
+    for (y, row) in image.data.chunks_exact_mut(width * samples_per_pixel).enumerate() {
+        for (x, pixel) in row.chunks_exact_mut(samples_per_pixel).enumerate() {
+            let gain = map.gain_at(x, y, width, height);
+            for value in pixel {
+                let signal = subtract_black(*value, black);
+                if signal > 0 {
+                    *value = (black as f32 + signal as f32 * gain).round().clamp(0.0, white) as u16;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Replaces each listed column with the mean of its nearest same-color neighbours.
+///
+/// In a Bayer mosaic the neighbours are two columns away on either side, so each row
+/// interpolates from pixels of its own color; for RGB data they are the adjacent
+/// columns, channel by channel. Neighbours that are listed themselves are skipped in
+/// favour of the next same-color column out, and a column with good neighbours on one
+/// side only copies that side. Columns past the image width are ignored.
+pub fn correct_bad_columns(image: &mut RawImageData, columns: &[usize]) {
+    let width = image.width;
+    let step = if image.is_bayer { 2 } else { 1 };
+    let samples_per_pixel = image.samples_per_pixel();
+    let is_bad = |x: usize| columns.contains(&x);
+
+    for &column in columns {
+        if column >= width {
+            warn!("Ignoring bad column {} outside the {} pixel wide image", column, width);
+            continue;
+        }
+
+        let left = (1..)
+            .map(|n| column.checked_sub(n * step))
+            .take_while(Option::is_some)
+            .flatten()
+            .find(|&x| !is_bad(x));
+        let right = (1..)
+            .map(|n| column + n * step)
+            .take_while(|&x| x < width)
+            .find(|&x| !is_bad(x));
+        let neighbours: Vec<usize> = left.into_iter().chain(right).collect();
+        if neighbours.is_empty() {
+            warn!("Bad column {} has no good same-color neighbours", column);
+            continue;
+        }
+
+        debug!("Interpolating bad column {} from columns {:?}", column, neighbours);
+
+        let count = neighbours.len() as u32;
+        for row in image.data.chunks_exact_mut(width * samples_per_pixel) {
+            for c in 0..samples_per_pixel {
+                let sum: u32 = neighbours.iter().map(|&x| row[x * samples_per_pixel + c] as u32).sum();
+                row[column * samples_per_pixel + c] = ((sum + count / 2) / count) as u16;
+            }
+        }
+    }
+}
+
+/// Balances the two green sites of an RGGB mosaic in place.
+///
+/// `wb_coeffs[1]` belongs to the green on the red rows (even row, odd column) and
+/// `wb_coeffs[3]` to the green on the blue rows (odd row, even column). The latter is
+/// scaled above the black level by `wb_coeffs[3] / wb_coeffs[1]`, so after this both
+/// greens carry the G1 balance and the debayer's [R, G, B] multipliers apply unchanged.
+/// Does nothing when the camera reports no separate second green (`NaN`, zero or equal)
+/// or the image is not a Bayer mosaic.
+pub fn balance_green_sites(image: &mut RawImageData) {
+    let [_, g1, _, g2] = image.wb_coeffs;
+    if !(image.is_bayer && g1.is_finite() && g2.is_finite() && g1 > 0.0 && g2 > 0.0) || g1 == g2 {
+        return;
+    }
+    let gain = g2 / g1;
+
+    debug!("Balancing second green sites by {}", gain);
+
+    let black = image.blacklevels[1];
+    let white = match image.whitelevels[1] {
+        0 => u16::MAX as f32,
+        level => level as f32,
+    };
+
+    for row in image.data.chunks_exact_mut(image.width).skip(1).step_by(2) {
+        for value in row.iter_mut().step_by(2) {
+            let signal = subtract_black(*value, black);
+            if signal > 0 {
+                *value = (black as f32 + signal as f32 * gain).round().clamp(0.0, white) as u16;
+            }
+        }
+    }
+}
+
+/// Shifts the second green sites of an RGGB mosaic onto the first by their measured means.
+///
+/// Unlike `balance_green_sites`, which trusts the camera's coefficients, this measures the
+/// green split from the frame itself: the mean of the G1 sites (even row, odd column) and
+/// of the G2 sites (odd row, even column) are taken over unclipped samples, and their
+/// difference is added to every unclipped G2 sample, clamped to the black and white
+/// levels; clipped samples stay at white. Returns the applied offset, or `None` for
+/// non-Bayer images and frames without unclipped greens.
+pub fn equalize_green_sites(image: &mut RawImageData) -> Option<f32> {
+    if !image.is_bayer || image.width < 2 || image.height < 2 {
+        return None;
+    }
+
+    let black = image.blacklevels[1] as f32;
+    let white = match image.whitelevels[1] {
+        0 => u16::MAX,
+        level => level,
+    };
+    let width = image.width;
+    let mean = |row_parity: usize, column_parity: usize| {
+        let (sum, count) = image
+            .data
+            .chunks_exact(width)
+            .skip(row_parity)
+            .step_by(2)
+            .flat_map(|row| row.iter().skip(column_parity).step_by(2))
+            .filter(|&&value| value < white)
+            .fold((0u64, 0u64), |(sum, count), &value| (sum + value as u64, count + 1));
+        (count > 0).then(|| sum as f64 / count as f64)
+    };
+    let offset = (mean(0, 1)? - mean(1, 0)?) as f32;
+    if offset == 0.0 {
+        return Some(0.0);
+    }
+
+    debug!("Equalizing second green sites by {:+.2}", offset);
+
+    for row in image.data.chunks_exact_mut(width).skip(1).step_by(2) {
+        for value in row.iter_mut().step_by(2).filter(|value| **value < white) {
+            *value = (*value as f32 + offset).round().clamp(black, white as f32) as u16;
+        }
+    }
+    Some(offset)
+}
+
+/// Fraction of samples at or below the estimated black level
+const BLACK_PERCENTILE: f64 = 0.001;
+/// Fraction of samples at or below the estimated white level
+const WHITE_PERCENTILE: f64 = 0.999;
+
+/// Replaces missing or inconsistent black/white levels with estimates from the histogram.
+///
+/// Levels are considered invalid when every white level is zero or any white level is at
+/// or below its black level, which would collapse the debayer's normalization range. The
+/// black level is then taken from the darkest 0.1% of samples and the white level from
+/// the brightest 0.1%, falling back to the bit-depth maximum if the two would meet.
+/// Returns whether the levels were estimated.
+pub fn estimate_missing_levels(image: &mut RawImageData) -> bool {
+    let valid = image.whitelevels.iter().any(|&white| white != 0)
+        && image.whitelevels.iter().zip(image.blacklevels).all(|(&white, black)| white > black);
+    if valid || image.data.is_empty() {
+        return false;
+    }
+
+    let mut histogram = vec![0usize; u16::MAX as usize + 1];
+    for &value in &image.data {
+        histogram[value as usize] += 1;
+    }
+
+    let percentile = |fraction: f64| {
+        let target = ((image.data.len() as f64 * fraction).ceil() as usize).max(1);
+        let mut seen = 0;
+        histogram
+            .iter()
+            .position(|&count| {
+                seen += count;
+                seen >= target
+            })
+            .unwrap_or(u16::MAX as usize) as u16
+    };
+
+    let black = percentile(BLACK_PERCENTILE);
+    let mut white = percentile(WHITE_PERCENTILE);
+    if white <= black {
+        white = ((1u32 << image.bits_per_sample.min(16)) - 1) as u16;
+    }
+    let white = white.max(black.saturating_add(1));
+
+    warn!(
+        "Invalid levels (black {:?}, white {:?}), estimated black {} and white {} from the histogram",
+        image.blacklevels, image.whitelevels, black, white
+    );
+
+    image.blacklevels = [black; 4];
+    image.whitelevels = [white; 4];
+    true
+}
@@ -0,0 +1,390 @@
+//! Minimal EXIF extraction from TIFF-based RAW containers (ARW, CR2, NEF, DNG, ...)
+//!
+//! rawloader does not expose the EXIF IFD, so the handful of capture settings we carry
+//! into the output are read directly from the container, along with the DNG tags the
+//! pipeline uses. Non-TIFF formats simply yield empty metadata.
+
+use crate::image_pipeline::raw::types::GainMap;
+
+/// EXIF capture settings carried from the source RAW into the output
+///
+/// Rationals are stored as `(numerator, denominator)` exactly as found in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExifMetadata {
+    /// ExposureTime (0x829A) in seconds
+    pub exposure_time: Option<(u32, u32)>,
+    /// FNumber (0x829D)
+    pub f_number: Option<(u32, u32)>,
+    /// ISOSpeedRatings (0x8827)
+    pub iso: Option<u16>,
+    /// FocalLength (0x920A) in millimetres
+    pub focal_length: Option<(u32, u32)>,
+}
+
+impl ExifMetadata {
+    pub const EXPOSURE_TIME: u16 = 0x829A;
+    pub const F_NUMBER: u16 = 0x829D;
+    pub const ISO_SPEED_RATINGS: u16 = 0x8827;
+    pub const FOCAL_LENGTH: u16 = 0x920A;
+
+    /// Whether none of the tags were found
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+const EXIF_IFD_POINTER: u16 = 0x8769;
+/// DNGVersion, present in IFD0 of every DNG
+const DNG_VERSION: u16 = 0xC612;
+/// AsShotNeutral, the camera's response to the as-shot white point
+const AS_SHOT_NEUTRAL: u16 = 0xC628;
+/// SubIFDs, holding the raw image of most DNGs
+const SUB_IFDS: u16 = 0x014A;
+/// OpcodeList2, processing applied to the raw data after linearization
+const OPCODE_LIST_2: u16 = 0xC741;
+/// DNG opcode ID of GainMap
+const OPCODE_GAIN_MAP: u32 = 9;
+/// Bytes of GainMap parameters ahead of the gains
+const GAIN_MAP_HEADER_LEN: usize = 76;
+
+/// NewSubfileType, Compression and the strip location of an image
+const NEW_SUBFILE_TYPE: u16 = 0x00FE;
+const COMPRESSION: u16 = 0x0103;
+const STRIP_OFFSETS: u16 = 0x0111;
+const STRIP_BYTE_COUNTS: u16 = 0x0117;
+/// JPEGInterchangeFormat and its length, locating the JPEG previews of most cameras
+const JPEG_INTERCHANGE_FORMAT: u16 = 0x0201;
+const JPEG_INTERCHANGE_FORMAT_LENGTH: u16 = 0x0202;
+/// Most IFDs `TiffReader::ifds` visits, against loops in malformed files
+const MAX_IFDS: usize = 64;
+
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+const TYPE_RATIONAL: u16 = 5;
+const TYPE_UNDEFINED: u16 = 7;
+const TYPE_IFD: u16 = 13;
+
+/// Reads the EXIF capture settings from a TIFF-structured file
+///
+/// Looks for the EXIF IFD pointer in IFD0. Anything malformed or missing is skipped,
+/// so this never fails; at worst the result is empty.
+pub fn read_exif(data: &[u8]) -> ExifMetadata {
+    let mut exif = ExifMetadata::default();
+
+    let Some(reader) = TiffReader::new(data) else {
+        return exif;
+    };
+    let Some(ifd0) = reader.u32(4) else {
+        return exif;
+    };
+    let Some(exif_ifd) = reader
+        .entries(ifd0 as usize)
+        .find(|entry| entry.tag == EXIF_IFD_POINTER)
+        .and_then(|entry| reader.unsigned(&entry))
+    else {
+        return exif;
+    };
+
+    for entry in reader.entries(exif_ifd as usize) {
+        match entry.tag {
+            ExifMetadata::EXPOSURE_TIME => exif.exposure_time = reader.rational(&entry),
+            ExifMetadata::F_NUMBER => exif.f_number = reader.rational(&entry),
+            ExifMetadata::ISO_SPEED_RATINGS => {
+                exif.iso = reader.unsigned(&entry).and_then(|v| u16::try_from(v).ok())
+            }
+            ExifMetadata::FOCAL_LENGTH => exif.focal_length = reader.rational(&entry),
+            _ => {}
+        }
+    }
+
+    exif
+}
+
+/// Reads a DNG's `AsShotNeutral` from IFD0
+///
+/// Returns the [R, G, B] camera-space neutral, or `None` for files that are not DNGs
+/// (no `DNGVersion` tag), that have no neutral, or whose neutral is not three positive
+/// finite values.
+pub fn read_as_shot_neutral(data: &[u8]) -> Option<[f32; 3]> {
+    let reader = TiffReader::new(data)?;
+    let ifd0 = reader.u32(4)? as usize;
+    if !reader.entries(ifd0).any(|entry| entry.tag == DNG_VERSION) {
+        return None;
+    }
+    let entry = reader.entries(ifd0).find(|entry| entry.tag == AS_SHOT_NEUTRAL)?;
+    if entry.count != 3 {
+        return None;
+    }
+    let neutral = match entry.field_type {
+        TYPE_RATIONAL => {
+            let offset = reader.u32_from(entry.value) as usize;
+            let value = |i: usize| {
+                let at = offset + 8 * i;
+                Some(reader.u32(at)? as f32 / reader.u32(at + 4)? as f32)
+            };
+            [value(0)?, value(1)?, value(2)?]
+        }
+        // Three SHORTs don't fit the 4-byte value field either, so they sit at an offset
+        TYPE_SHORT => {
+            let offset = reader.u32_from(entry.value) as usize;
+            let value = |i: usize| Some(reader.u16(offset + 2 * i)? as f32);
+            [value(0)?, value(1)?, value(2)?]
+        }
+        _ => return None,
+    };
+    neutral.iter().all(|v| v.is_finite() && *v > 0.0).then_some(neutral)
+}
+
+/// Reads the first whole-image `GainMap` opcode of a DNG's `OpcodeList2`
+///
+/// The opcode list is looked for in IFD0 and its SubIFDs. Only maps applying to every
+/// sample alike are taken: the area must start at the origin with a row and column pitch
+/// of 1 and the map must have one plane, which leaves out the per-CFA-site maps some
+/// cameras write as four opcodes. Returns `None` for non-DNGs and anything malformed.
+pub fn read_gain_map(data: &[u8]) -> Option<GainMap> {
+    let reader = TiffReader::new(data)?;
+    let ifd0 = reader.u32(4)? as usize;
+    if !reader.entries(ifd0).any(|entry| entry.tag == DNG_VERSION) {
+        return None;
+    }
+    std::iter::once(ifd0)
+        .chain(reader.sub_ifds(ifd0))
+        .filter_map(|ifd| reader.entries(ifd).find(|entry| entry.tag == OPCODE_LIST_2))
+        .filter_map(|entry| reader.bytes(&entry))
+        .find_map(gain_map_opcode)
+}
+
+/// Largest displayable JPEG embedded in a TIFF-based RAW
+///
+/// Every IFD of the IFD0 chain and their SubIFDs is searched for images located by
+/// `JPEGInterchangeFormat` or stored as a single strip. Only baseline and progressive
+/// JPEGs are taken, so the lossless JPEG raw data of CR2s and DNGs is never returned. The
+/// largest one is usually the full-size preview. Returns `None` for other containers and
+/// files without a preview.
+pub fn read_jpeg_preview(data: &[u8]) -> Option<&[u8]> {
+    let reader = TiffReader::new(data)?;
+    reader
+        .ifds()
+        .into_iter()
+        .flat_map(|ifd| reader.embedded_images(ifd))
+        .filter(|image| is_displayable_jpeg(image))
+        .max_by_key(|image| image.len())
+}
+
+/// Whether `data` is a JPEG whose first frame header is baseline or progressive DCT
+fn is_displayable_jpeg(data: &[u8]) -> bool {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return false;
+    }
+    let mut at = 2;
+    while let Some(&[0xFF, marker]) = data.get(at..at + 2) {
+        match marker {
+            0xC0..=0xC2 => return true,
+            // Lossless, hierarchical and arithmetic frames, or image data before any frame
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xD9 | 0xDA => return false,
+            // Fill byte ahead of a marker
+            0xFF => at += 1,
+            _ => match data.get(at + 2..at + 4) {
+                Some(len) => at += 2 + u16::from_be_bytes([len[0], len[1]]) as usize,
+                None => return false,
+            },
+        }
+    }
+    false
+}
+
+/// First usable GainMap in an opcode list, which DNG always stores big-endian
+fn gain_map_opcode(list: &[u8]) -> Option<GainMap> {
+    let u32_at = |at: usize| Some(u32::from_be_bytes(list.get(at..at + 4)?.try_into().ok()?));
+    let count = u32_at(0)?;
+    let mut at = 4;
+    for _ in 0..count {
+        let id = u32_at(at)?;
+        let len = u32_at(at + 12)? as usize;
+        let params = list.get(at + 16..at + 16 + len)?;
+        let map = (id == OPCODE_GAIN_MAP).then(|| parse_gain_map(params)).flatten();
+        if map.is_some() {
+            return map;
+        }
+        at += 16 + len;
+    }
+    None
+}
+
+fn parse_gain_map(params: &[u8]) -> Option<GainMap> {
+    let u32_at = |at: usize| Some(u32::from_be_bytes(params.get(at..at + 4)?.try_into().ok()?));
+    let f64_at = |at: usize| Some(f64::from_be_bytes(params.get(at..at + 8)?.try_into().ok()?));
+    let [top, left, _bottom, _right, _plane, _planes, row_pitch, col_pitch, points_v, points_h] =
+        std::array::from_fn(|i| u32_at(4 * i));
+    if top? != 0 || left? != 0 || row_pitch? != 1 || col_pitch? != 1 || u32_at(72)? != 1 {
+        return None;
+    }
+    let points = (points_v? as usize, points_h? as usize);
+    let gains = params
+        .get(GAIN_MAP_HEADER_LEN..)?
+        .chunks_exact(4)
+        .take(points.0 * points.1)
+        .map(|b| f32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    let map = GainMap {
+        points,
+        origin: (f64_at(56)?, f64_at(64)?),
+        spacing: (f64_at(40)?, f64_at(48)?),
+        gains,
+    };
+    map.is_valid().then_some(map)
+}
+
+/// One 12-byte IFD entry; `value` is the raw 4-byte value/offset field
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    value: [u8; 4],
+}
+
+struct TiffReader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> TiffReader<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(..4)? {
+            [b'I', b'I', 42, 0] => true,
+            [b'M', b'M', 0, 42] => false,
+            _ => return None,
+        };
+        Some(Self { data, little_endian })
+    }
+
+    fn u16_from(&self, bytes: [u8; 2]) -> u16 {
+        if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) }
+    }
+
+    fn u32_from(&self, bytes: [u8; 4]) -> u32 {
+        if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) }
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset + 2)?;
+        Some(self.u16_from([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?;
+        Some(self.u32_from([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn entries(&self, ifd_offset: usize) -> impl Iterator<Item = IfdEntry> + '_ {
+        let count = self.u16(ifd_offset).unwrap_or(0) as usize;
+        (0..count).filter_map(move |i| {
+            let at = ifd_offset + 2 + i * 12;
+            let value = self.data.get(at + 8..at + 12)?;
+            Some(IfdEntry {
+                tag: self.u16(at)?,
+                field_type: self.u16(at + 2)?,
+                count: self.u32(at + 4)?,
+                value: [value[0], value[1], value[2], value[3]],
+            })
+        })
+    }
+
+    /// Single SHORT, LONG or IFD value, stored inline in the entry
+    fn unsigned(&self, entry: &IfdEntry) -> Option<u32> {
+        if entry.count < 1 {
+            return None;
+        }
+        match entry.field_type {
+            TYPE_SHORT => Some(self.u16_from([entry.value[0], entry.value[1]]) as u32),
+            TYPE_LONG | TYPE_IFD => Some(self.u32_from(entry.value)),
+            _ => None,
+        }
+    }
+
+    /// Offsets of the IFDs listed in the SubIFDs tag of `ifd_offset`
+    fn sub_ifds(&self, ifd_offset: usize) -> Vec<usize> {
+        let Some(entry) = self.entries(ifd_offset).find(|entry| entry.tag == SUB_IFDS) else {
+            return Vec::new();
+        };
+        if !matches!(entry.field_type, TYPE_LONG | TYPE_IFD) {
+            return Vec::new();
+        }
+        if entry.count == 1 {
+            return vec![self.u32_from(entry.value) as usize];
+        }
+        let offset = self.u32_from(entry.value) as usize;
+        (0..entry.count as usize)
+            .map_while(|i| self.u32(offset + 4 * i).map(|v| v as usize))
+            .collect()
+    }
+
+    /// Offset of the IFD following `ifd_offset` in its chain, 0 at the end
+    fn next_ifd(&self, ifd_offset: usize) -> Option<usize> {
+        let count = self.u16(ifd_offset)? as usize;
+        self.u32(ifd_offset + 2 + count * 12).map(|v| v as usize)
+    }
+
+    /// Offsets of the IFD0 chain and the SubIFDs of each, without repeats
+    fn ifds(&self) -> Vec<usize> {
+        let mut ifds: Vec<usize> = Vec::new();
+        let mut next = self.u32(4).map(|v| v as usize);
+        while let Some(ifd) = next.filter(|&ifd| ifd != 0 && !ifds.contains(&ifd)) {
+            if ifds.len() >= MAX_IFDS {
+                break;
+            }
+            ifds.push(ifd);
+            for sub_ifd in self.sub_ifds(ifd) {
+                if ifds.len() < MAX_IFDS && sub_ifd != 0 && !ifds.contains(&sub_ifd) {
+                    ifds.push(sub_ifd);
+                }
+            }
+            next = self.next_ifd(ifd);
+        }
+        ifds
+    }
+
+    /// Bytes of the images in `ifd_offset` located by `JPEGInterchangeFormat` or, when it
+    /// is JPEG compressed or a reduced-resolution copy, by a single strip
+    fn embedded_images(&self, ifd_offset: usize) -> Vec<&'a [u8]> {
+        let value = |tag: u16| {
+            self.entries(ifd_offset)
+                .find(|entry| entry.tag == tag && entry.count == 1)
+                .and_then(|entry| self.unsigned(&entry))
+        };
+        let slice = |offset: u32, len: u32| {
+            let start = offset as usize;
+            self.data.get(start..start.checked_add(len as usize)?)
+        };
+
+        let mut images = Vec::new();
+        if let (Some(offset), Some(len)) = (value(JPEG_INTERCHANGE_FORMAT), value(JPEG_INTERCHANGE_FORMAT_LENGTH)) {
+            images.extend(slice(offset, len));
+        }
+        let reduced = value(NEW_SUBFILE_TYPE).is_some_and(|kind| kind & 1 == 1);
+        let jpeg = matches!(value(COMPRESSION), Some(6 | 7));
+        if let (true, Some(offset), Some(len)) = (reduced || jpeg, value(STRIP_OFFSETS), value(STRIP_BYTE_COUNTS)) {
+            images.extend(slice(offset, len));
+        }
+        images
+    }
+
+    /// UNDEFINED bytes of an entry stored at its offset; `None` for the four bytes or
+    /// fewer that would sit inline
+    fn bytes(&self, entry: &IfdEntry) -> Option<&'a [u8]> {
+        if entry.field_type != TYPE_UNDEFINED || entry.count <= 4 {
+            return None;
+        }
+        let offset = self.u32_from(entry.value) as usize;
+        self.data.get(offset..offset + entry.count as usize)
+    }
+
+    /// Single RATIONAL value, stored at the offset held in the entry
+    fn rational(&self, entry: &IfdEntry) -> Option<(u32, u32)> {
+        if entry.field_type != TYPE_RATIONAL || entry.count < 1 {
+            return None;
+        }
+        let offset = self.u32_from(entry.value) as usize;
+        Some((self.u32(offset)?, self.u32(offset + 4)?))
+    }
+}
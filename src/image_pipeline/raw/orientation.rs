@@ -0,0 +1,86 @@
+//! Image orientation recorded by the camera (TIFF/EXIF tag 0x0112)
+
+/// How the stored pixels must be transformed to display upright
+///
+/// Variant names follow the transform applied for display, in the order of the
+/// tag values 1..=8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Orientation {
+    /// 1: stored upright
+    #[default]
+    Normal,
+    /// 2: mirror left-right
+    FlipHorizontal,
+    /// 3: rotate 180°
+    Rotate180,
+    /// 4: mirror top-bottom
+    FlipVertical,
+    /// 5: mirror across the top-left to bottom-right diagonal
+    Transpose,
+    /// 6: rotate 90° clockwise
+    Rotate90,
+    /// 7: mirror across the top-right to bottom-left diagonal
+    Transverse,
+    /// 8: rotate 90° counter-clockwise
+    Rotate270,
+}
+
+impl Orientation {
+    /// Maps an Orientation tag value, treating missing or invalid values as `Normal`
+    pub fn from_tag(value: u16) -> Self {
+        match value {
+            2 => Orientation::FlipHorizontal,
+            3 => Orientation::Rotate180,
+            4 => Orientation::FlipVertical,
+            5 => Orientation::Transpose,
+            6 => Orientation::Rotate90,
+            7 => Orientation::Transverse,
+            8 => Orientation::Rotate270,
+            _ => Orientation::Normal,
+        }
+    }
+
+    /// Whether applying this orientation swaps width and height
+    pub fn swaps_dimensions(self) -> bool {
+        matches!(
+            self,
+            Orientation::Transpose | Orientation::Rotate90 | Orientation::Transverse | Orientation::Rotate270
+        )
+    }
+
+    /// Width and height of a `width`x`height` image after applying this orientation
+    pub fn oriented_dimensions(self, width: usize, height: usize) -> (usize, usize) {
+        if self.swaps_dimensions() { (height, width) } else { (width, height) }
+    }
+
+    /// Applies the orientation to interleaved pixel data with `channels` samples per pixel
+    ///
+    /// Returns the transformed samples; their dimensions are `oriented_dimensions`.
+    pub fn apply<T: Copy>(self, data: &[T], width: usize, height: usize, channels: usize) -> Vec<T> {
+        if self == Orientation::Normal {
+            return data.to_vec();
+        }
+
+        let (out_width, out_height) = self.oriented_dimensions(width, height);
+        let (last_x, last_y) = (width.saturating_sub(1), height.saturating_sub(1));
+
+        let mut out = Vec::with_capacity(data.len());
+        for y in 0..out_height {
+            for x in 0..out_width {
+                let (src_x, src_y) = match self {
+                    Orientation::Normal => (x, y),
+                    Orientation::FlipHorizontal => (last_x - x, y),
+                    Orientation::Rotate180 => (last_x - x, last_y - y),
+                    Orientation::FlipVertical => (x, last_y - y),
+                    Orientation::Transpose => (y, x),
+                    Orientation::Rotate90 => (y, last_y - x),
+                    Orientation::Transverse => (last_x - y, last_y - x),
+                    Orientation::Rotate270 => (last_x - y, x),
+                };
+                let src = (src_y * width + src_x) * channels;
+                out.extend_from_slice(&data[src..src + channels]);
+            }
+        }
+        out
+    }
+}
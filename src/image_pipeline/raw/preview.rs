@@ -0,0 +1,27 @@
+//! Embedded JPEG previews of RAW files
+
+use tracing::debug;
+use crate::image_pipeline::common::error::{ConversionError, Result};
+use crate::image_pipeline::raw::exif::read_jpeg_preview;
+use crate::image_pipeline::raw::sniff::{self, InputKind};
+
+/// Bytes of the largest JPEG preview embedded in a RAW file, without decoding the raw data
+///
+/// The camera's own rendering is found by scanning the IFDs of TIFF-based containers
+/// (ARW, CR2, NEF, DNG, ...) and returned as a standalone JPEG file, ready to write out or
+/// decode for a quick look. Fails with `UnsupportedFormat` for inputs that aren't RAW
+/// files, other containers, and files without a preview.
+pub fn extract_preview(data: &[u8]) -> Result<Vec<u8>> {
+    if let Some(reason) = sniff::reject_reason(data) {
+        return Err(ConversionError::UnsupportedFormat(reason));
+    }
+    if let Some(jpeg) = read_jpeg_preview(data) {
+        debug!(bytes = jpeg.len(), "Found embedded JPEG preview");
+        return Ok(jpeg.to_vec());
+    }
+    Err(ConversionError::UnsupportedFormat(match sniff::sniff(data) {
+        InputKind::TiffRaw => "no embedded JPEG preview found".to_string(),
+        InputKind::Raw(container) => format!("preview extraction is not supported for {}", container),
+        _ => "preview extraction needs a TIFF-based RAW container".to_string(),
+    }))
+}
@@ -0,0 +1,43 @@
+//! Camera color profiles overriding the decoder's `cam_to_xyz` matrix
+//!
+//! A profile file is a JSON object mapping camera model names to 3x4 row-major
+//! camera to XYZ matrices, in the layout of `RawImageData::cam_to_xyz`:
+//!
+//! ```json
+//! { "ILCE-7M3": [[0.71, 0.15, 0.10, 0.0], [0.29, 0.86, -0.15, 0.0], [0.02, -0.20, 1.07, 0.0]] }
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::image_pipeline::common::error::{ConversionError, Result};
+
+/// Color matrices keyed by camera model
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct CameraProfiles {
+    matrices: HashMap<String, [[f32; 4]; 3]>,
+}
+
+impl CameraProfiles {
+    /// Parses a profile from its JSON text
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| ConversionError::UnsupportedFormat(format!("camera profile: {}", e)))
+    }
+
+    /// Reads and parses a profile file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| ConversionError::InputReadError(format!("{}: {}", path.display(), e)))?;
+        Self::from_json(&json)
+    }
+
+    /// Matrix for `model`, for use as `ConversionConfig::color_matrix`
+    pub fn color_matrix(&self, model: &str) -> Option<[[f32; 4]; 3]> {
+        self.matrices.get(model).copied()
+    }
+}
@@ -6,11 +6,14 @@
 
 use std::io::Cursor;
 
-use tracing::debug;
+use tracing::{debug, warn};
 use rawloader::RawImageData as RawloaderImageData;
 use crate::image_pipeline::common::error::{Result, ConversionError};
-use crate::image_pipeline::raw::types::RawImageData;
+use crate::image_pipeline::raw::types::{RawImageData, RawImageInfo, SUPPORTED_BITS_PER_SAMPLE, expand_levels};
+use crate::image_pipeline::raw::exif::{read_as_shot_neutral, read_exif, read_gain_map};
+use crate::image_pipeline::raw::orientation::Orientation;
 use crate::image_pipeline::raw::reader::RawImageReader;
+use crate::image_pipeline::raw::sniff;
 
 /// RAW image reader that uses the rawloader library for decoding.
 ///
@@ -25,6 +28,36 @@ const DEFAULT_BITS_PER_SAMPLE: u32 = 16;
 /// The bit width of the u16 data type, used for calculating actual bits per sample.
 const U16_BITS: u32 = 16;
 
+impl RawLoaderReader {
+    /// Checks that a decoded layout is one the debayer handles, returning whether it is a mosaic.
+    ///
+    /// The debayer handles single-sample RGB Bayer mosaics and, skipping the demosaic,
+    /// already-RGB data such as linear DNGs. Anything else (four-color RGBE/CYGM
+    /// sensors, other component counts) fails with `UnsupportedFormat`.
+    pub fn check_layout(cpp: usize, cfa: &rawloader::CFA) -> Result<bool> {
+        let is_bayer = match cpp {
+            1 => true,
+            3 => false,
+            cpp => {
+                return Err(ConversionError::UnsupportedFormat(format!(
+                    "{} components per pixel, only CFA or RGB data is supported",
+                    cpp
+                )));
+            }
+        };
+
+        let cfa_colors = cfa_color_count(cfa);
+        if is_bayer && cfa_colors > 3 {
+            warn!("CFA pattern {} uses {} colors", cfa.name, cfa_colors);
+            return Err(ConversionError::UnsupportedFormat(format!(
+                "4-color CFA not supported (pattern {})",
+                cfa.name
+            )));
+        }
+        Ok(is_bayer)
+    }
+}
+
 impl RawImageReader for RawLoaderReader {
     /// Reads and decodes RAW image data from a byte array.
     ///
@@ -45,8 +78,7 @@ impl RawImageReader for RawLoaderReader {
     /// # Examples
     ///
     /// ```no_run
-    /// use ffed_protosat_rs::image_pipeline::arw_to_tiff::rawloader_reader::RawLoaderReader;
-    /// use ffed_protosat_rs::image_pipeline::arw_to_tiff::reader::RawImageReader;
+    /// use ffed_protosat_rs::image_pipeline::{RawImageReader, RawLoaderReader};
     ///
     /// let reader = RawLoaderReader;
     /// let raw_bytes = std::fs::read("image.arw").unwrap();
@@ -54,43 +86,67 @@ impl RawImageReader for RawLoaderReader {
     /// ```
     fn read_raw(&self, data: &[u8]) -> Result<RawImageData> {
         debug!("Decoding RAW image, {} bytes", data.len());
+        check_container(data)?;
         
-        let decoded = rawloader::decode(&mut Cursor::new(data))
-            .map_err(|e| ConversionError::DecodeError(e.to_string()))?;
+        let mut decoded = rawloader::decode(&mut Cursor::new(data))
+            .map_err(|e| decode_error(data, e))?;
         
         let width = decoded.width;
         let height = decoded.height;
+        let exif = read_exif(data);
         
         debug!("Decoded image: {}x{}", width, height);
         
         // Convert RAW data to u16 format
-        // Integer data is cast directly, float data (normalized 0.0-1.0) is scaled to u16 range
-        let data: Vec<u16> = match &decoded.data {
-            RawloaderImageData::Integer(values) => {
-                values.iter().map(|&v| v as u16).collect()
-            }
+        // rawloader's decoders already unpack bit-packed formats (10/12/14-bit, LE/BE,
+        // Sony/Nikon/Canon compression) into one right-aligned u16 per sample, so integer
+        // data is taken as-is. Float data (normalized 0.0-1.0) is scaled to u16 range.
+        let raw_data = std::mem::replace(&mut decoded.data, RawloaderImageData::Integer(Vec::new()));
+        let samples: Vec<u16> = match raw_data {
+            RawloaderImageData::Integer(values) => values,
             // If the data is in float format, we scale it to u16 range
             RawloaderImageData::Float(values) => {
                 values.iter().map(|&v| (v * u16::MAX as f32) as u16).collect()
             }
         };
         
-        // Calculate the actual bits per sample from the sensor's white level metadata.
-        // The white level represents the maximum pixel value the sensor can produce,
-        // which tells us the actual bit depth of the sensor (e.g., 12-bit, 14-bit, 16-bit).
-        // This makes the reader format-agnostic and works with any RAW format.
-        let max_white_level = decoded.whitelevels.iter().max().copied().unwrap_or( u16::MAX);
-        let bits_per_sample = if max_white_level == 0 {
-            // If white level is 0 (invalid), default to 16-bit
-            DEFAULT_BITS_PER_SAMPLE
-        } else {
-            // Calculate minimum bits needed to represent the max value
-            // e.g., max_white_level = 4095 (0xFFF) -> 12 bits
-            //       max_white_level = 16383 (0x3FFF) -> 14 bits
-            (U16_BITS - max_white_level.leading_zeros()) as u32
-        };
+        let is_bayer = Self::check_layout(decoded.cpp, &decoded.cfa)?;
+        
+        let expected_len = width * height * decoded.cpp;
+        if samples.len() != expected_len {
+            warn!(
+                "Decoded buffer holds {} samples, expected {} for {}x{} with {} component(s) per pixel",
+                samples.len(), expected_len, width, height, decoded.cpp
+            );
+            return Err(ConversionError::DataLengthMismatch { expected: expected_len, actual: samples.len() });
+        }
+        
+        let (bits_per_sample, max_white_level) = bits_per_sample(&decoded.whitelevels);
         
         debug!("Calculated bits_per_sample: {} (max white level: {})", bits_per_sample, max_white_level);
+        
+        // Samples above the bit depth's range would mean the data was not unpacked
+        // the way the white level suggests (e.g. left-aligned or still packed)
+        if bits_per_sample < U16_BITS {
+            let max_code = (1u16 << bits_per_sample) - 1;
+            let out_of_range = samples.iter().filter(|&&v| v > max_code).count();
+            if out_of_range > 0 {
+                warn!(
+                    "{} samples exceed the {}-bit range (max {}), data may be misaligned",
+                    out_of_range, bits_per_sample, max_code
+                );
+            }
+        }
+        
+        if !SUPPORTED_BITS_PER_SAMPLE.contains(&bits_per_sample) {
+            return Err(ConversionError::UnsupportedFormat(format!(
+                "{} bits per sample (white level {}), expected {}..={}",
+                bits_per_sample,
+                max_white_level,
+                SUPPORTED_BITS_PER_SAMPLE.start(),
+                SUPPORTED_BITS_PER_SAMPLE.end()
+            )));
+        }
         debug!("Black levels: {:?}", decoded.blacklevels);
         debug!("White levels: {:?}", decoded.whitelevels);
         debug!("make: {}", decoded.make);
@@ -108,6 +164,7 @@ impl RawImageReader for RawLoaderReader {
         debug!("crops: {:?}", decoded.crops);
         debug!("blackareas: {:?}", decoded.blackareas);
         debug!("orientation: {:?}", decoded.orientation);
+        debug!("exif: {:?}", exif);
   
         
         // Use rawloader's normalized cam_to_xyz which properly handles the inversion
@@ -117,19 +174,126 @@ impl RawImageReader for RawLoaderReader {
         let xyz_to_cam = decoded.xyz_to_cam;
         
         let wb_coeffs = decoded.wb_coeffs;
-        let blacklevels = decoded.blacklevels;
-        let whitelevels = decoded.whitelevels;
+        let blacklevels = levels("black", &decoded.blacklevels);
+        let whitelevels = levels("white", &decoded.whitelevels);
         
-        Ok(RawImageData {
+        let mut raw_image = RawImageData {
             width,
             height,
-            data,
+            data: samples,
+            is_bayer,
             bits_per_sample,
             wb_coeffs,
             blacklevels,
             whitelevels,
             cam_to_xyz,
             xyz_to_cam,
+            exif,
+            orientation: Orientation::from_tag(decoded.orientation.to_u16()),
+            gain_map: read_gain_map(data),
+        };
+        
+        // A DNG's AsShotNeutral is its authoritative white balance, ahead of whatever
+        // rawloader derived for wb_coeffs
+        if let Some(neutral) = read_as_shot_neutral(data) {
+            debug!("Using DNG AsShotNeutral {:?} for white balance", neutral);
+            raw_image.apply_as_shot_neutral(neutral);
+        }
+        
+        Ok(raw_image)
+    }
+
+    /// Reads the camera metadata through rawloader's dummy decode, which parses the
+    /// container but skips the pixel data, so no image buffer is allocated.
+    fn probe(&self, data: &[u8]) -> Result<RawImageInfo> {
+        check_container(data)?;
+        let decoded = rawloader::decode_dummy(&mut Cursor::new(data))
+            .map_err(|e| decode_error(data, e))?;
+        let (bits_per_sample, _) = bits_per_sample(&decoded.whitelevels);
+
+        Ok(RawImageInfo {
+            width: decoded.width,
+            height: decoded.height,
+            bits_per_sample,
+            make: Some(decoded.clean_make),
+            model: Some(decoded.clean_model),
+            orientation: Orientation::from_tag(decoded.orientation.to_u16()),
         })
     }
 }
+
+/// Rejects input whose magic bytes show it is not a RAW file before rawloader sees it
+fn check_container(data: &[u8]) -> Result<()> {
+    match sniff::reject_reason(data) {
+        Some(reason) => {
+            warn!("Rejecting input: {}", reason);
+            Err(ConversionError::UnsupportedFormat(reason))
+        }
+        None => Ok(()),
+    }
+}
+
+/// rawloader's error as the source, naming the container when the magic bytes were
+/// recognized since a known container failing to parse usually means a truncated file or
+/// unsupported camera
+fn decode_error(data: &[u8], source: rawloader::RawLoaderError) -> ConversionError {
+    let context = match sniff::sniff(data).raw_container() {
+        Some(container) => format!(
+            "{} container could not be decoded (truncated file or unsupported camera?)",
+            container
+        ),
+        None => "unrecognized container".to_string(),
+    };
+    ConversionError::RawDecode { context, source }
+}
+
+/// rawloader's levels as `[R, G, B, E]`, see `expand_levels`
+///
+/// rawloader always hands over four slots, leaving the unused ones zero when the camera
+/// gives fewer levels, so trailing zeros after a set level are taken as unfilled. Any
+/// broadcasting or averaging is logged.
+fn levels(kind: &str, levels: &[u16; 4]) -> [u16; 4] {
+    let filled = levels.iter().rposition(|&v| v != 0).map_or(0, |last| last + 1);
+    let expanded = expand_levels(&levels[..filled]);
+    match filled {
+        0 | 4 => {}
+        1 | 3 => debug!("Expanded {} {} level(s) {:?} to {:?}", filled, kind, &levels[..filled], expanded),
+        _ => warn!("Averaged {} {} levels {:?} to {:?}", filled, kind, &levels[..filled], expanded),
+    }
+    expanded
+}
+
+/// Bits per sample implied by the white levels, along with the largest white level.
+///
+/// The white level represents the maximum pixel value the sensor can produce, which
+/// tells us the actual bit depth of the sensor (e.g., 12-bit, 14-bit, 16-bit). This
+/// makes the reader format-agnostic and works with any RAW format.
+fn bits_per_sample(whitelevels: &[u16; 4]) -> (u32, u16) {
+    let max_white_level = whitelevels.iter().max().copied().unwrap_or(u16::MAX);
+    let bits_per_sample = if max_white_level == 0 {
+        // If white level is 0 (invalid), default to 16-bit
+        DEFAULT_BITS_PER_SAMPLE
+    } else {
+        // Calculate minimum bits needed to represent the max value
+        // e.g., max_white_level = 4095 (0xFFF) -> 12 bits
+        //       max_white_level = 16383 (0x3FFF) -> 14 bits
+        U16_BITS - max_white_level.leading_zeros()
+    };
+    (bits_per_sample, max_white_level)
+}
+
+/// Number of distinct color indices in one repeat of the CFA pattern.
+///
+/// rawloader maps R/G/B to 0..=2 and the fourth color of RGBE/CYGM sensors
+/// (E, or Y in CYGM) to 3, so a count above 3 means a four-color sensor.
+fn cfa_color_count(cfa: &rawloader::CFA) -> usize {
+    let mut seen = [false; 4];
+    for row in 0..cfa.height {
+        for col in 0..cfa.width {
+            if let Some(slot) = seen.get_mut(cfa.color_at(row, col)) {
+                *slot = true;
+            }
+        }
+    }
+    seen.iter().filter(|&&s| s).count()
+}
@@ -1,6 +1,14 @@
 use crate::image_pipeline::common::error::Result;
-use crate::image_pipeline::raw::types::RawImageData;
+use crate::image_pipeline::raw::types::{RawImageData, RawImageInfo};
 
 pub trait RawImageReader {
     fn read_raw(&self, data: &[u8]) -> Result<RawImageData>;
+
+    /// Dimensions, bit depth and camera of the RAW in `data`, for cataloguing
+    ///
+    /// The default decodes the whole image with `read_raw` and reports no camera;
+    /// readers that can parse just the header should override it.
+    fn probe(&self, data: &[u8]) -> Result<RawImageInfo> {
+        self.read_raw(data).map(|image| RawImageInfo::from(&image))
+    }
 }
@@ -0,0 +1,87 @@
+//! Input format detection from leading magic bytes
+//!
+//! rawloader reports anything it can't parse with a generic message, so the reader
+//! checks the container first and turns obviously wrong inputs (JPEGs, PNGs, empty
+//! files) into an actionable `UnsupportedFormat` error.
+
+/// Shortest input any supported RAW container can start with
+const MIN_RAW_BYTES: usize = 16;
+
+/// What the leading bytes of an input look like
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InputKind {
+    /// A TIFF-based RAW container (ARW, CR2, NEF, DNG, ...), whose IFDs can be scanned
+    TiffRaw,
+    /// Another container RAW files are stored in, named for error messages
+    Raw(&'static str),
+    /// A known format that never holds RAW sensor data
+    NotRaw(&'static str),
+    /// No known signature; some RAW formats have none, so rawloader gets to decide
+    Unknown,
+}
+
+impl InputKind {
+    /// Name of the RAW container for error messages, `None` unless the input looks like one
+    pub(crate) fn raw_container(self) -> Option<&'static str> {
+        match self {
+            InputKind::TiffRaw => Some("TIFF-based RAW"),
+            InputKind::Raw(container) => Some(container),
+            InputKind::NotRaw(_) | InputKind::Unknown => None,
+        }
+    }
+}
+
+pub(crate) fn sniff(data: &[u8]) -> InputKind {
+    let starts = |magic: &[u8]| data.starts_with(magic);
+    let at = |offset: usize, magic: &[u8]| data.get(offset..offset + magic.len()) == Some(magic);
+
+    if starts(b"IIRO") || starts(b"IIRS") || starts(b"MMOR") {
+        InputKind::Raw("Olympus ORF")
+    } else if starts(b"IIU\0") {
+        InputKind::Raw("Panasonic RW2")
+    } else if starts(b"II*\0") || starts(b"MM\0*") {
+        InputKind::TiffRaw
+    } else if starts(b"FUJIFILM") {
+        InputKind::Raw("Fujifilm RAF")
+    } else if at(6, b"HEAPCCDR") {
+        InputKind::Raw("Canon CRW")
+    } else if starts(b"\0MRM") {
+        InputKind::Raw("Minolta MRW")
+    } else if starts(b"FOVb") {
+        InputKind::Raw("Sigma X3F")
+    } else if starts(b"ARRI") {
+        InputKind::Raw("ARRI")
+    } else if at(4, b"ftypcrx ") {
+        InputKind::Raw("Canon CR3")
+    } else if at(4, b"ftyp") {
+        InputKind::NotRaw("HEIF/MP4")
+    } else if starts(&[0xFF, 0xD8, 0xFF]) {
+        InputKind::NotRaw("JPEG")
+    } else if starts(b"\x89PNG\r\n\x1a\n") {
+        InputKind::NotRaw("PNG")
+    } else if starts(b"GIF87a") || starts(b"GIF89a") {
+        InputKind::NotRaw("GIF")
+    } else if starts(b"RIFF") && at(8, b"WEBP") {
+        InputKind::NotRaw("WebP")
+    } else if starts(b"BM") {
+        InputKind::NotRaw("BMP")
+    } else if starts(b"%PDF") {
+        InputKind::NotRaw("PDF")
+    } else if starts(b"PK\x03\x04") {
+        InputKind::NotRaw("ZIP")
+    } else {
+        InputKind::Unknown
+    }
+}
+
+/// Error message for input that can't be a RAW file, `None` if it might be one
+pub(crate) fn reject_reason(data: &[u8]) -> Option<String> {
+    match sniff(data) {
+        _ if data.is_empty() => Some("not a RAW file: input is empty".to_string()),
+        InputKind::NotRaw(format) => Some(format!("not a RAW file: detected {}", format)),
+        _ if data.len() < MIN_RAW_BYTES => {
+            Some(format!("not a RAW file: input is only {} bytes", data.len()))
+        }
+        _ => None,
+    }
+}
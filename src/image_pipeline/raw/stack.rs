@@ -0,0 +1,77 @@
+//! Multi-frame averaging of aligned exposures in the raw domain
+
+use rayon::prelude::*;
+use tracing::{debug, warn};
+use crate::image_pipeline::common::error::{ConversionError, Result};
+use crate::image_pipeline::raw::types::RawImageData;
+
+/// Most frames whose u16 samples sum without overflowing a u32 accumulator
+const MAX_FRAMES: usize = 1 << 16;
+
+/// Averages aligned, same-size frames sample by sample into one image
+///
+/// Sums are accumulated in u32 and rounded to the nearest integer, so the result is the
+/// same on every run and platform. Metadata (levels, white balance, matrices, EXIF,
+/// orientation) comes from the first frame; frames whose black or white levels differ
+/// from it are averaged anyway with a warning. Fails if `inputs` is empty, holds more
+/// than 65536 frames, or any frame differs from the first in dimensions or Bayer layout
+/// (`InvalidDimensions`) or otherwise in sample count (`DataLengthMismatch`).
+pub fn stack_average(inputs: &[RawImageData]) -> Result<RawImageData> {
+    let Some(first) = inputs.first() else {
+        return Err(ConversionError::UnsupportedFormat("no frames to stack".to_string()));
+    };
+    if inputs.len() > MAX_FRAMES {
+        return Err(ConversionError::UnsupportedFormat(format!(
+            "{} frames to stack, at most {} are supported",
+            inputs.len(),
+            MAX_FRAMES
+        )));
+    }
+
+    for (index, frame) in inputs.iter().enumerate().skip(1) {
+        if frame.width != first.width || frame.height != first.height || frame.is_bayer != first.is_bayer {
+            warn!(
+                "Frame {} is {}x{} (Bayer: {}), frame 0 is {}x{} (Bayer: {})",
+                index, frame.width, frame.height, frame.is_bayer,
+                first.width, first.height, first.is_bayer
+            );
+            return Err(ConversionError::InvalidDimensions(frame.width, frame.height));
+        }
+        if frame.data.len() != first.data.len() {
+            warn!("Frame {} holds {} samples, frame 0 holds {}", index, frame.data.len(), first.data.len());
+            return Err(ConversionError::DataLengthMismatch {
+                expected: first.data.len(),
+                actual: frame.data.len(),
+            });
+        }
+        if frame.blacklevels != first.blacklevels || frame.whitelevels != first.whitelevels {
+            warn!("Frame {} has different black/white levels than frame 0, using frame 0's", index);
+        }
+    }
+
+    let count = inputs.len() as u32;
+    let data = (0..first.data.len())
+        .into_par_iter()
+        .map(|i| {
+            let sum: u32 = inputs.iter().map(|frame| u32::from(frame.data[i])).sum();
+            ((sum + count / 2) / count) as u16
+        })
+        .collect();
+
+    debug!("Stacked {} frames of {}x{}", count, first.width, first.height);
+    Ok(RawImageData {
+        width: first.width,
+        height: first.height,
+        data,
+        is_bayer: first.is_bayer,
+        bits_per_sample: first.bits_per_sample,
+        wb_coeffs: first.wb_coeffs,
+        blacklevels: first.blacklevels,
+        whitelevels: first.whitelevels,
+        cam_to_xyz: first.cam_to_xyz,
+        xyz_to_cam: first.xyz_to_cam,
+        exif: first.exif,
+        orientation: first.orientation,
+        gain_map: first.gain_map.clone(),
+    })
+}
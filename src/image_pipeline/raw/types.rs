@@ -1,5 +1,20 @@
 //! RAW image data types
 
+use std::ops::RangeInclusive;
+
+use rayon::prelude::*;
+
+use crate::image_pipeline::common::error::{ConversionError, Result};
+use crate::image_pipeline::debayer::color_math::{SRGB_TO_XYZ, XYZ_TO_SRGB};
+use crate::image_pipeline::debayer::types::RgbImageData;
+use crate::image_pipeline::debayer::white_balance::WhiteBalance;
+use crate::image_pipeline::raw::corrections;
+use crate::image_pipeline::raw::exif::ExifMetadata;
+use crate::image_pipeline::raw::orientation::Orientation;
+
+/// Sample bit depths the u16-based pipeline can represent
+pub const SUPPORTED_BITS_PER_SAMPLE: RangeInclusive<u32> = 8..=16;
+
 /// Represents decoded RAW image data
 #[derive(Debug, Clone)]
 pub struct RawImageData {
@@ -7,8 +22,12 @@ pub struct RawImageData {
     pub width: usize,
     /// Height of the image in pixels
     pub height: usize,
-    /// Raw pixel data (single channel Bayer pattern)
+    /// Raw pixel data: a single-channel Bayer mosaic, or interleaved RGB when `is_bayer`
+    /// is `false`
     pub data: Vec<u16>,
+    /// `false` for raws that are already demosaiced (e.g. linear DNGs), which skip the
+    /// demosaic and only go through the color stages of the debayer
+    pub is_bayer: bool,
     /// Actual bits per sample from the sensor (e.g., 12, 14, or 16)
     pub bits_per_sample: u32,
     /// White balance coefficients [R, G, B, E] from camera
@@ -24,4 +43,335 @@ pub struct RawImageData {
     /// XYZ to Camera color conversion matrix (raw, 4x3, row-major)
     /// Used for debayering and color correction
     pub xyz_to_cam: [[f32; 3]; 4],
+    /// Capture settings from the source file's EXIF IFD, empty if unavailable
+    pub exif: ExifMetadata,
+    /// Orientation recorded by the camera, applied to debayered output when
+    /// `apply_orientation` is set
+    pub orientation: Orientation,
+    /// Lens shading gain map from the source file's metadata, applied in the raw domain
+    /// when `apply_gain_map` is set and cleared once applied
+    pub gain_map: Option<GainMap>,
+}
+
+/// Maps per-channel black or white levels of any count onto the `[R, G, B, E]` layout of
+/// `RawImageData::blacklevels` and `whitelevels`
+///
+/// - 4 values are taken as they are
+/// - 1 value applies to every channel
+/// - 3 values are `[R, G, B]`, the second green taking the green level
+/// - any other count is averaged (rounded) into one level for every channel
+/// - no values give `[0; 4]`, which `estimate_missing_levels` treats as missing white
+pub fn expand_levels(values: &[u16]) -> [u16; 4] {
+    match *values {
+        [] => [0; 4],
+        [level] => [level; 4],
+        [r, g, b] => [r, g, b, g],
+        [r, g, b, e] => [r, g, b, e],
+        _ => {
+            let sum: u32 = values.iter().map(|&v| u32::from(v)).sum();
+            let count = values.len() as u32;
+            [((sum + count / 2) / count) as u16; 4]
+        }
+    }
+}
+
+/// Smooth gain correction over the sensor, as a DNG `GainMap` opcode describes it
+///
+/// Gains sit on a regular grid in coordinates relative to the image, 0.0 at the first
+/// row or column and 1.0 at the last, so the map stays valid when the image is binned.
+/// Pixels between grid points are interpolated bilinearly; pixels outside the grid use
+/// the nearest edge of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GainMap {
+    /// Grid points vertically and horizontally, at least 1 each
+    pub points: (usize, usize),
+    /// Relative position of the first grid point, (vertical, horizontal)
+    pub origin: (f64, f64),
+    /// Relative distance between grid points, (vertical, horizontal)
+    pub spacing: (f64, f64),
+    /// Gain at each grid point, `points.0` rows of `points.1`
+    pub gains: Vec<f32>,
+}
+
+impl GainMap {
+    /// Whether `gains` holds one value per grid point
+    pub fn is_valid(&self) -> bool {
+        self.points.0 > 0 && self.points.1 > 0 && self.gains.len() == self.points.0 * self.points.1
+    }
+
+    /// Interpolated gain at pixel (`x`, `y`) of a `width`x`height` image
+    pub fn gain_at(&self, x: usize, y: usize, width: usize, height: usize) -> f32 {
+        let relative = |i: usize, len: usize| if len > 1 { i as f64 / (len - 1) as f64 } else { 0.0 };
+        // Fractional grid index along one axis, clamped to the grid
+        let grid = |position: f64, origin: f64, spacing: f64, points: usize| {
+            let last = (points - 1) as f64;
+            if spacing > 0.0 { ((position - origin) / spacing).clamp(0.0, last) } else { 0.0 }
+        };
+        let row = grid(relative(y, height), self.origin.0, self.spacing.0, self.points.0);
+        let col = grid(relative(x, width), self.origin.1, self.spacing.1, self.points.1);
+
+        let (row0, col0) = (row.floor() as usize, col.floor() as usize);
+        let (row1, col1) = ((row0 + 1).min(self.points.0 - 1), (col0 + 1).min(self.points.1 - 1));
+        let (fy, fx) = ((row - row0 as f64) as f32, (col - col0 as f64) as f32);
+        let at = |r: usize, c: usize| self.gains[r * self.points.1 + c];
+        let top = at(row0, col0) + (at(row0, col1) - at(row0, col0)) * fx;
+        let bottom = at(row1, col0) + (at(row1, col1) - at(row1, col0)) * fx;
+        top + (bottom - top) * fy
+    }
+}
+
+/// Header-level description of a RAW, as returned by `RawImageReader::probe`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawImageInfo {
+    /// Width of the image in pixels
+    pub width: usize,
+    /// Height of the image in pixels
+    pub height: usize,
+    /// Actual bits per sample from the sensor (e.g., 12, 14, or 16)
+    pub bits_per_sample: u32,
+    /// Camera manufacturer, `None` if the reader does not know it
+    pub make: Option<String>,
+    /// Camera model, `None` if the reader does not know it
+    pub model: Option<String>,
+    /// Orientation recorded by the camera
+    pub orientation: Orientation,
+}
+
+impl From<&RawImageData> for RawImageInfo {
+    fn from(image: &RawImageData) -> Self {
+        Self {
+            width: image.width,
+            height: image.height,
+            bits_per_sample: image.bits_per_sample,
+            make: None,
+            model: None,
+            orientation: image.orientation,
+        }
+    }
+}
+
+impl Default for RawImageData {
+    /// An empty 0x0 16-bit Bayer frame from a camera whose RGB is linear sRGB: unit white
+    /// balance, black at 0, white at 65535, `SRGB_TO_XYZ` and its inverse as the color
+    /// matrices, no EXIF, normal orientation and no gain map. Meant as the base of
+    /// synthetic frames, filled in with `..Default::default()`.
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            data: Vec::new(),
+            is_bayer: true,
+            bits_per_sample: 16,
+            wb_coeffs: [1.0; 4],
+            blacklevels: [0; 4],
+            whitelevels: [u16::MAX; 4],
+            cam_to_xyz: SRGB_TO_XYZ,
+            xyz_to_cam: [XYZ_TO_SRGB[0], XYZ_TO_SRGB[1], XYZ_TO_SRGB[2], [0.0; 3]],
+            exif: ExifMetadata::default(),
+            orientation: Orientation::Normal,
+            gain_map: None,
+        }
+    }
+}
+
+impl RawImageData {
+    /// Samples per pixel in `data`: 1 for a Bayer mosaic, 3 for interleaved RGB
+    pub fn samples_per_pixel(&self) -> usize {
+        if self.is_bayer { 1 } else { 3 }
+    }
+
+    /// Whether `other` is the same frame up to sample noise of `tol`
+    ///
+    /// Dimensions, layout, bit depth, levels, white balance, color matrices, EXIF,
+    /// orientation and gain map must match exactly, with `NaN` coefficients equal to each
+    /// other as rawloader uses them for a missing second green. Every sample may then
+    /// differ by at most `tol`, so a `tol` of 0 checks for identical frames. Useful for
+    /// comparing decoder output in tests and spotting duplicate captures before stacking.
+    pub fn approx_eq(&self, other: &RawImageData, tol: u16) -> bool {
+        let same = |a: f32, b: f32| a == b || (a.is_nan() && b.is_nan());
+        let same_all = |a: &[f32], b: &[f32]| a.iter().zip(b).all(|(&a, &b)| same(a, b));
+
+        self.width == other.width
+            && self.height == other.height
+            && self.is_bayer == other.is_bayer
+            && self.bits_per_sample == other.bits_per_sample
+            && self.blacklevels == other.blacklevels
+            && self.whitelevels == other.whitelevels
+            && same_all(&self.wb_coeffs, &other.wb_coeffs)
+            && same_all(self.cam_to_xyz.as_flattened(), other.cam_to_xyz.as_flattened())
+            && same_all(self.xyz_to_cam.as_flattened(), other.xyz_to_cam.as_flattened())
+            && self.exif == other.exif
+            && self.orientation == other.orientation
+            && self.gain_map == other.gain_map
+            && self.data.len() == other.data.len()
+            && self.data.iter().zip(&other.data).all(|(&a, &b)| a.abs_diff(b) <= tol)
+    }
+
+    /// Takes the white balance from a camera-space neutral such as a DNG's `AsShotNeutral`
+    ///
+    /// `wb_coeffs` becomes the reciprocal of `neutral`, so `WhiteBalance::AsShot` maps the
+    /// neutral to gray. DNGs have no separate second green, so `wb_coeffs[3]` is `NaN` as
+    /// rawloader leaves it.
+    pub fn apply_as_shot_neutral(&mut self, neutral: [f32; 3]) {
+        let [r, g, b] = neutral.map(|v| 1.0 / v);
+        self.wb_coeffs = [r, g, b, f32::NAN];
+    }
+
+    /// Subtracts the black level and rescales the samples from `bits_per_sample` to the
+    /// full 16-bit range
+    ///
+    /// Each sample loses the black level of its CFA site (of its channel for RGB raws),
+    /// clamping at zero, and is multiplied by `65535 / (2^bits - 1 - black)` and rounded,
+    /// so black maps to 0 and the sensor's maximum code to 65535. 16-bit data with a zero
+    /// black level is returned unchanged.
+    pub fn normalized_to_16_bit(&self) -> Vec<u16> {
+        let max_code = (1u32 << self.bits_per_sample.min(16)) - 1;
+        // [R, G, B, E] level index of each RGGB site, `wb_coeffs[3]` being the blue-row green
+        let site_index = |i: usize| match (i / self.width % 2, i % self.width % 2) {
+            (0, 0) => 0,
+            (0, _) => 1,
+            (_, 0) => 3,
+            _ => 2,
+        };
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| {
+                let black = if self.is_bayer {
+                    self.blacklevels[site_index(i)]
+                } else {
+                    self.blacklevels[i % 3]
+                };
+                let range = max_code.saturating_sub(black as u32).max(1) as f32;
+                let signal = corrections::subtract_black(v, black) as f32;
+                (signal * u16::MAX as f32 / range).round().min(u16::MAX as f32) as u16
+            })
+            .collect()
+    }
+
+    /// Quick half-resolution 8-bit RGB preview that needs no debayer backend
+    ///
+    /// Each 2x2 RGGB cell becomes one pixel: red and blue are taken as is and the two
+    /// greens averaged, then black-subtracted, scaled by the as-shot white balance and
+    /// encoded with a 2.2 gamma. There is no demosaic or color matrix, so colors are only
+    /// approximate. Raws that are already RGB average each 2x2 block per channel instead.
+    /// Samples are 0..=255 with `bits_per_sample` 8; an odd trailing row or column is
+    /// dropped.
+    pub fn to_rgb_preview(&self) -> RgbImageData {
+        let (width, height) = (self.width / 2, self.height / 2);
+        let wb = WhiteBalance::AsShot
+            .multipliers(self)
+            .map(|m| if m.is_finite() && m > 0.0 { m } else { 1.0 });
+        let black = [0, 1, 2].map(|c| self.blacklevels[c] as f32);
+        let white = match self.whitelevels[0] {
+            0 => u16::MAX as f32,
+            level => level as f32,
+        };
+        let to_8_bit = |value: f32, c: usize| {
+            let linear = ((value - black[c]) * wb[c] / (white - black[c]).max(1.0)).clamp(0.0, 1.0);
+            (linear.powf(1.0 / 2.2) * 255.0).round() as u16
+        };
+
+        let row_len = self.width * self.samples_per_pixel();
+        let mut data = Vec::with_capacity(width * height * 3);
+        for (top, bottom) in self
+            .data
+            .chunks_exact(2 * row_len)
+            .take(height)
+            .map(|rows| rows.split_at(row_len))
+        {
+            for col in 0..width {
+                let cell = if self.is_bayer {
+                    let x = 2 * col;
+                    let green = (top[x + 1] as f32 + bottom[x] as f32) / 2.0;
+                    [top[x] as f32, green, bottom[x + 1] as f32]
+                } else {
+                    let x = 6 * col;
+                    [0, 1, 2].map(|c| {
+                        let sum = top[x + c] as u32 + top[x + 3 + c] as u32 + bottom[x + c] as u32 + bottom[x + 3 + c] as u32;
+                        sum as f32 / 4.0
+                    })
+                };
+                data.extend([0, 1, 2].map(|c| to_8_bit(cell[c], c)));
+            }
+        }
+
+        RgbImageData {
+            width,
+            height,
+            data,
+            bits_per_sample: 8,
+            exif: self.exif,
+        }
+    }
+
+    /// Reduces the resolution by `factor` in each direction, averaging same-color samples
+    ///
+    /// Bayer data keeps its mosaic and CFA phase: each 2x2 cell of the output comes from a
+    /// `2 * factor` square of the input, and every output site is the mean of the
+    /// `factor * factor` sites of its color in that square. `factor` must be even for
+    /// Bayer data. RGB raws average each `factor * factor` block per channel. Means are
+    /// rounded and all metadata is kept; rows and columns that don't fill a block are
+    /// dropped. Fails if `factor` is 0, odd for a mosaic, or larger than the image allows,
+    /// or if the buffer is shorter than the dimensions.
+    pub fn bin(&self, factor: usize) -> Result<RawImageData> {
+        if factor == 0 || (self.is_bayer && !factor.is_multiple_of(2)) {
+            return Err(ConversionError::UnsupportedFormat(format!(
+                "binning factor {} for a {} raw, expected {}",
+                factor,
+                if self.is_bayer { "Bayer" } else { "RGB" },
+                if self.is_bayer { "an even factor" } else { "a factor of at least 1" }
+            )));
+        }
+
+        // Bayer sites of one color are two apart, so a mosaic bins in 2x2 cells
+        let (cell, step) = if self.is_bayer { (2, 2) } else { (1, 1) };
+        let width = self.width / (cell * factor) * cell;
+        let height = self.height / (cell * factor) * cell;
+        let channels = self.samples_per_pixel();
+        let row_len = self.width * channels;
+        if self.data.len() < row_len * self.height {
+            return Err(ConversionError::DataLengthMismatch {
+                expected: row_len * self.height,
+                actual: self.data.len(),
+            });
+        }
+        if width == 0 || height == 0 {
+            return Err(ConversionError::InvalidDimensions(self.width, self.height));
+        }
+
+        // First input row or column averaged into output row or column `o`
+        let origin = |o: usize| o / cell * cell * factor + o % cell;
+        let count = (factor * factor) as u64;
+        let mut data = vec![0u16; width * height * channels];
+        data.par_chunks_mut(width * channels).enumerate().for_each(|(out_y, row)| {
+            let y0 = origin(out_y);
+            for out_x in 0..width {
+                let x0 = origin(out_x);
+                for c in 0..channels {
+                    let sum: u64 = (0..factor)
+                        .flat_map(|j| (0..factor).map(move |i| (y0 + step * j, x0 + step * i)))
+                        .map(|(y, x)| u64::from(self.data[y * row_len + x * channels + c]))
+                        .sum();
+                    row[out_x * channels + c] = ((sum + count / 2) / count) as u16;
+                }
+            }
+        });
+
+        Ok(RawImageData {
+            width,
+            height,
+            data,
+            is_bayer: self.is_bayer,
+            bits_per_sample: self.bits_per_sample,
+            wb_coeffs: self.wb_coeffs,
+            blacklevels: self.blacklevels,
+            whitelevels: self.whitelevels,
+            cam_to_xyz: self.cam_to_xyz,
+            xyz_to_cam: self.xyz_to_cam,
+            exif: self.exif,
+            orientation: self.orientation,
+            gain_map: self.gain_map.clone(),
+        })
+    }
 }
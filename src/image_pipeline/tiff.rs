@@ -4,8 +4,23 @@
 
 mod writer;
 mod standard_tiff_writer;
+mod multi_page_writer;
+pub(crate) mod verify;
+pub(crate) mod estimate;
+mod ifd;
+mod icc;
+mod parallel;
+pub(crate) mod thumbnail;
 pub mod types;
+pub mod presets;
+#[cfg(feature = "webp")]
+mod webp_writer;
 
-pub use writer::TiffWriter;
+pub use writer::{ImageKind, ImageWriter, TiffWriter};
 pub use standard_tiff_writer::StandardTiffWriter;
-pub use types::{TiffCompression, ConversionConfig, ConversionConfigBuilder};
+pub(crate) use standard_tiff_writer::max_sample_value;
+pub use multi_page_writer::MultiPageTiffWriter;
+pub use presets::config_for_model;
+#[cfg(feature = "webp")]
+pub use webp_writer::WebpWriter;
+pub use types::{TiffCompression, OutputMode, GrayPhotometric, ChannelOrder, ByteOrder, ConversionConfig, ConversionConfigBuilder};
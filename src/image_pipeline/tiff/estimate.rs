@@ -0,0 +1,87 @@
+//! Output size estimation mirroring the layout `StandardTiffWriter` produces
+
+use crate::image_pipeline::raw::exif::ExifMetadata;
+use crate::image_pipeline::tiff::types::TiffCompression;
+
+/// TIFF header: byte order, magic and first IFD offset
+const HEADER_BYTES: usize = 8;
+/// Tags the tiff crate writes for every image
+const IMAGE_TAGS: usize = 14;
+/// Tags `StandardTiffWriter` adds to every image: `Gamma`
+const WRITER_TAGS: usize = 1;
+/// Strip size the tiff crate targets when splitting an image into strips
+const STRIP_TARGET_BYTES: usize = 1_000_000;
+
+/// Bytes taken by an IFD with `entries` tags: count, entries and next-IFD offset
+fn ifd_bytes(entries: usize) -> usize {
+    2 + 12 * entries + 4
+}
+
+/// Bytes a tag value takes outside its IFD entry (values up to 4 bytes are stored inline)
+fn out_of_line(value_bytes: usize) -> usize {
+    if value_bytes > 4 { value_bytes } else { 0 }
+}
+
+/// Size of the EXIF IFD written by `StandardTiffWriter`, including its out-of-line rationals
+fn exif_bytes(exif: &ExifMetadata) -> usize {
+    let rationals = [exif.exposure_time, exif.f_number, exif.focal_length]
+        .iter()
+        .filter(|value| value.is_some())
+        .count();
+    let entries = rationals + usize::from(exif.iso.is_some());
+    8 * rationals + ifd_bytes(entries)
+}
+
+/// Size of an uncompressed 8-bit RGB thumbnail IFD: the image tags plus NewSubfileType,
+/// the out-of-line BitsPerSample, SampleFormat and resolutions, and its single strip
+fn thumbnail_bytes((width, height): (usize, usize)) -> usize {
+    2 * out_of_line(2 * 3) + 2 * 8 + width * height * 3 + ifd_bytes(IMAGE_TAGS + 1)
+}
+
+/// Expected size in bytes of a single-image TIFF
+///
+/// Pixel data is scaled by `TiffCompression::estimated_ratio`; everything else follows
+/// the tiff crate's encoder layout, so the result is exact for uncompressed output.
+/// `exif` is the EXIF IFD that will be linked, if any, and `thumbnail` the dimensions of
+/// the embedded preview.
+pub(crate) fn estimate_tiff_size(
+    width: usize,
+    height: usize,
+    channels: usize,
+    bytes_per_sample: usize,
+    exif: Option<&ExifMetadata>,
+    thumbnail: Option<(usize, usize)>,
+    compression: TiffCompression,
+) -> usize {
+    let pad = |offset: usize| offset.next_multiple_of(4);
+
+    let mut size = HEADER_BYTES;
+    let mut entries = IMAGE_TAGS + WRITER_TAGS;
+    if let Some(exif) = exif {
+        size = pad(size) + exif_bytes(exif);
+        entries += 1;
+    }
+    if let Some(thumbnail) = thumbnail {
+        size = pad(size) + thumbnail_bytes(thumbnail);
+        entries += 1;
+    }
+    size = pad(size);
+
+    // BitsPerSample and SampleFormat hold one u16 per channel, X/YResolution and Gamma a
+    // rational each
+    size += 2 * out_of_line(2 * channels) + 3 * 8;
+
+    let row_bytes = width * channels * bytes_per_sample;
+    let pixel_bytes = row_bytes * height;
+    size += match compression {
+        TiffCompression::None => pixel_bytes,
+        _ => (pixel_bytes as f64 * compression.estimated_ratio() as f64).ceil() as usize,
+    };
+
+    // StripOffsets and StripByteCounts hold one u32 per strip
+    let rows_per_strip = STRIP_TARGET_BYTES.div_ceil(row_bytes.max(1));
+    let strip_count = height.div_ceil(rows_per_strip);
+    size += 2 * out_of_line(4 * strip_count);
+
+    size + ifd_bytes(entries)
+}
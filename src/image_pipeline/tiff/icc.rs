@@ -0,0 +1,147 @@
+//! ICC profile describing the pipeline's RGB output
+//!
+//! The debayers produce sRGB primaries with a D65 white and no transfer curve, so the
+//! profile is sRGB with identity tone curves rather than the stock gamma-encoded sRGB
+//! profile, which would make color-managed viewers render the linear data far too dark.
+//! Output encoded with `apply_srgb_gamma` gets the sRGB transfer curve instead.
+
+use std::borrow::Cow;
+use tiff::encoder::TiffValue;
+use tiff::tags::Type;
+
+/// sRGB colorants adapted to the D50 profile connection space (Bradford), as in the
+/// IEC 61966-2.1 reference profile
+const SRGB_COLORANTS: [[f64; 3]; 3] = [
+    [0.4361, 0.2225, 0.0139],
+    [0.3851, 0.7169, 0.0971],
+    [0.1431, 0.0606, 0.7141],
+];
+const D65_WHITE: [f64; 3] = [0.9505, 1.0, 1.0891];
+const D50_ILLUMINANT: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+const LINEAR_DESCRIPTION: &str = "Linear sRGB";
+const SRGB_DESCRIPTION: &str = "sRGB";
+/// Entries of the sampled sRGB tone curve
+const SRGB_CURVE_POINTS: usize = 1024;
+const COPYRIGHT: &str = "No copyright, use freely";
+
+/// Builds a version 2.1 display profile for linear-light sRGB
+///
+/// The bytes are fixed, so embedding the profile keeps output reproducible.
+pub(crate) fn linear_srgb_profile() -> Vec<u8> {
+    display_profile(LINEAR_DESCRIPTION, tag_data(b"curv", &0u32.to_be_bytes()))
+}
+
+/// Builds a version 2.1 display profile for sRGB with its transfer curve
+///
+/// Version 2 profiles can't hold the piecewise sRGB function as parameters, so the
+/// curve is sampled at 1024 points.
+pub(crate) fn srgb_profile() -> Vec<u8> {
+    let mut payload = (SRGB_CURVE_POINTS as u32).to_be_bytes().to_vec();
+    for i in 0..SRGB_CURVE_POINTS {
+        let linear = srgb_decode(i as f64 / (SRGB_CURVE_POINTS - 1) as f64);
+        payload.extend_from_slice(&((linear * 65535.0).round() as u16).to_be_bytes());
+    }
+    display_profile(SRGB_DESCRIPTION, tag_data(b"curv", &payload))
+}
+
+/// Display profile with sRGB colorants and `curve` as the tone curve of every channel
+fn display_profile(text: &str, curve: Vec<u8>) -> Vec<u8> {
+    let tags: [(&[u8; 4], Vec<u8>); 9] = [
+        (b"desc", description(text)),
+        (b"cprt", tag_data(b"text", &[COPYRIGHT.as_bytes(), &[0]].concat())),
+        (b"wtpt", xyz(D65_WHITE)),
+        (b"rXYZ", xyz(SRGB_COLORANTS[0])),
+        (b"gXYZ", xyz(SRGB_COLORANTS[1])),
+        (b"bXYZ", xyz(SRGB_COLORANTS[2])),
+        (b"rTRC", curve.clone()),
+        (b"gTRC", curve.clone()),
+        (b"bTRC", curve),
+    ];
+
+    let table_len = 4 + tags.len() * 12;
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut data = Vec::new();
+    for (signature, element) in &tags {
+        let offset = 128 + table_len + data.len();
+        table.extend_from_slice(*signature);
+        table.extend_from_slice(&(offset as u32).to_be_bytes());
+        table.extend_from_slice(&(element.len() as u32).to_be_bytes());
+        data.extend_from_slice(element);
+        // Every tag element starts on a 4-byte boundary
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+
+    let size = 128 + table_len + data.len();
+    let mut profile = Vec::with_capacity(size);
+    profile.extend_from_slice(&(size as u32).to_be_bytes());
+    profile.extend_from_slice(&[0; 4]); // Preferred CMM
+    profile.extend_from_slice(&0x0210_0000u32.to_be_bytes());
+    profile.extend_from_slice(b"mntr");
+    profile.extend_from_slice(b"RGB ");
+    profile.extend_from_slice(b"XYZ ");
+    for field in [2024u16, 1, 1, 0, 0, 0] {
+        profile.extend_from_slice(&field.to_be_bytes());
+    }
+    profile.extend_from_slice(b"acsp");
+    profile.extend_from_slice(&[0; 24]); // Platform, flags, manufacturer, model, attributes
+    profile.extend_from_slice(&0u32.to_be_bytes()); // Perceptual intent
+    profile.extend_from_slice(&s15_fixed16(D50_ILLUMINANT));
+    profile.resize(128, 0);
+    profile.extend_from_slice(&table);
+    profile.extend_from_slice(&data);
+    profile
+}
+
+/// Profile bytes written as the `UNDEFINED` field type the TIFF spec gives `ICCProfile`
+pub(crate) struct IccProfile(pub Vec<u8>);
+
+impl TiffValue for IccProfile {
+    const BYTE_LEN: u8 = 1;
+    const FIELD_TYPE: Type = Type::UNDEFINED;
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn data(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+}
+
+/// Type signature, four reserved bytes, then `payload`
+fn tag_data(signature: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    [signature.as_slice(), &[0; 4], payload].concat()
+}
+
+fn xyz(value: [f64; 3]) -> Vec<u8> {
+    tag_data(b"XYZ ", &s15_fixed16(value))
+}
+
+/// Version 2 `textDescriptionType` with an ASCII description and empty Unicode and
+/// ScriptCode parts
+fn description(text: &str) -> Vec<u8> {
+    let mut payload = ((text.len() + 1) as u32).to_be_bytes().to_vec();
+    payload.extend_from_slice(text.as_bytes());
+    payload.push(0);
+    payload.extend_from_slice(&[0; 8]); // Unicode language code and count
+    payload.extend_from_slice(&[0; 3]); // ScriptCode code and count
+    payload.extend_from_slice(&[0; 67]);
+    tag_data(b"desc", &payload)
+}
+
+/// Inverse of the sRGB transfer function, encoded value to linear light
+fn srgb_decode(v: f64) -> f64 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn s15_fixed16(values: [f64; 3]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|&v| ((v * 65536.0).round() as i32).to_be_bytes())
+        .collect()
+}
@@ -0,0 +1,91 @@
+//! Image IFDs assembled from already encoded strips
+
+use std::io::{Seek, Write};
+
+use tiff::encoder::colortype::ColorType;
+use tiff::encoder::{DirectoryEncoder, Ifd, Predictor, Rational, TiffKindStandard};
+use tiff::tags::{CompressionMethod, ResolutionUnit, Tag};
+use tiff::TiffResult;
+
+use crate::image_pipeline::common::error::{ConversionError, Result};
+
+/// Offsets of the auxiliary IFDs an image links to
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct LinkedIfds {
+    /// EXIF IFD, tagged `ExifDirectory`
+    pub exif: Option<u32>,
+    /// Reduced-resolution preview, tagged `SubIfd`
+    pub thumbnail: Option<u32>,
+}
+
+impl LinkedIfds {
+    pub(crate) fn write_tags<W: Write + Seek>(
+        &self,
+        directory: &mut DirectoryEncoder<'_, W, TiffKindStandard>,
+    ) -> TiffResult<()> {
+        if let Some(offset) = self.exif {
+            directory.write_tag(Tag::ExifDirectory, Ifd(offset))?;
+        }
+        if let Some(offset) = self.thumbnail {
+            directory.write_tag(Tag::SubIfd, Ifd(offset))?;
+        }
+        Ok(())
+    }
+}
+
+/// Layout of the encoded strips passed to `write_image_ifd`
+pub(crate) struct StripLayout {
+    pub width: usize,
+    pub height: usize,
+    pub rows_per_strip: usize,
+    pub compression: CompressionMethod,
+    pub predictor: Predictor,
+}
+
+/// Writes `strips` and a complete image IFD describing them, returning the IFD's offset
+///
+/// Tags go out in the same order as from the tiff crate's `ImageEncoder`, so the layout
+/// matches an image encoded by it. `extra_tags` runs after the standard tags, before the
+/// strip data.
+pub(crate) fn write_image_ifd<C, W>(
+    mut directory: DirectoryEncoder<'_, W, TiffKindStandard>,
+    layout: &StripLayout,
+    strips: &[Vec<u8>],
+    extra_tags: impl FnOnce(&mut DirectoryEncoder<'_, W, TiffKindStandard>) -> TiffResult<()>,
+) -> Result<u32>
+where
+    C: ColorType,
+    W: Write + Seek,
+{
+    let encode_err = |e: tiff::TiffError| ConversionError::EncodeError(e.to_string());
+    let offset_err = |_| ConversionError::EncodeError("TIFF output exceeds 4 GiB".to_string());
+
+    let sample_format: Vec<u16> = C::SAMPLE_FORMAT.iter().map(|format| format.to_u16()).collect();
+    let tags = (|| {
+        directory.write_tag(Tag::ImageWidth, layout.width as u32)?;
+        directory.write_tag(Tag::ImageLength, layout.height as u32)?;
+        directory.write_tag(Tag::Compression, layout.compression.to_u16())?;
+        directory.write_tag(Tag::Predictor, layout.predictor.to_u16())?;
+        directory.write_tag(Tag::BitsPerSample, C::BITS_PER_SAMPLE)?;
+        directory.write_tag(Tag::SampleFormat, &sample_format[..])?;
+        directory.write_tag(Tag::PhotometricInterpretation, C::TIFF_VALUE.to_u16())?;
+        directory.write_tag(Tag::RowsPerStrip, layout.rows_per_strip as u32)?;
+        directory.write_tag(Tag::SamplesPerPixel, C::BITS_PER_SAMPLE.len() as u16)?;
+        directory.write_tag(Tag::XResolution, Rational { n: 1, d: 1 })?;
+        directory.write_tag(Tag::YResolution, Rational { n: 1, d: 1 })?;
+        directory.write_tag(Tag::ResolutionUnit, ResolutionUnit::None.to_u16())?;
+        extra_tags(&mut directory)
+    })();
+    tags.map_err(encode_err)?;
+
+    let mut offsets = Vec::with_capacity(strips.len());
+    let mut byte_counts = Vec::with_capacity(strips.len());
+    for strip in strips {
+        let offset = directory.write_data(strip.as_slice()).map_err(encode_err)?;
+        offsets.push(u32::try_from(offset).map_err(offset_err)?);
+        byte_counts.push(u32::try_from(strip.len()).map_err(offset_err)?);
+    }
+    directory.write_tag(Tag::StripOffsets, &offsets[..]).map_err(encode_err)?;
+    directory.write_tag(Tag::StripByteCounts, &byte_counts[..]).map_err(encode_err)?;
+    Ok(directory.finish_with_offsets().map_err(encode_err)?.offset)
+}
@@ -0,0 +1,75 @@
+//! Multi-page TIFF writer for bursts of frames.
+//!
+//! Each page is written as its own image file directory (IFD) as soon as it is added,
+//! so frames are streamed to the output instead of being held until the end.
+
+use std::io::{Seek, Write};
+use tracing::debug;
+use crate::image_pipeline::common::error::{Result, ConversionError};
+use crate::image_pipeline::debayer::types::RgbImageData;
+use crate::image_pipeline::tiff::standard_tiff_writer::StandardTiffWriter;
+use crate::image_pipeline::tiff::types::ConversionConfig;
+
+/// Writes several RGB images as pages of a single TIFF file.
+///
+/// All pages share the compression settings of the config passed to `begin`,
+/// but each page may have its own dimensions.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ffed_protosat_rs::image_pipeline::{ConversionConfig, MultiPageTiffWriter, RgbImageData};
+///
+/// # fn frames() -> Vec<RgbImageData> { Vec::new() }
+/// let mut file = std::fs::File::create("burst.tiff").unwrap();
+/// let mut writer = MultiPageTiffWriter::begin(&mut file, &ConversionConfig::default()).unwrap();
+/// for frame in frames() {
+///     writer.add_page(&frame).unwrap();
+/// }
+/// writer.finish().unwrap();
+/// ```
+pub struct MultiPageTiffWriter<W: Write + Seek> {
+    encoder: tiff::encoder::TiffEncoder<W>,
+    pages: usize,
+}
+
+impl<W: Write + Seek> MultiPageTiffWriter<W> {
+    /// Writes the TIFF header to `output` and prepares for pages
+    pub fn begin(output: W, config: &ConversionConfig) -> Result<Self> {
+        Ok(Self {
+            encoder: StandardTiffWriter::create_encoder(output, config)?,
+            pages: 0,
+        })
+    }
+
+    /// Appends `image` as the next page
+    pub fn add_page(&mut self, image: &RgbImageData) -> Result<()> {
+        debug!("Encoding TIFF page {}: {}x{}", self.pages, image.width, image.height);
+
+        self.encoder.write_image::<tiff::encoder::colortype::RGB16>(
+            image.width as u32,
+            image.height as u32,
+            &image.data,
+        ).map_err(|e| ConversionError::EncodeError(e.to_string()))?;
+
+        self.pages += 1;
+        Ok(())
+    }
+
+    /// Number of pages written so far
+    pub fn page_count(&self) -> usize {
+        self.pages
+    }
+
+    /// Completes the file and returns the number of pages written
+    ///
+    /// A TIFF must contain at least one image, so finishing without pages is an error.
+    pub fn finish(self) -> Result<usize> {
+        if self.pages == 0 {
+            return Err(ConversionError::EncodeError("multi-page TIFF has no pages".to_string()));
+        }
+
+        debug!("Multi-page TIFF encoding complete, {} pages", self.pages);
+        Ok(self.pages)
+    }
+}
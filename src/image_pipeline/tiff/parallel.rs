@@ -0,0 +1,259 @@
+//! Strip-parallel compression for `StandardTiffWriter`
+//!
+//! The tiff crate compresses strips one after another on the calling thread. Here the
+//! image is split at the same strip boundaries, each strip is predicted and compressed
+//! as its own rayon task, and the results are written in order into an IFD laid out like
+//! the one `ImageEncoder` produces, so the file is byte-identical to serial encoding.
+//! It also applies the floating-point predictor, which the tiff crate cannot encode, so
+//! float images with that predictor always come through here, and writes planar images,
+//! which it cannot encode either.
+
+use std::io::{self, Seek, Write};
+
+use rayon::prelude::*;
+use tiff::encoder::colortype::{ColorType, Gray16, Gray32Float, RGB16, RGB32Float};
+use tiff::encoder::compression::{CompressionAlgorithm, Deflate, DeflateLevel, Lzw};
+use tiff::encoder::{DirectoryEncoder, Predictor, TiffEncoder, TiffKindStandard, TiffValue};
+use tiff::TiffResult;
+use tiff::tags::{CompressionMethod, PlanarConfiguration, SampleFormat, Tag};
+
+use crate::image_pipeline::common::error::{ConversionError, Result};
+use crate::image_pipeline::tiff::ifd::{StripLayout, write_image_ifd};
+use crate::image_pipeline::tiff::types::TiffCompression;
+
+/// Strip size the tiff crate targets when splitting an image into strips
+const STRIP_TARGET_BYTES: usize = 1_000_000;
+
+/// Color types `write_image_planar` can split into one plane per sample
+pub(crate) trait Planar: ColorType {
+    /// Single-sample color type of each plane
+    type Plane: ColorType<Inner = Self::Inner>;
+}
+
+impl Planar for Gray16 {
+    type Plane = Gray16;
+}
+
+impl Planar for RGB16 {
+    type Plane = Gray16;
+}
+
+impl Planar for RGB32Float {
+    type Plane = Gray32Float;
+}
+
+/// Whether `write_image_parallel` can encode `C` with these settings
+///
+/// Uncompressed output has nothing to parallelize, the horizontal predictor is rejected
+/// for float samples, which the serial path reports, and the floating-point predictor
+/// only applies to them.
+pub(crate) fn supports<C: ColorType>(compression: TiffCompression, predictor: Predictor) -> bool {
+    if matches!(compression, TiffCompression::None) {
+        return false;
+    }
+    let float = C::SAMPLE_FORMAT[0] == SampleFormat::IEEEFP;
+    match predictor {
+        Predictor::Horizontal => !float,
+        Predictor::FloatingPoint => float,
+        _ => true,
+    }
+}
+
+/// Appends one image to `encoder`, compressing its strips in parallel
+///
+/// `extra_tags` runs after the standard tags, as in `write_image_ifd`. Call only when
+/// `supports` holds.
+pub(crate) fn write_image_parallel<C, S>(
+    encoder: &mut TiffEncoder<S>,
+    width: usize,
+    height: usize,
+    data: &[C::Inner],
+    compression: TiffCompression,
+    predictor: Predictor,
+    extra_tags: impl FnOnce(&mut DirectoryEncoder<'_, S, TiffKindStandard>) -> TiffResult<()>,
+) -> Result<()>
+where
+    C: ColorType,
+    C::Inner: Sync,
+    [C::Inner]: TiffValue,
+    S: Write + Seek,
+{
+    let row_samples = width * C::BITS_PER_SAMPLE.len();
+    let row_bytes = row_samples * usize::from(<[C::Inner]>::BYTE_LEN);
+    if width == 0 || height == 0 || data.len() < row_samples * height {
+        return Err(ConversionError::EncodeError(format!(
+            "{} samples do not fill a {}x{} image",
+            data.len(),
+            width,
+            height
+        )));
+    }
+    let rows_per_strip = STRIP_TARGET_BYTES.div_ceil(row_bytes);
+
+    let strips = data[..row_samples * height]
+        .par_chunks(rows_per_strip * row_samples)
+        .map(|strip| compress_strip::<C>(strip, row_samples, compression, predictor))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let directory = encoder
+        .image_directory()
+        .map_err(|e| ConversionError::EncodeError(e.to_string()))?;
+    let layout = StripLayout {
+        width,
+        height,
+        rows_per_strip,
+        compression: compression_method(compression),
+        predictor,
+    };
+    write_image_ifd::<C, _>(directory, &layout, &strips, extra_tags)?;
+    Ok(())
+}
+
+/// Appends one image to `encoder` with `PlanarConfiguration=2` (RRR...GGG...BBB...)
+///
+/// `data` is deinterleaved into one plane per sample, every plane is split into strips
+/// at the same rows and all strips are compressed in parallel. Strips are stored plane
+/// after plane, and predictors work within a plane. Unlike `write_image_parallel` this
+/// also handles uncompressed output; the predictor must still match the sample format.
+pub(crate) fn write_image_planar<C, S>(
+    encoder: &mut TiffEncoder<S>,
+    width: usize,
+    height: usize,
+    data: &[C::Inner],
+    compression: TiffCompression,
+    predictor: Predictor,
+    extra_tags: impl FnOnce(&mut DirectoryEncoder<'_, S, TiffKindStandard>) -> TiffResult<()>,
+) -> Result<()>
+where
+    C: Planar,
+    C::Inner: Copy + Send + Sync,
+    [C::Inner]: TiffValue,
+    S: Write + Seek,
+{
+    let channels = C::BITS_PER_SAMPLE.len();
+    let pixels = width * height;
+    if width == 0 || height == 0 || data.len() < pixels * channels {
+        return Err(ConversionError::EncodeError(format!(
+            "{} samples do not fill a {}x{} image",
+            data.len(),
+            width,
+            height
+        )));
+    }
+    let row_bytes = width * usize::from(<[C::Inner]>::BYTE_LEN);
+    let rows_per_strip = STRIP_TARGET_BYTES.div_ceil(row_bytes);
+
+    let planes: Vec<Vec<C::Inner>> = (0..channels)
+        .into_par_iter()
+        .map(|channel| data[..pixels * channels].iter().skip(channel).step_by(channels).copied().collect())
+        .collect();
+    let strips = planes
+        .par_iter()
+        .flat_map(|plane| plane.par_chunks(rows_per_strip * width))
+        .map(|strip| compress_strip::<C::Plane>(strip, width, compression, predictor))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let directory = encoder
+        .image_directory()
+        .map_err(|e| ConversionError::EncodeError(e.to_string()))?;
+    let layout = StripLayout {
+        width,
+        height,
+        rows_per_strip,
+        compression: compression_method(compression),
+        predictor,
+    };
+    write_image_ifd::<C, _>(directory, &layout, &strips, |directory| {
+        directory.write_tag(Tag::PlanarConfiguration, PlanarConfiguration::Planar.to_u16())?;
+        extra_tags(directory)
+    })?;
+    Ok(())
+}
+
+/// Applies `predictor` row by row and compresses the strip's native-endian bytes
+fn compress_strip<C>(
+    strip: &[C::Inner],
+    row_samples: usize,
+    compression: TiffCompression,
+    predictor: Predictor,
+) -> io::Result<Vec<u8>>
+where
+    C: ColorType,
+    [C::Inner]: TiffValue,
+{
+    let predicted;
+    let samples = if predictor == Predictor::Horizontal {
+        let mut rows = Vec::with_capacity(strip.len());
+        for row in strip.chunks_exact(row_samples) {
+            C::horizontal_predict(row, &mut rows);
+        }
+        predicted = rows;
+        &predicted[..]
+    } else {
+        strip
+    };
+    let bytes = match predictor {
+        Predictor::FloatingPoint => float_predict(
+            &samples.data(),
+            row_samples,
+            usize::from(<[C::Inner]>::BYTE_LEN),
+            C::BITS_PER_SAMPLE.len(),
+        )
+        .into(),
+        _ => samples.data(),
+    };
+
+    let mut compressed = Vec::new();
+    match compression {
+        TiffCompression::None => compressed.extend_from_slice(&bytes),
+        TiffCompression::Lzw => {
+            Lzw.write_to(&mut compressed, &bytes)?;
+        }
+        TiffCompression::DeflateFast => {
+            Deflate::with_level(DeflateLevel::Fast).write_to(&mut compressed, &bytes)?;
+        }
+        TiffCompression::DeflateBalanced => {
+            Deflate::with_level(DeflateLevel::Balanced).write_to(&mut compressed, &bytes)?;
+        }
+        TiffCompression::DeflateBest => {
+            Deflate::with_level(DeflateLevel::Best).write_to(&mut compressed, &bytes)?;
+        }
+    }
+    Ok(compressed)
+}
+
+/// TIFF Technical Note 3 floating-point predictor over native-endian `bytes`
+///
+/// Each row is split into byte planes, most significant byte first, and every byte is
+/// replaced by its difference from the byte `channels` positions earlier in the row.
+fn float_predict(bytes: &[u8], row_samples: usize, sample_bytes: usize, channels: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut planes = vec![0u8; row_samples * sample_bytes];
+    for row in bytes.chunks_exact(planes.len()) {
+        for (i, sample) in row.chunks_exact(sample_bytes).enumerate() {
+            for plane in 0..sample_bytes {
+                let byte = if cfg!(target_endian = "little") {
+                    sample[sample_bytes - 1 - plane]
+                } else {
+                    sample[plane]
+                };
+                planes[plane * row_samples + i] = byte;
+            }
+        }
+        for i in (channels..planes.len()).rev() {
+            planes[i] = planes[i].wrapping_sub(planes[i - channels]);
+        }
+        out.extend_from_slice(&planes);
+    }
+    out
+}
+
+fn compression_method(compression: TiffCompression) -> CompressionMethod {
+    match compression {
+        TiffCompression::None => CompressionMethod::None,
+        TiffCompression::Lzw => CompressionMethod::LZW,
+        TiffCompression::DeflateFast
+        | TiffCompression::DeflateBalanced
+        | TiffCompression::DeflateBest => CompressionMethod::Deflate,
+    }
+}
@@ -0,0 +1,49 @@
+//! Default conversion settings tuned per camera model
+//!
+//! Models are matched by their EXIF model name (e.g. `ILCE-7M3`), ignoring case and
+//! surrounding whitespace. Anything unknown gets `ConversionConfig::default()`.
+
+use crate::image_pipeline::debayer::WhiteBalance;
+use crate::image_pipeline::tiff::types::ConversionConfig;
+
+/// Settings a preset changes from `ConversionConfig::default()`
+struct Preset {
+    /// EXIF model names sharing the preset
+    models: &'static [&'static str],
+    exposure: f32,
+    baseline_exposure: f32,
+    white_balance: WhiteBalance,
+}
+
+const PRESETS: &[Preset] = &[
+    // Sony a7 III
+    Preset {
+        models: &["ILCE-7M3"],
+        exposure: 3.0,
+        baseline_exposure: 0.35,
+        white_balance: WhiteBalance::AsShot,
+    },
+    // Sony a6400 and a6100, same 24 MP APS-C sensor
+    Preset {
+        models: &["ILCE-6400", "ILCE-6100"],
+        exposure: 3.2,
+        baseline_exposure: 0.35,
+        white_balance: WhiteBalance::AsShot,
+    },
+];
+
+/// Default config for the camera `model`, with its tuned exposure and white balance if it
+/// is a known model and the generic defaults otherwise
+pub fn config_for_model(model: &str) -> ConversionConfig {
+    let model = model.trim();
+    let default = ConversionConfig::default();
+    match PRESETS.iter().find(|preset| preset.models.iter().any(|m| m.eq_ignore_ascii_case(model))) {
+        Some(preset) => ConversionConfig {
+            exposure: preset.exposure,
+            baseline_exposure: preset.baseline_exposure,
+            white_balance: preset.white_balance,
+            ..default
+        },
+        None => default,
+    }
+}
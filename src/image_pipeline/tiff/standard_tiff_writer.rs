@@ -1,13 +1,47 @@
-use std::io::Write;
+use std::io::{Cursor, Seek, Write};
 use tracing::debug;
+use tiff::encoder::colortype::ColorType;
+use tiff::encoder::{DirectoryEncoder, Rational, TiffEncoder, TiffKindStandard, TiffValue};
+use tiff::TiffResult;
+use tiff::tags::{PhotometricInterpretation, SampleFormat, Tag};
 use crate::image_pipeline::common::error::{Result, ConversionError};
+use crate::image_pipeline::raw::exif::ExifMetadata;
 use crate::image_pipeline::raw::types::RawImageData;
-use crate::image_pipeline::debayer::types::RgbImageData;
-use crate::image_pipeline::tiff::types::{ConversionConfig, TiffCompression};
-use crate::image_pipeline::tiff::writer::TiffWriter;
+use crate::image_pipeline::debayer::types::{GrayImageData, RgbImageData, RgbImageDataF32};
+use crate::image_pipeline::tiff::types::{ByteOrder, ConversionConfig, OutputMode, TiffCompression};
+use crate::image_pipeline::tiff::icc::{self, IccProfile};
+use crate::image_pipeline::tiff::ifd::LinkedIfds;
+use crate::image_pipeline::tiff::parallel;
+use crate::image_pipeline::tiff::thumbnail::Thumbnail;
+use crate::image_pipeline::tiff::writer::{TiffWriter, write_rgb_rows_buffered};
 
 pub struct StandardTiffWriter;
 
+/// EXIF `Gamma` tag, written to the image IFD itself so readers see it without
+/// following the EXIF link
+const GAMMA_TAG: u16 = 0xA500;
+
+/// Tags written after the standard image tags: links to the EXIF and thumbnail IFDs,
+/// `photometric` replacing the color type's `PhotometricInterpretation`, the ICC profile,
+/// `MaxSampleValue` and whether the samples are sRGB gamma encoded, written as `Gamma`
+#[derive(Clone, Copy)]
+struct ExtraTags<'a> {
+    links: LinkedIfds,
+    photometric: Option<PhotometricInterpretation>,
+    icc: Option<&'a IccProfile>,
+    max_sample_value: Option<u16>,
+    srgb_gamma: bool,
+}
+
+/// `MaxSampleValue` for grayscale samples of `bits_per_sample` bits held in a 16-bit
+/// container, `None` when they use the full range
+///
+/// The tiff encoder can only write whole bytes per sample, so 12- and 14-bit data keeps
+/// `BitsPerSample=16` and this tag tells readers how much of the range is used.
+pub(crate) fn max_sample_value(bits_per_sample: u32) -> Option<u16> {
+    (bits_per_sample < 16).then(|| ((1u32 << bits_per_sample) - 1) as u16)
+}
+
 impl StandardTiffWriter {
     fn get_compression(compression: TiffCompression) -> tiff::encoder::Compression {
         match compression {
@@ -19,22 +53,213 @@ impl StandardTiffWriter {
         }
     }
 
-    fn create_encoder<'a>(buffer: &'a mut Vec<u8>, config: &ConversionConfig) -> Result<tiff::encoder::TiffEncoder<std::io::Cursor<&'a mut Vec<u8>>>> {
+    /// Predictor the encoder applies to the integer images it writes itself
+    fn get_predictor(predictor: Option<u16>) -> tiff::tags::Predictor {
+        match predictor {
+            Some(2 | 3) => tiff::tags::Predictor::Horizontal,
+            _ => tiff::tags::Predictor::None,
+        }
+    }
+    
+    /// Predictor for samples of `C`: a configured predictor becomes the floating-point one
+    /// for float samples, dropped for uncompressed float output, which can't carry it
+    fn predictor_for<C: ColorType>(config: &ConversionConfig) -> tiff::tags::Predictor {
+        let predictor = Self::get_predictor(config.predictor);
+        if C::SAMPLE_FORMAT[0] != SampleFormat::IEEEFP || predictor == tiff::tags::Predictor::None {
+            predictor
+        } else if matches!(config.compression, TiffCompression::None) {
+            tiff::tags::Predictor::None
+        } else {
+            tiff::tags::Predictor::FloatingPoint
+        }
+    }
+
+    /// Encoder writing the header for `config`, failing before any bytes are written when
+    /// `byte_order` is not the host's, which the tiff crate can't produce
+    pub(crate) fn create_encoder<S: Write + Seek>(writer: S, config: &ConversionConfig) -> Result<tiff::encoder::TiffEncoder<S>> {
+        if config.byte_order != ByteOrder::NATIVE {
+            return Err(ConversionError::UnsupportedFormat(format!(
+                "{:?} TIFF output: the tiff encoder only writes the host's byte order ({:?})",
+                config.byte_order,
+                ByteOrder::NATIVE
+            )));
+        }
         let compression = Self::get_compression(config.compression);
         
-        let mut encoder = tiff::encoder::TiffEncoder::new(std::io::Cursor::new(buffer))
+        let encoder = tiff::encoder::TiffEncoder::new(writer)
             .map_err(|e| ConversionError::EncodeError(e.to_string()))?
-            .with_compression(compression);
+            .with_compression(compression)
+            .with_predictor(Self::get_predictor(config.predictor));
+        
+        Ok(encoder)
+    }
+
+    /// Writes the EXIF IFD (unlinked from the image chain) and returns its offset
+    fn write_exif_directory<S: Write + Seek>(encoder: &mut TiffEncoder<S>, exif: &ExifMetadata) -> Result<u32> {
+        let encode_err = |e: tiff::TiffError| ConversionError::EncodeError(e.to_string());
+        let rational = |(n, d): (u32, u32)| Rational { n, d };
         
-        if let Some(predictor_val) = config.predictor {
-            let predictor = match predictor_val {
-                2 => tiff::tags::Predictor::Horizontal,
-                _ => tiff::tags::Predictor::None,
-            };
-            encoder = encoder.with_predictor(predictor);
+        let mut directory = encoder.extra_directory().map_err(encode_err)?;
+        if let Some(value) = exif.exposure_time {
+            directory.write_tag(Tag::Unknown(ExifMetadata::EXPOSURE_TIME), rational(value)).map_err(encode_err)?;
+        }
+        if let Some(value) = exif.f_number {
+            directory.write_tag(Tag::Unknown(ExifMetadata::F_NUMBER), rational(value)).map_err(encode_err)?;
+        }
+        if let Some(value) = exif.iso {
+            directory.write_tag(Tag::Unknown(ExifMetadata::ISO_SPEED_RATINGS), value).map_err(encode_err)?;
+        }
+        if let Some(value) = exif.focal_length {
+            directory.write_tag(Tag::Unknown(ExifMetadata::FOCAL_LENGTH), rational(value)).map_err(encode_err)?;
         }
         
-        Ok(encoder)
+        Ok(directory.finish_with_offsets().map_err(encode_err)?.offset)
+    }
+
+    /// Encodes a single image into an in-memory TIFF, linking an EXIF IFD when
+    /// `preserve_exif` is set and the source carried any EXIF tags, and `thumbnail`
+    /// as a reduced-resolution sub-IFD. Grayscale images get `config.photometric`, RGB
+    /// images the linear sRGB profile when `embed_icc` is set, and `max_sample_value`
+    /// is written as `MaxSampleValue`. With `planar` set, multi-sample images are stored
+    /// one plane per sample
+    fn encode<C: parallel::Planar>(
+        width: usize,
+        height: usize,
+        data: &[C::Inner],
+        exif: &ExifMetadata,
+        thumbnail: Option<Thumbnail>,
+        max_sample_value: Option<u16>,
+        config: &ConversionConfig,
+    ) -> Result<Vec<u8>>
+    where
+        C::Inner: Copy + Send + Sync,
+        [C::Inner]: TiffValue,
+    {
+        let encode_err = |e: tiff::TiffError| ConversionError::EncodeError(e.to_string());
+        
+        // tiff 0.10 can't apply the floating-point predictor, so those images always take
+        // the strip compressor and the encoder itself is left without a predictor
+        let predictor = Self::predictor_for::<C>(config);
+        let float_predictor = predictor == tiff::tags::Predictor::FloatingPoint;
+        let encoder_predictor = if float_predictor { tiff::tags::Predictor::None } else { predictor };
+        
+        let mut buffer = Vec::new();
+        let mut encoder = Self::create_encoder(Cursor::new(&mut buffer), config)?.with_predictor(encoder_predictor);
+        let links = LinkedIfds {
+            exif: Self::write_exif_if_enabled(&mut encoder, exif, config)?,
+            thumbnail: thumbnail.map(|thumbnail| thumbnail.write(&mut encoder)).transpose()?,
+        };
+        
+        // Overrides the tag the color type writes, only ever for grayscale
+        let photometric = match C::TIFF_VALUE {
+            PhotometricInterpretation::BlackIsZero => Some(config.photometric.to_tiff()),
+            _ => None,
+        }
+        .filter(|&photometric| photometric != C::TIFF_VALUE);
+        let icc = Self::icc_profile(C::TIFF_VALUE, config);
+        let extra = ExtraTags {
+            links,
+            photometric,
+            icc: icc.as_ref(),
+            max_sample_value,
+            srgb_gamma: Self::gamma_encoded(C::TIFF_VALUE, config),
+        };
+        if config.planar && C::BITS_PER_SAMPLE.len() > 1 {
+            debug!("Writing planar samples");
+            parallel::write_image_planar::<C, _>(
+                &mut encoder,
+                width,
+                height,
+                data,
+                config.compression,
+                predictor,
+                |directory| Self::write_extra_tags(directory, extra),
+            )?;
+            return Ok(buffer);
+        }
+        if (config.parallel_strips || float_predictor) && parallel::supports::<C>(config.compression, predictor) {
+            debug!("Compressing strips in parallel");
+            parallel::write_image_parallel::<C, _>(
+                &mut encoder,
+                width,
+                height,
+                data,
+                config.compression,
+                predictor,
+                |directory| Self::write_extra_tags(directory, extra),
+            )?;
+            return Ok(buffer);
+        }
+        
+        let mut image = encoder.new_image::<C>(width as u32, height as u32).map_err(encode_err)?;
+        Self::write_extra_tags(image.encoder(), extra).map_err(encode_err)?;
+        image.write_data(data).map_err(encode_err)?;
+        
+        Ok(buffer)
+    }
+    
+    fn write_extra_tags<W: Write + Seek>(
+        directory: &mut DirectoryEncoder<'_, W, TiffKindStandard>,
+        extra: ExtraTags<'_>,
+    ) -> TiffResult<()> {
+        extra.links.write_tags(directory)?;
+        if let Some(photometric) = extra.photometric {
+            directory.write_tag(Tag::PhotometricInterpretation, photometric.to_u16())?;
+        }
+        if let Some(icc) = extra.icc {
+            directory.write_tag(Tag::IccProfile, icc)?;
+        }
+        if let Some(max) = extra.max_sample_value {
+            directory.write_tag(Tag::MaxSampleValue, max)?;
+        }
+        let gamma = if extra.srgb_gamma { Rational { n: 22, d: 10 } } else { Rational { n: 1, d: 1 } };
+        directory.write_tag(Tag::Unknown(GAMMA_TAG), gamma)?;
+        Ok(())
+    }
+    
+    /// Whether `apply_srgb_gamma` encoded the samples: RGB output and the luminance derived
+    /// from it are, the Bayer mosaic never is
+    fn gamma_encoded(photometric: PhotometricInterpretation, config: &ConversionConfig) -> bool {
+        config.apply_srgb_gamma
+            && (photometric == PhotometricInterpretation::RGB || config.output == OutputMode::Luminance)
+    }
+    
+    /// Profile for `config.embed_icc`, `None` when disabled or the image is not RGB
+    fn icc_profile(photometric: PhotometricInterpretation, config: &ConversionConfig) -> Option<IccProfile> {
+        if !config.embed_icc || photometric != PhotometricInterpretation::RGB {
+            return None;
+        }
+        Some(IccProfile(if config.apply_srgb_gamma {
+            icc::srgb_profile()
+        } else {
+            icc::linear_srgb_profile()
+        }))
+    }
+    
+    /// Preview for `config.embed_thumbnail`, `None` when disabled or the image already fits
+    fn thumbnail<T: Copy>(
+        data: &[T],
+        width: usize,
+        height: usize,
+        config: &ConversionConfig,
+        to_unit: impl Fn(T) -> f32,
+    ) -> Option<Thumbnail> {
+        let max_edge = config.embed_thumbnail?;
+        let _span = tracing::info_span!("thumbnail", max_edge).entered();
+        Thumbnail::from_rgb(data, width, height, max_edge, to_unit)
+    }
+    
+    /// Writes the EXIF IFD when `preserve_exif` is set and the source carried any EXIF tags
+    fn write_exif_if_enabled<S: Write + Seek>(
+        encoder: &mut TiffEncoder<S>,
+        exif: &ExifMetadata,
+        config: &ConversionConfig,
+    ) -> Result<Option<u32>> {
+        if config.preserve_exif && !exif.is_empty() {
+            Self::write_exif_directory(encoder, exif).map(Some)
+        } else {
+            Ok(None)
+        }
     }
 }
 
@@ -42,14 +267,15 @@ impl TiffWriter for StandardTiffWriter {
     fn write_tiff(&self, image: &RawImageData, output: &mut dyn Write, config: &ConversionConfig) -> Result<()> {
         debug!("Encoding grayscale TIFF image: {}x{}", image.width, image.height);
         
-        let mut buffer = Vec::new();
-        let mut encoder = Self::create_encoder(&mut buffer, config)?;
-        
-        encoder.write_image::<tiff::encoder::colortype::Gray16>(
-            image.width as u32,
-            image.height as u32,
+        let buffer = Self::encode::<tiff::encoder::colortype::Gray16>(
+            image.width,
+            image.height,
             &image.data,
-        ).map_err(|e| ConversionError::EncodeError(e.to_string()))?;
+            &image.exif,
+            None,
+            max_sample_value(image.bits_per_sample),
+            config,
+        )?;
         
         output.write_all(&buffer)?;
         
@@ -57,21 +283,138 @@ impl TiffWriter for StandardTiffWriter {
         Ok(())
     }
     
+    fn write_gray_tiff(&self, image: &GrayImageData, output: &mut dyn Write, config: &ConversionConfig) -> Result<()> {
+        debug!("Encoding gray TIFF image: {}x{}", image.width, image.height);
+        
+        let buffer = Self::encode::<tiff::encoder::colortype::Gray16>(
+            image.width,
+            image.height,
+            &image.data,
+            &image.exif,
+            None,
+            max_sample_value(image.bits_per_sample),
+            config,
+        )?;
+        
+        output.write_all(&buffer)?;
+        
+        debug!("Gray TIFF encoding complete");
+        Ok(())
+    }
+    
     fn write_rgb_tiff(&self, image: &RgbImageData, output: &mut dyn Write, config: &ConversionConfig) -> Result<()> {
         debug!("Encoding RGB TIFF image: {}x{}", image.width, image.height);
         
-        let mut buffer = Vec::new();
-        let mut encoder = Self::create_encoder(&mut buffer, config)?;
-        
-        encoder.write_image::<tiff::encoder::colortype::RGB16>(
-            image.width as u32,
-            image.height as u32,
+        let thumbnail = Self::thumbnail(&image.data, image.width, image.height, config, |v: u16| {
+            v as f32 / u16::MAX as f32
+        });
+        let buffer = Self::encode::<tiff::encoder::colortype::RGB16>(
+            image.width,
+            image.height,
             &image.data,
-        ).map_err(|e| ConversionError::EncodeError(e.to_string()))?;
+            &image.exif,
+            thumbnail,
+            None,
+            config,
+        )?;
         
         output.write_all(&buffer)?;
         
         debug!("RGB TIFF encoding complete");
         Ok(())
     }
+    
+    fn write_rgb_tiff_f32(&self, image: &RgbImageDataF32, output: &mut dyn Write, config: &ConversionConfig) -> Result<()> {
+        debug!("Encoding RGB float TIFF image: {}x{}", image.width, image.height);
+        
+        let thumbnail = Self::thumbnail(&image.data, image.width, image.height, config, |v: f32| v);
+        let buffer = Self::encode::<tiff::encoder::colortype::RGB32Float>(
+            image.width,
+            image.height,
+            &image.data,
+            &image.exif,
+            thumbnail,
+            None,
+            config,
+        )?;
+        
+        output.write_all(&buffer)?;
+        
+        debug!("RGB float TIFF encoding complete");
+        Ok(())
+    }
+    
+    /// Encodes strip by strip straight into `output`, holding one strip of rows at a time
+    ///
+    /// tiff 0.10 only applies compression when a whole image is written at once, and a
+    /// thumbnail needs every row and planar output every plane, so compressed output,
+    /// `embed_thumbnail` and `planar` fall back to the buffered default implementation.
+    fn write_rgb_tiff_streaming<'a, I, S>(
+        &self,
+        width: usize,
+        height: usize,
+        rows: I,
+        exif: &ExifMetadata,
+        output: S,
+        config: &ConversionConfig,
+    ) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a [u16]>,
+        S: Write + Seek,
+    {
+        if !matches!(config.compression, TiffCompression::None) || config.embed_thumbnail.is_some() || config.planar {
+            debug!("Compressed, planar or thumbnail output requested, buffering rows before encoding");
+            return write_rgb_rows_buffered(self, width, height, rows, exif, output, config);
+        }
+        
+        debug!("Streaming RGB TIFF image: {}x{}", width, height);
+        let encode_err = |e: tiff::TiffError| ConversionError::EncodeError(e.to_string());
+        let row_err = |message: String| ConversionError::EncodeError(format!("Streaming {}x{} RGB: {}", width, height, message));
+        
+        let mut encoder = Self::create_encoder(output, config)?;
+        let links = LinkedIfds {
+            exif: Self::write_exif_if_enabled(&mut encoder, exif, config)?,
+            thumbnail: None,
+        };
+        
+        let mut image = encoder
+            .new_image::<tiff::encoder::colortype::RGB16>(width as u32, height as u32)
+            .map_err(encode_err)?;
+        let icc = Self::icc_profile(PhotometricInterpretation::RGB, config);
+        let extra = ExtraTags {
+            links,
+            photometric: None,
+            icc: icc.as_ref(),
+            max_sample_value: None,
+            srgb_gamma: Self::gamma_encoded(PhotometricInterpretation::RGB, config),
+        };
+        Self::write_extra_tags(image.encoder(), extra).map_err(encode_err)?;
+        
+        let row_len = width * 3;
+        let mut rows = rows.into_iter().enumerate();
+        let mut strip = Vec::new();
+        loop {
+            let strip_len = image.next_strip_sample_count() as usize;
+            if strip_len == 0 {
+                break;
+            }
+            
+            strip.clear();
+            while strip.len() < strip_len {
+                let (index, row) = rows.next().ok_or_else(|| row_err("ran out of rows".to_string()))?;
+                if row.len() != row_len {
+                    return Err(row_err(format!("row {} holds {} samples, expected {}", index, row.len(), row_len)));
+                }
+                strip.extend_from_slice(row);
+            }
+            image.write_strip(&strip).map_err(encode_err)?;
+        }
+        if rows.next().is_some() {
+            return Err(row_err(format!("more than {} rows supplied", height)));
+        }
+        image.finish().map_err(encode_err)?;
+        
+        debug!("Streaming RGB TIFF encoding complete");
+        Ok(())
+    }
 }
@@ -0,0 +1,93 @@
+//! Reduced-resolution previews embedded by `StandardTiffWriter`
+
+use std::io::{Seek, Write};
+
+use tiff::encoder::colortype::RGB8;
+use tiff::encoder::{Predictor, TiffEncoder};
+use tiff::tags::{CompressionMethod, Tag};
+
+use crate::image_pipeline::common::error::{ConversionError, Result};
+use crate::image_pipeline::tiff::ifd::{StripLayout, write_image_ifd};
+
+/// `NewSubfileType` value marking a reduced-resolution version of another image
+const REDUCED_RESOLUTION: u32 = 1;
+
+/// 8-bit RGB preview of an image
+pub(crate) struct Thumbnail {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+/// Size of the thumbnail of a `width`x`height` image with its longer edge at `max_edge`
+///
+/// `None` when the image already fits, as a preview would not be any smaller.
+pub(crate) fn thumbnail_dimensions(width: usize, height: usize, max_edge: u32) -> Option<(usize, usize)> {
+    let max_edge = max_edge as usize;
+    let long_edge = width.max(height);
+    if max_edge == 0 || long_edge <= max_edge {
+        return None;
+    }
+    let scale = |edge: usize| ((edge * max_edge) as f64 / long_edge as f64).round().max(1.0) as usize;
+    Some((scale(width), scale(height)))
+}
+
+impl Thumbnail {
+    /// Box-filters interleaved RGB down to `thumbnail_dimensions`
+    ///
+    /// `to_unit` maps a sample to 0.0..=1.0; the average is clamped and quantized to 8 bits.
+    pub(crate) fn from_rgb<T: Copy>(
+        data: &[T],
+        width: usize,
+        height: usize,
+        max_edge: u32,
+        to_unit: impl Fn(T) -> f32,
+    ) -> Option<Self> {
+        let (thumb_width, thumb_height) = thumbnail_dimensions(width, height, max_edge)?;
+        // Thumbnails are strictly smaller, so every span covers at least one source pixel
+        let span = |index: usize, thumb_len: usize, len: usize| index * len / thumb_len..(index + 1) * len / thumb_len;
+
+        let mut out = Vec::with_capacity(thumb_width * thumb_height * 3);
+        for ty in 0..thumb_height {
+            let rows = span(ty, thumb_height, height);
+            for tx in 0..thumb_width {
+                let columns = span(tx, thumb_width, width);
+                let mut sum = [0.0f32; 3];
+                for y in rows.clone() {
+                    for x in columns.clone() {
+                        let pixel = (y * width + x) * 3;
+                        for (c, total) in sum.iter_mut().enumerate() {
+                            *total += to_unit(data[pixel + c]);
+                        }
+                    }
+                }
+                let count = (rows.len() * columns.len()) as f32;
+                out.extend(sum.map(|total| ((total / count).clamp(0.0, 1.0) * 255.0).round() as u8));
+            }
+        }
+
+        Some(Thumbnail {
+            width: thumb_width,
+            height: thumb_height,
+            data: out,
+        })
+    }
+
+    /// Writes the thumbnail as an uncompressed IFD outside the main image chain,
+    /// returning its offset for the parent's `SubIfd` tag
+    pub(crate) fn write<S: Write + Seek>(&self, encoder: &mut TiffEncoder<S>) -> Result<u32> {
+        let directory = encoder
+            .extra_directory()
+            .map_err(|e| ConversionError::EncodeError(e.to_string()))?;
+        let layout = StripLayout {
+            width: self.width,
+            height: self.height,
+            rows_per_strip: self.height,
+            compression: CompressionMethod::None,
+            predictor: Predictor::None,
+        };
+        write_image_ifd::<RGB8, _>(directory, &layout, std::slice::from_ref(&self.data), |directory| {
+            directory.write_tag(Tag::NewSubfileType, REDUCED_RESOLUTION)
+        })
+    }
+}
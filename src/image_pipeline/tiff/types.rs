@@ -1,5 +1,15 @@
 //! TIFF conversion configuration types
 
+use crate::image_pipeline::common::error::{ConversionError, Result};
+use crate::image_pipeline::debayer::types::{ColorTransform, DebayerBackend, DebayerQuality, NppInterpolation, WorkingPrecision};
+use crate::image_pipeline::debayer::border::BorderMode;
+use crate::image_pipeline::debayer::denoise::DenoiseStrength;
+use crate::image_pipeline::debayer::quantize::OverflowMode;
+use crate::image_pipeline::debayer::tone_curve::ToneCurve;
+use crate::image_pipeline::debayer::white_balance::WhiteBalance;
+use std::time::Duration;
+use tiff::tags::PhotometricInterpretation;
+
 /// TIFF compression methods
 #[derive(Debug, Clone, Copy)]
 pub enum TiffCompression {
@@ -15,18 +25,286 @@ pub enum TiffCompression {
     DeflateBalanced,
 }
 
+impl TiffCompression {
+    /// Rough compressed / uncompressed size ratio for 16-bit sensor data
+    ///
+    /// A heuristic only: noisy high-ISO frames compress worse, flat frames much better.
+    pub fn estimated_ratio(self) -> f32 {
+        match self {
+            TiffCompression::None => 1.0,
+            TiffCompression::Lzw => 0.75,
+            TiffCompression::DeflateFast => 0.70,
+            TiffCompression::DeflateBalanced => 0.65,
+            TiffCompression::DeflateBest => 0.62,
+        }
+    }
+}
+
+/// What kind of image the pipeline writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Debayered RGB (RGB16)
+    Rgb,
+    /// Undemosaiced sensor data as grayscale (Gray16)
+    BayerGray,
+    /// Rec.709 luminance of the debayered RGB (Gray16)
+    Luminance,
+}
+
+impl OutputMode {
+    /// Whether this mode needs the debayer backend
+    pub fn requires_debayer(self) -> bool {
+        !matches!(self, OutputMode::BayerGray)
+    }
+}
+
+/// `PhotometricInterpretation` tag written for grayscale output
+///
+/// Only the tag changes, samples are written as they are either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GrayPhotometric {
+    /// Sample 0 is black
+    #[default]
+    BlackIsZero,
+    /// Sample 0 is white
+    WhiteIsZero,
+}
+
+impl GrayPhotometric {
+    pub(crate) fn to_tiff(self) -> PhotometricInterpretation {
+        match self {
+            GrayPhotometric::BlackIsZero => PhotometricInterpretation::BlackIsZero,
+            GrayPhotometric::WhiteIsZero => PhotometricInterpretation::WhiteIsZero,
+        }
+    }
+}
+
+/// Order of the interleaved channels in RGB output
+///
+/// Only the sample order changes; the file is still tagged as RGB, so this is for
+/// downstream tools that expect a fixed layout rather than for TIFF readers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelOrder {
+    /// Red, green, blue
+    #[default]
+    Rgb,
+    /// Red, blue, green
+    Rbg,
+    /// Green, red, blue
+    Grb,
+    /// Green, blue, red
+    Gbr,
+    /// Blue, red, green
+    Brg,
+    /// Blue, green, red
+    Bgr,
+}
+
+impl ChannelOrder {
+    /// Index in RGB order of the channel stored at each output position
+    pub fn source_indices(self) -> [usize; 3] {
+        match self {
+            ChannelOrder::Rgb => [0, 1, 2],
+            ChannelOrder::Rbg => [0, 2, 1],
+            ChannelOrder::Grb => [1, 0, 2],
+            ChannelOrder::Gbr => [1, 2, 0],
+            ChannelOrder::Brg => [2, 0, 1],
+            ChannelOrder::Bgr => [2, 1, 0],
+        }
+    }
+
+    /// Permutes interleaved RGB samples in place into this order
+    pub fn apply<T: Copy>(self, data: &mut [T]) {
+        if self == ChannelOrder::Rgb {
+            return;
+        }
+        let [a, b, c] = self.source_indices();
+        for px in data.chunks_exact_mut(3) {
+            let rgb = [px[0], px[1], px[2]];
+            px.copy_from_slice(&[rgb[a], rgb[b], rgb[c]]);
+        }
+    }
+}
+
+/// Byte order of TIFF output, announced by the `II` or `MM` at the start of the file
+///
+/// The tiff encoder only writes the host's byte order, so `StandardTiffWriter` rejects
+/// the other one. The default is the host's order, little-endian on every target the
+/// pipeline is built for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// `II`, least significant byte first
+    LittleEndian,
+    /// `MM`, most significant byte first
+    BigEndian,
+}
+
+impl ByteOrder {
+    /// Byte order of the host, the only one the tiff encoder writes
+    pub const NATIVE: ByteOrder = if cfg!(target_endian = "little") {
+        ByteOrder::LittleEndian
+    } else {
+        ByteOrder::BigEndian
+    };
+}
+
+impl Default for ByteOrder {
+    fn default() -> Self {
+        ByteOrder::NATIVE
+    }
+}
+
+/// Linear gain applied with the color matrix when no exposure is configured
+pub const DEFAULT_EXPOSURE: f32 = 3.5;
+
+/// Lossy `WebpWriter` quality when none is configured
+pub const DEFAULT_WEBP_QUALITY: f32 = 80.0;
+
 /// Configuration for RAW to TIFF conversion
 #[derive(Debug, Clone)]
 pub struct ConversionConfig {
     /// Compression method to use
     pub compression: TiffCompression,
-    /// Predictor value for compression (typically 2 for horizontal differencing)
+    /// Predictor value for compression: 2 (horizontal differencing) or 3 (floating point)
+    /// enable the predictor suited to the output, horizontal for integer samples and
+    /// floating point for float samples, which then always use the strip compressor
     /// Note: Predictor adds processing time, set to None for maximum speed
     pub predictor: Option<u16>,
     /// Whether to validate image dimensions before conversion
     pub validate_dimensions: bool,
-    /// Whether to debayer the image to RGB (true) or output grayscale Bayer (false)
-    pub debayer: bool,
+    /// Largest accepted width or height, checked when `validate_dimensions` is set
+    pub max_dimension: Option<usize>,
+    /// Output image kind, which decides whether the image is debayered
+    pub output: OutputMode,
+    /// Demosaic algorithm used by the CPU debayer
+    pub debayer_quality: DebayerQuality,
+    /// Interpolation mode used by the NPP debayer
+    pub npp_interpolation: NppInterpolation,
+    /// Float precision of the NPP debayer's color pipeline. See `WorkingPrecision` for the
+    /// accuracy cost of `F16`
+    pub working_precision: WorkingPrecision,
+    /// How the CPU and NPP debayers handle the edges of a mosaic. `BorderMode::Crop`
+    /// trims `BORDER` (2) pixels from every side of debayered output
+    pub border_mode: BorderMode,
+    /// Demosaic in horizontal bands of this many rows on the CPU debayer, bounding its
+    /// intermediate buffers to one band instead of the whole frame. `None` demosaics the
+    /// full frame at once; output is the same either way
+    pub cpu_band_height: Option<usize>,
+    /// Write `OutputMode::Rgb` output as unclamped linear 32-bit float instead of 16-bit integer
+    pub output_float: bool,
+    /// White balance applied by the debayer
+    pub white_balance: WhiteBalance,
+    /// Radial vignetting coefficients [k1, k2], applied as `1 + k1*r^2 + k2*r^4` in the raw domain
+    pub vignette_correction: Option<[f32; 2]>,
+    /// Apply the lens shading gain map a raw carries in its metadata (`RawImageData::gain_map`),
+    /// in the raw domain before demosaicing. Raws without one are unaffected
+    pub apply_gain_map: bool,
+    /// Defective sensor columns, each replaced in the raw domain by the mean of its nearest
+    /// good same-color columns. Empty skips the correction
+    pub bad_columns: Vec<usize>,
+    /// Tone curve applied by the CPU debayer after the color matrix, `None` keeps output linear
+    pub tone_curve: Option<ToneCurve>,
+    /// Encode debayered output with the sRGB transfer curve after `tone_curve`, instead of
+    /// keeping it linear. Applied by the built-in debayers; TIFF output is tagged with the
+    /// matching EXIF `Gamma` (2.2 here, 1.0 for linear data) and ICC profile curves
+    pub apply_srgb_gamma: bool,
+    /// Frames allowed in the GPU debayer stage at once during batch conversion.
+    /// Decode and encode still run on every worker; the CPU debayer is never limited.
+    pub max_gpu_concurrency: usize,
+    /// Retry a failed GPU debayer once, then debayer that frame with `CpuDebayer` instead
+    /// of failing the conversion
+    pub gpu_fallback: bool,
+    /// Longest the GPU debayer waits for a frame's device work before failing it with a
+    /// `CudaError` timeout, so a hung GPU can't block the caller. `None` waits indefinitely
+    pub gpu_timeout: Option<Duration>,
+    /// Worker threads for batch conversion, `None` uses the rayon default (one per core)
+    pub batch_threads: Option<usize>,
+    /// Files a batch conversion may hold open at once, input and output combined,
+    /// independently of `batch_threads`. Outputs are only opened once encoded, so workers
+    /// waiting for a slot have finished their compute. `None` leaves opens unbounded.
+    pub max_open_files: Option<usize>,
+    /// Write the source RAW's EXIF capture settings into the output TIFF
+    pub preserve_exif: bool,
+    /// Decode the encoded TIFF and check dimensions and channel count before writing it out
+    pub verify_output: bool,
+    /// Subtract the black level from `OutputMode::BayerGray` samples, clamping at zero, and
+    /// scale them from `bits_per_sample` to the full 16-bit range. Unscaled samples below
+    /// 16 bits are tagged with their `MaxSampleValue` instead
+    pub normalize_bayer: bool,
+    /// Linear exposure gain applied with the color matrix by the CPU and NPP debayers
+    pub exposure: f32,
+    /// Saturation applied with the color matrix by the CPU and NPP debayers: each pixel's
+    /// difference from its luminance gray is scaled by this, keeping luminance. 0.0 gives
+    /// grayscale, 1.0 is neutral; samples pushed out of range are handled by `overflow`
+    pub saturation: f32,
+    /// Color space the CPU and NPP debayers output: linear sRGB, or the white-balanced
+    /// camera channels with no color matrix
+    pub color_transform: ColorTransform,
+    /// Exposure offset in EV, applied as a `2^ev` gain on the white-balanced linear
+    /// camera values ahead of the color matrix by the CPU and NPP debayers
+    pub baseline_exposure: f32,
+    /// Raises the black point by this fraction of the white-minus-black range (`0.0..1.0`),
+    /// so the darkest values clip to zero and the rest is stretched back to full range.
+    /// Applied in the linear domain by the CPU and NPP debayers
+    pub black_clip: f32,
+    /// Debayer implementation used for RGB and luminance output
+    pub backend: DebayerBackend,
+    /// Write a `.json` sidecar with the conversion report next to each `convert_file` output
+    pub write_sidecar: bool,
+    /// Log the white balance multipliers, black and white levels and combined color matrix
+    /// the debayer applies at `info` level for every debayered conversion, for diagnosing
+    /// color issues in the field
+    pub log_color_pipeline: bool,
+    /// Before debayering, scale the second green CFA site by `wb_coeffs[3] / wb_coeffs[1]`
+    /// so sensors with unequal G1/G2 responses demosaic without green maze artifacts
+    pub balance_green_sites: bool,
+    /// Before debayering, measure the mean G1/G2 difference of each frame and shift the
+    /// second green sites to match, for sensors whose green split the coefficients miss
+    pub green_equalization: bool,
+    /// Rotate or mirror RGB and luminance output upright according to the camera's
+    /// recorded orientation. `OutputMode::BayerGray` is always written as stored
+    pub apply_orientation: bool,
+    /// Median noise reduction applied on the CPU to debayered output, `None` skips it
+    pub denoise: Option<DenoiseStrength>,
+    /// Standard deviation in pixels of a Gaussian blur applied on the CPU to the chroma
+    /// (Cb/Cr) of debayered RGB output only, removing color blotches without softening
+    /// luma detail. Runs after `denoise`; `None` skips it. Luminance output ignores it
+    pub chroma_denoise: Option<f32>,
+    /// How the CPU and NPP debayers quantize samples outside 0.0..=1.0 to 16-bit.
+    /// Ignored by `output_float`, which keeps the values unclamped
+    pub overflow: OverflowMode,
+    /// Camera to XYZ matrix (3x4, row-major) replacing the decoded `cam_to_xyz` before
+    /// debayering, for cameras rawloader has no or a wrong matrix for. See `CameraProfiles`
+    pub color_matrix: Option<[[f32; 4]; 3]>,
+    /// XYZ to linear RGB matrix (row-major) replacing `XYZ_TO_SRGB` in the CPU and NPP
+    /// debayers, for wide-gamut or custom working spaces. Output is still tagged as sRGB.
+    /// Ignored by `ColorTransform::CameraNative`
+    pub xyz_to_rgb: Option<[[f32; 3]; 3]>,
+    /// Compress TIFF strips in parallel on the rayon pool. The file is identical to serial
+    /// encoding; has no effect on uncompressed output
+    pub parallel_strips: bool,
+    /// Embed an 8-bit RGB preview with its longer edge at this many pixels as a
+    /// reduced-resolution sub-IFD of RGB output. Images that already fit get none
+    pub embed_thumbnail: Option<u32>,
+    /// Lossy quality of `WebpWriter` output, 0.0 (smallest) to 100.0 (best), or `None` for
+    /// lossless. Other writers ignore it
+    pub webp_quality: Option<f32>,
+    /// Photometric interpretation tagged on `BayerGray` and `Luminance` output
+    pub photometric: GrayPhotometric,
+    /// Embed an ICC profile for linear sRGB, the color space of all RGB output, in the
+    /// `ICCProfile` tag so color-managed viewers display it correctly. Grayscale output
+    /// gets none
+    pub embed_icc: bool,
+    /// Interleaving of RGB output samples, applied just before encoding. Luminance output
+    /// and `debayer_only` stay in RGB order
+    pub channel_order: ChannelOrder,
+    /// Byte order of TIFF output. Only `ByteOrder::NATIVE` can be written; the other order
+    /// fails at the encode stage
+    pub byte_order: ByteOrder,
+    /// Store RGB output planar (`PlanarConfiguration=2`): all red samples, then all green,
+    /// then all blue, for tools that can't read interleaved samples. Grayscale output has
+    /// a single plane either way
+    pub planar: bool,
 }
 
 impl Default for ConversionConfig {
@@ -35,7 +313,52 @@ impl Default for ConversionConfig {
             compression: TiffCompression::None,
             predictor: None,
             validate_dimensions: true,
-            debayer: false,
+            max_dimension: None,
+            output: OutputMode::BayerGray,
+            debayer_quality: DebayerQuality::default(),
+            npp_interpolation: NppInterpolation::default(),
+            working_precision: WorkingPrecision::default(),
+            border_mode: BorderMode::default(),
+            cpu_band_height: None,
+            output_float: false,
+            white_balance: WhiteBalance::default(),
+            vignette_correction: None,
+            apply_gain_map: true,
+            bad_columns: Vec::new(),
+            tone_curve: None,
+            apply_srgb_gamma: false,
+            max_gpu_concurrency: 1,
+            gpu_fallback: true,
+            gpu_timeout: None,
+            batch_threads: None,
+            max_open_files: None,
+            preserve_exif: false,
+            verify_output: false,
+            normalize_bayer: false,
+            exposure: DEFAULT_EXPOSURE,
+            saturation: 1.0,
+            color_transform: ColorTransform::default(),
+            baseline_exposure: 0.0,
+            black_clip: 0.0,
+            backend: DebayerBackend::default(),
+            write_sidecar: false,
+            log_color_pipeline: false,
+            balance_green_sites: true,
+            green_equalization: false,
+            apply_orientation: false,
+            denoise: None,
+            chroma_denoise: None,
+            overflow: OverflowMode::default(),
+            color_matrix: None,
+            xyz_to_rgb: None,
+            parallel_strips: false,
+            embed_thumbnail: None,
+            webp_quality: Some(DEFAULT_WEBP_QUALITY),
+            photometric: GrayPhotometric::default(),
+            embed_icc: false,
+            channel_order: ChannelOrder::default(),
+            byte_order: ByteOrder::default(),
+            planar: false,
         }
     }
 }
@@ -44,6 +367,70 @@ impl ConversionConfig {
     pub fn builder() -> ConversionConfigBuilder {
         ConversionConfigBuilder::default()
     }
+
+    /// Whether the output is debayered, as set by `output`
+    #[deprecated(note = "use `output.requires_debayer()`")]
+    pub fn debayer(&self) -> bool {
+        self.output.requires_debayer()
+    }
+
+    /// Checks for settings no conversion can honor, so they fail up front with
+    /// `ConversionError::InvalidConfig` instead of partway through a batch
+    ///
+    /// Run by the pipeline constructors. Writer-specific limits such as `byte_order` are
+    /// left to the writer, since a custom writer may support them.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(ConversionError::InvalidConfig(message));
+        if let Some(predictor) = self.predictor.filter(|p| !(1..=3).contains(p)) {
+            return invalid(format!(
+                "predictor {}, expected 1 (none), 2 (horizontal) or 3 (floating point)",
+                predictor
+            ));
+        }
+        if self.max_gpu_concurrency == 0 {
+            return invalid("max_gpu_concurrency of 0 would never debayer a frame".to_string());
+        }
+        if self.gpu_timeout == Some(Duration::ZERO) {
+            return invalid("gpu_timeout of 0 would fail every GPU frame".to_string());
+        }
+        if self.max_open_files == Some(0) {
+            return invalid("max_open_files of 0 would never open a file".to_string());
+        }
+        if self.max_dimension == Some(0) {
+            return invalid("max_dimension of 0 rejects every image".to_string());
+        }
+        if self.cpu_band_height == Some(0) {
+            return invalid("cpu_band_height of 0, expected at least 1 row".to_string());
+        }
+        if self.embed_thumbnail == Some(0) {
+            return invalid("embed_thumbnail of 0 pixels, use None for no thumbnail".to_string());
+        }
+        if let Some(quality) = self.webp_quality.filter(|q| !(0.0..=100.0).contains(q)) {
+            return invalid(format!("webp_quality {}, expected 0.0..=100.0", quality));
+        }
+        if !(self.exposure.is_finite() && self.exposure > 0.0) {
+            return invalid(format!("exposure {}, expected a positive gain", self.exposure));
+        }
+        if !(self.saturation.is_finite() && self.saturation >= 0.0) {
+            return invalid(format!("saturation {}, expected 0.0 or more", self.saturation));
+        }
+        if !self.baseline_exposure.is_finite() {
+            return invalid(format!("baseline_exposure {} EV", self.baseline_exposure));
+        }
+        if !(0.0..1.0).contains(&self.black_clip) {
+            return invalid(format!("black_clip {}, expected 0.0..1.0", self.black_clip));
+        }
+        if let Some(sigma) = self.chroma_denoise.filter(|s| !(s.is_finite() && *s > 0.0)) {
+            return invalid(format!("chroma_denoise sigma {}, expected a positive radius", sigma));
+        }
+        if let Some(coefficients) = self.vignette_correction.filter(|k| !k.iter().all(|v| v.is_finite())) {
+            return invalid(format!("vignette_correction {:?}, expected finite coefficients", coefficients));
+        }
+        if let Some(matrix) = self.xyz_to_rgb.filter(|m| !m.iter().flatten().all(|v| v.is_finite())) {
+            return invalid(format!("xyz_to_rgb {:?}, expected finite coefficients", matrix));
+        }
+        Ok(())
+    }
 }
 
 /// Builder for ConversionConfig
@@ -52,7 +439,53 @@ pub struct ConversionConfigBuilder {
     compression: Option<TiffCompression>,
     predictor: Option<Option<u16>>,
     validate_dimensions: Option<bool>,
+    max_dimension: Option<Option<usize>>,
     debayer: Option<bool>,
+    output: Option<OutputMode>,
+    debayer_quality: Option<DebayerQuality>,
+    npp_interpolation: Option<NppInterpolation>,
+    working_precision: Option<WorkingPrecision>,
+    border_mode: Option<BorderMode>,
+    cpu_band_height: Option<Option<usize>>,
+    output_float: Option<bool>,
+    white_balance: Option<WhiteBalance>,
+    vignette_correction: Option<Option<[f32; 2]>>,
+    apply_gain_map: Option<bool>,
+    bad_columns: Option<Vec<usize>>,
+    tone_curve: Option<Option<ToneCurve>>,
+    apply_srgb_gamma: Option<bool>,
+    max_gpu_concurrency: Option<usize>,
+    gpu_fallback: Option<bool>,
+    gpu_timeout: Option<Option<Duration>>,
+    batch_threads: Option<Option<usize>>,
+    max_open_files: Option<Option<usize>>,
+    preserve_exif: Option<bool>,
+    verify_output: Option<bool>,
+    normalize_bayer: Option<bool>,
+    exposure: Option<f32>,
+    saturation: Option<f32>,
+    color_transform: Option<ColorTransform>,
+    baseline_exposure: Option<f32>,
+    black_clip: Option<f32>,
+    backend: Option<DebayerBackend>,
+    write_sidecar: Option<bool>,
+    log_color_pipeline: Option<bool>,
+    balance_green_sites: Option<bool>,
+    green_equalization: Option<bool>,
+    apply_orientation: Option<bool>,
+    denoise: Option<Option<DenoiseStrength>>,
+    chroma_denoise: Option<Option<f32>>,
+    overflow: Option<OverflowMode>,
+    color_matrix: Option<Option<[[f32; 4]; 3]>>,
+    xyz_to_rgb: Option<Option<[[f32; 3]; 3]>>,
+    parallel_strips: Option<bool>,
+    embed_thumbnail: Option<Option<u32>>,
+    webp_quality: Option<Option<f32>>,
+    photometric: Option<GrayPhotometric>,
+    embed_icc: Option<bool>,
+    channel_order: Option<ChannelOrder>,
+    byte_order: Option<ByteOrder>,
+    planar: Option<bool>,
 }
 
 impl ConversionConfigBuilder {
@@ -71,18 +504,301 @@ impl ConversionConfigBuilder {
         self
     }
     
+    pub fn max_dimension(mut self, max: Option<usize>) -> Self {
+        self.max_dimension = Some(max);
+        self
+    }
+    
+    /// Shorthand for `output(OutputMode::Rgb)` when set, `BayerGray` otherwise. An explicit
+    /// `output` wins
     pub fn debayer(mut self, enable: bool) -> Self {
         self.debayer = Some(enable);
         self
     }
     
+    pub fn output(mut self, output: OutputMode) -> Self {
+        self.output = Some(output);
+        self
+    }
+    
+    pub fn debayer_quality(mut self, quality: DebayerQuality) -> Self {
+        self.debayer_quality = Some(quality);
+        self
+    }
+    
+    pub fn npp_interpolation(mut self, interpolation: NppInterpolation) -> Self {
+        self.npp_interpolation = Some(interpolation);
+        self
+    }
+    
+    pub fn working_precision(mut self, precision: WorkingPrecision) -> Self {
+        self.working_precision = Some(precision);
+        self
+    }
+    
+    pub fn border_mode(mut self, mode: BorderMode) -> Self {
+        self.border_mode = Some(mode);
+        self
+    }
+    
+    pub fn cpu_band_height(mut self, rows: Option<usize>) -> Self {
+        self.cpu_band_height = Some(rows);
+        self
+    }
+    
+    pub fn output_float(mut self, enable: bool) -> Self {
+        self.output_float = Some(enable);
+        self
+    }
+    
+    pub fn white_balance(mut self, white_balance: WhiteBalance) -> Self {
+        self.white_balance = Some(white_balance);
+        self
+    }
+    
+    pub fn vignette_correction(mut self, coefficients: Option<[f32; 2]>) -> Self {
+        self.vignette_correction = Some(coefficients);
+        self
+    }
+    
+    pub fn apply_gain_map(mut self, enable: bool) -> Self {
+        self.apply_gain_map = Some(enable);
+        self
+    }
+    
+    pub fn bad_columns(mut self, columns: Vec<usize>) -> Self {
+        self.bad_columns = Some(columns);
+        self
+    }
+    
+    pub fn tone_curve(mut self, curve: Option<ToneCurve>) -> Self {
+        self.tone_curve = Some(curve);
+        self
+    }
+    
+    pub fn apply_srgb_gamma(mut self, enable: bool) -> Self {
+        self.apply_srgb_gamma = Some(enable);
+        self
+    }
+    
+    pub fn max_gpu_concurrency(mut self, max: usize) -> Self {
+        self.max_gpu_concurrency = Some(max);
+        self
+    }
+    
+    pub fn gpu_fallback(mut self, enable: bool) -> Self {
+        self.gpu_fallback = Some(enable);
+        self
+    }
+    
+    pub fn gpu_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.gpu_timeout = Some(timeout);
+        self
+    }
+    
+    pub fn batch_threads(mut self, threads: Option<usize>) -> Self {
+        self.batch_threads = Some(threads);
+        self
+    }
+    
+    pub fn max_open_files(mut self, max: Option<usize>) -> Self {
+        self.max_open_files = Some(max);
+        self
+    }
+    
+    pub fn preserve_exif(mut self, enable: bool) -> Self {
+        self.preserve_exif = Some(enable);
+        self
+    }
+    
+    pub fn verify_output(mut self, enable: bool) -> Self {
+        self.verify_output = Some(enable);
+        self
+    }
+    
+    pub fn normalize_bayer(mut self, enable: bool) -> Self {
+        self.normalize_bayer = Some(enable);
+        self
+    }
+    
+    pub fn exposure(mut self, exposure: f32) -> Self {
+        self.exposure = Some(exposure);
+        self
+    }
+    
+    pub fn saturation(mut self, saturation: f32) -> Self {
+        self.saturation = Some(saturation);
+        self
+    }
+    
+    pub fn color_transform(mut self, transform: ColorTransform) -> Self {
+        self.color_transform = Some(transform);
+        self
+    }
+    
+    pub fn baseline_exposure(mut self, ev: f32) -> Self {
+        self.baseline_exposure = Some(ev);
+        self
+    }
+    
+    pub fn black_clip(mut self, black_clip: f32) -> Self {
+        self.black_clip = Some(black_clip);
+        self
+    }
+    
+    pub fn backend(mut self, backend: DebayerBackend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+    
+    pub fn write_sidecar(mut self, enable: bool) -> Self {
+        self.write_sidecar = Some(enable);
+        self
+    }
+    
+    pub fn log_color_pipeline(mut self, enable: bool) -> Self {
+        self.log_color_pipeline = Some(enable);
+        self
+    }
+    
+    pub fn balance_green_sites(mut self, enable: bool) -> Self {
+        self.balance_green_sites = Some(enable);
+        self
+    }
+    
+    pub fn green_equalization(mut self, enable: bool) -> Self {
+        self.green_equalization = Some(enable);
+        self
+    }
+    
+    pub fn apply_orientation(mut self, enable: bool) -> Self {
+        self.apply_orientation = Some(enable);
+        self
+    }
+    
+    pub fn denoise(mut self, strength: Option<DenoiseStrength>) -> Self {
+        self.denoise = Some(strength);
+        self
+    }
+    
+    pub fn chroma_denoise(mut self, radius: Option<f32>) -> Self {
+        self.chroma_denoise = Some(radius);
+        self
+    }
+    
+    pub fn overflow(mut self, mode: OverflowMode) -> Self {
+        self.overflow = Some(mode);
+        self
+    }
+    
+    pub fn color_matrix(mut self, matrix: Option<[[f32; 4]; 3]>) -> Self {
+        self.color_matrix = Some(matrix);
+        self
+    }
+    
+    pub fn xyz_to_rgb(mut self, matrix: Option<[[f32; 3]; 3]>) -> Self {
+        self.xyz_to_rgb = Some(matrix);
+        self
+    }
+    
+    pub fn parallel_strips(mut self, enable: bool) -> Self {
+        self.parallel_strips = Some(enable);
+        self
+    }
+    
+    pub fn embed_thumbnail(mut self, max_edge: Option<u32>) -> Self {
+        self.embed_thumbnail = Some(max_edge);
+        self
+    }
+    
+    pub fn webp_quality(mut self, quality: Option<f32>) -> Self {
+        self.webp_quality = Some(quality);
+        self
+    }
+    
+    pub fn photometric(mut self, photometric: GrayPhotometric) -> Self {
+        self.photometric = Some(photometric);
+        self
+    }
+    
+    pub fn embed_icc(mut self, enable: bool) -> Self {
+        self.embed_icc = Some(enable);
+        self
+    }
+    
+    pub fn channel_order(mut self, order: ChannelOrder) -> Self {
+        self.channel_order = Some(order);
+        self
+    }
+    
+    pub fn byte_order(mut self, order: ByteOrder) -> Self {
+        self.byte_order = Some(order);
+        self
+    }
+    
+    pub fn planar(mut self, enable: bool) -> Self {
+        self.planar = Some(enable);
+        self
+    }
+    
     pub fn build(self) -> ConversionConfig {
         let default = ConversionConfig::default();
+        // An explicit output mode wins; otherwise derive it from the debayer flag
+        let output = self.output.unwrap_or(match self.debayer {
+            Some(true) => OutputMode::Rgb,
+            Some(false) => OutputMode::BayerGray,
+            None => default.output,
+        });
         ConversionConfig {
             compression: self.compression.unwrap_or(default.compression),
             predictor: self.predictor.unwrap_or(default.predictor),
             validate_dimensions: self.validate_dimensions.unwrap_or(default.validate_dimensions),
-            debayer: self.debayer.unwrap_or(default.debayer),
+            max_dimension: self.max_dimension.unwrap_or(default.max_dimension),
+            output,
+            debayer_quality: self.debayer_quality.unwrap_or(default.debayer_quality),
+            npp_interpolation: self.npp_interpolation.unwrap_or(default.npp_interpolation),
+            working_precision: self.working_precision.unwrap_or(default.working_precision),
+            border_mode: self.border_mode.unwrap_or(default.border_mode),
+            cpu_band_height: self.cpu_band_height.unwrap_or(default.cpu_band_height),
+            output_float: self.output_float.unwrap_or(default.output_float),
+            white_balance: self.white_balance.unwrap_or(default.white_balance),
+            vignette_correction: self.vignette_correction.unwrap_or(default.vignette_correction),
+            apply_gain_map: self.apply_gain_map.unwrap_or(default.apply_gain_map),
+            bad_columns: self.bad_columns.unwrap_or(default.bad_columns),
+            tone_curve: self.tone_curve.unwrap_or(default.tone_curve),
+            apply_srgb_gamma: self.apply_srgb_gamma.unwrap_or(default.apply_srgb_gamma),
+            max_gpu_concurrency: self.max_gpu_concurrency.unwrap_or(default.max_gpu_concurrency),
+            gpu_fallback: self.gpu_fallback.unwrap_or(default.gpu_fallback),
+            gpu_timeout: self.gpu_timeout.unwrap_or(default.gpu_timeout),
+            batch_threads: self.batch_threads.unwrap_or(default.batch_threads),
+            max_open_files: self.max_open_files.unwrap_or(default.max_open_files),
+            preserve_exif: self.preserve_exif.unwrap_or(default.preserve_exif),
+            verify_output: self.verify_output.unwrap_or(default.verify_output),
+            normalize_bayer: self.normalize_bayer.unwrap_or(default.normalize_bayer),
+            exposure: self.exposure.unwrap_or(default.exposure),
+            saturation: self.saturation.unwrap_or(default.saturation),
+            color_transform: self.color_transform.unwrap_or(default.color_transform),
+            baseline_exposure: self.baseline_exposure.unwrap_or(default.baseline_exposure),
+            black_clip: self.black_clip.unwrap_or(default.black_clip),
+            backend: self.backend.unwrap_or(default.backend),
+            write_sidecar: self.write_sidecar.unwrap_or(default.write_sidecar),
+            log_color_pipeline: self.log_color_pipeline.unwrap_or(default.log_color_pipeline),
+            balance_green_sites: self.balance_green_sites.unwrap_or(default.balance_green_sites),
+            green_equalization: self.green_equalization.unwrap_or(default.green_equalization),
+            apply_orientation: self.apply_orientation.unwrap_or(default.apply_orientation),
+            denoise: self.denoise.unwrap_or(default.denoise),
+            chroma_denoise: self.chroma_denoise.unwrap_or(default.chroma_denoise),
+            overflow: self.overflow.unwrap_or(default.overflow),
+            color_matrix: self.color_matrix.unwrap_or(default.color_matrix),
+            xyz_to_rgb: self.xyz_to_rgb.unwrap_or(default.xyz_to_rgb),
+            parallel_strips: self.parallel_strips.unwrap_or(default.parallel_strips),
+            embed_thumbnail: self.embed_thumbnail.unwrap_or(default.embed_thumbnail),
+            webp_quality: self.webp_quality.unwrap_or(default.webp_quality),
+            photometric: self.photometric.unwrap_or(default.photometric),
+            embed_icc: self.embed_icc.unwrap_or(default.embed_icc),
+            channel_order: self.channel_order.unwrap_or(default.channel_order),
+            byte_order: self.byte_order.unwrap_or(default.byte_order),
+            planar: self.planar.unwrap_or(default.planar),
         }
     }
 }
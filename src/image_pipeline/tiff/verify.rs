@@ -0,0 +1,67 @@
+//! Read-back verification of encoded TIFF output
+
+use std::io::Cursor;
+use tiff::ColorType;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::{PlanarConfiguration, Tag};
+use tracing::debug;
+use crate::image_pipeline::common::error::{Result, ConversionError};
+
+/// Decodes `encoded` and checks it holds a `width`x`height` image of the `expected` color type
+///
+/// The pixel data is fully decoded, so truncated strips or a corrupt compressed stream
+/// are caught as well as header mismatches.
+pub(crate) fn verify_tiff(encoded: &[u8], width: usize, height: usize, expected: ColorType) -> Result<()> {
+    let verify_err = |message: String| ConversionError::EncodeError(format!("Output verification failed: {}", message));
+    
+    let mut decoder = Decoder::new(Cursor::new(encoded))
+        .map_err(|e| verify_err(e.to_string()))?;
+    
+    let dimensions = decoder.dimensions().map_err(|e| verify_err(e.to_string()))?;
+    if dimensions != (width as u32, height as u32) {
+        return Err(verify_err(format!(
+            "dimensions {}x{}, expected {}x{}",
+            dimensions.0, dimensions.1, width, height
+        )));
+    }
+    
+    let color_type = decoder.colortype().map_err(|e| verify_err(e.to_string()))?;
+    if color_type != expected {
+        return Err(verify_err(format!("color type {:?}, expected {:?}", color_type, expected)));
+    }
+    
+    // `read_image` only returns the first plane of planar images, so those are read
+    // strip by strip
+    let planar = decoder
+        .find_tag_unsigned::<u16>(Tag::PlanarConfiguration)
+        .map_err(|e| verify_err(e.to_string()))?
+        == Some(PlanarConfiguration::Planar.to_u16());
+    let decoded = if planar {
+        let strips = decoder.strip_count().map_err(|e| verify_err(e.to_string()))?;
+        (0..strips)
+            .map(|strip| decoder.read_chunk(strip))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| verify_err(e.to_string()))?
+    } else {
+        vec![decoder.read_image().map_err(|e| verify_err(e.to_string()))?]
+    };
+    let mut samples = 0;
+    for result in decoded {
+        samples += match result {
+            DecodingResult::U16(data) => data.len(),
+            DecodingResult::F32(data) => data.len(),
+            _ => return Err(verify_err("unexpected sample format".to_string())),
+        };
+    }
+    let samples_per_pixel = match expected {
+        ColorType::RGB(_) => 3,
+        _ => 1,
+    };
+    let expected_samples = width * height * samples_per_pixel;
+    if samples != expected_samples {
+        return Err(verify_err(format!("{} samples, expected {}", samples, expected_samples)));
+    }
+    
+    debug!("Verified {}x{} {:?} output", width, height, expected);
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! 8-bit WebP output for lightweight previews, behind the `webp` feature
+
+use std::io::Write;
+use tracing::debug;
+use crate::image_pipeline::common::error::{ConversionError, Result};
+use crate::image_pipeline::tiff::types::ConversionConfig;
+use crate::image_pipeline::tiff::writer::{ImageKind, ImageWriter};
+
+/// Largest width or height a WebP image can have
+const MAX_DIMENSION: usize = 16383;
+
+/// Writes pipeline images as 8-bit RGB WebP, lossy at `webp_quality` or lossless
+///
+/// 16-bit samples are scaled down from their `bits_per_sample` range and float samples
+/// are clamped to 0.0..=1.0; gray images are written as RGB. Samples are stored as
+/// given, so enable `apply_srgb_gamma` for previews meant for display. No EXIF or ICC
+/// metadata is embedded. Register with `RawToTiffPipeline::add_writer` to get a WebP
+/// next to the TIFF from `convert_multi`.
+pub struct WebpWriter;
+
+impl ImageWriter for WebpWriter {
+    fn write(&self, image: ImageKind<'_>, output: &mut dyn Write, config: &ConversionConfig) -> Result<()> {
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(ConversionError::EncodeError(format!(
+                "{}x{} does not fit WebP, which allows 1 to {} pixels per side",
+                width, height, MAX_DIMENSION
+            )));
+        }
+
+        let rgb = to_rgb8(image);
+        let encoder = webp::Encoder::from_rgb(&rgb, width as u32, height as u32);
+        let encoded = match config.webp_quality {
+            Some(quality) => encoder.encode_simple(false, quality),
+            None => encoder.encode_simple(true, 100.0),
+        }
+        .map_err(|e| ConversionError::EncodeError(format!("WebP encoding failed: {:?}", e)))?;
+
+        debug!(width, height, quality = ?config.webp_quality, bytes = encoded.len(), "Encoded WebP");
+        output.write_all(&encoded)?;
+        Ok(())
+    }
+}
+
+/// Interleaved 8-bit RGB samples of `image`
+fn to_rgb8(image: ImageKind<'_>) -> Vec<u8> {
+    let scale = |value: u16, bits: u32| {
+        let max = ((1u32 << bits.clamp(1, 16)) - 1) as f32;
+        (f32::from(value) / max * 255.0).round().min(255.0) as u8
+    };
+    match image {
+        ImageKind::Gray16(image) => image
+            .data
+            .iter()
+            .flat_map(|&v| [scale(v, image.bits_per_sample); 3])
+            .collect(),
+        ImageKind::Rgb16(image) => image
+            .data
+            .iter()
+            .map(|&v| scale(v, image.bits_per_sample))
+            .collect(),
+        ImageKind::RgbF32(image) => image
+            .data
+            .iter()
+            .map(|&v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect(),
+    }
+}
@@ -1,10 +1,112 @@
-use std::io::Write;
-use crate::image_pipeline::common::error::Result;
+use std::io::{Seek, Write};
+use crate::image_pipeline::common::error::{ConversionError, Result};
+use crate::image_pipeline::raw::exif::ExifMetadata;
 use crate::image_pipeline::raw::types::RawImageData;
-use crate::image_pipeline::debayer::types::RgbImageData;
+use crate::image_pipeline::debayer::types::{GrayImageData, RgbImageData, RgbImageDataF32};
 use crate::image_pipeline::tiff::types::ConversionConfig;
 
 pub trait TiffWriter {
     fn write_tiff(&self, image: &RawImageData, output: &mut dyn Write, config: &ConversionConfig) -> Result<()>;
+    fn write_gray_tiff(&self, image: &GrayImageData, output: &mut dyn Write, config: &ConversionConfig) -> Result<()>;
     fn write_rgb_tiff(&self, image: &RgbImageData, output: &mut dyn Write, config: &ConversionConfig) -> Result<()>;
+    fn write_rgb_tiff_f32(&self, image: &RgbImageDataF32, output: &mut dyn Write, config: &ConversionConfig) -> Result<()>;
+
+    /// Writes a 16-bit RGB image supplied as `height` rows of `width * 3` interleaved samples
+    ///
+    /// Lets producers hand over rows as they become available instead of materializing
+    /// an `RgbImageData`. The default implementation collects the rows and calls
+    /// `write_rgb_tiff`; writers that can encode incrementally override it.
+    fn write_rgb_tiff_streaming<'a, I, S>(
+        &self,
+        width: usize,
+        height: usize,
+        rows: I,
+        exif: &ExifMetadata,
+        output: S,
+        config: &ConversionConfig,
+    ) -> Result<()>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = &'a [u16]>,
+        S: Write + Seek,
+    {
+        write_rgb_rows_buffered(self, width, height, rows, exif, output, config)
+    }
+}
+
+/// Image handed to an `ImageWriter`, by sample layout
+#[derive(Clone, Copy)]
+pub enum ImageKind<'a> {
+    /// Undemosaiced Bayer mosaic, one 16-bit sample per pixel
+    Gray16(&'a RawImageData),
+    /// Interleaved 16-bit RGB
+    Rgb16(&'a RgbImageData),
+    /// Interleaved linear floating-point RGB
+    RgbF32(&'a RgbImageDataF32),
+}
+
+impl ImageKind<'_> {
+    /// Width and height of the image in pixels
+    pub fn dimensions(&self) -> (usize, usize) {
+        match self {
+            ImageKind::Gray16(image) => (image.width, image.height),
+            ImageKind::Rgb16(image) => (image.width, image.height),
+            ImageKind::RgbF32(image) => (image.width, image.height),
+        }
+    }
+}
+
+/// Output format for pipeline images, see `RawToTiffPipeline::convert_multi`
+///
+/// Every `TiffWriter` is one, writing each kind as the matching TIFF; implement it
+/// directly for other formats such as JPEG, PNG or FITS. Writers that cannot hold a
+/// kind return `ConversionError::UnsupportedFormat` for it.
+pub trait ImageWriter: Send + Sync {
+    fn write(&self, image: ImageKind<'_>, output: &mut dyn Write, config: &ConversionConfig) -> Result<()>;
+}
+
+impl<T: TiffWriter + Send + Sync> ImageWriter for T {
+    fn write(&self, image: ImageKind<'_>, output: &mut dyn Write, config: &ConversionConfig) -> Result<()> {
+        match image {
+            ImageKind::Gray16(image) => self.write_tiff(image, output, config),
+            ImageKind::Rgb16(image) => self.write_rgb_tiff(image, output, config),
+            ImageKind::RgbF32(image) => self.write_rgb_tiff_f32(image, output, config),
+        }
+    }
+}
+
+/// Collects `rows` into an `RgbImageData` and writes it with `write_rgb_tiff`
+pub(crate) fn write_rgb_rows_buffered<'a, W, I, S>(
+    writer: &W,
+    width: usize,
+    height: usize,
+    rows: I,
+    exif: &ExifMetadata,
+    mut output: S,
+    config: &ConversionConfig,
+) -> Result<()>
+where
+    W: TiffWriter,
+    I: IntoIterator<Item = &'a [u16]>,
+    S: Write + Seek,
+{
+    let data: Vec<u16> = rows.into_iter().flatten().copied().collect();
+    if data.len() != width * height * 3 {
+        return Err(ConversionError::EncodeError(format!(
+            "Row data holds {} samples, expected {} for {}x{} RGB",
+            data.len(),
+            width * height * 3,
+            width,
+            height
+        )));
+    }
+
+    let image = RgbImageData {
+        width,
+        height,
+        data,
+        bits_per_sample: 16,
+        exif: *exif,
+    };
+    writer.write_rgb_tiff(&image, &mut output, config)
 }
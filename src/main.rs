@@ -1,34 +1,185 @@
-use ffed_protosat_rs::image_pipeline::{ConversionConfig, RawToTiffPipeline, TiffCompression};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, ValueEnum};
+use ffed_protosat_rs::image_pipeline::tiff::types::DEFAULT_EXPOSURE;
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, DebayerBackend, RawToTiffPipeline, TiffCompression, tiff_output_path,
+};
 use ffed_protosat_rs::logger;
 
 use tracing::{error, info};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    logger::init();
+/// Convert camera RAW files to TIFF
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    /// RAW files to convert; patterns such as `captures/*.arw` are expanded
+    #[arg(required = true, num_args = 1..)]
+    inputs: Vec<String>,
 
-    info!("Starting ffed_protosat...");
+    /// Output TIFF file, or a directory when converting several inputs
+    output: PathBuf,
+
+    /// TIFF compression
+    #[arg(long, value_enum, default_value_t = Compression::None)]
+    compression: Compression,
+
+    /// Debayer to RGB instead of writing the grayscale Bayer mosaic
+    #[arg(long)]
+    debayer: bool,
+
+    /// Linear exposure gain applied with the color matrix
+    #[arg(long, default_value_t = DEFAULT_EXPOSURE)]
+    exposure: f32,
+
+    /// Debayer implementation
+    #[arg(long, value_enum, default_value_t = Backend::Auto)]
+    backend: Backend,
+
+    /// Write per-stage timings of every conversion to this file in folded-stack format,
+    /// for `inferno-flamegraph` or flamegraph.pl
+    #[arg(long, value_name = "FILE")]
+    profile: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Compression {
+    None,
+    Lzw,
+    DeflateFast,
+    Deflate,
+    DeflateBest,
+}
+
+impl From<Compression> for TiffCompression {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::None => TiffCompression::None,
+            Compression::Lzw => TiffCompression::Lzw,
+            Compression::DeflateFast => TiffCompression::DeflateFast,
+            Compression::Deflate => TiffCompression::DeflateBalanced,
+            Compression::DeflateBest => TiffCompression::DeflateBest,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Backend {
+    Auto,
+    Cpu,
+    Npp,
+}
+
+impl From<Backend> for DebayerBackend {
+    fn from(backend: Backend) -> Self {
+        match backend {
+            Backend::Auto => DebayerBackend::Auto,
+            Backend::Cpu => DebayerBackend::Cpu,
+            Backend::Npp => DebayerBackend::Npp,
+        }
+    }
+}
+
+/// Expands glob patterns, passing plain paths through untouched
+fn expand_inputs(inputs: &[String]) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        if input.contains(['*', '?', '[']) {
+            let matches = glob::glob(input)?.collect::<Result<Vec<_>, _>>()?;
+            if matches.is_empty() {
+                return Err(format!("No files match {}", input).into());
+            }
+            paths.extend(matches);
+        } else {
+            paths.push(PathBuf::from(input));
+        }
+    }
+    Ok(paths)
+}
+
+/// Input paired with the TIFF it converts to
+type Job = (PathBuf, PathBuf);
 
+/// Pairs each input with its output path: `output` itself for a single input,
+/// otherwise `<output>/<input stem>.tiff`
+///
+/// Inputs whose output path is already taken by an earlier input, such as `x/a.dng`
+/// and `y/a.dng`, are returned separately with that path instead of being planned.
+fn plan_jobs(inputs: Vec<PathBuf>, output: &Path) -> std::io::Result<(Vec<Job>, Vec<Job>)> {
+    if inputs.len() == 1 && !output.is_dir() {
+        return Ok((vec![(inputs[0].clone(), output.to_path_buf())], Vec::new()));
+    }
+
+    std::fs::create_dir_all(output)?;
+    let mut targets = HashSet::new();
+    Ok(inputs
+        .into_iter()
+        .map(|input| {
+            let target = tiff_output_path(&input, output);
+            (input, target)
+        })
+        .partition(|(_, target)| targets.insert(target.clone())))
+}
+
+fn run(cli: Cli) -> Result<bool, Box<dyn std::error::Error>> {
     let config = ConversionConfig::builder()
-        .compression(TiffCompression::None)
-        .debayer(true)
+        .compression(cli.compression.into())
+        .debayer(cli.debayer)
+        .exposure(cli.exposure)
+        .backend(cli.backend.into())
         .build();
     let pipeline = RawToTiffPipeline::new(config)?;
 
     info!("RAW to TIFF pipeline initialized");
     info!("Compression: {:?}", pipeline.config().compression);
-    info!(
-        "Debayering: {}",
-        if pipeline.config().debayer {
-            "enabled"
-        } else {
-            "disabled"
+    info!("Output: {:?}", pipeline.config().output);
+
+    let (jobs, duplicates) = plan_jobs(expand_inputs(&cli.inputs)?, &cli.output)?;
+    let mut all_ok = duplicates.is_empty();
+    for (input, target) in &duplicates {
+        error!(
+            "{}: skipped, {} is already the output of another input",
+            input.display(),
+            target.display()
+        );
+    }
+
+    let results = pipeline.convert_batch_with_reports(&jobs);
+    let mut folded = String::new();
+    for ((input, output), result) in jobs.iter().zip(results) {
+        match result {
+            Ok(report) => {
+                info!("{} -> {}", input.display(), output.display());
+                folded.push_str(&report.timings.to_folded_stacks());
+            }
+            Err(e) => {
+                error!("{}: conversion failed: {}", input.display(), e);
+                all_ok = false;
+            }
         }
-    );
+    }
 
-    match pipeline.convert_file("input.arw", "output.tiff") {
-        Ok(_) => info!("Conversion successful!"),
-        Err(e) => error!("Conversion failed: {}", e),
+    if let Some(profile) = &cli.profile {
+        std::fs::write(profile, folded)?;
+        info!("Stage timings written to {}", profile.display());
     }
 
-    Ok(())
+    Ok(all_ok)
+}
+
+fn main() -> ExitCode {
+    logger::init();
+
+    info!("Starting ffed_protosat...");
+
+    match run(Cli::parse()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
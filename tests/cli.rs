@@ -0,0 +1,159 @@
+//! Checks the `ffed_protosat_rs` command line end to end.
+//!
+//! A minimal uncompressed 12-bit RGGB DNG is written to a temporary directory and
+//! converted with the `--compression`, `--debayer`, `--exposure` and `--backend` flags.
+//! Each run must exit successfully and leave a TIFF of the frame's dimensions whose
+//! sample layout and compression follow the flags. Inputs that aren't RAW files must
+//! fail with the reason logged, and invalid flag values must fail without writing anything.
+//! Inputs that would share an output path must be skipped with an error, keeping dotted
+//! stems such as `b.v1` whole.
+//!
+//! Run with `cargo test --test cli`.
+
+use std::io::Cursor;
+
+use assert_cmd::Command;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+use tiff::ColorType;
+
+mod common;
+
+const WIDTH: u32 = 32;
+const HEIGHT: u32 = 24;
+const WHITE: u16 = 4095;
+
+/// Little-endian DNG holding one uncompressed RGGB mosaic of 12-bit ramps
+fn dng() -> Vec<u8> {
+    let strip: Vec<u8> = (0..WIDTH * HEIGHT)
+        .flat_map(|i| ((256 + (i % WIDTH) * 100 + (i / WIDTH) * 20).min(WHITE as u32) as u16).to_le_bytes())
+        .collect();
+    common::dng(WIDTH, HEIGHT, 16, WHITE, &strip)
+}
+
+fn cli() -> Command {
+    Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap()
+}
+
+/// Converts the fixture with `args` and returns the decoded output
+fn convert(args: &[&str]) -> (Decoder<Cursor<Vec<u8>>>, DecodingResult) {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("frame.dng");
+    let output = dir.path().join("frame.tiff");
+    std::fs::write(&input, dng()).unwrap();
+
+    cli().arg(&input).arg(&output).args(args).assert().success();
+
+    let mut decoder = Decoder::new(Cursor::new(std::fs::read(&output).unwrap())).unwrap();
+    assert_eq!(decoder.dimensions().unwrap(), (WIDTH, HEIGHT), "args {:?}", args);
+    let image = decoder.read_image().unwrap();
+    (decoder, image)
+}
+
+fn mean(image: &DecodingResult) -> f64 {
+    match image {
+        DecodingResult::U16(samples) => samples.iter().map(|&v| v as f64).sum::<f64>() / samples.len() as f64,
+        _ => panic!("Output is not 16-bit"),
+    }
+}
+
+#[test]
+fn writes_bayer_mosaic_by_default() {
+    let (mut decoder, image) = convert(&[]);
+    assert_eq!(decoder.colortype().unwrap(), ColorType::Gray(16));
+    assert!(matches!(image, DecodingResult::U16(ref samples) if samples.len() == (WIDTH * HEIGHT) as usize));
+}
+
+#[test]
+fn debayer_writes_rgb() {
+    let (mut decoder, image) = convert(&["--debayer", "--backend", "cpu"]);
+    assert_eq!(decoder.colortype().unwrap(), ColorType::RGB(16));
+    assert!(matches!(image, DecodingResult::U16(ref samples) if samples.len() == (WIDTH * HEIGHT * 3) as usize));
+}
+
+#[test]
+fn compression_sets_the_tiff_compression() {
+    for (flag, tag) in [("none", 1), ("lzw", 5), ("deflate", 8), ("deflate-best", 8)] {
+        let (mut decoder, _) = convert(&["--compression", flag]);
+        assert_eq!(decoder.get_tag_u32(Tag::Compression).unwrap(), tag, "--compression {}", flag);
+    }
+}
+
+#[test]
+fn exposure_scales_debayered_output() {
+    let (_, dark) = convert(&["--debayer", "--backend", "cpu", "--exposure", "0.5"]);
+    let (_, bright) = convert(&["--debayer", "--backend", "cpu", "--exposure", "2.0"]);
+    assert!(mean(&bright) > mean(&dark), "exposure 2.0 is not brighter than 0.5");
+}
+
+#[test]
+fn converts_several_inputs_into_a_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let outputs = dir.path().join("out");
+    for name in ["a.dng", "b.dng"] {
+        std::fs::write(dir.path().join(name), dng()).unwrap();
+    }
+
+    cli()
+        .arg(dir.path().join("a.dng"))
+        .arg(dir.path().join("b.dng"))
+        .arg(&outputs)
+        .assert()
+        .success();
+
+    for name in ["a.tiff", "b.tiff"] {
+        assert!(std::fs::read(outputs.join(name)).unwrap().starts_with(b"II*\0"), "{} is not a TIFF", name);
+    }
+}
+
+#[test]
+fn skips_inputs_sharing_an_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let outputs = dir.path().join("out");
+    let inputs = ["x/a.dng", "y/a.dng", "z/a.arw", "z/b.v1.dng", "z/b.v2.dng"].map(|name| dir.path().join(name));
+    for input in &inputs {
+        std::fs::create_dir_all(input.parent().unwrap()).unwrap();
+        std::fs::write(input, dng()).unwrap();
+    }
+
+    cli()
+        .args(&inputs)
+        .arg(&outputs)
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("already the output of another input"));
+
+    let mut written: Vec<_> = std::fs::read_dir(&outputs)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    written.sort();
+    assert_eq!(written, ["a.tiff", "b.v1.tiff", "b.v2.tiff"]);
+}
+
+#[test]
+fn rejects_non_raw_input() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("frame.dng");
+    std::fs::write(&input, [0xFF, 0xD8, 0xFF, 0xE0, 0, 16, b'J', b'F', b'I', b'F']).unwrap();
+
+    cli()
+        .arg(&input)
+        .arg(dir.path().join("frame.tiff"))
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("not a RAW file: detected JPEG"));
+}
+
+#[test]
+fn rejects_invalid_flag_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("frame.dng");
+    let output = dir.path().join("frame.tiff");
+    std::fs::write(&input, dng()).unwrap();
+
+    for args in [["--compression", "zip"], ["--backend", "opencl"], ["--exposure", "bright"]] {
+        cli().arg(&input).arg(&output).args(args).assert().failure().code(2);
+        assert!(!output.exists(), "args {:?} left an output file", args);
+    }
+}
@@ -0,0 +1,99 @@
+//! Fixtures shared by the integration tests
+
+/// TIFF field types used by the fixture
+const BYTE: u16 = 1;
+const ASCII: u16 = 2;
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const RATIONAL: u16 = 5;
+const SRATIONAL: u16 = 10;
+
+/// sRGB's XYZ to linear RGB matrix, as `ColorMatrix1` rationals over 10000
+const XYZ_TO_SRGB: [i32; 9] = [32406, -15372, -4986, -9689, 18758, 415, 557, -2040, 10570];
+
+fn shorts(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn longs(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn ascii(text: &str) -> Vec<u8> {
+    let mut bytes = text.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
+
+/// Little-endian DNG holding one uncompressed RGGB mosaic in IFD0
+///
+/// `strip` is the image data exactly as stored: little-endian samples at 16 bits, and a
+/// big-endian bit stream with no row padding at other depths, as the DNG spec packs them.
+pub fn dng(width: u32, height: u32, bits_per_sample: u16, white: u16, strip: &[u8]) -> Vec<u8> {
+
+    let mut entries: Vec<(u16, u16, u32, Vec<u8>)> = vec![
+        (254, LONG, 1, longs(&[0])),
+        (256, LONG, 1, longs(&[width])),
+        (257, LONG, 1, longs(&[height])),
+        (258, SHORT, 1, shorts(&[bits_per_sample])),
+        (259, SHORT, 1, shorts(&[1])),
+        (262, SHORT, 1, shorts(&[32803])),
+        (271, ASCII, 0, ascii("Test")),
+        (272, ASCII, 0, ascii("Fixture")),
+        // StripOffsets, patched once the layout is known
+        (273, LONG, 1, longs(&[0])),
+        (277, SHORT, 1, shorts(&[1])),
+        (278, LONG, 1, longs(&[height])),
+        (279, LONG, 1, longs(&[strip.len() as u32])),
+        (284, SHORT, 1, shorts(&[1])),
+        (33421, SHORT, 2, shorts(&[2, 2])),
+        (33422, BYTE, 4, vec![0, 1, 1, 2]),
+        (50706, BYTE, 4, vec![1, 4, 0, 0]),
+        (50708, ASCII, 0, ascii("Test Fixture")),
+        (50717, SHORT, 1, shorts(&[white])),
+        (
+            50721,
+            SRATIONAL,
+            9,
+            XYZ_TO_SRGB.iter().flat_map(|&v| [v.to_le_bytes(), 10000i32.to_le_bytes()].concat()).collect(),
+        ),
+        (50728, RATIONAL, 3, longs(&[1, 1, 1, 1, 1, 1])),
+        (50778, SHORT, 1, shorts(&[21])),
+    ];
+    for (_, field_type, count, value) in entries.iter_mut() {
+        if *field_type == ASCII {
+            *count = value.len() as u32;
+        }
+    }
+
+    let ifd_len = 2 + 12 * entries.len() + 4;
+    let mut data_at = 8 + ifd_len;
+    let mut ifd = (entries.len() as u16).to_le_bytes().to_vec();
+    let mut data: Vec<u8> = Vec::new();
+    for (tag, field_type, count, value) in &entries {
+        ifd.extend(tag.to_le_bytes());
+        ifd.extend(field_type.to_le_bytes());
+        ifd.extend(count.to_le_bytes());
+        if value.len() <= 4 {
+            let mut inline = value.clone();
+            inline.resize(4, 0);
+            ifd.extend(inline);
+        } else {
+            ifd.extend((data_at as u32).to_le_bytes());
+            data.extend(value);
+            data_at += value.len();
+        }
+    }
+    ifd.extend(0u32.to_le_bytes());
+
+    let strip_at = (data_at as u32).to_le_bytes();
+    let strip_entry = 2 + 12 * entries.iter().position(|entry| entry.0 == 273).unwrap();
+    ifd[strip_entry + 8..strip_entry + 12].copy_from_slice(&strip_at);
+
+    let mut file = b"II*\0".to_vec();
+    file.extend(8u32.to_le_bytes());
+    file.extend(ifd);
+    file.extend(data);
+    file.extend(strip);
+    file
+}
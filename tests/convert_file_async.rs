@@ -0,0 +1,73 @@
+//! Checks `RawToTiffPipeline::convert_file_async`.
+//!
+//! A mock reader hands a synthetic mosaic to a pipeline with `write_sidecar` enabled.
+//! The async conversion must write the same TIFF as `convert_to_vec` and, like
+//! `convert_file`, a JSON sidecar next to it. A missing input must fail at the read stage.
+//!
+//! Run with `cargo test --features tokio --test convert_file_async`.
+
+use std::sync::Arc;
+
+use ffed_protosat_rs::image_pipeline::{
+    ConversionConfig, OutputMode, PipelineStage, RawImageData, RawImageReader, RawToTiffPipeline,
+    Result, StandardTiffWriter,
+};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 24;
+
+/// Returns a 12-bit gradient mosaic whatever the input bytes
+struct GradientReader;
+
+impl RawImageReader for GradientReader {
+    fn read_raw(&self, _data: &[u8]) -> Result<RawImageData> {
+        Ok(RawImageData {
+            width: WIDTH,
+            height: HEIGHT,
+            data: (0..WIDTH * HEIGHT).map(|i| 256 + (i * 5 % 3800) as u16).collect(),
+            bits_per_sample: 12,
+            blacklevels: [256; 4],
+            whitelevels: [4095; 4],
+            ..Default::default()
+        })
+    }
+}
+
+fn pipeline() -> Arc<RawToTiffPipeline<GradientReader, StandardTiffWriter>> {
+    let config = ConversionConfig::builder()
+        .output(OutputMode::Rgb)
+        .write_sidecar(true)
+        .build();
+    Arc::new(RawToTiffPipeline::with_custom(GradientReader, StandardTiffWriter, config).unwrap())
+}
+
+#[tokio::test]
+async fn writes_tiff_and_sidecar() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("frame.raw");
+    let output = dir.path().join("frame.tiff");
+    std::fs::write(&input, b"raw").unwrap();
+
+    let pipeline = pipeline();
+    let expected = pipeline.convert_to_vec(b"raw").unwrap();
+    pipeline.convert_file_async(&input, &output).await.unwrap();
+
+    assert_eq!(std::fs::read(&output).unwrap(), expected);
+    let sidecar = std::fs::read_to_string(output.with_extension("json")).unwrap();
+    let report: serde_json::Value = serde_json::from_str(&sidecar).unwrap();
+    assert!(report.is_object(), "sidecar is not a JSON object: {}", sidecar);
+}
+
+#[tokio::test]
+async fn missing_input_fails_at_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("frame.tiff");
+
+    let err = pipeline()
+        .convert_file_async(dir.path().join("missing.raw"), &output)
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.stage(), Some(PipelineStage::Read));
+    assert!(!output.exists());
+}
@@ -0,0 +1,120 @@
+//! Checks that `RawLoaderReader` hands back unpacked samples for bit-packed raws.
+//!
+//! Uncompressed DNG strips below 16 bits are packed as a big-endian bit stream. A known
+//! pattern of 12-bit codes, including both ends of the range and values straddling byte
+//! boundaries, is packed by hand and must decode to exactly those codes. The same holds
+//! for 14-bit codes whenever rawloader decodes such a strip. Samples above the range the
+//! white level implies, as left-aligned data would be, must be kept but logged.
+//!
+//! Run with `cargo test --test packed_dng`.
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ffed_protosat_rs::image_pipeline::{ConversionError, RawImageReader, RawLoaderReader};
+
+mod common;
+
+const WIDTH: u32 = 8;
+const HEIGHT: u32 = 2;
+
+/// Packs `codes` most significant bit first into a stream of `bits`-bit fields
+fn pack(codes: &[u16], bits: u32) -> Vec<u8> {
+    let mut packed = Vec::new();
+    let (mut buffer, mut filled) = (0u32, 0);
+    for &code in codes {
+        buffer = (buffer << bits) | u32::from(code);
+        filled += bits;
+        while filled >= 8 {
+            filled -= 8;
+            packed.push((buffer >> filled) as u8);
+        }
+    }
+    assert_eq!(filled, 0, "rows must end on a byte boundary");
+    packed
+}
+
+/// `WIDTH * HEIGHT` codes covering 0, the maximum, alternating bit patterns and values
+/// whose fields straddle byte boundaries
+fn codes(bits: u32) -> Vec<u16> {
+    let max = (1u16 << bits) - 1;
+    let mut codes = vec![0, max, 0xAAAA & max, 0x5555 & max, 1, max - 1, 1 << (bits - 1), (1 << (bits - 1)) - 1];
+    codes.extend((0..WIDTH as u16).map(|i| (i * 0x0123 + 0x0F0) & max));
+    codes
+}
+
+#[test]
+fn unpacks_12_bit_strip() {
+    // Two codes share three bytes: 0xABC, 0x123 -> AB C1 23
+    assert_eq!(pack(&[0xABC, 0x123], 12), [0xAB, 0xC1, 0x23]);
+
+    let codes = codes(12);
+    let image = RawLoaderReader
+        .read_raw(&common::dng(WIDTH, HEIGHT, 12, 4095, &pack(&codes, 12)))
+        .unwrap();
+
+    assert_eq!(image.bits_per_sample, 12);
+    assert_eq!((image.width, image.height), (WIDTH as usize, HEIGHT as usize));
+    assert_eq!(image.data, codes);
+}
+
+#[test]
+fn unpacks_14_bit_strip_or_rejects_it() {
+    let codes = codes(14);
+    // Not every rawloader release decodes 14-bit uncompressed DNG strips; one that can't
+    // must fail to decode rather than hand back packed or shifted samples
+    match RawLoaderReader.read_raw(&common::dng(WIDTH, HEIGHT, 14, 16383, &pack(&codes, 14))) {
+        Ok(image) => {
+            assert_eq!(image.bits_per_sample, 14);
+            assert_eq!(image.data, codes);
+        }
+        Err(e) => assert!(matches!(e, ConversionError::RawDecode { .. }), "unexpected error {}", e),
+    }
+}
+
+/// Log output of `f`, without color codes
+fn logged<T>(f: impl FnOnce() -> T) -> (T, String) {
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    let result = tracing::subscriber::with_default(subscriber, f);
+    let log = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    (result, log)
+}
+
+#[test]
+fn logs_samples_above_the_white_level_range() {
+    const WARNING: &str = "samples exceed the 12-bit range";
+    let codes = codes(12);
+    let strip = |codes: &[u16]| codes.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+
+    // 12-bit codes stored right-aligned in 16-bit samples are in range
+    let (image, log) = logged(|| RawLoaderReader.read_raw(&common::dng(WIDTH, HEIGHT, 16, 4095, &strip(&codes))));
+    assert_eq!(image.unwrap().data, codes);
+    assert!(!log.contains(WARNING), "in-range samples logged: {}", log);
+
+    // Left-aligned, the nonzero codes land above 4095 and must be flagged but kept
+    let left_aligned: Vec<u16> = codes.iter().map(|v| v << 4).collect();
+    let (image, log) = logged(|| RawLoaderReader.read_raw(&common::dng(WIDTH, HEIGHT, 16, 4095, &strip(&left_aligned))));
+    let image = image.unwrap();
+    assert_eq!(image.bits_per_sample, 12);
+    assert_eq!(image.data, left_aligned);
+    let above = left_aligned.iter().filter(|&&v| v > 4095).count();
+    assert!(log.contains(&format!("{} {}", above, WARNING)), "no warning for {} samples: {}", above, log);
+}